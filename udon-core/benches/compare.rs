@@ -195,7 +195,7 @@ fn generate_documents(item_count: usize) -> Documents {
 
         if has_children {
             for j in 0..3 {
-                toml_str.push_str(&format!("[[doc.items.children]]\n"));
+                toml_str.push_str("[[doc.items.children]]\n");
                 toml_str.push_str(&format!("id = \"sub-{}-{}\"\n", i, j));
                 toml_str.push_str(&format!("order = {}\n", j + 1));
                 toml_str.push_str(&format!("label = \"Task {}\"\n", j + 1));
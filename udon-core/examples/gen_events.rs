@@ -53,7 +53,6 @@ fn main() {
     // Read YAML from stdin, process each test, write YAML to stdout
     let mut in_udon = false;
     let mut udon_content = String::new();
-    let mut indent = "";
     
     for line in stdin.lock().lines() {
         let line = line.unwrap();
@@ -67,7 +66,6 @@ fn main() {
                 if after_udon == "|" || after_udon == "|-" {
                     in_udon = true;
                     udon_content.clear();
-                    indent = "    "; // assume 4 space indent
                 } else {
                     // Inline string - parse it
                     // Extract quoted content
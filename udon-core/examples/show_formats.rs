@@ -45,7 +45,7 @@ fn example() {
     ));
     udon.push_str("    |content\n");
     udon.push_str(&indent(content, "      "));
-    udon.push_str("\n");
+    udon.push('\n');
 
     // XML
     let mut xml = String::new();
@@ -75,7 +75,7 @@ fn example() {
     md.push_str(&format!("- **priority** {}\n", priority));
     md.push_str(&format!("- **category** {}\n\n", category));
     md.push_str(content);
-    md.push_str("\n");
+    md.push('\n');
 
     // JSON
     let json = serde_json::json!({
@@ -109,7 +109,7 @@ fn example() {
     yaml.push_str(&format!("      category: {}\n", category));
     yaml.push_str("      content: |\n");
     yaml.push_str(&indent(content, "        "));
-    yaml.push_str("\n");
+    yaml.push('\n');
 
     // TOML
     let mut toml = String::new();
@@ -1,11 +1,9 @@
-use std::io::Read;
-use udon_core::Parser;
+use udon_core::StreamingParser;
 
 fn main() {
-    let mut input = Vec::new();
-    std::io::stdin().read_to_end(&mut input).unwrap();
-
-    Parser::new(&input).parse(|event| {
-        eprintln!("EVENT: {:?}", event);
-    });
+    StreamingParser::new()
+        .parse_reader(std::io::stdin().lock(), |event| {
+            eprintln!("EVENT: {:?}", event);
+        })
+        .unwrap();
 }
@@ -0,0 +1,752 @@
+//! Lossless concrete syntax tree for format-preserving edits.
+//!
+//! A `CstDocument` owns the exact source text and a node tree whose spans
+//! point into it. Printing gives back the text byte for byte. Edits
+//! (`set_attr`, `remove_attr`, `insert_child`, `remove`) splice only the
+//! bytes they change, so comments, blank lines, alignment and attribute
+//! order elsewhere stay as written.
+//!
+//! Every edit re-parses the whole document to rebuild the tree, so an edit
+//! costs time proportional to the document's size, not the change's; apply
+//! many changes to a large document through `tree::Document` and the
+//! writer instead, or gather new content into one `insert_child` snippet.
+//! IDs of nodes that come before the edited region are unchanged; look
+//! other nodes up again.
+//! IDs are shared with the `tree::Document` returned by
+//! [`CstDocument::tree`], so the tree API can be used to find nodes to edit.
+//!
+//! # Example
+//!
+//! ```
+//! use udon_core::cst::CstDocument;
+//! use udon_core::Value;
+//!
+//! let mut doc = CstDocument::parse("|server\n  :host   localhost  ; dev box\n  :port   8080\n").unwrap();
+//! let server = doc.root().children().next().unwrap().id();
+//! doc.set_attr(server, "port", &Value::Integer("9090".into())).unwrap();
+//! assert_eq!(doc.to_string(), "|server\n  :host   localhost  ; dev box\n  :port   9090\n");
+//! ```
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::span::Span;
+use crate::tree::{Document, NodeId, NodeKind, ParseError, Value};
+use crate::writer::{write_label, write_value, Context};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// The syntactic kind of a CST node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CstKind {
+    Document,
+    Element,
+    Text,
+    Comment,
+    Directive,
+    Interpolation,
+    Reference,
+    Raw,
+}
+
+/// An attribute as written in the source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstAttr {
+    name: String,
+    name_span: Span,
+    value_span: Span,
+}
+
+impl CstAttr {
+    /// The attribute key.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Span of the key, without the leading `:`.
+    pub fn name_span(&self) -> Span {
+        self.name_span
+    }
+
+    /// Span of the value as written (quotes and brackets included).
+    /// Empty for flags such as `:debug`.
+    pub fn value_span(&self) -> Span {
+        self.value_span
+    }
+
+    /// End of the attribute's source text.
+    fn end(&self) -> usize {
+        if self.value_span.is_empty() {
+            self.name_span.end as usize
+        } else {
+            self.value_span.end as usize
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CstNodeData {
+    kind: CstKind,
+    name: Option<String>,
+    embedded: bool,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    span: Span,
+    attrs: Vec<CstAttr>,
+}
+
+/// A UDON document that can be edited without disturbing its formatting.
+#[derive(Debug)]
+pub struct CstDocument {
+    text: String,
+    nodes: Vec<CstNodeData>,
+}
+
+/// Error returned when an edit cannot be applied.
+///
+/// A failed edit leaves the document unchanged.
+#[derive(Debug)]
+pub enum EditError {
+    /// The ID does not refer to a node in this document.
+    InvalidNode(NodeId),
+    /// Attribute edits need an element.
+    NotAnElement(NodeId),
+    /// The document root cannot be removed.
+    RemoveRoot,
+    /// The inserted text, or the document after the edit, does not parse.
+    Parse(ParseError),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::InvalidNode(id) => write!(f, "no node with id {:?}", id),
+            EditError::NotAnElement(id) => write!(f, "node {:?} is not an element", id),
+            EditError::RemoveRoot => write!(f, "cannot remove the document root"),
+            EditError::Parse(err) => write!(f, "edit does not parse: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+// ============================================================================
+// CstDocument
+// ============================================================================
+
+impl CstDocument {
+    /// Parse source text into an editable document.
+    pub fn parse(text: impl Into<String>) -> Result<Self, ParseError> {
+        let text = text.into();
+        let nodes = build(&text)?;
+        Ok(CstDocument { text, nodes })
+    }
+
+    /// The current source text.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Get the root node.
+    pub fn root(&self) -> CstNode<'_> {
+        CstNode { doc: self, id: NodeId::new(0) }
+    }
+
+    /// Get a node by ID.
+    pub fn get(&self, id: NodeId) -> Option<CstNode<'_>> {
+        (id.index() < self.nodes.len()).then_some(CstNode { doc: self, id })
+    }
+
+    /// Build a `tree::Document` over the current text.
+    ///
+    /// Node IDs in the returned tree match the IDs in this document.
+    pub fn tree(&self) -> Document<'_> {
+        Document::parse(self.text.as_bytes()).expect("CST text parsed when it was last edited")
+    }
+
+    /// Get the source text covered by a span.
+    pub fn span_text(&self, span: Span) -> &str {
+        self.text.get(span.range()).unwrap_or("")
+    }
+
+    // ========== Edits ==========
+
+    /// Set an attribute on an element, replacing only its value if it exists.
+    ///
+    /// A new attribute is added after the element's last attribute, matching
+    /// its layout: on the same line for sameline and embedded attributes,
+    /// otherwise on a new line with the same indentation.
+    pub fn set_attr(&mut self, id: NodeId, name: &str, value: &Value<'_>) -> Result<(), EditError> {
+        let node = self.element(id)?;
+
+        if let Some(attr) = node.attrs.iter().find(|a| a.name == name) {
+            let ctx = if node.embedded || !self.starts_line(self.colon(attr)) {
+                Context::Embedded
+            } else {
+                Context::Block
+            };
+            let mut formatted = String::new();
+            write_value(value, ctx, &mut formatted);
+            return if attr.value_span.is_empty() {
+                let end = attr.name_span.end as usize;
+                self.splice(end..end, &format!(" {}", formatted))
+            } else {
+                self.splice(attr.value_span.range(), &formatted)
+            };
+        }
+
+        let last = node.attrs.iter().rfind(|a| !is_suffix(&a.name));
+        match last {
+            Some(last) if !node.embedded && self.starts_line(self.colon(last)) => {
+                let colon = self.colon(last);
+                let indent = &self.text[line_start(&self.text, colon)..colon];
+                let line = format!("{}{}{}", indent, attr_source(name, value, Context::Block), self.newline());
+                self.insert_line(line_end(&self.text, last.end()), line)
+            }
+            Some(last) => {
+                let at = last.end();
+                let ctx = Context::Embedded;
+                self.splice(at..at, &format!(" {}", attr_source(name, value, ctx)))
+            }
+            None if node.embedded => {
+                let at = identity_end(&self.text, node.span.start as usize);
+                let ctx = Context::Embedded;
+                self.splice(at..at, &format!(" {}", attr_source(name, value, ctx)))
+            }
+            None => {
+                let line = format!("{}{}{}", self.child_indent(id), attr_source(name, value, Context::Block), self.newline());
+                self.insert_line(line_end(&self.text, node.span.start as usize), line)
+            }
+        }
+    }
+
+    /// Remove an attribute from an element. Returns whether it was present.
+    pub fn remove_attr(&mut self, id: NodeId, name: &str) -> Result<bool, EditError> {
+        let node = self.element(id)?;
+        let Some(attr) = node.attrs.iter().find(|a| a.name == name) else {
+            return Ok(false);
+        };
+        let range = self.removal_range(self.colon(attr), attr.end());
+        self.splice(range, "")?;
+        Ok(true)
+    }
+
+    /// Insert UDON source as a child of `parent`, before its `index`-th
+    /// block child (a child that starts its own line). An index past the
+    /// end appends after the parent's last line.
+    ///
+    /// The snippet is re-indented to match the parent's existing children.
+    /// Returns the ID of the first inserted node.
+    pub fn insert_child(&mut self, parent: NodeId, index: usize, udon: &str) -> Result<NodeId, EditError> {
+        self.node(parent)?;
+        Document::parse(udon.as_bytes()).map_err(EditError::Parse)?;
+
+        let blocks = self.block_children(parent);
+        let index = index.min(blocks.len());
+        let at = match blocks.get(index) {
+            Some(&child) => line_start(&self.text, self.nodes[child.index()].span.start as usize),
+            None if parent.index() == 0 => self.text.len(),
+            None => line_end(&self.text, self.nodes[parent.index()].span.end as usize),
+        };
+        let body = reindent(udon, &self.child_indent(parent), self.newline());
+        self.insert_line(at, body)?;
+
+        self.block_children(parent)
+            .get(index)
+            .copied()
+            .ok_or(EditError::InvalidNode(parent))
+    }
+
+    /// Remove a node and its subtree.
+    ///
+    /// A node on its own lines takes those lines with it; an inline node
+    /// takes the whitespace separating it from its neighbour.
+    pub fn remove(&mut self, id: NodeId) -> Result<(), EditError> {
+        let node = self.node(id)?;
+        if node.parent.is_none() {
+            return Err(EditError::RemoveRoot);
+        }
+        let range = self.removal_range(node.span.start as usize, node.span.end as usize);
+        self.splice(range, "")
+    }
+
+    // ========== Internals ==========
+
+    fn node(&self, id: NodeId) -> Result<&CstNodeData, EditError> {
+        self.nodes.get(id.index()).ok_or(EditError::InvalidNode(id))
+    }
+
+    fn element(&self, id: NodeId) -> Result<&CstNodeData, EditError> {
+        match self.node(id)? {
+            node if node.kind == CstKind::Element => Ok(node),
+            _ => Err(EditError::NotAnElement(id)),
+        }
+    }
+
+    /// Replace a byte range and rebuild the tree from the whole new text.
+    /// The text is only kept if it still parses.
+    fn splice(&mut self, range: Range<usize>, replacement: &str) -> Result<(), EditError> {
+        let mut text = String::with_capacity(self.text.len() + replacement.len());
+        text.push_str(&self.text[..range.start]);
+        text.push_str(replacement);
+        text.push_str(&self.text[range.end..]);
+
+        self.nodes = build(&text).map_err(EditError::Parse)?;
+        self.text = text;
+        Ok(())
+    }
+
+    /// Insert whole lines at a line start, adding the missing newline when
+    /// inserting after an unterminated last line.
+    fn insert_line(&mut self, at: usize, mut lines: String) -> Result<(), EditError> {
+        if at == self.text.len() && !self.text.is_empty() && !self.text.ends_with('\n') {
+            lines.insert_str(0, self.newline());
+        }
+        self.splice(at..at, &lines)
+    }
+
+    /// The document's line ending, taken from its first line, for the lines
+    /// edits add.
+    fn newline(&self) -> &'static str {
+        match self.text.find('\n') {
+            Some(i) if self.text[..i].ends_with('\r') => "\r\n",
+            _ => "\n",
+        }
+    }
+
+    /// Children that start their own line.
+    fn block_children(&self, parent: NodeId) -> Vec<NodeId> {
+        self.nodes[parent.index()]
+            .children
+            .iter()
+            .copied()
+            .filter(|child| self.starts_line(self.nodes[child.index()].span.start as usize))
+            .collect()
+    }
+
+    /// Indentation for a new child line: that of an existing block child or
+    /// attribute line, otherwise two spaces past the parent.
+    fn child_indent(&self, parent: NodeId) -> String {
+        if parent.index() == 0 {
+            return String::new();
+        }
+        let node = &self.nodes[parent.index()];
+        let first_line = node
+            .attrs
+            .iter()
+            .map(|a| self.colon(a))
+            .chain(self.block_children(parent).iter().map(|c| self.nodes[c.index()].span.start as usize))
+            .find(|&pos| self.starts_line(pos));
+        match first_line {
+            Some(pos) => self.text[line_start(&self.text, pos)..pos].to_string(),
+            None => {
+                let start = node.span.start as usize;
+                " ".repeat(start - line_start(&self.text, start) + 2)
+            }
+        }
+    }
+
+    /// Range to delete for source text `start..end`: whole lines when it
+    /// occupies them alone, otherwise the text plus adjoining whitespace.
+    fn removal_range(&self, start: usize, end: usize) -> Range<usize> {
+        let text = &self.text;
+        match (self.starts_line(start), self.ends_line(end)) {
+            (true, true) => line_start(text, start)..line_end(text, end),
+            (true, false) => {
+                let rest = &text[end..];
+                start..end + (rest.len() - rest.trim_start_matches([' ', '\t']).len())
+            }
+            (false, _) => text[..start].trim_end_matches([' ', '\t']).len()..end,
+        }
+    }
+
+    /// Whether only indentation precedes `pos` on its line.
+    fn starts_line(&self, pos: usize) -> bool {
+        self.text[line_start(&self.text, pos)..pos].bytes().all(|b| b == b' ' || b == b'\t')
+    }
+
+    /// Whether only whitespace follows `pos` on its line.
+    fn ends_line(&self, pos: usize) -> bool {
+        let rest = &self.text[pos..];
+        rest[..rest.find('\n').unwrap_or(rest.len())].trim().is_empty()
+    }
+
+    /// Position of an attribute's `:` (keys may be quoted).
+    fn colon(&self, attr: &CstAttr) -> usize {
+        self.text[..attr.name_span.start as usize].rfind(':').unwrap_or(attr.name_span.start as usize)
+    }
+}
+
+impl fmt::Display for CstDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+// ============================================================================
+// CstNode (navigation handle)
+// ============================================================================
+
+/// A handle for navigating a `CstDocument`.
+#[derive(Clone, Copy)]
+pub struct CstNode<'c> {
+    doc: &'c CstDocument,
+    id: NodeId,
+}
+
+impl<'c> CstNode<'c> {
+    fn data(&self) -> &'c CstNodeData {
+        &self.doc.nodes[self.id.index()]
+    }
+
+    /// Get the node's ID.
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    /// Get the node's kind.
+    pub fn kind(&self) -> CstKind {
+        self.data().kind
+    }
+
+    /// Element or directive name.
+    pub fn name(&self) -> Option<&'c str> {
+        self.data().name.as_deref()
+    }
+
+    /// Whether this is an embedded `|{...}` element.
+    pub fn is_embedded(&self) -> bool {
+        self.data().embedded
+    }
+
+    /// Source span, including sigils and delimiters.
+    pub fn span(&self) -> Span {
+        self.data().span
+    }
+
+    /// The exact source text of the node.
+    pub fn text(&self) -> &'c str {
+        self.doc.span_text(self.span())
+    }
+
+    /// Get the parent node.
+    pub fn parent(&self) -> Option<CstNode<'c>> {
+        let doc = self.doc;
+        self.data().parent.map(|id| CstNode { doc, id })
+    }
+
+    /// Iterate over children.
+    pub fn children(&self) -> impl Iterator<Item = CstNode<'c>> + 'c {
+        let doc = self.doc;
+        self.data().children.iter().map(move |&id| CstNode { doc, id })
+    }
+
+    /// Attributes in source order.
+    pub fn attrs(&self) -> &'c [CstAttr] {
+        &self.data().attrs
+    }
+
+    /// Get an attribute by name.
+    pub fn attr(&self, name: &str) -> Option<&'c CstAttr> {
+        self.attrs().iter().find(|a| a.name == name)
+    }
+
+    /// Get an attribute's value exactly as written.
+    pub fn attr_text(&self, name: &str) -> Option<&'c str> {
+        self.attr(name).map(|a| self.doc.span_text(a.value_span))
+    }
+}
+
+impl fmt::Debug for CstNode<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CstNode")
+            .field("id", &self.id)
+            .field("kind", &self.kind())
+            .field("span", &self.span())
+            .finish()
+    }
+}
+
+// ============================================================================
+// Building
+// ============================================================================
+
+/// Build the node arena from the tree, keeping the tree's node IDs.
+fn build(text: &str) -> Result<Vec<CstNodeData>, ParseError> {
+    let doc = Document::parse(text.as_bytes())?;
+    let mut nodes = Vec::new();
+    let mut index = 0;
+    while let Some(node) = doc.get(NodeId::new(index)) {
+        let (kind, name, embedded, attrs) = match node.kind() {
+            NodeKind::Document => (CstKind::Document, None, false, &[][..]),
+            NodeKind::Element { name, embedded, attrs, .. } => {
+                (CstKind::Element, Some(name.to_string()), *embedded, &attrs[..])
+            }
+            NodeKind::Text(_) => (CstKind::Text, None, false, &[][..]),
+            NodeKind::Comment(_) => (CstKind::Comment, None, false, &[][..]),
+            NodeKind::Directive { name, attrs } => (CstKind::Directive, Some(name.to_string()), false, &attrs[..]),
+            NodeKind::Interpolation(_) => (CstKind::Interpolation, None, false, &[][..]),
+            NodeKind::Reference(_) => (CstKind::Reference, None, false, &[][..]),
+            NodeKind::Raw { .. } => (CstKind::Raw, None, false, &[][..]),
+        };
+        nodes.push(CstNodeData {
+            kind,
+            name,
+            embedded,
+            parent: node.parent().map(|p| p.id()),
+            children: node.children().map(|c| c.id()).collect(),
+            span: node.span(),
+            attrs: attrs
+                .iter()
+                .map(|a| CstAttr {
+                    name: a.name.to_string(),
+                    name_span: a.name_span,
+                    value_span: trim_end(text, a.value_span),
+                })
+                .collect(),
+        });
+        index += 1;
+    }
+    Ok(nodes)
+}
+
+/// Bare values can carry trailing spaces that belong to the layout.
+fn trim_end(text: &str, span: Span) -> Span {
+    let value = text.get(span.range()).unwrap_or("");
+    Span::new(span.start as usize, span.start as usize + value.trim_end().len())
+}
+
+// ============================================================================
+// Text helpers
+// ============================================================================
+
+fn line_start(text: &str, pos: usize) -> usize {
+    text[..pos].rfind('\n').map_or(0, |i| i + 1)
+}
+
+/// Position just past the newline ending the line containing `pos`.
+fn line_end(text: &str, pos: usize) -> usize {
+    text[pos..].find('\n').map_or(text.len(), |i| pos + i + 1)
+}
+
+/// End of an element's `name[id].class` identity, starting at its `|`.
+fn identity_end(text: &str, start: usize) -> usize {
+    let bytes = text.as_bytes();
+    let mut pos = start + if text[start..].starts_with("|{") { 2 } else { 1 };
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'\'' => {
+                pos += 1;
+                while pos < bytes.len() && bytes[pos] != b'\'' {
+                    pos += if bytes[pos] == b'\\' { 2 } else { 1 };
+                }
+                pos += 1;
+            }
+            b'[' => pos += text[pos..].find(']').map_or(bytes.len() - pos, |i| i + 1),
+            b' ' | b'\t' | b'\n' | b'\r' | b'}' | b';' | b'|' | b':' => break,
+            _ => pos += 1,
+        }
+    }
+    pos.min(bytes.len())
+}
+
+/// Shift a snippet's lines to a new base indentation, ending each with
+/// `newline`.
+fn reindent(udon: &str, indent: &str, newline: &str) -> String {
+    let base = udon
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start_matches([' ', '\t']).len())
+        .min()
+        .unwrap_or(0);
+    let mut out = String::new();
+    for line in udon.lines() {
+        if !line.trim().is_empty() {
+            out.push_str(indent);
+            out.push_str(&line[base..]);
+        }
+        out.push_str(newline);
+    }
+    out
+}
+
+fn attr_source(name: &str, value: &Value<'_>, ctx: Context) -> String {
+    let mut out = String::from(":");
+    write_label(name, &mut out);
+    out.push(' ');
+    write_value(value, ctx, &mut out);
+    out
+}
+
+/// Element suffix flags are stored as attributes named `?`, `!`, `*`, `+`.
+fn is_suffix(name: &str) -> bool {
+    matches!(name, "?" | "!" | "*" | "+")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "\
+; Server settings
+|server[main]
+  :host     localhost   ; dev box
+  :port     8080
+  :debug
+
+  ; upstreams
+  |upstream :weight 1
+    |{addr :host db1}
+  |upstream :weight 2
+";
+
+    fn find<'c>(doc: &'c CstDocument, name: &str) -> CstNode<'c> {
+        fn walk<'c>(node: CstNode<'c>, name: &str) -> Option<CstNode<'c>> {
+            if node.name() == Some(name) {
+                return Some(node);
+            }
+            node.children().find_map(|c| walk(c, name))
+        }
+        walk(doc.root(), name).unwrap()
+    }
+
+    #[test]
+    fn test_unedited_round_trip() {
+        let doc = CstDocument::parse(CONFIG).unwrap();
+        assert_eq!(doc.to_string(), CONFIG);
+        assert_eq!(find(&doc, "server").attr_text("host"), Some("localhost"));
+    }
+
+    #[test]
+    fn test_set_existing_attr() {
+        let mut doc = CstDocument::parse(CONFIG).unwrap();
+        let server = find(&doc, "server").id();
+        doc.set_attr(server, "host", &Value::Bare("example.com".into())).unwrap();
+        doc.set_attr(server, "port", &Value::Integer("9090".into())).unwrap();
+        assert_eq!(
+            doc.as_str(),
+            CONFIG
+                .replace("localhost   ;", "example.com   ;")
+                .replace("8080", "9090")
+        );
+
+        doc.set_attr(server, "debug", &Value::BoolFalse).unwrap();
+        assert!(doc.as_str().contains("  :debug false\n\n"));
+    }
+
+    #[test]
+    fn test_add_attr_follows_layout() {
+        let mut doc = CstDocument::parse(CONFIG).unwrap();
+        let server = find(&doc, "server").id();
+        doc.set_attr(server, "name", &Value::String("a b".into())).unwrap();
+        assert!(doc.as_str().contains("  :debug\n  :name \"a b\"\n\n"));
+
+        let upstream = find(&doc, "upstream").id();
+        doc.set_attr(upstream, "backup", &Value::BoolTrue).unwrap();
+        assert!(doc.as_str().contains("|upstream :weight 1 :backup true\n"));
+
+        let addr = find(&doc, "addr").id();
+        doc.set_attr(addr, "port", &Value::Integer("80".into())).unwrap();
+        assert!(doc.as_str().contains("|{addr :host db1 :port 80}"));
+
+        let mut doc = CstDocument::parse("|a\n  |b\n").unwrap();
+        let a = find(&doc, "a").id();
+        doc.set_attr(a, "k", &Value::Bare("v".into())).unwrap();
+        assert_eq!(doc.as_str(), "|a\n  :k v\n  |b\n");
+        let tree = doc.tree();
+        let el = tree.get(a).unwrap().as_element().unwrap();
+        assert!(matches!(el.attr("k"), Some(Value::Bare(v)) if v == "v"));
+
+        // Names that are not plain labels are quoted, in every layout
+        doc.set_attr(a, "odd name", &Value::Integer("1".into())).unwrap();
+        assert_eq!(doc.as_str(), "|a\n  :k v\n  :'odd name' 1\n  |b\n");
+        assert_eq!(find(&doc, "a").attr_text("odd name"), Some("1"));
+
+        let mut doc = CstDocument::parse("|p :x 1\n").unwrap();
+        let p = find(&doc, "p").id();
+        doc.set_attr(p, "it's", &Value::Integer("2".into())).unwrap();
+        assert_eq!(doc.as_str(), "|p :x 1 :'it\\'s' 2\n");
+    }
+
+    #[test]
+    fn test_remove_attr() {
+        let mut doc = CstDocument::parse(CONFIG).unwrap();
+        let server = find(&doc, "server").id();
+        assert!(doc.remove_attr(server, "port").unwrap());
+        assert!(!doc.remove_attr(server, "port").unwrap());
+        assert_eq!(doc.as_str(), CONFIG.replace("  :port     8080\n", ""));
+
+        let upstream = find(&doc, "upstream").id();
+        doc.remove_attr(upstream, "weight").unwrap();
+        assert!(doc.as_str().contains("  |upstream\n    |{addr"));
+    }
+
+    #[test]
+    fn test_insert_child() {
+        let mut doc = CstDocument::parse(CONFIG).unwrap();
+        let server = find(&doc, "server").id();
+
+        let id = doc.insert_child(server, 2, "|upstream :weight 3\n  |{addr :host db3}").unwrap();
+        assert_eq!(doc.get(id).unwrap().attr_text("weight"), Some("3"));
+        assert!(doc.as_str().contains(
+            "    |{addr :host db1}\n  |upstream :weight 3\n    |{addr :host db3}\n  |upstream :weight 2\n"
+        ));
+
+        let id = doc.insert_child(server, usize::MAX, "|last").unwrap();
+        assert!(doc.as_str().ends_with("  |upstream :weight 2\n  |last\n"));
+        assert_eq!(doc.get(id).unwrap().name(), Some("last"));
+
+        let mut doc = CstDocument::parse("|a").unwrap();
+        doc.insert_child(NodeId::new(0), 1, "|b").unwrap();
+        assert_eq!(doc.as_str(), "|a\n|b\n");
+    }
+
+    #[test]
+    fn test_remove_node() {
+        let mut doc = CstDocument::parse(CONFIG).unwrap();
+        let upstream = find(&doc, "upstream").id();
+        doc.remove(upstream).unwrap();
+        assert_eq!(doc.as_str(), CONFIG.replace("  |upstream :weight 1\n    |{addr :host db1}\n", ""));
+
+        let mut doc = CstDocument::parse("|p Hello |{em there} world\n").unwrap();
+        let em = find(&doc, "em").id();
+        doc.remove(em).unwrap();
+        assert_eq!(doc.as_str(), "|p Hello world\n");
+
+        assert!(matches!(doc.remove(NodeId::new(0)), Err(EditError::RemoveRoot)));
+    }
+
+    #[test]
+    fn test_crlf_line_endings() {
+        let mut doc = CstDocument::parse("|a\r\n  :k v\r\n  |b\r\n|c").unwrap();
+        let a = find(&doc, "a").id();
+        doc.set_attr(a, "k", &Value::Bare("w".into())).unwrap();
+        doc.set_attr(a, "n", &Value::Integer("1".into())).unwrap();
+        doc.insert_child(a, 0, "|x\n  |y").unwrap();
+        let c = find(&doc, "c").id();
+        doc.set_attr(c, "m", &Value::Integer("2".into())).unwrap();
+        doc.insert_child(NodeId::new(0), usize::MAX, "|d").unwrap();
+        assert_eq!(doc.as_str(), "|a\r\n  :k w\r\n  :n 1\r\n  |x\r\n    |y\r\n  |b\r\n|c\r\n  :m 2\r\n|d\r\n");
+
+        let mut doc = CstDocument::parse("|e\r\n").unwrap();
+        let e = find(&doc, "e").id();
+        doc.set_attr(e, "k", &Value::Bare("v".into())).unwrap();
+        assert_eq!(doc.as_str(), "|e\r\n  :k v\r\n");
+        doc.remove_attr(e, "k").unwrap();
+        assert_eq!(doc.as_str(), "|e\r\n");
+    }
+
+    #[test]
+    fn test_failed_edit_leaves_text() {
+        let mut doc = CstDocument::parse(CONFIG).unwrap();
+        let server = find(&doc, "server").id();
+        let text = find(&doc, "upstream").id();
+        assert!(matches!(doc.insert_child(server, 0, "|x :s \"unclosed"), Err(EditError::Parse(_))));
+        assert!(matches!(doc.set_attr(NodeId::new(1), "k", &Value::Nil), Err(EditError::NotAnElement(_))));
+        assert!(matches!(doc.remove(NodeId::new(999)), Err(EditError::InvalidNode(_))));
+        assert_eq!(doc.as_str(), CONFIG);
+        assert_eq!(doc.get(text).unwrap().name(), Some("upstream"));
+    }
+}
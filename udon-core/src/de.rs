@@ -0,0 +1,1065 @@
+//! serde deserialization from UDON documents (`serde` feature).
+//!
+//! Mapping:
+//!
+//! - An element deserializes as a map or struct. Its attributes are
+//!   entries keyed by name; child elements are entries keyed by element
+//!   name, and repeated children with the same name form a sequence (a
+//!   single child also deserializes into a `Vec`). The id, classes and
+//!   text content are available under the `$id`, `$classes` and `$text`
+//!   keys.
+//! - An element asked for a scalar (`|port 8080` into a `u16` field)
+//!   deserializes its text content. An element asked for a sequence
+//!   yields its child elements.
+//! - Typed values map onto the matching serde types: integers of every
+//!   radix, floats, booleans, `nil` (`None` or `()`), arrays (sequences),
+//!   and strings. Rationals and complex numbers deserialize as their
+//!   source text, or as `(num, den)` / `(re, im)` tuples; temporal values
+//!   as their source text. Any scalar can be read into a `String`.
+//! - A document holding a single top-level element deserializes as that
+//!   element. Otherwise the document acts as an element whose children
+//!   are the top-level elements.
+//!
+//! Comments and directives are ignored. Mixins and references are not
+//! applied; run those passes and use [`from_document`] if needed.
+//!
+//! # Example
+//!
+//! ```
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Config {
+//!     name: String,
+//!     port: u16,
+//!     tags: Vec<String>,
+//!     server: Vec<Server>,
+//! }
+//!
+//! #[derive(Deserialize)]
+//! struct Server {
+//!     #[serde(rename = "$id")]
+//!     id: String,
+//!     weight: f64,
+//! }
+//!
+//! let input = b"|config :name demo :port 8080 :tags [a b]\n  |server[s1] :weight 0.5\n  |server[s2] :weight 2\n";
+//! let config: Config = udon_core::from_slice(input).unwrap();
+//! assert_eq!((config.name.as_str(), config.port), ("demo", 8080));
+//! assert_eq!(config.server[1].id, "s2");
+//! assert_eq!(config.server[1].weight, 2.0);
+//! ```
+
+use std::borrow::Cow;
+use std::fmt;
+
+use serde::de::{self, DeserializeSeed, Error as _, IntoDeserializer, MapAccess, SeqAccess, Unexpected, Visitor};
+use serde::forward_to_deserialize_any;
+
+use crate::span::Span;
+use crate::tree::{unescape, Attribute, Document, Node, NodeKind, ParseError, Value};
+
+/// Error returned by deserialization, located by source span where known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub message: String,
+    /// The innermost element or value being deserialized when the error
+    /// occurred.
+    pub span: Option<Span>,
+}
+
+impl Error {
+    fn at(mut self, span: Span) -> Self {
+        self.span.get_or_insert(span);
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.span {
+            Some(span) => write!(f, "{} at {}..{}", self.message, span.start, span.end),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error { message: msg.to_string(), span: None }
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Self {
+        Error { message: err.message, span: Some(Span::new(err.span.start, err.span.end)) }
+    }
+}
+
+/// Parse `input` and deserialize it as a `T`.
+pub fn from_slice<'de, T: de::Deserialize<'de>>(input: &'de [u8]) -> Result<T, Error> {
+    from_document(&Document::parse(input)?)
+}
+
+/// Parse `input` and deserialize it as a `T`.
+pub fn from_str<'de, T: de::Deserialize<'de>>(input: &'de str) -> Result<T, Error> {
+    from_slice(input.as_bytes())
+}
+
+/// Deserialize an already parsed document as a `T`.
+pub fn from_document<'de, T: de::Deserialize<'de>>(doc: &Document<'de>) -> Result<T, Error> {
+    let root = doc.root();
+    let mut elements = root.children().filter(|n| is_block_element(*n));
+    let has_text = root.children().any(|n| matches!(n.kind(), NodeKind::Text(_)));
+    let node = match (elements.next(), elements.next()) {
+        (Some(only), None) if !has_text => only,
+        _ => root,
+    };
+    T::deserialize(ElementDeserializer { node }).map_err(|e| e.at(node.span()))
+}
+
+fn is_block_element(node: Node<'_, '_>) -> bool {
+    matches!(node.kind(), NodeKind::Element { embedded: false, .. })
+}
+
+/// Text content of an element: its text children and embedded elements,
+/// with a newline between source lines.
+fn element_text(node: Node<'_, '_>) -> Option<String> {
+    let mut text: Option<String> = None;
+    let mut last = None;
+    for child in node.children() {
+        let piece = match child.kind() {
+            NodeKind::Text(s) => s.to_string(),
+            NodeKind::Element { embedded: true, .. } => child.all_text(),
+            _ => continue,
+        };
+        let text = text.get_or_insert_with(String::new);
+        if let Some(last) = last {
+            if child.source_since(last, last.span().end).is_some_and(|gap| gap.contains(&b'\n')) {
+                text.push('\n');
+            }
+        }
+        text.push_str(&piece);
+        last = Some(child);
+    }
+    text
+}
+
+fn str_deserializer(s: &str) -> de::value::StrDeserializer<'_, Error> {
+    s.into_deserializer()
+}
+
+fn visit_cow<'de, V: Visitor<'de>>(s: &Cow<'de, str>, visitor: V) -> Result<V::Value, Error> {
+    match s {
+        Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+        Cow::Owned(s) => visitor.visit_str(s),
+    }
+}
+
+fn visit_integer<'de, V: Visitor<'de>>(n: i128, visitor: V) -> Result<V::Value, Error> {
+    if let Ok(n) = i64::try_from(n) {
+        visitor.visit_i64(n)
+    } else if let Ok(n) = u64::try_from(n) {
+        visitor.visit_u64(n)
+    } else {
+        visitor.visit_i128(n)
+    }
+}
+
+// ============================================================================
+// Elements
+// ============================================================================
+
+/// Deserializes an element (or the document root) as a map.
+struct ElementDeserializer<'doc, 'de> {
+    node: Node<'doc, 'de>,
+}
+
+impl<'doc, 'de> ElementDeserializer<'doc, 'de> {
+    fn text(&self) -> Result<TextDeserializer, Error> {
+        let text = element_text(self.node)
+            .ok_or_else(|| Error::custom("expected an element with text content"))?;
+        Ok(TextDeserializer(text))
+    }
+
+    fn is_map_like(&self) -> bool {
+        match self.node.as_element() {
+            Some(el) => {
+                el.id().is_some()
+                    || !el.classes().is_empty()
+                    || !el.attributes().is_empty()
+                    || self.node.children().any(is_block_element)
+            }
+            None => true,
+        }
+    }
+
+    fn entries(&self) -> Vec<Entry<'doc, 'de>> {
+        let mut entries = Vec::new();
+        if let Some(el) = self.node.as_element() {
+            if let Some(id) = el.id() {
+                entries.push(Entry::Id(id));
+            }
+            if !el.classes().is_empty() {
+                entries.push(Entry::Classes(el.classes()));
+            }
+            entries.extend(el.attributes().iter().map(Entry::Attr));
+        }
+        if let Some(text) = element_text(self.node) {
+            entries.push(Entry::Text(text));
+        }
+        let mut groups: Vec<(&'doc str, Vec<Node<'doc, 'de>>)> = Vec::new();
+        for child in self.node.children().filter(|n| is_block_element(*n)) {
+            let name = child.as_element().unwrap().name();
+            match groups.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, nodes)) => nodes.push(child),
+                None => groups.push((name, vec![child])),
+            }
+        }
+        entries.extend(groups.into_iter().map(|(name, nodes)| Entry::Children(name, nodes)));
+        entries
+    }
+}
+
+macro_rules! text_scalars {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                self.text()?.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'doc, 'de> de::Deserializer<'de> for ElementDeserializer<'doc, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.is_map_like() {
+            self.deserialize_map(visitor)
+        } else if let Some(text) = element_text(self.node) {
+            visitor.visit_string(text)
+        } else {
+            visitor.visit_unit()
+        }
+    }
+
+    text_scalars! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf deserialize_identifier
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let children: Vec<_> = self.node.children().filter(|n| is_block_element(*n)).collect();
+        visitor.visit_seq(ElementSeq { nodes: children.into_iter() })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(ElementMap { entries: self.entries().into_iter(), pending: None })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    /// A unit variant from the text (`|mode fast`), or a variant named by
+    /// the only child element (`|mode\n  |fast :level 3`).
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let mut children = self.node.children().filter(|n| is_block_element(*n));
+        match (children.next(), children.next()) {
+            (Some(child), None) => visitor.visit_enum(ElementEnum { node: child }),
+            (None, _) => self.text()?.deserialize_enum("", &[], visitor),
+            _ => Err(Error::custom("expected a single child element naming the variant")),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+}
+
+/// One map entry of an element.
+enum Entry<'doc, 'de> {
+    Id(&'doc str),
+    Classes(&'doc [Cow<'de, str>]),
+    Attr(&'doc Attribute<'de>),
+    Text(String),
+    Children(&'doc str, Vec<Node<'doc, 'de>>),
+}
+
+struct ElementMap<'doc, 'de> {
+    entries: std::vec::IntoIter<Entry<'doc, 'de>>,
+    pending: Option<Entry<'doc, 'de>>,
+}
+
+impl<'doc, 'de> MapAccess<'de> for ElementMap<'doc, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        let Some(entry) = self.entries.next() else {
+            return Ok(None);
+        };
+        let key: &str = match &entry {
+            Entry::Id(_) => "$id",
+            Entry::Classes(_) => "$classes",
+            Entry::Text(_) => "$text",
+            Entry::Attr(attr) => &attr.name,
+            Entry::Children(name, _) => name,
+        };
+        let key = seed.deserialize(str_deserializer(key))?;
+        self.pending = Some(entry);
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        match self.pending.take().expect("next_value_seed called before next_key_seed") {
+            Entry::Id(id) => seed.deserialize(str_deserializer(id)),
+            Entry::Classes(classes) => {
+                let classes = classes.iter().map(|c| c.as_ref());
+                seed.deserialize(de::value::SeqDeserializer::new(classes))
+            }
+            Entry::Text(text) => seed.deserialize(TextDeserializer(text)),
+            Entry::Attr(attr) => {
+                let span = attr.value_span;
+                seed.deserialize(ValueDeserializer { value: &attr.value }).map_err(|e| e.at(span))
+            }
+            Entry::Children(_, nodes) => {
+                let span = nodes[0].span();
+                seed.deserialize(GroupDeserializer { nodes }).map_err(|e| e.at(span))
+            }
+        }
+    }
+}
+
+struct ElementSeq<'doc, 'de> {
+    nodes: std::vec::IntoIter<Node<'doc, 'de>>,
+}
+
+impl<'doc, 'de> SeqAccess<'de> for ElementSeq<'doc, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        match self.nodes.next() {
+            Some(node) => seed
+                .deserialize(ElementDeserializer { node })
+                .map(Some)
+                .map_err(|e| e.at(node.span())),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.nodes.len())
+    }
+}
+
+struct ElementEnum<'doc, 'de> {
+    node: Node<'doc, 'de>,
+}
+
+impl<'doc, 'de> de::EnumAccess<'de> for ElementEnum<'doc, 'de> {
+    type Error = Error;
+    type Variant = ElementDeserializer<'doc, 'de>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self::Variant), Error> {
+        let name = self.node.as_element().unwrap().name();
+        let variant = seed.deserialize(str_deserializer(name))?;
+        Ok((variant, ElementDeserializer { node: self.node }))
+    }
+}
+
+impl<'doc, 'de> de::VariantAccess<'de> for ElementDeserializer<'doc, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+/// Deserializes sibling elements sharing a name: a sequence when asked for
+/// one (or when there are several), otherwise the single element.
+struct GroupDeserializer<'doc, 'de> {
+    nodes: Vec<Node<'doc, 'de>>,
+}
+
+impl<'doc, 'de> GroupDeserializer<'doc, 'de> {
+    fn single(self) -> Result<ElementDeserializer<'doc, 'de>, Error> {
+        if let [_, second, ..] = self.nodes[..] {
+            let name = second.as_element().unwrap().name();
+            return Err(Error {
+                message: format!("element '{}' appears more than once", name),
+                span: Some(second.span()),
+            });
+        }
+        Ok(ElementDeserializer { node: self.nodes[0] })
+    }
+}
+
+macro_rules! single_element {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                self.single()?.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'doc, 'de> de::Deserializer<'de> for GroupDeserializer<'doc, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.nodes.len() > 1 {
+            self.deserialize_seq(visitor)
+        } else {
+            self.single()?.deserialize_any(visitor)
+        }
+    }
+
+    single_element! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf deserialize_unit
+        deserialize_map deserialize_identifier deserialize_ignored_any
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(ElementSeq { nodes: self.nodes.into_iter() })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        self.single()?.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        self.single()?.deserialize_newtype_struct(name, visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.single()?.deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.single()?.deserialize_enum(name, variants, visitor)
+    }
+}
+
+// ============================================================================
+// Text and values
+// ============================================================================
+
+/// Deserializes element text, decoding numbers and booleans as the value
+/// grammar would.
+struct TextDeserializer(String);
+
+impl TextDeserializer {
+    fn integer(&self) -> Result<i128, Error> {
+        Value::Integer(Cow::Borrowed(self.0.trim()))
+            .as_i128()
+            .map_err(|_| Error::invalid_type(Unexpected::Str(&self.0), &"an integer"))
+    }
+}
+
+macro_rules! text_integers {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                visit_integer(self.integer()?, visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for TextDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_string(self.0)
+    }
+
+    text_integers! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let text = self.0.trim();
+        let value = match Value::Float(Cow::Borrowed(text)).as_f64() {
+            Ok(f) => Ok(f),
+            Err(_) => Value::Integer(Cow::Borrowed(text)).as_f64(),
+        };
+        let value = value.map_err(|_| Error::invalid_type(Unexpected::Str(&self.0), &"a number"))?;
+        visitor.visit_f64(value)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0.trim() {
+            "true" => visitor.visit_bool(true),
+            "false" => visitor.visit_bool(false),
+            _ => Err(Error::invalid_type(Unexpected::Str(&self.0), &"a boolean")),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(str_deserializer(self.0.trim()))
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        char str string bytes byte_buf unit unit_struct seq tuple tuple_struct
+        map struct identifier ignored_any
+    }
+}
+
+/// Deserializes an attribute value.
+struct ValueDeserializer<'doc, 'de> {
+    value: &'doc Value<'de>,
+}
+
+impl<'doc, 'de> ValueDeserializer<'doc, 'de> {
+    fn unexpected(&self) -> Unexpected<'doc> {
+        match self.value {
+            Value::String(s) | Value::Bare(s) => Unexpected::Str(s),
+            Value::BoolTrue => Unexpected::Bool(true),
+            Value::BoolFalse => Unexpected::Bool(false),
+            Value::Nil => Unexpected::Unit,
+            Value::Array(_) => Unexpected::Seq,
+            Value::Integer(_) => Unexpected::Other("an integer"),
+            Value::Float(_) => Unexpected::Other("a float"),
+            Value::Rational(_) => Unexpected::Other("a rational"),
+            Value::Complex(_) => Unexpected::Other("a complex number"),
+            _ => Unexpected::Other("a temporal value"),
+        }
+    }
+
+    /// Source text of any scalar; quoted strings are unescaped.
+    fn text(&self) -> Option<Cow<'de, str>> {
+        match self.value {
+            Value::String(s) => match s {
+                Cow::Borrowed(s) => Some(unescape(s)),
+                Cow::Owned(s) => Some(Cow::Owned(unescape(s).into_owned())),
+            },
+            Value::Bare(s)
+            | Value::Integer(s)
+            | Value::Float(s)
+            | Value::Rational(s)
+            | Value::Complex(s)
+            | Value::Date(s)
+            | Value::Time(s)
+            | Value::DateTime(s)
+            | Value::Duration(s)
+            | Value::RelativeTime(s) => Some(s.clone()),
+            Value::BoolTrue => Some(Cow::Borrowed("true")),
+            Value::BoolFalse => Some(Cow::Borrowed("false")),
+            Value::Nil | Value::Array(_) => None,
+        }
+    }
+
+    fn integer(&self) -> Result<i128, Error> {
+        self.value.as_i128().map_err(|err| match err {
+            crate::number::NumberError::WrongType => Error::invalid_type(self.unexpected(), &"an integer"),
+            err => Error::custom(err),
+        })
+    }
+}
+
+macro_rules! value_integers {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                visit_integer(self.integer()?, visitor)
+            }
+        )*
+    };
+}
+
+impl<'doc, 'de> de::Deserializer<'de> for ValueDeserializer<'doc, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Integer(_) => visit_integer(self.integer()?, visitor),
+            Value::Float(_) => visitor.visit_f64(self.value.as_f64().map_err(Error::custom)?),
+            Value::BoolTrue => visitor.visit_bool(true),
+            Value::BoolFalse => visitor.visit_bool(false),
+            Value::Nil => visitor.visit_unit(),
+            Value::Array(items) => visitor.visit_seq(ValueSeq { items: items.iter() }),
+            _ => self.deserialize_str(visitor),
+        }
+    }
+
+    value_integers! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value.as_f64() {
+            Ok(f) => visitor.visit_f64(f),
+            Err(crate::number::NumberError::WrongType) => {
+                Err(Error::invalid_type(self.unexpected(), &"a number"))
+            }
+            Err(err) => Err(Error::custom(err)),
+        }
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::BoolTrue => visitor.visit_bool(true),
+            Value::BoolFalse => visitor.visit_bool(false),
+            _ => Err(Error::invalid_type(self.unexpected(), &"a boolean")),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.text() {
+            Some(text) => visit_cow(&text, visitor),
+            None => Err(Error::invalid_type(self.unexpected(), &"a string")),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Nil => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Array(items) => visitor.visit_seq(ValueSeq { items: items.iter() }),
+            Value::Rational(_) => {
+                let (num, den) = self.value.as_rational().map_err(Error::custom)?;
+                visitor.visit_seq(de::value::SeqDeserializer::new([num, den].into_iter()))
+            }
+            Value::Complex(_) => {
+                let (re, im) = self.value.as_complex().map_err(Error::custom)?;
+                visitor.visit_seq(de::value::SeqDeserializer::new([re, im].into_iter()))
+            }
+            _ => Err(Error::invalid_type(self.unexpected(), &"an array")),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.text() {
+            Some(text) => visitor.visit_enum(str_deserializer(text.as_ref())),
+            None => Err(Error::invalid_type(self.unexpected(), &"a variant name")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bytes byte_buf unit unit_struct map struct ignored_any
+    }
+}
+
+struct ValueSeq<'doc, 'de> {
+    items: std::slice::Iter<'doc, Value<'de>>,
+}
+
+impl<'doc, 'de> SeqAccess<'de> for ValueSeq<'doc, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        match self.items.next() {
+            Some(value) => seed.deserialize(ValueDeserializer { value }).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config {
+        name: String,
+        version: String,
+        port: u16,
+        mask: u32,
+        ratio: (i64, i64),
+        debug: bool,
+        fallback: Option<String>,
+        timeout: Option<u32>,
+        tags: Vec<String>,
+        mode: Mode,
+        title: String,
+        server: Vec<Server>,
+        limits: Limits,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Mode {
+        Fast,
+        Safe,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Server {
+        #[serde(rename = "$id")]
+        id: String,
+        #[serde(rename = "$classes", default)]
+        classes: Vec<String>,
+        host: String,
+        #[serde(default)]
+        weight: f64,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Limits {
+        #[serde(rename = "$text")]
+        note: String,
+        cpu: f64,
+    }
+
+    const CONFIG: &str = "\
+|config :name \"my \\\"app\\\"\" :version 1.0 :port 0x1F90 :mask 0b1111
+  :ratio 3/4r
+  :fallback nil
+  :tags [web api]
+  :debug
+  |mode fast
+  |title Hello |{em there}
+  |server[a].primary :host db1 :weight 0.5
+  |server[b] :host db2
+  |limits :cpu 2
+    Soft limits
+    apply here.
+";
+
+    #[test]
+    fn test_config() {
+        let config: Config = from_str(CONFIG).unwrap();
+        assert_eq!(config.name, "my \"app\"");
+        assert_eq!(config.version, "1.0");
+        assert_eq!((config.port, config.mask), (8080, 15));
+        assert_eq!(config.ratio, (3, 4));
+        assert!(config.debug);
+        assert_eq!((config.fallback, config.timeout), (None, None));
+        assert_eq!(config.tags, ["web", "api"]);
+        assert_eq!(config.mode, Mode::Fast);
+        assert_eq!(config.title, "Hello there");
+        assert_eq!(config.server.len(), 2);
+        assert_eq!(config.server[0].classes, ["primary"]);
+        assert_eq!((config.server[1].id.as_str(), config.server[1].weight), ("b", 0.0));
+        assert_eq!(config.limits, Limits { note: "Soft limits\napply here.".into(), cpu: 2.0 });
+    }
+
+    #[test]
+    fn test_borrowed_and_maps() {
+        #[derive(Deserialize)]
+        struct Entry<'a> {
+            name: &'a str,
+        }
+        let entry: Entry = from_slice(b"|entry :name plain\n").unwrap();
+        assert_eq!(entry.name, "plain");
+
+        let map: HashMap<String, i64> = from_slice(b"|limits :a 1 :b -2 :c 0o10\n").unwrap();
+        assert_eq!(map, HashMap::from([("a".into(), 1), ("b".into(), -2), ("c".into(), 8)]));
+
+        // Several top-level elements: the document is the map
+        let groups: HashMap<String, Vec<HashMap<String, String>>> =
+            from_slice(b"|a :x 1\n|a :x 2\n|b :y 3\n").unwrap();
+        assert_eq!(groups["a"].len(), 2);
+        assert_eq!(groups["b"][0]["y"], "3");
+    }
+
+    #[test]
+    fn test_enums() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        #[serde(rename_all = "lowercase")]
+        enum Shape {
+            Point,
+            Circle(f64),
+            Rect { w: u32, h: u32 },
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct Scene {
+            attr: Shape,
+            text: Shape,
+            unit: Shape,
+            newtype: Shape,
+            fields: Shape,
+            many: Vec<Shape>,
+        }
+
+        let input = b"\
+|scene :attr point
+  |text point
+  |unit
+    |point
+  |newtype
+    |circle 2.5
+  |fields
+    |rect :w 3 :h 4
+  |many
+    |circle 1
+  |many
+    |rect :w 1 :h 2
+";
+        let scene: Scene = from_slice(input).unwrap();
+        assert_eq!((scene.attr, scene.text, scene.unit), (Shape::Point, Shape::Point, Shape::Point));
+        assert_eq!(scene.newtype, Shape::Circle(2.5));
+        assert_eq!(scene.fields, Shape::Rect { w: 3, h: 4 });
+        assert_eq!(scene.many, [Shape::Circle(1.0), Shape::Rect { w: 1, h: 2 }]);
+
+        assert!(from_slice::<Shape>(b"|shape hexagon\n").is_err());
+        assert!(from_slice::<Shape>(b"|shape\n  |point\n  |point\n").is_err());
+    }
+
+    #[test]
+    fn test_options_and_nil() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Opts {
+            set: Option<u32>,
+            nil: Option<u32>,
+            null: Option<String>,
+            missing: Option<u32>,
+            element: Option<u32>,
+            unit: (),
+        }
+
+        let opts: Opts = from_slice(b"|opts :set 5 :nil nil :null null :unit nil\n  |element 7\n").unwrap();
+        assert_eq!(
+            opts,
+            Opts { set: Some(5), nil: None, null: None, missing: None, element: Some(7), unit: () }
+        );
+
+        // `nil` is not a number
+        assert!(from_slice::<HashMap<String, u32>>(b"|m :n nil\n").is_err());
+    }
+
+    #[test]
+    fn test_borrowed_and_escaped_strings() {
+        #[derive(Deserialize)]
+        struct Texts<'a> {
+            #[serde(borrow)]
+            bare: Cow<'a, str>,
+            #[serde(borrow)]
+            quoted: Cow<'a, str>,
+            #[serde(borrow)]
+            escaped: Cow<'a, str>,
+        }
+
+        let texts: Texts = from_slice(b"|t :bare plain :quoted \"two words\" :escaped \"say \\\"hi\\\"\"\n").unwrap();
+        assert!(matches!(texts.bare, Cow::Borrowed("plain")));
+        assert!(matches!(texts.quoted, Cow::Borrowed("two words")));
+        assert!(matches!(texts.escaped, Cow::Owned(ref s) if s == "say \"hi\""));
+
+        // Escaped text cannot be borrowed as `&str`
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Borrowed<'a> {
+            name: &'a str,
+        }
+        assert!(from_slice::<Borrowed>(b"|t :name \"a\\\"b\"\n").is_err());
+    }
+
+    #[test]
+    fn test_rationals_and_complex() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Numbers {
+            r_text: String,
+            r_pair: (i64, i64),
+            r_vec: Vec<i64>,
+            c_text: String,
+            c_pair: (f64, f64),
+            c_imaginary: (f64, f64),
+        }
+
+        let input = b"|n :r_text -3/4r :r_pair -3/4r :r_vec 1/2r :c_text 3+4i :c_pair 1.5-2e3i :c_imaginary 2i\n";
+        let numbers: Numbers = from_slice(input).unwrap();
+        assert_eq!(
+            numbers,
+            Numbers {
+                r_text: "-3/4r".into(),
+                r_pair: (-3, 4),
+                r_vec: vec![1, 2],
+                c_text: "3+4i".into(),
+                c_pair: (1.5, -2000.0),
+                c_imaginary: (0.0, 2.0),
+            }
+        );
+
+        // A rational is not a float, and its parts must fit
+        assert!(from_slice::<HashMap<String, f64>>(b"|n :r 1/2r\n").is_err());
+        assert!(from_slice::<HashMap<String, (i64, i64)>>(b"|n :r 1/0r\n").is_err());
+    }
+
+    #[test]
+    fn test_errors_carry_spans() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Port {
+            port: u8,
+        }
+        let input = "|svc\n  :port 300\n";
+        let err = from_str::<Port>(input).unwrap_err();
+        let span = err.span.unwrap();
+        assert_eq!(&input[span.range()], "300");
+        assert!(err.message.contains("300"), "{}", err);
+
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Single {
+            item: HashMap<String, String>,
+        }
+        let input = "|list\n  |item :a 1\n  |item :a 2\n";
+        let err = from_str::<Single>(input).unwrap_err();
+        assert_eq!(&input[err.span.unwrap().range()], "|item :a 2");
+
+        let err = from_str::<Port>("|svc :port \"unclosed\n").unwrap_err();
+        assert!(err.span.is_some());
+    }
+}
@@ -0,0 +1,203 @@
+//! Diagnostics reported by parsing and resolution passes.
+
+use std::fmt;
+
+use crate::parser::ParseErrorCode;
+use crate::span::Span;
+
+/// How serious a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// Stable identifier for the kind of problem a diagnostic reports.
+///
+/// Displays as the code's name, e.g. `NoTabs` or `UnknownMixin`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Code {
+    /// An `Event::Error` from the parser.
+    Parse(ParseErrorCode),
+    /// Content lines indented less than the first one.
+    InconsistentIndentation,
+    /// Any other `Event::Warning` from the parser.
+    ParseWarning,
+    UnknownMixin,
+    DuplicateMixin,
+    DanglingReference,
+    DuplicateId,
+    CyclicReference,
+    /// Reference expansion would copy more nodes than allowed.
+    ExpansionLimit,
+    UnmatchedBranch,
+    MissingArgument,
+    MalformedFor,
+    MalformedLet,
+}
+
+impl Code {
+    /// The code's name, e.g. `NoTabs`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Code::Parse(code) => code.name(),
+            Code::InconsistentIndentation => "InconsistentIndentation",
+            Code::ParseWarning => "ParseWarning",
+            Code::UnknownMixin => "UnknownMixin",
+            Code::DuplicateMixin => "DuplicateMixin",
+            Code::DanglingReference => "DanglingReference",
+            Code::DuplicateId => "DuplicateId",
+            Code::CyclicReference => "CyclicReference",
+            Code::ExpansionLimit => "ExpansionLimit",
+            Code::UnmatchedBranch => "UnmatchedBranch",
+            Code::MissingArgument => "MissingArgument",
+            Code::MalformedFor => "MalformedFor",
+            Code::MalformedLet => "MalformedLet",
+        }
+    }
+
+    /// A hint on how to fix the problem, where there is a useful one.
+    pub fn help(&self) -> Option<&'static str> {
+        match self {
+            Code::Parse(code) => Some(code.help()),
+            Code::InconsistentIndentation => Some("indent each line of content at least as far as the first one"),
+            Code::UnknownMixin => Some("define the mixin as a class-only element (`|.name`); classes used only for styling can ignore this"),
+            Code::DuplicateMixin => Some("rename or remove the later definition; the first one is used"),
+            Code::DanglingReference => Some("add an element with this id, or correct the reference"),
+            Code::DuplicateId => Some("give each element a unique id; references resolve to the first"),
+            Code::ExpansionLimit => Some("raise the limit with `Document::expand_references_with_max_nodes`, or reference smaller elements"),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseErrorCode> for Code {
+    fn from(code: ParseErrorCode) -> Self {
+        Code::Parse(code)
+    }
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A problem found in a document, located by source span.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Code,
+    pub span: Span,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(code: Code, span: Span, message: impl Into<String>) -> Self {
+        Diagnostic { severity: Severity::Error, code, span, message: message.into() }
+    }
+
+    pub fn warning(code: Code, span: Span, message: impl Into<String>) -> Self {
+        Diagnostic { severity: Severity::Warning, code, span, message: message.into() }
+    }
+
+    pub fn info(code: Code, span: Span, message: impl Into<String>) -> Self {
+        Diagnostic { severity: Severity::Info, code, span, message: message.into() }
+    }
+
+    /// Diagnostic for an `Event::Error` from the parser.
+    pub(crate) fn parse_error(code: ParseErrorCode, span: std::ops::Range<usize>) -> Self {
+        Diagnostic::error(Code::Parse(code), Span::new(span.start, span.end), code.message())
+    }
+
+    /// Diagnostic for an `Event::Warning` from the parser, which carries
+    /// only a message.
+    pub(crate) fn parse_warning(content: &[u8], span: std::ops::Range<usize>) -> Self {
+        let code = match content {
+            INCONSISTENT_INDENTATION => Code::InconsistentIndentation,
+            _ => Code::ParseWarning,
+        };
+        let message = String::from_utf8_lossy(content).to_lowercase();
+        Diagnostic::warning(code, Span::new(span.start, span.end), message)
+    }
+
+    /// A hint on how to fix the problem, for the codes this crate reports.
+    pub fn help(&self) -> Option<&'static str> {
+        self.code.help()
+    }
+}
+
+/// The message of the parser's only warning; the grammar gives warnings no
+/// code of their own.
+const INCONSISTENT_INDENTATION: &[u8] = b"Inconsistent indentation";
+
+impl ParseErrorCode {
+    /// Stable identifier used as the diagnostic code, e.g. `NoTabs`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ParseErrorCode::UnexpectedEof => "UnexpectedEof",
+            ParseErrorCode::UnexpectedChar => "UnexpectedChar",
+            ParseErrorCode::Unclosed => "Unclosed",
+            ParseErrorCode::UnclosedStringValue => "UnclosedStringValue",
+            ParseErrorCode::UnclosedArray => "UnclosedArray",
+            ParseErrorCode::UnclosedFreeform => "UnclosedFreeform",
+            ParseErrorCode::UnclosedText => "UnclosedText",
+            ParseErrorCode::NoTabs => "NoTabs",
+            ParseErrorCode::UnclosedInterpolation => "UnclosedInterpolation",
+            ParseErrorCode::BufferOverflow => "BufferOverflow",
+        }
+    }
+
+    /// Human-readable description of the error.
+    pub fn message(&self) -> &'static str {
+        match self {
+            ParseErrorCode::UnexpectedEof => "unexpected end of input",
+            ParseErrorCode::UnexpectedChar => "unexpected character",
+            ParseErrorCode::Unclosed => "unclosed quote",
+            ParseErrorCode::UnclosedStringValue => "unclosed string value: missing closing quote",
+            ParseErrorCode::UnclosedArray => "unclosed array: missing `]`",
+            ParseErrorCode::UnclosedFreeform => "unclosed freeform block: missing closing ```",
+            ParseErrorCode::UnclosedText => "unclosed embedded element: missing `}`",
+            ParseErrorCode::NoTabs => "tabs are not allowed for indentation",
+            ParseErrorCode::UnclosedInterpolation => "unclosed interpolation: missing `}}`",
+            ParseErrorCode::BufferOverflow => "top-level construct exceeds the streaming buffer limit",
+        }
+    }
+
+    /// A hint on how to fix the error.
+    pub fn help(&self) -> &'static str {
+        match self {
+            ParseErrorCode::UnexpectedEof => "the input ends inside a construct; check for a missing closing delimiter",
+            ParseErrorCode::UnexpectedChar => "quote the value if this character is meant literally",
+            ParseErrorCode::Unclosed => "add the closing quote",
+            ParseErrorCode::UnclosedStringValue => "add the closing `\"`; escape quotes inside the string as `\\\"`",
+            ParseErrorCode::UnclosedArray => "close the array with `]`",
+            ParseErrorCode::UnclosedFreeform => "close the block with a ``` line",
+            ParseErrorCode::UnclosedText => "close the embedded element with `}`",
+            ParseErrorCode::NoTabs => "indent with spaces instead of tabs",
+            ParseErrorCode::UnclosedInterpolation => "close the interpolation with `}}`",
+            ParseErrorCode::BufferOverflow => "raise the limit with `StreamingParser::with_max_buffer`, or split the construct into smaller top-level ones",
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}]: {} at {}..{}",
+            self.severity, self.code, self.message, self.span.start, self.span.end
+        )
+    }
+}
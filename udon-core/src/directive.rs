@@ -0,0 +1,347 @@
+//! Structured view of block directives.
+//!
+//! The parser gives a block directive a name and keeps the rest of its
+//! line as a leading `Text` child (the argument); the remaining children
+//! are its body. [`Node::directive_blocks`] reads a node's children with
+//! the control-flow directives typed:
+//!
+//! - `!if cond` / `!unless cond`, followed by any `!elif cond` and an
+//!   optional `!else` siblings, form one [`Conditional`].
+//! - `!for binding in expr` is a [`ForLoop`]; the binding may be a single
+//!   name or `key, value`.
+//! - `!let name = expr` is a [`Let`].
+//! - `!include path` is an [`Include`].
+//!
+//! Other nodes, including other directives (`!raw:lang`, custom ones), are
+//! passed through as [`Block::Node`]. Malformed forms are reported as
+//! diagnostics and passed through too. [`Document::check_directives`]
+//! collects the diagnostics for a whole document.
+//!
+//! Arguments are kept as source text with their spans; parsing them as
+//! expressions is up to the caller.
+//!
+//! # Example
+//!
+//! ```
+//! use udon_core::tree::Document;
+//! use udon_core::directive::Block;
+//!
+//! let doc = Document::parse(b"!if user.admin\n  |admin\n!else\n  |guest\n!for post in posts\n  |li\n").unwrap();
+//! let (blocks, diagnostics) = doc.root().directive_blocks();
+//! assert!(diagnostics.is_empty());
+//!
+//! let Block::Conditional(chain) = &blocks[0] else { panic!() };
+//! assert_eq!(chain.branches[0].condition.unwrap().text, "user.admin");
+//! assert!(chain.branches[1].condition.is_none());
+//!
+//! let Block::For(each) = &blocks[1] else { panic!() };
+//! assert_eq!((each.binding.text, each.iterable.text), ("post", "posts"));
+//! ```
+
+use crate::diagnostic::{Code, Diagnostic};
+use crate::reference::descendants;
+use crate::span::Span;
+use crate::tree::{Document, Node, NodeKind};
+
+/// Source text of a directive argument, or part of one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Argument<'doc> {
+    pub text: &'doc str,
+    pub span: Span,
+}
+
+/// A node's child, with control-flow directives typed.
+#[derive(Debug, Clone)]
+pub enum Block<'doc, 'a> {
+    /// Any other node, passed through as-is.
+    Node(Node<'doc, 'a>),
+    Conditional(Conditional<'doc, 'a>),
+    For(ForLoop<'doc, 'a>),
+    Let(Let<'doc, 'a>),
+    Include(Include<'doc, 'a>),
+}
+
+/// An `!if` / `!unless` with its `!elif` and `!else` siblings.
+#[derive(Debug, Clone)]
+pub struct Conditional<'doc, 'a> {
+    /// In source order; the first is `If` or `Unless`, only the last may
+    /// be `Else`.
+    pub branches: Vec<Branch<'doc, 'a>>,
+}
+
+/// One branch of a [`Conditional`].
+#[derive(Debug, Clone)]
+pub struct Branch<'doc, 'a> {
+    pub kind: BranchKind,
+    /// The condition; `None` for `!else`.
+    pub condition: Option<Argument<'doc>>,
+    /// The directive node; its body is [`Node::directive_body`].
+    pub node: Node<'doc, 'a>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BranchKind {
+    If,
+    /// `!unless cond`: taken when the condition is false.
+    Unless,
+    Elif,
+    Else,
+}
+
+/// `!for binding in iterable`.
+#[derive(Debug, Clone)]
+pub struct ForLoop<'doc, 'a> {
+    /// The loop variable, or `key, value`.
+    pub binding: Argument<'doc>,
+    pub iterable: Argument<'doc>,
+    pub node: Node<'doc, 'a>,
+}
+
+/// `!let name = value`.
+#[derive(Debug, Clone)]
+pub struct Let<'doc, 'a> {
+    pub name: Argument<'doc>,
+    pub value: Argument<'doc>,
+    pub node: Node<'doc, 'a>,
+}
+
+/// `!include path`.
+#[derive(Debug, Clone)]
+pub struct Include<'doc, 'a> {
+    pub path: Argument<'doc>,
+    pub node: Node<'doc, 'a>,
+}
+
+impl<'doc, 'a: 'doc> Node<'doc, 'a> {
+    /// For a directive, the rest of its first line: a leading `Text` child
+    /// that starts on the directive's line.
+    ///
+    /// `!else` takes no argument, so any text after it is body; this
+    /// returns `None` for it.
+    pub fn directive_argument(&self) -> Option<Argument<'doc>> {
+        let NodeKind::Directive { name, .. } = self.kind() else {
+            return None;
+        };
+        if name == "else" {
+            return None;
+        }
+        let first = self.first_child()?;
+        let NodeKind::Text(text) = first.kind() else {
+            return None;
+        };
+        let gap = first.source_since(*self, self.span().start);
+        if gap.is_some_and(|gap| gap.contains(&b'\n')) {
+            return None;
+        }
+        Some(Argument { text: text.as_ref(), span: first.span() })
+    }
+
+    /// For a directive, its children after the argument. For other nodes,
+    /// all children.
+    pub fn directive_body(&self) -> impl Iterator<Item = Node<'doc, 'a>> + 'doc {
+        let skip = usize::from(self.directive_argument().is_some());
+        self.children().skip(skip)
+    }
+
+    /// This node's children (for a directive, its body), with conditional
+    /// chains grouped and control-flow directives typed.
+    ///
+    /// Diagnostics (all errors):
+    /// - `UnmatchedBranch`: `!elif` / `!else` not following `!if`,
+    ///   `!unless` or `!elif`.
+    /// - `MissingArgument`: `!if`, `!unless`, `!elif`, `!for`, `!let` or
+    ///   `!include` with nothing after the name.
+    /// - `MalformedFor`: a `!for` argument that is not `binding in expr`.
+    /// - `MalformedLet`: a `!let` argument that is not `name = expr`.
+    ///
+    /// Malformed directives are returned as [`Block::Node`].
+    pub fn directive_blocks(&self) -> (Vec<Block<'doc, 'a>>, Vec<Diagnostic>) {
+        let mut blocks = Vec::new();
+        let mut diagnostics = Vec::new();
+
+        for child in self.directive_body() {
+            let NodeKind::Directive { name, .. } = child.kind() else {
+                blocks.push(Block::Node(child));
+                continue;
+            };
+            let argument = child.directive_argument();
+            let block = match name.as_ref() {
+                "if" | "unless" | "elif" => {
+                    let kind = match name.as_ref() {
+                        "if" => BranchKind::If,
+                        "unless" => BranchKind::Unless,
+                        _ => BranchKind::Elif,
+                    };
+                    match argument {
+                        Some(condition) => branch(&mut blocks, kind, Some(condition), child),
+                        None => Err(missing_argument(child, name)),
+                    }
+                }
+                "else" => branch(&mut blocks, BranchKind::Else, None, child),
+                "for" => argument.ok_or_else(|| missing_argument(child, name)).and_then(|arg| {
+                    let (binding, iterable) = split_for(arg).ok_or_else(|| {
+                        Diagnostic::error(Code::MalformedFor, arg.span, "expected `!for <binding> in <expr>`")
+                    })?;
+                    Ok(Some(Block::For(ForLoop { binding, iterable, node: child })))
+                }),
+                "let" => argument.ok_or_else(|| missing_argument(child, name)).and_then(|arg| {
+                    let (name, value) = split_let(arg).ok_or_else(|| {
+                        Diagnostic::error(Code::MalformedLet, arg.span, "expected `!let <name> = <expr>`")
+                    })?;
+                    Ok(Some(Block::Let(Let { name, value, node: child })))
+                }),
+                "include" => argument
+                    .map(|path| Some(Block::Include(Include { path, node: child })))
+                    .ok_or_else(|| missing_argument(child, name)),
+                _ => Ok(Some(Block::Node(child))),
+            };
+            match block {
+                Ok(Some(block)) => blocks.push(block),
+                // Joined an existing chain
+                Ok(None) => {}
+                Err(diagnostic) => {
+                    diagnostics.push(diagnostic);
+                    blocks.push(Block::Node(child));
+                }
+            }
+        }
+        (blocks, diagnostics)
+    }
+}
+
+impl<'a> Document<'a> {
+    /// Report malformed directives anywhere in the document; see
+    /// [`Node::directive_blocks`] for the codes.
+    pub fn check_directives(&self) -> Vec<Diagnostic> {
+        std::iter::once(self.root())
+            .chain(descendants(self.root()))
+            .filter(|node| node.first_child().is_some())
+            .flat_map(|node| node.directive_blocks().1)
+            .collect()
+    }
+}
+
+/// Start a chain, or add a branch to the chain just before it.
+fn branch<'doc, 'a>(
+    blocks: &mut [Block<'doc, 'a>],
+    kind: BranchKind,
+    condition: Option<Argument<'doc>>,
+    node: Node<'doc, 'a>,
+) -> Result<Option<Block<'doc, 'a>>, Diagnostic> {
+    let new = Branch { kind, condition, node };
+    if matches!(kind, BranchKind::If | BranchKind::Unless) {
+        return Ok(Some(Block::Conditional(Conditional { branches: vec![new] })));
+    }
+    match blocks.last_mut() {
+        Some(Block::Conditional(chain)) if chain.branches.last().is_some_and(|b| b.kind != BranchKind::Else) => {
+            chain.branches.push(new);
+            Ok(None)
+        }
+        _ => {
+            let name = if kind == BranchKind::Else { "else" } else { "elif" };
+            Err(Diagnostic::error(
+                Code::UnmatchedBranch,
+                node.span(),
+                format!("`!{}` without a preceding `!if`", name),
+            ))
+        }
+    }
+}
+
+fn missing_argument(node: Node<'_, '_>, name: &str) -> Diagnostic {
+    Diagnostic::error(Code::MissingArgument, node.span(), format!("`!{}` needs an argument", name))
+}
+
+/// Part of an argument, with its span narrowed when the text maps to the
+/// source one-to-one.
+fn part<'doc>(arg: Argument<'doc>, text: &'doc str) -> Argument<'doc> {
+    let offset = text.as_ptr() as usize - arg.text.as_ptr() as usize;
+    let span = if arg.span.len() == arg.text.len() {
+        Span::new(arg.span.start as usize + offset, arg.span.start as usize + offset + text.len())
+    } else {
+        arg.span
+    };
+    Argument { text, span }
+}
+
+fn split_for(arg: Argument<'_>) -> Option<(Argument<'_>, Argument<'_>)> {
+    let text = arg.text;
+    let at = text.find(" in ")?;
+    let binding = text[..at].trim();
+    let iterable = text[at + 4..].trim();
+    let names_ok = binding.split(',').all(|name| is_identifier(name.trim()));
+    if !names_ok || iterable.is_empty() {
+        return None;
+    }
+    Some((part(arg, binding), part(arg, iterable)))
+}
+
+fn split_let(arg: Argument<'_>) -> Option<(Argument<'_>, Argument<'_>)> {
+    let (name, value) = arg.text.split_once('=')?;
+    let (name, value) = (name.trim(), value.trim());
+    if !is_identifier(name) || value.is_empty() || value.starts_with('=') {
+        return None;
+    }
+    Some((part(arg, name), part(arg, value)))
+}
+
+fn is_identifier(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks() {
+        let source = b"!if user.admin\n  |a hi\n!elif y > 2\n  |b\n!else\n  |c\n!for key, value in items.all\n  |li !{{value}}\n!let total = a + b\n!include partials/nav\n!unless z\n  ok\n!raw:sh\n  ls\n";
+        let doc = Document::parse(source).unwrap();
+        let (blocks, diagnostics) = doc.root().directive_blocks();
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        assert_eq!(blocks.len(), 6);
+
+        let Block::Conditional(chain) = &blocks[0] else { panic!("{:?}", blocks[0]) };
+        let kinds: Vec<_> = chain.branches.iter().map(|b| b.kind).collect();
+        assert_eq!(kinds, [BranchKind::If, BranchKind::Elif, BranchKind::Else]);
+        let condition = chain.branches[1].condition.unwrap();
+        assert_eq!(condition.text, "y > 2");
+        assert_eq!(doc.span_text(condition.span), "y > 2");
+        let body: Vec<_> = chain.branches[0].node.directive_body().collect();
+        assert_eq!(body.len(), 1);
+        assert_eq!(body[0].as_element().unwrap().name(), "a");
+
+        let Block::For(each) = &blocks[1] else { panic!("{:?}", blocks[1]) };
+        assert_eq!(each.binding.text, "key, value");
+        assert_eq!(doc.span_text(each.iterable.span), "items.all");
+
+        let Block::Let(binding) = &blocks[2] else { panic!("{:?}", blocks[2]) };
+        assert_eq!((binding.name.text, binding.value.text), ("total", "a + b"));
+        assert_eq!(doc.span_text(binding.value.span), "a + b");
+
+        let Block::Include(include) = &blocks[3] else { panic!("{:?}", blocks[3]) };
+        assert_eq!(include.path.text, "partials/nav");
+
+        let Block::Conditional(unless) = &blocks[4] else { panic!("{:?}", blocks[4]) };
+        assert_eq!(unless.branches[0].kind, BranchKind::Unless);
+        assert_eq!(unless.branches[0].node.directive_body().next().unwrap().text_content(), Some("ok"));
+
+        assert!(matches!(&blocks[5], Block::Node(node) if matches!(node.kind(), NodeKind::Directive { name, .. } if name == "raw")));
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let source = b"!else\n  |a\n|p\n  !if x\n    |b\n  |c\n  !elif y\n!for item of items\n!let = 3\n!if\n!include\n!if a\n!else\n!else\n";
+        let doc = Document::parse(source).unwrap();
+        let diagnostics = doc.check_directives();
+        let codes: Vec<_> = diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(
+            codes,
+            [Code::UnmatchedBranch, Code::MalformedFor, Code::MalformedLet, Code::MissingArgument, Code::MissingArgument, Code::UnmatchedBranch, Code::UnmatchedBranch]
+        );
+        assert_eq!(diagnostics[0].message, "`!else` without a preceding `!if`");
+        assert_eq!(doc.span_text(diagnostics[1].span), "item of items");
+        assert!(diagnostics.iter().all(|d| d.severity == crate::diagnostic::Severity::Error));
+    }
+}
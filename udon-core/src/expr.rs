@@ -0,0 +1,638 @@
+//! Expressions in interpolations and directive arguments.
+//!
+//! `!{{user.name | truncate 20}}`, `!if count > 0 and not hidden` and
+//! `!for post in posts | where "draft" false` all share one grammar, from
+//! loosest to tightest binding:
+//!
+//! | Form | Example |
+//! |------|---------|
+//! | `a or b` | `draft or hidden` |
+//! | `a and b` | `user and user.admin` |
+//! | `not a` | `not posts` |
+//! | `a == b`, `!=`, `<`, `<=`, `>`, `>=` | `posts \| size > 0` |
+//! | `a \| filter arg ...` | `title \| truncate 20 "..."` |
+//! | `a.field`, `a.0`, `a[expr]` | `items[0].name`, `map["a key"]` |
+//! | literal, `[a b c]`, `(a)`, name | `"text"`, `42`, `1/3r`, `2024-01-05`, `true`, `null` |
+//!
+//! Literals use UDON value syntax and parse to the same [`Value`] as an
+//! attribute value would. Filter arguments are separated by whitespace, so
+//! an argument's `.field` and `[index]` must follow it directly.
+//!
+//! Spans point into the original document when the expression is parsed
+//! with the offset of its text, as [`Argument::parse_expr`] and
+//! [`Node::interpolation_expr`] do.
+//!
+//! Nesting is limited to [`MAX_DEPTH`] levels (or the limit given to
+//! [`Expr::parse_with_max_depth`]), counting open brackets and
+//! `not`s as well as the height of the parsed tree (each operator, filter,
+//! field or index is a level), so that parsing, evaluating and dropping an
+//! expression cannot overflow the stack.
+//!
+//! # Example
+//!
+//! ```
+//! use udon_core::expr::{BinaryOp, Expr, ExprKind};
+//!
+//! let expr = Expr::parse("items[0].price >= 10 and not sold_out", 0).unwrap();
+//! let ExprKind::Binary { op: BinaryOp::And, left, .. } = &expr.kind else { panic!() };
+//! assert_eq!(left.span.start..left.span.end, 0..20);
+//!
+//! let err = Expr::parse("name | ", 100).unwrap_err();
+//! assert_eq!((err.message.as_str(), err.span.start), ("expected filter name", 107));
+//! ```
+
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::directive::Argument;
+use crate::span::Span;
+use crate::tree::{Document, Node, NodeKind, Value};
+
+/// Maximum nesting of an expression; see the [module docs](self).
+pub const MAX_DEPTH: usize = 64;
+
+/// Error returned for a malformed expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExprError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}..{}", self.message, self.span.start, self.span.end)
+    }
+}
+
+impl std::error::Error for ExprError {}
+
+/// A parsed expression.
+#[derive(Debug, Clone)]
+pub struct Expr<'a> {
+    pub kind: ExprKind<'a>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExprKind<'a> {
+    /// A name looked up in the context.
+    Variable(&'a str),
+    /// A literal, as it would parse as an attribute value.
+    Literal(Value<'a>),
+    /// `[a b c]`; items may also be separated by commas.
+    List(Vec<Expr<'a>>),
+    /// `base.name`.
+    Field { base: Box<Expr<'a>>, name: &'a str },
+    /// `base[index]`, or `base.0` with an integer index.
+    Index { base: Box<Expr<'a>>, index: Box<Expr<'a>> },
+    /// `not operand`.
+    Not(Box<Expr<'a>>),
+    Binary { op: BinaryOp, left: Box<Expr<'a>>, right: Box<Expr<'a>> },
+    /// `input | name args...`.
+    Filter { input: Box<Expr<'a>>, name: &'a str, name_span: Span, args: Vec<Expr<'a>> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::And => "and",
+            BinaryOp::Or => "or",
+        }
+    }
+}
+
+impl<'a> Expr<'a> {
+    /// Parse an expression. `offset` is where `input` starts in the
+    /// document, and is added to every span.
+    pub fn parse(input: &'a str, offset: usize) -> Result<Self, ExprError> {
+        Self::parse_with_max_depth(input, offset, MAX_DEPTH)
+    }
+
+    /// Parse an expression nested at most `max_depth` levels deep.
+    pub fn parse_with_max_depth(input: &'a str, offset: usize, max_depth: usize) -> Result<Self, ExprError> {
+        ExprParser { input, pos: 0, offset, max_depth, depth: 0, height: 0 }.parse()
+    }
+}
+
+impl<'doc> Argument<'doc> {
+    /// Parse the argument as an expression, with spans in the document.
+    pub fn parse_expr(&self) -> Result<Expr<'doc>, ExprError> {
+        Expr::parse(self.text, self.span.start as usize)
+    }
+}
+
+impl<'doc, 'a: 'doc> Node<'doc, 'a> {
+    /// For an interpolation, its parsed expression, with spans in the
+    /// document.
+    pub fn interpolation_expr(&self) -> Option<Result<Expr<'doc>, ExprError>> {
+        self.interpolation_source().map(|(text, start)| Expr::parse(text, start))
+    }
+
+    /// For an interpolation, its expression text and where that starts in
+    /// the document.
+    pub(crate) fn interpolation_source(&self) -> Option<(&'doc str, usize)> {
+        let NodeKind::Interpolation(text) = self.kind() else {
+            return None;
+        };
+        let span = self.span();
+        let source = self.source().get(span.start as usize..span.end as usize).unwrap_or_default();
+        Some((text, span.start as usize + find(source, text.as_bytes()).unwrap_or(0)))
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len().max(1)).position(|w| w == needle)
+}
+
+// ============================================================================
+// Parsing
+// ============================================================================
+
+/// Characters that end a literal or name.
+const DELIMITERS: &[char] = &['(', ')', '[', ']', '|', ',', '=', '!', '<', '>', '"', '\''];
+
+struct ExprParser<'a> {
+    input: &'a str,
+    pos: usize,
+    offset: usize,
+    max_depth: usize,
+    /// Brackets and `not`s open around the current position.
+    depth: usize,
+    /// Height of the expression parsed last.
+    height: usize,
+}
+
+impl<'a> ExprParser<'a> {
+    fn parse(mut self) -> Result<Expr<'a>, ExprError> {
+        self.skip_ws();
+        if self.peek().is_none() {
+            return Err(self.error("expected expression"));
+        }
+        let expr = self.or()?;
+        self.skip_ws();
+        match self.peek() {
+            None => Ok(expr),
+            Some(c) => Err(self.error(format!("unexpected '{}'", c))),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr<'a>, ExprError> {
+        let mut left = self.and()?;
+        while self.keyword("or") {
+            let height = self.height;
+            let right = self.and()?;
+            self.grow(height.max(self.height))?;
+            left = binary(BinaryOp::Or, left, right);
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr<'a>, ExprError> {
+        let mut left = self.not()?;
+        while self.keyword("and") {
+            let height = self.height;
+            let right = self.not()?;
+            self.grow(height.max(self.height))?;
+            left = binary(BinaryOp::And, left, right);
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr<'a>, ExprError> {
+        self.skip_ws();
+        let start = self.pos;
+        if self.keyword("not") {
+            let operand = self.nested(start, Self::not)?;
+            self.grow(self.height)?;
+            let span = Span::new(self.offset + start, operand.span.end as usize);
+            return Ok(Expr { kind: ExprKind::Not(Box::new(operand)), span });
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr<'a>, ExprError> {
+        let left = self.pipeline()?;
+        self.skip_ws();
+        let rest = &self.input[self.pos..];
+        let op = [
+            ("==", BinaryOp::Eq),
+            ("!=", BinaryOp::Ne),
+            ("<=", BinaryOp::Le),
+            (">=", BinaryOp::Ge),
+            ("<", BinaryOp::Lt),
+            (">", BinaryOp::Gt),
+        ]
+        .into_iter()
+        .find(|(text, _)| rest.starts_with(text));
+        let Some((text, op)) = op else {
+            return Ok(left);
+        };
+        self.pos += text.len();
+        let height = self.height;
+        let right = self.pipeline()?;
+        self.grow(height.max(self.height))?;
+        Ok(binary(op, left, right))
+    }
+
+    fn pipeline(&mut self) -> Result<Expr<'a>, ExprError> {
+        let mut input = self.postfix()?;
+        loop {
+            self.skip_ws();
+            if !self.eat('|') {
+                return Ok(input);
+            }
+            self.skip_ws();
+            let name_start = self.pos;
+            let name = self.name().ok_or_else(|| self.error("expected filter name"))?;
+            let name_span = self.span_from(name_start);
+            let mut height = self.height;
+            let mut args = Vec::new();
+            loop {
+                self.skip_ws();
+                if !self.at_operand() {
+                    break;
+                }
+                args.push(self.postfix()?);
+                height = height.max(self.height);
+            }
+            self.grow(height)?;
+            let end = args.last().map_or(name_span.end, |arg| arg.span.end);
+            let span = Span::new(input.span.start as usize, end as usize);
+            input = Expr { kind: ExprKind::Filter { input: Box::new(input), name, name_span, args }, span };
+        }
+    }
+
+    /// Whether the next token can start an operand (a filter argument).
+    fn at_operand(&self) -> bool {
+        let rest = &self.input[self.pos..];
+        match rest.chars().next() {
+            Some('"' | '\'' | '(' | '[') => true,
+            Some(c) if c.is_alphanumeric() || c == '_' || c == '+' || c == '-' => {
+                let word = &rest[..rest.find(|c: char| c.is_whitespace()).unwrap_or(rest.len())];
+                !matches!(word, "and" | "or" | "not")
+            }
+            _ => false,
+        }
+    }
+
+    fn postfix(&mut self) -> Result<Expr<'a>, ExprError> {
+        self.skip_ws();
+        let mut base = self.primary()?;
+        loop {
+            let start = base.span.start;
+            if self.eat('.') {
+                let segment_start = self.pos;
+                let rest = &self.input[self.pos..];
+                let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+                let kind = if digits > 0 {
+                    self.pos += digits;
+                    let text = &self.input[segment_start..self.pos];
+                    let index = Expr {
+                        kind: ExprKind::Literal(Value::Integer(Cow::Borrowed(text))),
+                        span: self.span_from(segment_start),
+                    };
+                    ExprKind::Index { base: Box::new(base), index: Box::new(index) }
+                } else {
+                    let name = self.name().ok_or_else(|| self.error("expected field name after '.'"))?;
+                    ExprKind::Field { base: Box::new(base), name }
+                };
+                self.grow(self.height)?;
+                base = Expr { kind, span: Span::new(start as usize, self.offset + self.pos) };
+            } else if self.eat('[') {
+                let height = self.height;
+                let index = self.nested(self.pos - 1, Self::or)?;
+                self.skip_ws();
+                if !self.eat(']') {
+                    return Err(self.error("expected ']'"));
+                }
+                self.grow(height.max(self.height))?;
+                let span = Span::new(start as usize, self.offset + self.pos);
+                base = Expr { kind: ExprKind::Index { base: Box::new(base), index: Box::new(index) }, span };
+            } else {
+                return Ok(base);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr<'a>, ExprError> {
+        let start = self.pos;
+        let kind = match self.peek() {
+            None => return Err(self.error("expected expression")),
+            Some('(') => {
+                self.pos += 1;
+                let inner = self.nested(start, Self::or)?;
+                self.skip_ws();
+                if !self.eat(')') {
+                    return Err(self.error("expected ')'"));
+                }
+                return Ok(Expr { kind: inner.kind, span: self.span_from(start) });
+            }
+            Some('[') => {
+                self.pos += 1;
+                let mut height = 0;
+                let mut items = Vec::new();
+                loop {
+                    self.skip_ws();
+                    if self.eat(']') {
+                        break;
+                    }
+                    if self.peek().is_none() {
+                        return Err(self.error("expected ']'"));
+                    }
+                    items.push(self.nested(start, Self::or)?);
+                    height = height.max(self.height);
+                    self.skip_ws();
+                    self.eat(',');
+                }
+                self.grow(height)?;
+                return Ok(Expr { kind: ExprKind::List(items), span: self.span_from(start) });
+            }
+            Some(quote @ ('"' | '\'')) => {
+                self.pos += 1;
+                let inner = self.quoted(quote, start)?;
+                ExprKind::Literal(Value::String(Cow::Borrowed(inner)))
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let name = self.name().unwrap_or_default();
+                match name {
+                    "true" => ExprKind::Literal(Value::BoolTrue),
+                    "false" => ExprKind::Literal(Value::BoolFalse),
+                    "null" | "nil" => ExprKind::Literal(Value::Nil),
+                    "and" | "or" | "not" => {
+                        self.pos = start;
+                        return Err(self.error(format!("unexpected '{}'", name)));
+                    }
+                    _ => ExprKind::Variable(name),
+                }
+            }
+            Some(c) if c.is_ascii_digit() || c == '+' || c == '-' => {
+                let rest = &self.input[self.pos..];
+                let len = rest.find(|c: char| c.is_whitespace() || DELIMITERS.contains(&c)).unwrap_or(rest.len());
+                let text = &rest[..len];
+                self.pos += len;
+                let value = literal(text).ok_or_else(|| ExprError {
+                    message: format!("invalid literal `{}`", text),
+                    span: self.span_from(start),
+                })?;
+                ExprKind::Literal(value)
+            }
+            Some(c) => return Err(self.error(format!("unexpected '{}'", c))),
+        };
+        self.height = 1;
+        Ok(Expr { kind, span: self.span_from(start) })
+    }
+
+    /// Parse with `parse` inside the bracket or `not` at `start`, one
+    /// level deeper.
+    fn nested(&mut self, start: usize, parse: fn(&mut Self) -> Result<Expr<'a>, ExprError>) -> Result<Expr<'a>, ExprError> {
+        if self.depth == self.max_depth {
+            return Err(ExprError { span: self.span_from(start), ..self.too_deep() });
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Record the height of an expression built on subexpressions at most
+    /// `below` high.
+    fn grow(&mut self, below: usize) -> Result<(), ExprError> {
+        self.height = below + 1;
+        if self.height > self.max_depth {
+            return Err(self.too_deep());
+        }
+        Ok(())
+    }
+
+    fn too_deep(&self) -> ExprError {
+        self.error(format!("expression nested more than {} deep", self.max_depth))
+    }
+
+    /// A name: a letter or `_`, then letters, digits, `_` or `-`.
+    fn name(&mut self) -> Option<&'a str> {
+        let rest = &self.input[self.pos..];
+        if !rest.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+            return None;
+        }
+        let len = rest.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-')).unwrap_or(rest.len());
+        self.pos += len;
+        Some(&rest[..len])
+    }
+
+    /// Consume a keyword followed by a non-name character.
+    fn keyword(&mut self, word: &str) -> bool {
+        self.skip_ws();
+        let rest = &self.input[self.pos..];
+        let follows = rest[word.len().min(rest.len())..].chars().next();
+        if rest.starts_with(word) && !follows.is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+            self.pos += word.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The rest of a quoted string, after its opening quote, with escapes
+    /// left in place.
+    fn quoted(&mut self, quote: char, start: usize) -> Result<&'a str, ExprError> {
+        let rest = &self.input[self.pos..];
+        let mut escaped = false;
+        for (i, c) in rest.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                c if c == quote => {
+                    self.pos += i + 1;
+                    return Ok(&rest[..i]);
+                }
+                _ => {}
+            }
+        }
+        self.pos = self.input.len();
+        Err(ExprError { message: "unclosed quote".to_string(), span: self.span_from(start) })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_ws(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn span_from(&self, start: usize) -> Span {
+        Span::new(self.offset + start, self.offset + self.pos)
+    }
+
+    /// An error at the current position, covering the next character.
+    fn error(&self, message: impl Into<String>) -> ExprError {
+        let len = self.peek().map_or(0, char::len_utf8);
+        let at = self.offset + self.pos;
+        ExprError { message: message.into(), span: Span::new(at, at + len) }
+    }
+}
+
+fn binary<'a>(op: BinaryOp, left: Expr<'a>, right: Expr<'a>) -> Expr<'a> {
+    let span = Span::new(left.span.start as usize, right.span.end as usize);
+    Expr { kind: ExprKind::Binary { op, left: Box::new(left), right: Box::new(right) }, span }
+}
+
+/// Classify a numeric or temporal literal the way an attribute value would
+/// parse, keeping its text borrowed.
+fn literal(text: &str) -> Option<Value<'_>> {
+    let source = format!("|a :v {}\n", text);
+    let doc = Document::parse(source.as_bytes()).ok()?;
+    let attr = doc.root().first_child()?.as_element()?.attribute("v")?;
+    if attr.value_span.end - attr.value_span.start != text.len() as u32 {
+        return None;
+    }
+    let text = Cow::Borrowed(text);
+    Some(match attr.value {
+        Value::Integer(_) => Value::Integer(text),
+        Value::Float(_) => Value::Float(text),
+        Value::Rational(_) => Value::Rational(text),
+        Value::Complex(_) => Value::Complex(text),
+        Value::Date(_) => Value::Date(text),
+        Value::Time(_) => Value::Time(text),
+        Value::DateTime(_) => Value::DateTime(text),
+        Value::Duration(_) => Value::Duration(text),
+        Value::RelativeTime(_) => Value::RelativeTime(text),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Render an expression fully parenthesized, to check its structure.
+    fn show(expr: &Expr<'_>) -> String {
+        match &expr.kind {
+            ExprKind::Variable(name) => name.to_string(),
+            ExprKind::Literal(value) => format!("{:?}", value),
+            ExprKind::List(items) => format!("[{}]", items.iter().map(show).collect::<Vec<_>>().join(" ")),
+            ExprKind::Field { base, name } => format!("{}.{}", show(base), name),
+            ExprKind::Index { base, index } => format!("{}[{}]", show(base), show(index)),
+            ExprKind::Not(operand) => format!("(not {})", show(operand)),
+            ExprKind::Binary { op, left, right } => format!("({} {} {})", show(left), op.as_str(), show(right)),
+            ExprKind::Filter { input, name, args, .. } => {
+                let args: String = args.iter().map(|a| format!(" {}", show(a))).collect();
+                format!("({} | {}{})", show(input), name, args)
+            }
+        }
+    }
+
+    fn parsed(input: &str) -> String {
+        show(&Expr::parse(input, 0).unwrap())
+    }
+
+    #[test]
+    fn test_grammar() {
+        assert_eq!(parsed("user.profile.name"), "user.profile.name");
+        assert_eq!(parsed("items[0]"), "items[Integer(\"0\")]");
+        assert_eq!(parsed("posts.1.title"), "posts[Integer(\"1\")].title");
+        assert_eq!(parsed("map[key]['a b']"), "map[key][String(\"a b\")]");
+        assert_eq!(parsed("name | truncate 200"), "(name | truncate Integer(\"200\"))");
+        assert_eq!(
+            parsed("posts | where \"featured\" true | first"),
+            "((posts | where String(\"featured\") BoolTrue) | first)",
+        );
+        assert_eq!(parsed("posts | size > 0"), "((posts | size) > Integer(\"0\"))");
+        assert_eq!(
+            parsed("not a or b and c != nil"),
+            "((not a) or (b and (c != Nil)))",
+        );
+        assert_eq!(parsed("not (a or b)"), "(not (a or b))");
+        assert_eq!(parsed("tags | join ', '"), "(tags | join String(\", \"))");
+        assert_eq!(parsed("[1 2, x]"), "[Integer(\"1\") Integer(\"2\") x]");
+        assert_eq!(parsed("page-title"), "page-title");
+        assert_eq!(parsed("nothing"), "nothing");
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(parsed("-5"), "Integer(\"-5\")");
+        assert_eq!(parsed("0x1F"), "Integer(\"0x1F\")");
+        assert_eq!(parsed("1.5e3"), "Float(\"1.5e3\")");
+        assert_eq!(parsed("1/3r"), "Rational(\"1/3r\")");
+        assert_eq!(parsed("due < 2024-01-05"), "(due < Date(\"2024-01-05\"))");
+        assert_eq!(parsed("30s"), "Duration(\"30s\")");
+        assert_eq!(parsed("'it\\'s'"), "String(\"it\\\\'s\")");
+    }
+
+    #[test]
+    fn test_errors_and_spans() {
+        let err = |input: &str| {
+            let err = Expr::parse(input, 10).unwrap_err();
+            (err.message, err.span.start - 10, err.span.end - 10)
+        };
+        assert_eq!(err(""), ("expected expression".to_string(), 0, 0));
+        assert_eq!(err("a ="), ("unexpected '='".to_string(), 2, 3));
+        assert_eq!(err("items[0"), ("expected ']'".to_string(), 7, 7));
+        assert_eq!(err("x == 12abc"), ("invalid literal `12abc`".to_string(), 5, 10));
+        assert_eq!(err("\"open"), ("unclosed quote".to_string(), 0, 5));
+        assert_eq!(err("a and"), ("expected expression".to_string(), 5, 5));
+
+        // Nesting is limited, however deep the input goes
+        let too_deep = format!("expression nested more than {} deep", MAX_DEPTH);
+        let nested = |open: &str, close: &str, n: usize| format!("{}x{}", open.repeat(n), close.repeat(n));
+        assert!(Expr::parse(&nested("(", ")", MAX_DEPTH), 0).is_ok());
+        assert_eq!(err(&nested("(", ")", MAX_DEPTH + 1)), (too_deep.clone(), MAX_DEPTH as u32, MAX_DEPTH as u32 + 1));
+        for deep in [nested("(", "", 200_000), nested("[", "", 200_000), nested("not ", "", 200_000), nested("a[", "]", 200_000)] {
+            assert_eq!(Expr::parse(&deep, 0).unwrap_err().message, too_deep);
+        }
+        // ...and so is the height of a tree built without brackets
+        let chain = |op: &str, n: usize| format!("a{}", op.repeat(n));
+        assert!(Expr::parse(&chain(" or a", MAX_DEPTH - 1), 0).is_ok());
+        assert_eq!(Expr::parse(&chain(" or a", MAX_DEPTH), 0).unwrap_err().message, too_deep);
+        let err = Expr::parse_with_max_depth("a.b.c.d", 0, 3).unwrap_err();
+        assert_eq!((err.message.as_str(), err.span), ("expression nested more than 3 deep", Span::new(7, 7)));
+        for long in [chain(" and a", 200_000), chain(".b", 200_000), chain("[0]", 200_000), chain(" | f", 200_000)] {
+            assert_eq!(Expr::parse(&long, 0).unwrap_err().message, too_deep);
+        }
+
+        let expr = Expr::parse("a | f x", 10).unwrap();
+        let ExprKind::Filter { name_span, args, .. } = &expr.kind else { panic!() };
+        assert_eq!((expr.span, *name_span, args[0].span), (Span::new(10, 17), Span::new(14, 15), Span::new(16, 17)));
+
+        // Directive arguments and interpolations parse with document spans
+        let doc = Document::parse(b"|p\n  !if user.admin\n    |b Hi !{{ user.name | upcase }}\n").unwrap();
+        let directive = doc.root().first_child().unwrap().first_child().unwrap();
+        let expr = directive.directive_argument().unwrap().parse_expr().unwrap();
+        assert_eq!(doc.span_text(expr.span), "user.admin");
+        let b = directive.directive_body().next().unwrap();
+        let interpolation = b.children().nth(1).unwrap();
+        let expr = interpolation.interpolation_expr().unwrap().unwrap();
+        let ExprKind::Filter { input, .. } = &expr.kind else { panic!() };
+        assert_eq!(doc.span_text(input.span), "user.name");
+        assert!(doc.root().interpolation_expr().is_none());
+    }
+}
@@ -0,0 +1,395 @@
+//! HTML rendering for document-style UDON.
+//!
+//! Elements become tags: `|p.note[intro]` renders as
+//! `<p id="intro" class="note">`, and attributes become HTML attributes.
+//! Embedded elements (`|{em ...}`) render inline with the surrounding
+//! text, block elements start on their own line. Raw blocks (`!raw:lang`,
+//! `!:lang:` and ` ``` ` freeform blocks) become
+//! `<pre><code class="language-lang">`, with the code's common indentation
+//! removed. All text and attribute values are HTML-escaped.
+//!
+//! Attribute values render as their text; `true` renders as a bare boolean
+//! attribute, `false` and `null` omit the attribute, and arrays are joined
+//! with spaces. A `:class` attribute is appended to the element's classes.
+//! Class-only elements (`|.note`) render as `div`.
+//!
+//! Directives other than raw blocks, interpolations and references have no
+//! HTML form and are an error: expand them first (for references,
+//! [`Document::expand_references`]).
+//!
+//! # Example
+//!
+//! ```
+//! use udon_core::tree::Document;
+//! use udon_core::to_html;
+//!
+//! let doc = Document::parse(b"|article[intro]\n  |h1 Fish & Chips\n  Served |{em hot}.\n").unwrap();
+//! assert_eq!(
+//!     to_html(&doc).unwrap(),
+//!     "<article id=\"intro\">\n<h1>Fish &amp; Chips</h1>\nServed <em>hot</em>.\n</article>\n",
+//! );
+//! ```
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::span::Span;
+use crate::tree::{unescape, Document, Node, NodeKind, Value};
+use crate::writer::content_text;
+
+/// Error returned when a document cannot be rendered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HtmlError {
+    pub message: String,
+    /// Span of the offending node.
+    pub span: Span,
+}
+
+impl fmt::Display for HtmlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}..{}", self.message, self.span.start, self.span.end)
+    }
+}
+
+impl std::error::Error for HtmlError {}
+
+/// Render a document to HTML with default settings.
+pub fn to_html(doc: &Document<'_>) -> Result<String, HtmlError> {
+    HtmlRenderer::new().render(doc)
+}
+
+/// HTML elements accepted when unknown tags are rejected.
+const KNOWN_TAGS: &[&str] = &[
+    "a", "abbr", "address", "area", "article", "aside", "audio", "b", "base", "bdi", "bdo", "blockquote", "body",
+    "br", "button", "canvas", "caption", "cite", "code", "col", "colgroup", "data", "datalist", "dd", "del",
+    "details", "dfn", "dialog", "div", "dl", "dt", "em", "embed", "fieldset", "figcaption", "figure", "footer",
+    "form", "h1", "h2", "h3", "h4", "h5", "h6", "head", "header", "hgroup", "hr", "html", "i", "iframe", "img",
+    "input", "ins", "kbd", "label", "legend", "li", "link", "main", "map", "mark", "menu", "meta", "meter", "nav",
+    "noscript", "object", "ol", "optgroup", "option", "output", "p", "picture", "pre", "progress", "q", "rp",
+    "rt", "ruby", "s", "samp", "script", "search", "section", "select", "slot", "small", "source", "span",
+    "strong", "style", "sub", "summary", "sup", "table", "tbody", "td", "template", "textarea", "tfoot", "th",
+    "thead", "time", "title", "tr", "track", "u", "ul", "var", "video", "wbr",
+];
+
+/// Elements that have no closing tag.
+const VOID_TAGS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr",
+];
+
+/// Configurable HTML renderer.
+#[derive(Debug, Clone, Default)]
+pub struct HtmlRenderer {
+    comments: bool,
+    reject_unknown_tags: bool,
+    extra_tags: HashSet<String>,
+}
+
+impl HtmlRenderer {
+    /// Create a renderer that drops comments and accepts any tag name.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep comments as `<!-- ... -->` instead of dropping them.
+    pub fn comments(mut self, keep: bool) -> Self {
+        self.comments = keep;
+        self
+    }
+
+    /// Reject element names that are not standard HTML elements (or
+    /// allowed with [`allow_tag`](Self::allow_tag)).
+    pub fn reject_unknown_tags(mut self, reject: bool) -> Self {
+        self.reject_unknown_tags = reject;
+        self
+    }
+
+    /// Accept an extra tag name when unknown tags are rejected.
+    pub fn allow_tag(mut self, name: impl Into<String>) -> Self {
+        self.extra_tags.insert(name.into());
+        self
+    }
+
+    /// Render a whole document.
+    pub fn render(&self, doc: &Document<'_>) -> Result<String, HtmlError> {
+        let mut out = String::new();
+        self.render_node(doc.root(), &mut out)?;
+        Ok(out)
+    }
+
+    /// Render a single node (and its subtree).
+    pub fn render_node(&self, node: Node<'_, '_>, out: &mut String) -> Result<(), HtmlError> {
+        match node.kind() {
+            NodeKind::Document => {
+                self.render_children(node, out)?;
+                if !out.is_empty() && !out.ends_with('\n') {
+                    out.push('\n');
+                }
+                Ok(())
+            }
+            NodeKind::Element { name, id, classes, attrs, .. } => {
+                let tag = if name.is_empty() { "div" } else { name.as_ref() };
+                self.check_tag(tag, node)?;
+                out.push('<');
+                out.push_str(tag);
+                if let Some(id) = id {
+                    push_attr(out, "id", id);
+                }
+                let mut class = classes.join(" ");
+                if let Some(attr) = attrs.iter().find(|a| a.name == "class") {
+                    if !class.is_empty() {
+                        class.push(' ');
+                    }
+                    class.push_str(&value_text(&attr.value));
+                }
+                if !class.is_empty() {
+                    push_attr(out, "class", &class);
+                }
+                for attr in attrs {
+                    if attr.name == "class" || (attr.name == "id" && id.is_some()) {
+                        continue;
+                    }
+                    if !is_attr_name(&attr.name) {
+                        return Err(error(node, format!("invalid attribute name `{}`", attr.name)));
+                    }
+                    match &attr.value {
+                        Value::BoolFalse | Value::Nil => {}
+                        Value::BoolTrue => {
+                            out.push(' ');
+                            out.push_str(&attr.name);
+                        }
+                        value => push_attr(out, &attr.name, &value_text(value)),
+                    }
+                }
+                out.push('>');
+                if VOID_TAGS.contains(&tag) {
+                    if node.first_child().is_some() {
+                        return Err(error(node, format!("void element <{}> cannot have content", tag)));
+                    }
+                    return Ok(());
+                }
+                self.render_children(node, out)?;
+                out.push_str("</");
+                out.push_str(tag);
+                out.push('>');
+                Ok(())
+            }
+            NodeKind::Text(text) => {
+                push_escaped(out, text);
+                Ok(())
+            }
+            NodeKind::Comment(content) => {
+                if self.comments {
+                    // Padded and with no `--`, the text cannot close the comment early
+                    let text = content_text(node, content);
+                    out.push_str("<!-- ");
+                    let mut chars = text.chars().peekable();
+                    while let Some(c) = chars.next() {
+                        out.push(c);
+                        if c == '-' && chars.peek() == Some(&'-') {
+                            out.push(' ');
+                        }
+                    }
+                    out.push_str(" -->");
+                }
+                Ok(())
+            }
+            NodeKind::Directive { name, .. } if node.raw_block().is_none() => {
+                Err(error(node, format!("directive `!{}` has no HTML form", name)))
+            }
+            NodeKind::Raw { .. } | NodeKind::Directive { .. } => {
+                if let Some(block) = node.raw_block() {
+                    render_code(block.lang, &block.code, out);
+                }
+                Ok(())
+            }
+            NodeKind::Interpolation(expr) => Err(error(node, format!("interpolation `{}` has no HTML form", expr))),
+            NodeKind::Reference(id) => Err(error(node, format!("unexpanded reference to `{}`", id))),
+        }
+    }
+
+    /// Render children in order. Block-level children go on their own
+    /// lines; inline runs keep the line breaks of the source.
+    fn render_children(&self, node: Node<'_, '_>, out: &mut String) -> Result<(), HtmlError> {
+        let top = matches!(node.kind(), NodeKind::Document);
+        let mut prev: Option<(Node<'_, '_>, bool)> = None;
+        let mut multiline = false;
+        for child in node.children() {
+            if matches!(child.kind(), NodeKind::Comment(_)) && !self.comments {
+                continue;
+            }
+            let block = is_block(child);
+            let newline = match prev {
+                Some((prev, prev_block)) => prev_block || block || newline_between(prev, prev.span().end, child),
+                None => {
+                    // Content starting below the opening tag closes on its own line too
+                    multiline = !top && (block || newline_between(node, node.span().start, child));
+                    multiline
+                }
+            };
+            if newline {
+                out.push('\n');
+            }
+            self.render_node(child, out)?;
+            prev = Some((child, block));
+        }
+        if multiline || matches!(prev, Some((_, true))) && !top {
+            out.push('\n');
+        }
+        Ok(())
+    }
+
+    fn check_tag(&self, tag: &str, node: Node<'_, '_>) -> Result<(), HtmlError> {
+        let valid = tag.starts_with(|c: char| c.is_ascii_alphabetic())
+            && tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+        if !valid {
+            return Err(error(node, format!("`{}` is not a valid tag name", tag)));
+        }
+        if self.reject_unknown_tags && !KNOWN_TAGS.contains(&tag) && !self.extra_tags.contains(tag) {
+            return Err(error(node, format!("unknown tag <{}>", tag)));
+        }
+        Ok(())
+    }
+}
+
+fn error(node: Node<'_, '_>, message: String) -> HtmlError {
+    HtmlError { message, span: node.span() }
+}
+
+fn is_block(node: Node<'_, '_>) -> bool {
+    match node.kind() {
+        NodeKind::Element { embedded, .. } => !embedded,
+        NodeKind::Raw { .. } | NodeKind::Directive { .. } => true,
+        _ => false,
+    }
+}
+
+/// Whether a node starts on a later source line than `offset` in `from`.
+fn newline_between<'a>(from: Node<'_, 'a>, offset: u32, node: Node<'_, 'a>) -> bool {
+    node.source_since(from, offset).is_some_and(|gap| gap.contains(&b'\n'))
+}
+
+fn is_attr_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || matches!(c, '"' | '\'' | '>' | '<' | '/' | '=' | '&'))
+}
+
+/// Text of an attribute value.
+fn value_text(value: &Value<'_>) -> String {
+    match value {
+        Value::String(s) => unescape(s).into_owned(),
+        Value::Bare(s)
+        | Value::Integer(s)
+        | Value::Float(s)
+        | Value::Rational(s)
+        | Value::Complex(s)
+        | Value::Date(s)
+        | Value::Time(s)
+        | Value::DateTime(s)
+        | Value::Duration(s)
+        | Value::RelativeTime(s) => s.to_string(),
+        Value::BoolTrue => "true".into(),
+        Value::BoolFalse => "false".into(),
+        Value::Nil => String::new(),
+        Value::Array(items) => items.iter().map(value_text).collect::<Vec<_>>().join(" "),
+    }
+}
+
+fn render_code(lang: Option<&str>, code: &str, out: &mut String) {
+    out.push_str("<pre><code");
+    if let Some(lang) = lang.filter(|l| !l.is_empty()) {
+        push_attr(out, "class", &format!("language-{}", lang));
+    }
+    out.push('>');
+    push_escaped(out, code);
+    out.push_str("</code></pre>");
+}
+
+fn push_attr(out: &mut String, name: &str, value: &str) {
+    out.push(' ');
+    out.push_str(name);
+    out.push_str("=\"");
+    push_escaped(out, value);
+    out.push('"');
+}
+
+/// Escape text for element content and double-quoted attribute values.
+fn push_escaped(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(source: &str) -> String {
+        to_html(&Document::parse(source.as_bytes()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_elements_and_text() {
+        assert_eq!(
+            render("|section[getting-started].intro.wide\n  :level 2\n  :data-x \"a<b\"\n  |p Prose with |{em emphasis} and\n     |{a :href /doc :title \"Full docs\" the docs}.\n  |img :src x.png :alt \"\"\n"),
+            "<section id=\"getting-started\" class=\"intro wide\" level=\"2\" data-x=\"a&lt;b\">\n\
+             <p>Prose with <em>emphasis</em> and\n<a href=\"/doc\" title=\"Full docs\">the docs</a>.</p>\n\
+             <img src=\"x.png\" alt=\"\">\n\
+             </section>\n",
+        );
+        assert_eq!(
+            render("|input\n  :class big\n  :checked\n  :disabled false\n|.note Tom & \"Jerry\" <3\n"),
+            "<input class=\"big\" checked>\n<div class=\"note\">Tom &amp; &quot;Jerry&quot; &lt;3</div>\n",
+        );
+    }
+
+    #[test]
+    fn test_raw_blocks() {
+        assert_eq!(
+            render("|div\n  !raw:elixir\n    def x do\n      \"<y>\"\n\n    end\n  !:js:\n    if (a) {\n      b\n    }\n"),
+            "<div>\n<pre><code class=\"language-elixir\">def x do\n  &quot;&lt;y&gt;&quot;\n\nend</code></pre>\n\
+             <pre><code class=\"language-js\">if (a) {\n  b\n}</code></pre>\n</div>\n",
+        );
+        assert_eq!(
+            render("|div\n  ```\n  free <form>\n    indented\n  ```\n"),
+            "<div>\n<pre><code>free &lt;form&gt;\n  indented</code></pre>\n</div>\n",
+        );
+    }
+
+    #[test]
+    fn test_options_and_errors() {
+        let doc = Document::parse(b"|p Hi ;{note -- here}\n|widget x\n").unwrap();
+        assert_eq!(to_html(&doc).unwrap(), "<p>Hi </p>\n<widget>x</widget>\n");
+        assert_eq!(
+            HtmlRenderer::new().comments(true).render(&doc).unwrap(),
+            "<p>Hi <!-- note - - here --></p>\n<widget>x</widget>\n",
+        );
+        for (source, comment) in [
+            (&b"|p ;{x ---><script>alert(1)</script>}\n"[..], "<!-- x - - -><script>alert(1)</script> -->"),
+            (b"|p ;{><img src=x onerror=alert(1)>}\n", "<!-- ><img src=x onerror=alert(1)> -->"),
+            (b"|p ;{->x-}\n", "<!-- ->x- -->"),
+        ] {
+            let html = HtmlRenderer::new().comments(true).render(&Document::parse(source).unwrap()).unwrap();
+            assert_eq!(html, format!("<p>{}</p>\n", comment));
+        }
+
+        let strict = HtmlRenderer::new().reject_unknown_tags(true);
+        let err = strict.render(&doc).unwrap_err();
+        assert_eq!(err.message, "unknown tag <widget>");
+        assert_eq!(err.span, Span::new(22, 31));
+        assert!(strict.allow_tag("widget").render(&doc).is_ok());
+
+        let doc = Document::parse(b"|p Hello !{{name}}\n").unwrap();
+        assert!(to_html(&doc).unwrap_err().message.contains("interpolation"));
+        let doc = Document::parse(b"|br text\n").unwrap();
+        assert!(to_html(&doc).is_err());
+    }
+}
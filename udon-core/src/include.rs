@@ -0,0 +1,481 @@
+//! `!include` expansion.
+//!
+//! [`IncludeExpander`] replaces each `!include path` directive with the
+//! top-level nodes of the file it names, loaded through an
+//! [`IncludeResolver`] and parsed. Included files may include others.
+//!
+//! Paths are `/`-separated and relative to the including file (or, with a
+//! leading `/`, to the resolver's root); `.` and `..` are resolved before
+//! the resolver sees them, and may not climb above the root. A path may be
+//! quoted. [`FileResolver`] and [`MemoryResolver`] also accept the path
+//! without its `.udon` extension.
+//!
+//! Spliced nodes keep the spans they have in their own file, and the
+//! document keeps that file's text for them: [`Node::source`] and the
+//! helpers built on it (directive arguments, raw blocks, source text) read
+//! the right bytes. The returned [`SourceMap`] records which file each of
+//! them came from. An include of
+//! a file that is already being included is a cycle and an error, as is
+//! nesting deeper than [`IncludeExpander::max_depth`].
+//!
+//! # Example
+//!
+//! ```
+//! use udon_core::tree::Document;
+//! use udon_core::include::{IncludeExpander, MemoryResolver};
+//!
+//! let resolver = MemoryResolver::new()
+//!     .file("partials/head.udon", "|title Home\n!include meta\n")
+//!     .file("partials/meta.udon", "|meta\n");
+//! let mut doc = Document::parse(b"|html\n  !include partials/head\n  |body\n").unwrap();
+//! let map = IncludeExpander::new(&resolver).expand(&mut doc).unwrap();
+//!
+//! let html = doc.root().first_child().unwrap();
+//! let names: Vec<_> = html.children().map(|n| n.as_element().unwrap().name()).collect();
+//! assert_eq!(names, ["title", "meta", "body"]);
+//! let meta = html.children().nth(1).unwrap();
+//! assert_eq!(map.file(meta.id()), Some("partials/meta.udon"));
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::reference::descendants;
+use crate::span::Span;
+use crate::tree::{Document, Node, NodeId, NodeKind};
+
+/// Default for [`IncludeExpander::max_depth`].
+pub const DEFAULT_MAX_DEPTH: usize = 16;
+
+/// A loaded include file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Source {
+    /// Name the file is known by, used to detect cycles and reported by
+    /// [`SourceMap::file`].
+    pub name: String,
+    pub text: Vec<u8>,
+}
+
+/// Loads the files named by `!include` directives.
+pub trait IncludeResolver {
+    /// Load `path`: `/`-separated, relative to the resolver's root, with no
+    /// `.` or `..` segments.
+    fn load(&self, path: &str) -> io::Result<Source>;
+}
+
+impl<R: IncludeResolver + ?Sized> IncludeResolver for &R {
+    fn load(&self, path: &str) -> io::Result<Source> {
+        (**self).load(path)
+    }
+}
+
+impl<R: IncludeResolver + ?Sized> IncludeResolver for Box<R> {
+    fn load(&self, path: &str) -> io::Result<Source> {
+        (**self).load(path)
+    }
+}
+
+/// Loads include files from a directory.
+#[derive(Debug, Clone)]
+pub struct FileResolver {
+    root: PathBuf,
+}
+
+impl FileResolver {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        FileResolver { root: root.into() }
+    }
+}
+
+impl IncludeResolver for FileResolver {
+    fn load(&self, path: &str) -> io::Result<Source> {
+        let mut last_error = None;
+        for name in candidates(path) {
+            match std::fs::read(self.root.join(&name)) {
+                Ok(text) => return Ok(Source { name, text }),
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| io::ErrorKind::NotFound.into()))
+    }
+}
+
+/// Serves include files from memory, e.g. for tests or bundled templates.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryResolver {
+    files: HashMap<String, Vec<u8>>,
+}
+
+impl MemoryResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file, replacing any with the same name.
+    pub fn file(mut self, name: impl Into<String>, text: impl Into<Vec<u8>>) -> Self {
+        self.insert(name, text);
+        self
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, text: impl Into<Vec<u8>>) {
+        self.files.insert(name.into(), text.into());
+    }
+}
+
+impl IncludeResolver for MemoryResolver {
+    fn load(&self, path: &str) -> io::Result<Source> {
+        candidates(path)
+            .find_map(|name| self.files.get(&name).map(|text| Source { name, text: text.clone() }))
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+}
+
+/// `path` as given, then with a `.udon` extension if it has none.
+fn candidates(path: &str) -> impl Iterator<Item = String> {
+    let file = path.rsplit('/').next().unwrap_or(path);
+    let with_extension = (!file.contains('.')).then(|| format!("{}.udon", path));
+    std::iter::once(path.to_string()).chain(with_extension)
+}
+
+/// Error returned when an include cannot be expanded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncludeError {
+    pub message: String,
+    /// The file `span` is in; `None` for the document being expanded.
+    pub file: Option<String>,
+    pub span: Span,
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}: ", file)?;
+        }
+        write!(f, "{} at {}..{}", self.message, self.span.start, self.span.end)
+    }
+}
+
+impl std::error::Error for IncludeError {}
+
+/// Which file each spliced node came from.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<Source>,
+    /// Each splice: the file's index and the splice it happened inside.
+    splices: Vec<(usize, Option<usize>)>,
+    /// Spliced nodes, mapped to their splice.
+    origins: HashMap<NodeId, usize>,
+}
+
+impl SourceMap {
+    /// Name of the file a node came from; `None` for nodes of the expanded
+    /// document itself.
+    pub fn file(&self, id: NodeId) -> Option<&str> {
+        self.source(id).map(|source| source.name.as_str())
+    }
+
+    /// The file a node came from, whose text its span refers to.
+    pub fn source(&self, id: NodeId) -> Option<&Source> {
+        let splice = *self.origins.get(&id)?;
+        Some(&self.files[self.splices[splice].0])
+    }
+
+    /// The included files, in the order they were first included.
+    pub fn files(&self) -> impl Iterator<Item = &Source> {
+        self.files.iter()
+    }
+
+    /// Names of the files an include inside `splice` is nested in,
+    /// innermost first.
+    fn chain(&self, mut splice: Option<usize>) -> Vec<&str> {
+        let mut names = Vec::new();
+        while let Some(index) = splice {
+            let (file, parent) = self.splices[index];
+            names.push(self.files[file].name.as_str());
+            splice = parent;
+        }
+        names
+    }
+}
+
+/// Configurable include expansion.
+#[derive(Debug, Clone)]
+pub struct IncludeExpander<R> {
+    resolver: R,
+    name: Option<String>,
+    max_depth: usize,
+}
+
+impl<R: IncludeResolver> IncludeExpander<R> {
+    pub fn new(resolver: R) -> Self {
+        IncludeExpander { resolver, name: None, max_depth: DEFAULT_MAX_DEPTH }
+    }
+
+    /// Name of the document being expanded, as the resolver knows it. Its
+    /// includes are then relative to it, and including it is a cycle.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Maximum nesting of includes.
+    pub fn max_depth(mut self, max: usize) -> Self {
+        self.max_depth = max;
+        self
+    }
+
+    /// Expand every `!include` in the document, in place.
+    pub fn expand(&self, doc: &mut Document<'_>) -> Result<SourceMap, IncludeError> {
+        let mut map = SourceMap::default();
+        // Where the document keeps the text of each of `map.files`
+        let mut texts = Vec::new();
+
+        // Includes in the tree, in document order; those in spliced files
+        // are added as they are spliced. An include's own children are
+        // dropped when it is expanded, so includes among them are skipped.
+        let mut pending: VecDeque<NodeId> = includes(doc.root()).collect();
+        while let Some(id) = pending.pop_front() {
+            let node = doc.get(id).unwrap();
+            if std::iter::successors(Some(node), |n| n.parent()).last().is_none_or(|top| top.id() != doc.root().id()) {
+                continue;
+            }
+            let splice = map.origins.get(&id).copied();
+            let error = |message: String| IncludeError {
+                message,
+                file: splice.map(|s| map.files[map.splices[s].0].name.clone()),
+                span: node.span(),
+            };
+
+            let argument = node.directive_argument().ok_or_else(|| error("missing include path".to_string()))?;
+            let chain = map.chain(splice);
+            let from = chain.first().copied().or(self.name.as_deref());
+            let path = join(from, unquote(argument.text.trim())).map_err(|message| error(message.to_string()))?;
+            if chain.len() >= self.max_depth {
+                return Err(error(format!("includes nested more than {} deep", self.max_depth)));
+            }
+            let source = self
+                .resolver
+                .load(&path)
+                .map_err(|err| error(format!("cannot include `{}`: {}", path, err)))?;
+            if chain.contains(&source.name.as_str()) || self.name.as_deref() == Some(&source.name) {
+                let mut cycle: Vec<&str> = chain.iter().rev().copied().collect();
+                if let Some(name) = &self.name {
+                    cycle.insert(0, name);
+                }
+                cycle.push(&source.name);
+                return Err(error(format!("include cycle: {}", cycle.join(" -> "))));
+            }
+
+            let included = Document::parse(&source.text).map_err(|err| IncludeError {
+                message: err.message,
+                file: Some(source.name.clone()),
+                span: Span::new(err.span.start, err.span.end),
+            })?;
+            let old: Vec<NodeId> = node.children().map(|c| c.id()).collect();
+            let file = match map.files.iter().position(|f| f.name == source.name) {
+                Some(file) => file,
+                None => {
+                    texts.push(doc.add_included(source.text.clone()));
+                    map.files.push(source.clone());
+                    map.files.len() - 1
+                }
+            };
+            map.splices.push((file, splice));
+            let new_splice = map.splices.len() - 1;
+
+            // Swap the directive's own children for the file's, then put
+            // them in the directive's place.
+            for child in old {
+                doc.detach(child);
+            }
+            for child in included.root().children() {
+                copy_node(doc, id, child, (new_splice, texts[file]), &mut map.origins);
+            }
+            pending.extend(includes(doc.get(id).unwrap()));
+            doc.unwrap_node(id);
+        }
+        Ok(map)
+    }
+}
+
+impl Document<'_> {
+    /// Expand every `!include` with default settings; see
+    /// [`IncludeExpander`].
+    pub fn expand_includes(&mut self, resolver: impl IncludeResolver) -> Result<SourceMap, IncludeError> {
+        IncludeExpander::new(resolver).expand(self)
+    }
+}
+
+/// `!include` directives below `node`, in document order.
+fn includes<'doc>(node: Node<'doc, '_>) -> impl Iterator<Item = NodeId> + 'doc {
+    descendants(node)
+        .filter(|n| matches!(n.kind(), NodeKind::Directive { name, .. } if name == "include"))
+        .map(|n| n.id())
+}
+
+/// Copy `node` under `parent`, as part of `splice` from the file whose
+/// text the document keeps at `text`.
+fn copy_node(
+    doc: &mut Document<'_>,
+    parent: NodeId,
+    node: Node<'_, '_>,
+    (splice, text): (usize, usize),
+    origins: &mut HashMap<NodeId, usize>,
+) {
+    let copy = doc.append_child(parent, node.kind().clone().into_owned());
+    doc.set_included(copy, text, node.span());
+    origins.insert(copy, splice);
+    for child in node.children() {
+        copy_node(doc, copy, child, (splice, text), origins);
+    }
+}
+
+fn unquote(path: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|&q| path.strip_prefix(q).and_then(|p| p.strip_suffix(q)))
+        .unwrap_or(path)
+}
+
+/// Resolve `path` against the directory of `from`.
+fn join(from: Option<&str>, path: &str) -> Result<String, &'static str> {
+    let mut parts: Vec<&str> = match (path.starts_with('/'), from) {
+        (false, Some(from)) => from.split('/').collect(),
+        _ => Vec::new(),
+    };
+    parts.pop();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                parts.pop().ok_or("include path climbs above the root")?;
+            }
+            segment => parts.push(segment),
+        }
+    }
+    if parts.is_empty() {
+        return Err("empty include path");
+    }
+    Ok(parts.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writer::to_udon_string;
+
+    #[test]
+    fn test_paths() {
+        assert_eq!(join(None, "partials/head"), Ok("partials/head".to_string()));
+        assert_eq!(join(Some("pages/a.udon"), "../partials/./head"), Ok("partials/head".to_string()));
+        assert_eq!(join(Some("pages/a.udon"), "/head"), Ok("head".to_string()));
+        assert_eq!(join(Some("a.udon"), "../x"), Err("include path climbs above the root"));
+        assert_eq!(unquote("\"my file\""), "my file");
+        assert_eq!(candidates("a/b").collect::<Vec<_>>(), ["a/b", "a/b.udon"]);
+        assert_eq!(candidates("a.x/b.txt").collect::<Vec<_>>(), ["a.x/b.txt"]);
+    }
+
+    #[test]
+    fn test_expand() {
+        let resolver = MemoryResolver::new()
+            .file("pages/index.udon", "|page\n  !include ../partials/nav\n  |main\n")
+            .file("partials/nav.udon", "|nav\n  !include item\n  !include item\n")
+            .file("partials/item.udon", "|a[home] Home\n");
+        let source = resolver.load("pages/index.udon").unwrap().text;
+        let mut doc = Document::parse(&source).unwrap();
+        let map = IncludeExpander::new(&resolver).name("pages/index.udon").expand(&mut doc).unwrap();
+        assert_eq!(to_udon_string(&doc), "|page\n  |nav\n    |a[home] Home\n    |a[home] Home\n  |main\n");
+
+        let nav = doc.root().first_child().unwrap().first_child().unwrap();
+        assert_eq!(map.file(doc.root().first_child().unwrap().id()), None);
+        assert_eq!(map.file(nav.id()), Some("partials/nav.udon"));
+        let item = doc.get_by_id("home").unwrap();
+        assert_eq!(map.file(item.id()), Some("partials/item.udon"));
+        // Spans refer to the file the node came from
+        assert_eq!(item.span(), Span::new(0, 13));
+        let files: Vec<_> = map.files().map(|f| f.name.as_str()).collect();
+        assert_eq!(files, ["partials/nav.udon", "partials/item.udon"]);
+    }
+
+    #[test]
+    fn test_included_source() {
+        use crate::template::{Data, Engine};
+
+        let resolver = MemoryResolver::new()
+            .file("p.udon", "!if show\n  |yes\n")
+            .file("q.udon", "!raw:sh\n  ls\n    -l\n|q one\n");
+        let mut doc = Document::parse(b"|h\n  !include p\n|b\n  |c\n  !include q\n").unwrap();
+        let map = doc.expand_includes(&resolver).unwrap();
+
+        // Helpers that read source text read the included file's
+        let h = doc.root().first_child().unwrap();
+        let directive = h.first_child().unwrap();
+        assert_eq!(directive.source(), b"!if show\n  |yes\n");
+        assert_eq!(directive.directive_argument().unwrap().text, "show");
+        assert_eq!(doc.source_text(directive), "!if show\n  |yes");
+        let (_, diagnostics) = h.directive_blocks();
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let b = h.next_sibling().unwrap();
+        let raw = b.children().nth(1).unwrap();
+        assert_eq!(map.file(raw.id()), Some("q.udon"));
+        assert_eq!(raw.raw_block().unwrap().code, "ls\n  -l");
+        assert_eq!(doc.source(), b"|h\n  !include p\n|b\n  |c\n  !include q\n");
+
+        let data = Data::from_iter([("show", Data::from(true))]);
+        let inlined = Document::parse(b"|h\n  !if show\n    |yes\n|b\n  |c\n  !raw:sh\n    ls\n      -l\n  |q one\n").unwrap();
+        let render = |doc: &Document<'_>| to_udon_string(&Engine::new().render(doc, &data).unwrap());
+        assert_eq!(render(&doc), render(&inlined));
+        assert!(render(&doc).starts_with("|h\n  |yes\n|b\n"));
+        assert_eq!(to_udon_string(&doc.into_owned()), to_udon_string(&inlined));
+    }
+
+    #[test]
+    fn test_errors() {
+        let resolver = MemoryResolver::new()
+            .file("a.udon", "|x\n!include b\n")
+            .file("b.udon", "!include a\n")
+            .file("bad.udon", "|a :list [1\n");
+
+        let mut doc = Document::parse(b"!include a\n").unwrap();
+        let err = doc.expand_includes(&resolver).unwrap_err();
+        assert_eq!(err.message, "include cycle: a.udon -> b.udon -> a.udon");
+        assert_eq!((err.file.as_deref(), err.span), (Some("b.udon"), Span::new(0, 10)));
+
+        let mut doc = Document::parse(b"!include b\n").unwrap();
+        let err = IncludeExpander::new(&resolver).name("a.udon").expand(&mut doc).unwrap_err();
+        assert_eq!(err.message, "include cycle: a.udon -> b.udon -> a.udon");
+
+        let mut doc = Document::parse(b"!include a\n").unwrap();
+        let err = IncludeExpander::new(&resolver).max_depth(1).expand(&mut doc).unwrap_err();
+        assert_eq!(err.message, "includes nested more than 1 deep");
+
+        let mut doc = Document::parse(b"|p\n  !include missing\n").unwrap();
+        let err = doc.expand_includes(&resolver).unwrap_err();
+        assert!(err.message.starts_with("cannot include `missing`"), "{}", err.message);
+        assert_eq!((err.file, err.span), (None, Span::new(5, 21)));
+
+        // Includes in the body an include replaces are never loaded
+        let mut doc = Document::parse(b"!include a\n  |x\n    !include missing\n").unwrap();
+        doc.expand_includes(MemoryResolver::new().file("a.udon", "|p\n")).unwrap();
+        assert_eq!(to_udon_string(&doc), "|p\n");
+
+        let mut doc = Document::parse(b"!include bad\n").unwrap();
+        let err = doc.expand_includes(&resolver).unwrap_err();
+        assert_eq!(err.file.as_deref(), Some("bad.udon"));
+    }
+
+    #[test]
+    fn test_file_resolver() {
+        let dir = std::env::temp_dir().join(format!("udon-include-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("partials")).unwrap();
+        std::fs::write(dir.join("partials/head.udon"), "|title Hi\n").unwrap();
+
+        let mut doc = Document::parse(b"|html\n  !include \"partials/head\"\n").unwrap();
+        let map = doc.expand_includes(FileResolver::new(&dir)).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(to_udon_string(&doc), "|html\n  |title Hi\n");
+        assert_eq!(map.files().next().unwrap().name, "partials/head.udon");
+    }
+}
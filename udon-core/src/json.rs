@@ -0,0 +1,511 @@
+//! UDON ⇄ JSON conversion (`json` feature).
+//!
+//! [`to_json`] turns a document into a [`serde_json::Value`] and
+//! [`from_json`] builds a document back from one. The mapping:
+//!
+//! | UDON | JSON |
+//! |------|------|
+//! | document | array of its top-level nodes |
+//! | element | `{"name", "id", "classes", "attrs", "children"}` (`"embedded": true` for `\|{...}`) |
+//! | text | string |
+//! | comment | `{"comment": text}` |
+//! | directive | `{"directive": name, "attrs", "children"}` |
+//! | interpolation | `{"interpolation": expr}` |
+//! | reference | `{"reference": id}` (`"kind": "attributes"` for `:[id]`) |
+//! | raw block | `{"raw": content, "lang": lang}` |
+//!
+//! `id` and `lang` are `null` when absent. `attrs` is an object in source
+//! order.
+//!
+//! Attribute values:
+//!
+//! | UDON | JSON |
+//! |------|------|
+//! | string, bare value | string (escapes resolved) |
+//! | integer | number; `{"$type": "integer", "value": text}` if it does not fit in 64 bits |
+//! | float | number; `{"$type": "float", "value": text}` if it does not fit in `f64` |
+//! | `true` / `false` | boolean |
+//! | `null` / `nil` | `null` |
+//! | array | array |
+//! | rational, complex | `{"$type": "rational" \| "complex", "value": text}` |
+//! | date, time, datetime, duration, relative time | `{"$type": "date" \| "time" \| "datetime" \| "duration" \| "relative_time", "value": text}` |
+//!
+//! Tagged values keep their UDON source text, so converting back is
+//! lossless. Numbers are not: `0xFF` comes back as `255`. A reference
+//! built from JSON is always an `@[id]` reference, since the tree records
+//! the `:[id]` form only through its source text.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_json::json;
+//! use udon_core::json::{from_json, to_json};
+//! use udon_core::tree::Document;
+//!
+//! let doc = Document::parse(b"|server[web].primary\n  :port 8080\n  :ratio 1/3r\n  Ready\n").unwrap();
+//! let value = to_json(&doc);
+//! assert_eq!(value, json!([{
+//!     "name": "server",
+//!     "id": "web",
+//!     "classes": ["primary"],
+//!     "attrs": {"port": 8080, "ratio": {"$type": "rational", "value": "1/3r"}},
+//!     "children": ["Ready"],
+//! }]));
+//!
+//! let back = from_json(&value).unwrap();
+//! assert_eq!(udon_core::to_udon_string(&back), "|server[web].primary\n  :port 8080\n  :ratio 1/3r\n  Ready\n");
+//! ```
+
+use std::borrow::Cow;
+use std::fmt;
+
+use serde_json::{Map, Number, Value as Json};
+
+use crate::reference::ReferenceKind;
+use crate::tree::{string_value, unescape, Attribute, Document, Node, NodeId, NodeKind, Value};
+use crate::writer::content_text;
+
+/// Error returned when JSON does not follow the mapping.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub message: String,
+    /// JSON Pointer to the offending value (`""` for the top level).
+    pub path: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "{} at {}", self.message, self.path)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// ============================================================================
+// UDON -> JSON
+// ============================================================================
+
+/// Convert a document to JSON: an array of its top-level nodes.
+pub fn to_json(doc: &Document<'_>) -> Json {
+    children_json(doc.root())
+}
+
+/// Convert a single node (and its subtree) to JSON.
+pub fn node_to_json(node: Node<'_, '_>) -> Json {
+    match node.kind() {
+        NodeKind::Document => children_json(node),
+        NodeKind::Element { name, id, classes, attrs, embedded } => {
+            let mut object = Map::new();
+            object.insert("name".into(), Json::String(name.to_string()));
+            object.insert("id".into(), id.as_ref().map_or(Json::Null, |id| Json::String(id.to_string())));
+            object.insert(
+                "classes".into(),
+                Json::Array(classes.iter().map(|c| Json::String(c.to_string())).collect()),
+            );
+            object.insert("attrs".into(), attrs_json(attrs));
+            object.insert("children".into(), children_json(node));
+            if *embedded {
+                object.insert("embedded".into(), Json::Bool(true));
+            }
+            Json::Object(object)
+        }
+        NodeKind::Text(text) => Json::String(text.to_string()),
+        NodeKind::Comment(content) => tagged_node("comment", &content_text(node, content)),
+        NodeKind::Directive { name, attrs } => {
+            let mut object = Map::new();
+            object.insert("directive".into(), Json::String(name.to_string()));
+            object.insert("attrs".into(), attrs_json(attrs));
+            object.insert("children".into(), children_json(node));
+            Json::Object(object)
+        }
+        NodeKind::Interpolation(expr) => tagged_node("interpolation", expr),
+        NodeKind::Reference(id) => {
+            let mut object = Map::new();
+            object.insert("reference".into(), Json::String(id.to_string()));
+            if node.reference_kind() == Some(ReferenceKind::Attributes) {
+                object.insert("kind".into(), Json::String("attributes".into()));
+            }
+            Json::Object(object)
+        }
+        NodeKind::Raw { lang, content } => {
+            let mut object = Map::new();
+            object.insert("raw".into(), Json::String(content_text(node, content)));
+            object.insert("lang".into(), lang.as_ref().map_or(Json::Null, |l| Json::String(l.to_string())));
+            Json::Object(object)
+        }
+    }
+}
+
+/// Convert an attribute value to JSON.
+pub fn value_to_json(value: &Value<'_>) -> Json {
+    match value {
+        Value::String(s) => Json::String(unescape(s).into_owned()),
+        Value::Bare(s) => Json::String(s.to_string()),
+        Value::Integer(s) => match value.as_i128() {
+            Ok(n) => i64::try_from(n)
+                .map(Number::from)
+                .or_else(|_| u64::try_from(n).map(Number::from))
+                .map_or_else(|_| tagged_value("integer", s), Json::Number),
+            Err(_) => tagged_value("integer", s),
+        },
+        Value::Float(s) => match value.as_f64().ok().and_then(Number::from_f64) {
+            Some(n) => Json::Number(n),
+            None => tagged_value("float", s),
+        },
+        Value::Rational(s) => tagged_value("rational", s),
+        Value::Complex(s) => tagged_value("complex", s),
+        Value::BoolTrue => Json::Bool(true),
+        Value::BoolFalse => Json::Bool(false),
+        Value::Nil => Json::Null,
+        Value::Array(items) => Json::Array(items.iter().map(value_to_json).collect()),
+        Value::Date(s) => tagged_value("date", s),
+        Value::Time(s) => tagged_value("time", s),
+        Value::DateTime(s) => tagged_value("datetime", s),
+        Value::Duration(s) => tagged_value("duration", s),
+        Value::RelativeTime(s) => tagged_value("relative_time", s),
+    }
+}
+
+fn children_json(node: Node<'_, '_>) -> Json {
+    Json::Array(node.children().map(node_to_json).collect())
+}
+
+fn attrs_json(attrs: &[Attribute<'_>]) -> Json {
+    Json::Object(
+        attrs
+            .iter()
+            .map(|attr| (attr.name.to_string(), value_to_json(&attr.value)))
+            .collect(),
+    )
+}
+
+fn tagged_node(key: &str, text: &str) -> Json {
+    let mut object = Map::new();
+    object.insert(key.into(), Json::String(text.to_string()));
+    Json::Object(object)
+}
+
+fn tagged_value(tag: &str, text: &str) -> Json {
+    let mut object = Map::new();
+    object.insert("$type".into(), Json::String(tag.into()));
+    object.insert("value".into(), Json::String(text.to_string()));
+    Json::Object(object)
+}
+
+// ============================================================================
+// JSON -> UDON
+// ============================================================================
+
+/// Build a document from JSON: an array of top-level nodes, or a single
+/// node.
+pub fn from_json(json: &Json) -> Result<Document<'static>, Error> {
+    let mut doc = Document::new();
+    let root = doc.root().id();
+    let mut path = String::new();
+    match json {
+        Json::Array(nodes) => append_nodes(&mut doc, root, nodes, &mut path)?,
+        node => append_node(&mut doc, root, node, &mut path)?,
+    }
+    Ok(doc)
+}
+
+/// Convert a JSON attribute value back to a [`Value`].
+pub fn value_from_json(json: &Json) -> Result<Value<'static>, Error> {
+    value(json, &mut String::new())
+}
+
+fn error(message: impl Into<String>, path: &str) -> Error {
+    Error { message: message.into(), path: path.to_string() }
+}
+
+/// Run `f` with `key` appended to the JSON Pointer `path`.
+fn at<T>(path: &mut String, key: &str, f: impl FnOnce(&mut String) -> T) -> T {
+    let len = path.len();
+    path.push('/');
+    path.push_str(&key.replace('~', "~0").replace('/', "~1"));
+    let result = f(path);
+    path.truncate(len);
+    result
+}
+
+fn append_nodes(doc: &mut Document<'static>, parent: NodeId, nodes: &[Json], path: &mut String) -> Result<(), Error> {
+    for (i, node) in nodes.iter().enumerate() {
+        at(path, &i.to_string(), |path| append_node(doc, parent, node, path))?;
+    }
+    Ok(())
+}
+
+fn append_node(doc: &mut Document<'static>, parent: NodeId, json: &Json, path: &mut String) -> Result<(), Error> {
+    let object = match json {
+        Json::String(text) => {
+            doc.append_child(parent, NodeKind::Text(Cow::Owned(text.clone())));
+            return Ok(());
+        }
+        Json::Object(object) => object,
+        _ => return Err(error("expected a node object or text string", path)),
+    };
+
+    let kind = if object.contains_key("directive") {
+        NodeKind::Directive {
+            name: Cow::Owned(string(object, "directive", path)?),
+            attrs: attrs(object, path)?,
+        }
+    } else if object.contains_key("comment") {
+        NodeKind::Comment(Cow::Owned(string(object, "comment", path)?))
+    } else if object.contains_key("interpolation") {
+        NodeKind::Interpolation(Cow::Owned(string(object, "interpolation", path)?))
+    } else if object.contains_key("reference") {
+        NodeKind::Reference(Cow::Owned(string(object, "reference", path)?))
+    } else if object.contains_key("raw") {
+        NodeKind::Raw {
+            lang: optional_string(object, "lang", path)?.map(Cow::Owned),
+            content: Cow::Owned(string(object, "raw", path)?),
+        }
+    } else if object.contains_key("name") {
+        let classes = match object.get("classes") {
+            None | Some(Json::Null) => Vec::new(),
+            Some(Json::Array(classes)) => classes
+                .iter()
+                .enumerate()
+                .map(|(i, class)| match class {
+                    Json::String(class) => Ok(Cow::Owned(class.clone())),
+                    _ => Err(at(path, "classes", |path| at(path, &i.to_string(), |path| error("expected a string", path)))),
+                })
+                .collect::<Result<_, _>>()?,
+            Some(_) => return Err(at(path, "classes", |path| error("expected an array of strings", path))),
+        };
+        NodeKind::Element {
+            name: Cow::Owned(string(object, "name", path)?),
+            id: optional_string(object, "id", path)?.map(Cow::Owned),
+            classes,
+            attrs: attrs(object, path)?,
+            embedded: match object.get("embedded") {
+                None | Some(Json::Null) => false,
+                Some(Json::Bool(embedded)) => *embedded,
+                Some(_) => return Err(at(path, "embedded", |path| error("expected a boolean", path))),
+            },
+        }
+    } else {
+        return Err(error(
+            "node object needs one of name, directive, comment, interpolation, reference or raw",
+            path,
+        ));
+    };
+
+    let has_children = matches!(kind, NodeKind::Element { .. } | NodeKind::Directive { .. });
+    let id = doc.append_child(parent, kind);
+    match object.get("children") {
+        None | Some(Json::Null) => Ok(()),
+        Some(Json::Array(children)) if has_children => {
+            at(path, "children", |path| append_nodes(doc, id, children, path))
+        }
+        Some(_) if has_children => Err(at(path, "children", |path| error("expected an array", path))),
+        Some(_) => Err(at(path, "children", |path| error("only elements and directives have children", path))),
+    }
+}
+
+fn string(object: &Map<String, Json>, key: &str, path: &mut String) -> Result<String, Error> {
+    match object.get(key) {
+        Some(Json::String(s)) => Ok(s.clone()),
+        _ => Err(at(path, key, |path| error("expected a string", path))),
+    }
+}
+
+fn optional_string(object: &Map<String, Json>, key: &str, path: &mut String) -> Result<Option<String>, Error> {
+    match object.get(key) {
+        None | Some(Json::Null) => Ok(None),
+        Some(Json::String(s)) => Ok(Some(s.clone())),
+        Some(_) => Err(at(path, key, |path| error("expected a string or null", path))),
+    }
+}
+
+fn attrs(object: &Map<String, Json>, path: &mut String) -> Result<Vec<Attribute<'static>>, Error> {
+    match object.get("attrs") {
+        None | Some(Json::Null) => Ok(Vec::new()),
+        Some(Json::Object(attrs)) => at(path, "attrs", |path| {
+            attrs
+                .iter()
+                .map(|(name, json)| {
+                    let value = at(path, name, |path| value(json, path))?;
+                    Ok(Attribute::new(name.clone(), value))
+                })
+                .collect()
+        }),
+        Some(_) => Err(at(path, "attrs", |path| error("expected an object", path))),
+    }
+}
+
+fn value(json: &Json, path: &mut String) -> Result<Value<'static>, Error> {
+    Ok(match json {
+        Json::Null => Value::Nil,
+        Json::Bool(true) => Value::BoolTrue,
+        Json::Bool(false) => Value::BoolFalse,
+        Json::Number(n) if n.is_f64() => Value::Float(Cow::Owned(format!("{:?}", n.as_f64().unwrap_or_default()))),
+        Json::Number(n) => Value::Integer(Cow::Owned(n.to_string())),
+        Json::String(s) => string_value(s),
+        Json::Array(items) => Value::Array(
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| at(path, &i.to_string(), |path| value(item, path)))
+                .collect::<Result<_, _>>()?,
+        ),
+        Json::Object(object) => tagged(object, path)?,
+    })
+}
+
+fn tagged(object: &Map<String, Json>, path: &mut String) -> Result<Value<'static>, Error> {
+    let (Some(Json::String(tag)), Some(Json::String(text))) = (object.get("$type"), object.get("value")) else {
+        return Err(error("expected a tagged value with string $type and value", path));
+    };
+    let text = Cow::Owned(text.clone());
+    let value = match tag.as_str() {
+        "integer" => Value::Integer(text),
+        "float" => Value::Float(text),
+        "rational" => Value::Rational(text),
+        "complex" => Value::Complex(text),
+        "date" => Value::Date(text),
+        "time" => Value::Time(text),
+        "datetime" => Value::DateTime(text),
+        "duration" => Value::Duration(text),
+        "relative_time" => Value::RelativeTime(text),
+        _ => return Err(at(path, "$type", |path| error(format!("unknown value type `{}`", tag), path))),
+    };
+    if !parses_as(&value) {
+        return Err(at(path, "value", |path| error(format!("not a valid {} value", tag), path)));
+    }
+    Ok(value)
+}
+
+/// Whether the value's text parses back as the same kind of value.
+fn parses_as(value: &Value<'_>) -> bool {
+    let Some(text) = source_text(value) else {
+        return true;
+    };
+    let source = format!("|a\n  :v {}\n", text);
+    let Ok(doc) = Document::parse(source.as_bytes()) else {
+        return false;
+    };
+    let parsed = doc.root().first_child().and_then(|a| a.as_element()).and_then(|a| a.attr("v"));
+    parsed.is_some_and(|parsed| {
+        std::mem::discriminant(parsed) == std::mem::discriminant(value) && source_text(parsed) == Some(text)
+    })
+}
+
+/// Source text of the values that map to tagged objects.
+fn source_text<'v>(value: &'v Value<'_>) -> Option<&'v str> {
+    match value {
+        Value::Integer(s)
+        | Value::Float(s)
+        | Value::Rational(s)
+        | Value::Complex(s)
+        | Value::Date(s)
+        | Value::Time(s)
+        | Value::DateTime(s)
+        | Value::Duration(s)
+        | Value::RelativeTime(s) => Some(s),
+        _ => None,
+    }
+}
+
+impl From<&Document<'_>> for Json {
+    fn from(doc: &Document<'_>) -> Self {
+        to_json(doc)
+    }
+}
+
+impl From<&Value<'_>> for Json {
+    fn from(value: &Value<'_>) -> Self {
+        value_to_json(value)
+    }
+}
+
+impl TryFrom<&Json> for Document<'static> {
+    type Error = Error;
+
+    fn try_from(json: &Json) -> Result<Self, Error> {
+        from_json(json)
+    }
+}
+
+impl TryFrom<&Json> for Value<'static> {
+    type Error = Error;
+
+    fn try_from(json: &Json) -> Result<Self, Error> {
+        value_from_json(json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::to_udon_string;
+
+    #[test]
+    fn test_values() {
+        let source = "|a\n  :s \"tab\\there\"\n  :b word\n  :i -42\n  :h 0xFF\n  :big 0xFFFF_FFFF_FFFF_FFFF_F\n  :f 2.5\n  :r 22/7r\n  :c 0.5-4.5i\n  :d 2024-01-15\n  :t 10:30:00\n  :dt 2024-01-15T10:30:00Z\n  :dur 30s\n  :rel +1h\n  :list [1 two 3.0]\n  :n null\n  :on\n";
+        let doc = Document::parse(source.as_bytes()).unwrap();
+        let json = to_json(&doc);
+        let attrs = &json[0]["attrs"];
+        assert_eq!(attrs["s"], json!("tab\there"));
+        assert_eq!(attrs["b"], json!("word"));
+        assert_eq!(attrs["i"], json!(-42));
+        assert_eq!(attrs["h"], json!(255));
+        assert_eq!(attrs["big"], json!({"$type": "integer", "value": "0xFFFF_FFFF_FFFF_FFFF_F"}));
+        assert_eq!(attrs["f"], json!(2.5));
+        assert_eq!(attrs["r"], json!({"$type": "rational", "value": "22/7r"}));
+        assert_eq!(attrs["c"], json!({"$type": "complex", "value": "0.5-4.5i"}));
+        assert_eq!(attrs["d"], json!({"$type": "date", "value": "2024-01-15"}));
+        assert_eq!(attrs["t"], json!({"$type": "time", "value": "10:30:00"}));
+        assert_eq!(attrs["dt"], json!({"$type": "datetime", "value": "2024-01-15T10:30:00Z"}));
+        assert_eq!(attrs["dur"], json!({"$type": "duration", "value": "30s"}));
+        assert_eq!(attrs["rel"], json!({"$type": "relative_time", "value": "+1h"}));
+        assert_eq!(attrs["list"], json!([1, "two", 3.0]));
+        assert_eq!(attrs["n"], json!(null));
+        assert_eq!(attrs["on"], json!(true));
+
+        // Back to UDON and out again gives the same JSON
+        let back = from_json(&json).unwrap();
+        let text = to_udon_string(&back);
+        let reparsed = Document::parse(text.as_bytes()).unwrap();
+        assert_eq!(to_json(&reparsed), json);
+    }
+
+    #[test]
+    fn test_nodes_round_trip() {
+        let source = "|page\n  ; note\n  |p Hello |{em there}, !{{user.name}}\n  @[footer]\n  ```\n  echo hi\n  ```\n|footer[footer] Bye\n";
+        let doc = Document::parse(source.as_bytes()).unwrap();
+        let json = to_json(&doc);
+        let page = &json[0]["children"];
+        assert_eq!(page[0], json!({"comment": " note"}));
+        assert_eq!(page[1]["children"][1]["embedded"], json!(true));
+        assert_eq!(page[1]["children"][3], json!({"interpolation": "user.name"}));
+        assert_eq!(page[2], json!({"reference": "footer"}));
+        assert_eq!(page[3], json!({"raw": "  echo hi", "lang": null}));
+
+        let back = from_json(&json).unwrap();
+        assert_eq!(to_udon_string(&back), to_udon_string(&doc));
+        assert!(back.get_by_id("footer").is_some());
+
+        let text: Json = serde_json::from_str(&serde_json::to_string(&json).unwrap()).unwrap();
+        assert_eq!(Json::from(&Document::try_from(&text).unwrap()), json);
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = from_json(&json!([{"name": "a", "attrs": {"x": {"$type": "date", "value": "soon"}}}])).unwrap_err();
+        assert_eq!(err.path, "/0/attrs/x/value");
+        let err = from_json(&json!([{"name": "a", "children": [1]}])).unwrap_err();
+        assert_eq!(err.path, "/0/children/0");
+        let err = from_json(&json!({"name": 3})).unwrap_err();
+        assert_eq!(err.to_string(), "expected a string at /name");
+        assert!(from_json(&json!([{"id": "x"}])).is_err());
+        assert!(value_from_json(&json!({"$type": "matrix", "value": "1"})).is_err());
+    }
+}
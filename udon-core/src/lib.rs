@@ -5,9 +5,30 @@
 //!
 //! # Architecture
 //!
-//! - **parser.rs** - Generated recursive descent parser (callback-based)
+//! - **parser.rs** - Generated recursive descent parser (callback-based),
+//!   post-processed to hook in `parser/{control,event,streaming}.rs`
 //! - **tree.rs** - Tree/AST representation built from parser events
 //! - **span.rs** - Span/Location types
+//! - **writer.rs** - Serializer from tree back to UDON text
+//! - **cst.rs** - Lossless syntax tree for format-preserving edits
+//! - **select.rs** - CSS-style selector queries over the tree
+//! - **mixin.rs** - Mixin resolution for class-only elements
+//! - **directive.rs** - Typed view of `!if` / `!for` / `!let` / `!include` directives
+//! - **expr.rs** - Expression grammar for interpolations and directive arguments
+//! - **include.rs** - `!include` expansion through pluggable resolvers
+//! - **raw.rs** - Raw block languages and per-language handlers
+//! - **reference.rs** - ID references (`@[id]`, `:[id]`) and their expansion
+//! - **diagnostic.rs** - Diagnostics from parsing and resolution passes
+//! - **report.rs** - Rendered diagnostic reports with source excerpts
+//! - **number.rs** - Checked decoding of numeric values
+//! - **temporal.rs** - Decoded dates, times, durations and relative times
+//! - **de.rs** - serde deserialization (`serde` feature)
+//! - **ser.rs** - serde serialization to UDON text (`serde` feature)
+//! - **html.rs** - HTML rendering for document-style UDON
+//! - **template.rs** - Template expansion (`!if`, `!for`, `!{{expr}}`) against a data context
+//! - **json.rs** - UDON <-> JSON conversion (`json` feature)
+//! - **mmap.rs** - Parsing memory-mapped files (`mmap` feature)
+//! - **stream.rs** - `futures` Stream of events from an `AsyncRead` (`async` feature)
 //!
 //! # Two APIs
 //!
@@ -19,6 +40,10 @@
 //! Parser::new(b"|div Hello\n").parse(|event| {
 //!     println!("{}", event.format_line());
 //! });
+//!
+//! // Or pull them one at a time
+//! let names = Parser::events(b"|a\n|b\n").filter(|event| matches!(event, udon_core::Event::Name { .. }));
+//! assert_eq!(names.count(), 2);
 //! ```
 //!
 //! ## Tree (DOM-like)
@@ -32,10 +57,49 @@
 //! }
 //! ```
 
+#[allow(clippy::all, unreachable_patterns)]
 pub mod parser;
+pub mod cst;
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod diagnostic;
+pub mod directive;
+pub mod expr;
+pub mod html;
+pub mod include;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod mixin;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod number;
+pub mod raw;
+pub mod reference;
+pub mod report;
+pub mod select;
+#[cfg(feature = "serde")]
+pub mod ser;
 pub mod span;
+#[cfg(feature = "async")]
+pub mod stream;
+pub mod template;
+pub mod temporal;
 pub mod tree;
+pub mod writer;
 
 pub use parser::*;
-pub use span::{Location, Span};
+pub use span::{LineIndex, Location, Span};
 pub use tree::{Document, Node, NodeKind, Value, Attribute, ElementView};
+pub use writer::{to_udon_string, Writer};
+pub use cst::{CstDocument, CstNode, EditError};
+pub use select::{Selector, SelectorError};
+pub use diagnostic::{Code, Diagnostic, Severity};
+pub use html::{to_html, HtmlError, HtmlRenderer};
+pub use reference::ReferenceKind;
+pub use report::ReportRenderer;
+pub use number::NumberError;
+pub use temporal::{Date, DateTime, Duration, RelativeTime, TemporalError, Time};
+#[cfg(feature = "serde")]
+pub use de::{from_document, from_slice, from_str};
+#[cfg(feature = "serde")]
+pub use ser::{to_document, to_string, to_string_named};
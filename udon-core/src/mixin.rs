@@ -0,0 +1,204 @@
+//! Mixin resolution: class-only elements as reusable attribute sets.
+//!
+//! An element with no name and at least one class (`|.base-service`) defines
+//! a mixin for each of its classes. Elements carrying those classes inherit
+//! the mixin's attributes when [`Document::resolve_mixins`] is run.
+//!
+//! Precedence: an element's own attributes always win; mixins are then
+//! applied left to right, so an earlier class wins over a later one.
+//! Inherited attributes are appended after the element's own, keeping the
+//! spans of the mixin definition they came from.
+//!
+//! # Example
+//!
+//! ```
+//! use udon_core::tree::Document;
+//!
+//! let mut doc = Document::parse(b"|.base :timeout 30 :retries 3\n|cache.base :retries 5\n").unwrap();
+//! let diagnostics = doc.resolve_mixins();
+//! assert!(diagnostics.is_empty());
+//!
+//! let cache = doc.root().select_first("cache").unwrap().unwrap().as_element().unwrap();
+//! assert_eq!(format!("{:?}", cache.attr("timeout")), "Some(Integer(\"30\"))");
+//! assert_eq!(format!("{:?}", cache.attr("retries")), "Some(Integer(\"5\"))");
+//! ```
+
+use std::collections::HashMap;
+
+use crate::diagnostic::{Code, Diagnostic};
+use crate::reference::descendants;
+use crate::span::Span;
+use crate::tree::{Attribute, Document, Node, NodeId, NodeKind};
+
+impl<'a> Document<'a> {
+    /// Apply mixin attributes to elements whose classes name a mixin.
+    ///
+    /// Returns diagnostics for classes that name no mixin (`UnknownMixin`,
+    /// a warning) and for mixins defined more than once (`DuplicateMixin`;
+    /// the first definition is used). Classes used only for styling are
+    /// reported too; filter by code if that is expected.
+    pub fn resolve_mixins(&mut self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        // Collect definitions first; their attributes are copied below.
+        let mut mixins: HashMap<String, NodeId> = HashMap::new();
+        let ids: Vec<NodeId> = descendants(self.root()).map(|n| n.id()).collect();
+        for &id in &ids {
+            let node = self.get(id).unwrap();
+            let Some(el) = node.as_element() else { continue };
+            if !is_definition(node) {
+                continue;
+            }
+            for class in el.classes() {
+                if let Some(&first) = mixins.get(class.as_ref()) {
+                    let first_span = self.get(first).unwrap().span();
+                    diagnostics.push(Diagnostic::warning(
+                        Code::DuplicateMixin,
+                        class_span(node, class),
+                        format!(
+                            "mixin '{}' is already defined at {}..{}",
+                            class, first_span.start, first_span.end
+                        ),
+                    ));
+                } else {
+                    mixins.insert(class.to_string(), id);
+                }
+            }
+        }
+
+        for id in ids {
+            let node = self.get(id).unwrap();
+            let Some(el) = node.as_element() else { continue };
+            if is_definition(node) || el.classes().is_empty() {
+                continue;
+            }
+
+            let mut inherited: Vec<Attribute<'a>> = Vec::new();
+            for class in el.classes() {
+                let Some(&mixin) = mixins.get(class.as_ref()) else {
+                    diagnostics.push(Diagnostic::warning(
+                        Code::UnknownMixin,
+                        class_span(node, class),
+                        format!("class '{}' does not name a mixin", class),
+                    ));
+                    continue;
+                };
+                let mixin = self.get(mixin).unwrap().as_element().unwrap();
+                for attr in mixin.attributes() {
+                    let taken = el.attribute(&attr.name).is_some()
+                        || inherited.iter().any(|a| a.name == attr.name);
+                    if !taken {
+                        inherited.push(attr.clone());
+                    }
+                }
+            }
+
+            if let NodeKind::Element { attrs, .. } = self.kind_mut(id) {
+                attrs.extend(inherited);
+            }
+        }
+
+        diagnostics
+    }
+}
+
+/// Class-only elements (no name) define mixins.
+fn is_definition(node: Node<'_, '_>) -> bool {
+    node.as_element()
+        .is_some_and(|el| el.name().is_empty() && !el.classes().is_empty())
+}
+
+/// Locate `.class` in an element's header, falling back to the element span.
+fn class_span(node: Node<'_, '_>, class: &str) -> Span {
+    node.header_span(&format!(".{}", class)).unwrap_or(node.span())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::Severity;
+    use crate::tree::Value;
+
+    const DOC: &[u8] = b"\
+|.base-service
+  :version 1.0
+  :timeout 30
+  :retries 3
+
+|.logging :log-level info :timeout 5
+
+|.cached
+  :cache-ttl 3600
+
+|config
+  |cache[redis].base-service.logging.cached
+    :host cache.example.com
+    :retries 10
+  |web.logging.base-service.styled
+";
+
+    fn attr<'d>(doc: &'d Document<'_>, selector: &str, name: &str) -> Option<&'d Value<'d>> {
+        let node = doc.root().select_first(selector).unwrap().unwrap();
+        match node.kind() {
+            NodeKind::Element { attrs, .. } => attrs.iter().find(|a| a.name == name).map(|a| &a.value),
+            _ => None,
+        }
+    }
+
+    fn text(value: Option<&Value<'_>>) -> String {
+        match value {
+            Some(Value::Integer(s) | Value::Float(s) | Value::Bare(s)) => s.to_string(),
+            other => format!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_precedence() {
+        let mut doc = Document::parse(DOC).unwrap();
+        assert!(attr(&doc, "cache", "timeout").is_none());
+        doc.resolve_mixins();
+
+        // Own attributes win
+        assert_eq!(text(attr(&doc, "cache", "retries")), "10");
+        assert_eq!(text(attr(&doc, "cache", "host")), "cache.example.com");
+        // Earlier mixins win over later ones
+        assert_eq!(text(attr(&doc, "cache", "timeout")), "30");
+        assert_eq!(text(attr(&doc, "web", "timeout")), "5");
+        assert_eq!(text(attr(&doc, "cache", "cache-ttl")), "3600");
+        assert_eq!(text(attr(&doc, "web", "version")), "1.0");
+        assert!(attr(&doc, "web", "cache-ttl").is_none());
+    }
+
+    #[test]
+    fn test_inherited_attrs_keep_definition_spans() {
+        let mut doc = Document::parse(DOC).unwrap();
+        doc.resolve_mixins();
+        let cache = doc.root().select_first("cache").unwrap().unwrap().as_element().unwrap();
+        let names: Vec<_> = cache.attributes().iter().map(|a| a.name.as_ref()).collect();
+        assert_eq!(names, ["host", "retries", "version", "timeout", "log-level", "cache-ttl"]);
+        assert_eq!(doc.span_text(cache.attribute("version").unwrap().value_span), "1.0");
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let mut doc = Document::parse(DOC).unwrap();
+        let diagnostics = doc.resolve_mixins();
+        assert_eq!(diagnostics.len(), 1);
+        let d = &diagnostics[0];
+        assert_eq!((d.severity, d.code), (Severity::Warning, Code::UnknownMixin));
+        assert_eq!(doc.span_text(d.span), ".styled");
+
+        let mut doc = Document::parse(b"|.a :x 1\n|.a :x 2\n|el.a\n").unwrap();
+        let diagnostics = doc.resolve_mixins();
+        assert_eq!(diagnostics.iter().map(|d| d.code).collect::<Vec<_>>(), [Code::DuplicateMixin]);
+        assert_eq!(text(attr(&doc, "el", "x")), "1");
+
+        // Definitions no longer in the tree do not count
+        let mut doc = Document::parse(b"!include a\n  |.m :x 1\n|el.m\n").unwrap();
+        doc.expand_includes(crate::include::MemoryResolver::new().file("a.udon", "|p\n")).unwrap();
+        let diagnostics = doc.resolve_mixins();
+        assert_eq!(diagnostics.iter().map(|d| d.code).collect::<Vec<_>>(), [Code::UnknownMixin]);
+        let el = doc.root().select_first("el").unwrap().unwrap();
+        assert!(el.as_element().unwrap().attribute("x").is_none());
+    }
+}
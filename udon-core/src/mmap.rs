@@ -0,0 +1,122 @@
+//! Memory-mapped input (`mmap` feature).
+//!
+//! Parsing a mapped file keeps the zero-copy path: event content and tree
+//! text borrow straight from the mapping instead of a buffer the file was
+//! read into, and pages are only loaded as the parser reaches them.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use udon_core::mmap::MappedFile;
+//! use udon_core::tree::Document;
+//! use udon_core::{Event, Parser};
+//!
+//! // Events
+//! Parser::parse_file("large.udon", |event| {
+//!     if let Event::Name { content, .. } = event {
+//!         println!("{}", String::from_utf8_lossy(&content));
+//!     }
+//! })
+//! .unwrap();
+//!
+//! // Tree, borrowing from the mapping
+//! let file = MappedFile::open("large.udon").unwrap();
+//! let doc = Document::parse(&file).unwrap();
+//! ```
+
+use std::fs::File;
+use std::io;
+use std::ops::Deref;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::parser::{Control, Event, Parser};
+
+/// A file mapped read-only into memory; derefs to its bytes.
+///
+/// The mapping reflects the file as it is on disk, so the file must not be
+/// truncated or modified while it is mapped.
+#[derive(Debug)]
+pub struct MappedFile {
+    /// `None` for an empty file, which cannot be mapped on every platform.
+    map: Option<Mmap>,
+}
+
+impl MappedFile {
+    /// Map the file at `path`.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        if file.metadata()?.len() == 0 {
+            return Ok(MappedFile { map: None });
+        }
+        // SAFETY: the mapping is read-only; see the type docs for the
+        // requirement that the file is not changed while mapped.
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(MappedFile { map: Some(map) })
+    }
+}
+
+impl Deref for MappedFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.map.as_deref().unwrap_or_default()
+    }
+}
+
+impl AsRef<[u8]> for MappedFile {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl Parser<'_> {
+    /// Map the file at `path` and parse it, calling the callback for each
+    /// event as [`parse`](Parser::parse) does.
+    ///
+    /// Content borrows from the mapping, which is released on return.
+    pub fn parse_file<F, R>(path: impl AsRef<Path>, on_event: F) -> io::Result<()>
+    where
+        F: FnMut(Event<'_>) -> R,
+        R: Into<Control>,
+    {
+        let file = MappedFile::open(path)?;
+        Parser::new(&file).parse(on_event);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::{Document, NodeKind};
+
+    #[test]
+    fn test_mapped_file() {
+        let dir = std::env::temp_dir().join(format!("udon-mmap-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("doc.udon"), "|a[x] Hello\n|b\n").unwrap();
+        std::fs::write(dir.join("empty.udon"), "").unwrap();
+
+        let mut names = Vec::new();
+        Parser::parse_file(dir.join("doc.udon"), |event| {
+            if let Event::Name { content, .. } = event {
+                assert!(matches!(content, std::borrow::Cow::Borrowed(_)));
+                names.push(content.into_owned());
+            }
+        })
+        .unwrap();
+        assert_eq!(names, [b"a", b"b"]);
+
+        let file = MappedFile::open(dir.join("doc.udon")).unwrap();
+        let doc = Document::parse(&file).unwrap();
+        let text = doc.get_by_id("x").unwrap().first_child().unwrap();
+        assert!(matches!(text.kind(), NodeKind::Text(std::borrow::Cow::Borrowed("Hello"))));
+
+        assert!(MappedFile::open(dir.join("empty.udon")).unwrap().is_empty());
+        let missing = Parser::parse_file(dir.join("missing.udon"), |_| {}).unwrap_err();
+        assert_eq!(missing.kind(), io::ErrorKind::NotFound);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,347 @@
+//! Checked decoding of numeric values.
+//!
+//! [`Value::Integer`], [`Value::Float`], [`Value::Rational`] and
+//! [`Value::Complex`] keep their source text; the accessors here decode it
+//! following the number rules in `generator/values.desc`:
+//!
+//! - Integers: optional `+`/`-`, then decimal digits, or `0x`/`0o`/`0b`
+//!   (either case) followed by hex, octal or binary digits. `_` separators
+//!   may appear anywhere after the first digit.
+//! - Floats: decimal digits with a `.` fraction and/or an `e`/`E` exponent.
+//!   The grammar accepts an empty fraction (`1.`) or exponent (`1e`), which
+//!   decode as if absent.
+//! - Rationals: a decimal numerator, `/`, a decimal denominator, then `r`.
+//! - Complex: `re+imi` / `re-imi`, or a bare imaginary part (`5i`).
+//!
+//! Values that do not fit the requested type are an error, never wrapped or
+//! truncated.
+//!
+//! # Example
+//!
+//! ```
+//! use udon_core::tree::Document;
+//!
+//! let doc = Document::parse(b"|reg :mask 0xFF_FF :ratio 22/7r :z 0.5-4.5i\n").unwrap();
+//! let reg = doc.root().first_child().unwrap().as_element().unwrap();
+//! assert_eq!(reg.attr("mask").unwrap().as_u64(), Ok(0xFFFF));
+//! assert_eq!(reg.attr("ratio").unwrap().as_rational(), Ok((22, 7)));
+//! assert_eq!(reg.attr("z").unwrap().as_complex(), Ok((0.5, -4.5)));
+//! ```
+
+use std::fmt;
+
+use crate::tree::Value;
+
+/// Error returned by the numeric accessors on [`Value`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberError {
+    /// The value is not of a type the accessor decodes.
+    WrongType,
+    /// The source text is not a well-formed number (e.g. `0x` with no digits).
+    Invalid,
+    /// The number does not fit in the requested type.
+    Overflow,
+    /// A rational with a zero denominator.
+    ZeroDenominator,
+}
+
+impl fmt::Display for NumberError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NumberError::WrongType => "value is not of the requested numeric type",
+            NumberError::Invalid => "malformed number",
+            NumberError::Overflow => "number out of range",
+            NumberError::ZeroDenominator => "rational with zero denominator",
+        })
+    }
+}
+
+impl std::error::Error for NumberError {}
+
+impl Value<'_> {
+    /// Decode an integer value as `i64`.
+    pub fn as_i64(&self) -> Result<i64, NumberError> {
+        i64::try_from(self.as_i128()?).map_err(|_| NumberError::Overflow)
+    }
+
+    /// Decode an integer value as `u64`. Negative values are an overflow.
+    pub fn as_u64(&self) -> Result<u64, NumberError> {
+        u64::try_from(self.as_i128()?).map_err(|_| NumberError::Overflow)
+    }
+
+    /// Decode an integer value as `i128`.
+    pub fn as_i128(&self) -> Result<i128, NumberError> {
+        match self {
+            Value::Integer(s) => decode_integer(s),
+            _ => Err(NumberError::WrongType),
+        }
+    }
+
+    /// Decode an integer or float value as `f64`.
+    ///
+    /// Integers are converted with rounding; floats too large for `f64`
+    /// are an overflow rather than infinity.
+    pub fn as_f64(&self) -> Result<f64, NumberError> {
+        match self {
+            Value::Integer(s) => Ok(decode_integer(s)? as f64),
+            Value::Float(s) => decode_float(s),
+            _ => Err(NumberError::WrongType),
+        }
+    }
+
+    /// Decode a rational value as `(numerator, denominator)`.
+    ///
+    /// The fraction is returned as written, not reduced; the denominator is
+    /// always positive.
+    pub fn as_rational(&self) -> Result<(i64, i64), NumberError> {
+        let Value::Rational(s) = self else {
+            return Err(NumberError::WrongType);
+        };
+        let (num, den) = s
+            .strip_suffix('r')
+            .and_then(|s| s.split_once('/'))
+            .ok_or(NumberError::Invalid)?;
+        // Only a decimal numerator is allowed
+        if radix_prefix(split_sign(num).1).is_some() {
+            return Err(NumberError::Invalid);
+        }
+        let num = decode_integer(num)?;
+        let den = decode_digits(false, den, 10)?;
+        if den == 0 {
+            return Err(NumberError::ZeroDenominator);
+        }
+        let num = i64::try_from(num).map_err(|_| NumberError::Overflow)?;
+        let den = i64::try_from(den).map_err(|_| NumberError::Overflow)?;
+        Ok((num, den))
+    }
+
+    /// Decode a complex value as `(re, im)`. A bare imaginary part (`5i`)
+    /// has a zero real part.
+    pub fn as_complex(&self) -> Result<(f64, f64), NumberError> {
+        let Value::Complex(s) = self else {
+            return Err(NumberError::WrongType);
+        };
+        let s = s.strip_suffix('i').ok_or(NumberError::Invalid)?;
+        // The real/imaginary split is the last sign that does not start an
+        // exponent.
+        let bytes = s.as_bytes();
+        let split = (1..bytes.len())
+            .rev()
+            .find(|&i| matches!(bytes[i], b'+' | b'-') && !matches!(bytes[i - 1], b'e' | b'E'));
+        match split {
+            Some(i) => Ok((decode_float(&s[..i])?, decode_float(&s[i..])?)),
+            None => Ok((0.0, decode_float(s)?)),
+        }
+    }
+}
+
+fn split_sign(s: &str) -> (bool, &str) {
+    match s.as_bytes().first() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    }
+}
+
+fn radix_prefix(s: &str) -> Option<(u32, &str)> {
+    let radix = match s.get(..2)? {
+        "0x" | "0X" => 16,
+        "0o" | "0O" => 8,
+        "0b" | "0B" => 2,
+        _ => return None,
+    };
+    Some((radix, &s[2..]))
+}
+
+fn decode_integer(s: &str) -> Result<i128, NumberError> {
+    let (negative, rest) = split_sign(s);
+    match radix_prefix(rest) {
+        Some((radix, digits)) => decode_digits(negative, digits, radix),
+        None if rest.starts_with(|c: char| c.is_ascii_digit()) => decode_digits(negative, rest, 10),
+        None => Err(NumberError::Invalid),
+    }
+}
+
+/// Accumulate digits (skipping `_`) with overflow checks.
+fn decode_digits(negative: bool, digits: &str, radix: u32) -> Result<i128, NumberError> {
+    let mut magnitude: u128 = 0;
+    let mut any = false;
+    for c in digits.chars().filter(|&c| c != '_') {
+        let digit = c.to_digit(radix).ok_or(NumberError::Invalid)?;
+        magnitude = magnitude
+            .checked_mul(radix as u128)
+            .and_then(|m| m.checked_add(digit as u128))
+            .ok_or(NumberError::Overflow)?;
+        any = true;
+    }
+    if !any {
+        return Err(NumberError::Invalid);
+    }
+    if negative {
+        if magnitude > i128::MIN.unsigned_abs() {
+            return Err(NumberError::Overflow);
+        }
+        Ok((magnitude as i128).wrapping_neg())
+    } else {
+        i128::try_from(magnitude).map_err(|_| NumberError::Overflow)
+    }
+}
+
+fn decode_float(s: &str) -> Result<f64, NumberError> {
+    let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit() || b == b'_');
+    let (negative, rest) = split_sign(s);
+    let (mantissa, exponent) = match rest.find(['e', 'E']) {
+        Some(i) => (&rest[..i], Some(&rest[i + 1..])),
+        None => (rest, None),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if !int.starts_with(|c: char| c.is_ascii_digit()) || !is_digits(int) || !is_digits(frac) {
+        return Err(NumberError::Invalid);
+    }
+
+    let mut text = String::with_capacity(s.len() + 2);
+    if negative {
+        text.push('-');
+    }
+    text.extend(int.chars().filter(|&c| c != '_'));
+    text.push('.');
+    text.extend(frac.chars().filter(|&c| c != '_'));
+    if let Some(exponent) = exponent {
+        let (exp_negative, digits) = split_sign(exponent);
+        // Without a sign the exponent must start with a digit
+        let signed = digits.len() < exponent.len();
+        if !is_digits(digits) || (!signed && digits.starts_with('_')) {
+            return Err(NumberError::Invalid);
+        }
+        if digits.bytes().any(|b| b.is_ascii_digit()) {
+            text.push('e');
+            if exp_negative {
+                text.push('-');
+            }
+            text.extend(digits.chars().filter(|&c| c != '_'));
+        }
+    }
+
+    let value: f64 = text.parse().map_err(|_| NumberError::Invalid)?;
+    if value.is_infinite() {
+        return Err(NumberError::Overflow);
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::Document;
+
+    /// Parse `text` as an attribute value, as the grammar classifies it.
+    fn value(text: &str) -> Value<'static> {
+        let source = format!("|a :v {}\n", text);
+        let doc = Document::parse(source.as_bytes()).unwrap();
+        let el = doc.root().first_child().unwrap().as_element().unwrap();
+        match el.attr("v").unwrap() {
+            Value::Integer(s) => Value::Integer(s.to_string().into()),
+            Value::Float(s) => Value::Float(s.to_string().into()),
+            Value::Rational(s) => Value::Rational(s.to_string().into()),
+            Value::Complex(s) => Value::Complex(s.to_string().into()),
+            other => panic!("{} parsed as {:?}", text, other),
+        }
+    }
+
+    #[test]
+    fn test_integers() {
+        let cases: &[(&str, i128)] = &[
+            ("42", 42),
+            ("1_000_000", 1_000_000),
+            ("007", 7),
+            ("0", 0),
+            ("-17", -17),
+            ("+5", 5),
+            ("0x1F", 31),
+            ("0XdEaD_bEeF", 0xdead_beef),
+            ("0o17", 15),
+            ("0O7_55", 0o755),
+            ("0b1010", 10),
+            ("0B1111_0000", 0xf0),
+            ("-0x10", -16),
+            ("+0b11", 3),
+        ];
+        for &(text, expected) in cases {
+            let v = value(text);
+            assert!(matches!(v, Value::Integer(_)), "{}", text);
+            assert_eq!(v.as_i128(), Ok(expected), "{}", text);
+            assert_eq!(v.as_i64(), Ok(expected as i64), "{}", text);
+        }
+        assert_eq!(value("0x").as_i64(), Err(NumberError::Invalid));
+    }
+
+    #[test]
+    fn test_integer_overflow() {
+        assert_eq!(value("9223372036854775807").as_i64(), Ok(i64::MAX));
+        assert_eq!(value("-9223372036854775808").as_i64(), Ok(i64::MIN));
+        assert_eq!(value("9223372036854775808").as_i64(), Err(NumberError::Overflow));
+        assert_eq!(value("9223372036854775808").as_u64(), Ok(1 << 63));
+        assert_eq!(value("0xFFFF_FFFF_FFFF_FFFF").as_u64(), Ok(u64::MAX));
+        assert_eq!(value("0x1_0000_0000_0000_0000").as_u64(), Err(NumberError::Overflow));
+        assert_eq!(value("-1").as_u64(), Err(NumberError::Overflow));
+        assert_eq!(value("-0").as_u64(), Ok(0));
+
+        let min = format!("-0x8{}", "0".repeat(31));
+        assert_eq!(value(&min).as_i128(), Ok(i128::MIN));
+        let too_big = format!("0x8{}", "0".repeat(31));
+        assert_eq!(value(&too_big).as_i128(), Err(NumberError::Overflow));
+        let huge = format!("0x1{}", "0".repeat(32));
+        assert_eq!(value(&huge).as_i128(), Err(NumberError::Overflow));
+    }
+
+    #[test]
+    fn test_floats() {
+        let cases: &[(&str, f64)] = &[
+            ("3.25", 3.25),
+            ("1_000.5", 1000.5),
+            ("-0.5", -0.5),
+            ("1e3", 1000.0),
+            ("2.5E-2", 0.025),
+            ("6.02e+2_3", 6.02e23),
+            ("1.", 1.0),
+            ("1e", 1.0),
+        ];
+        for &(text, expected) in cases {
+            let v = value(text);
+            assert!(matches!(v, Value::Float(_)), "{}", text);
+            assert_eq!(v.as_f64(), Ok(expected), "{}", text);
+        }
+        assert_eq!(value("0x10").as_f64(), Ok(16.0));
+        assert_eq!(value("1e999").as_f64(), Err(NumberError::Overflow));
+        assert_eq!(value("1.5").as_i64(), Err(NumberError::WrongType));
+    }
+
+    #[test]
+    fn test_rationals() {
+        assert_eq!(value("1/3r").as_rational(), Ok((1, 3)));
+        assert_eq!(value("22/7r").as_rational(), Ok((22, 7)));
+        assert_eq!(value("-2/4r").as_rational(), Ok((-2, 4)));
+        assert_eq!(value("1_000/3r").as_rational(), Ok((1000, 3)));
+        assert_eq!(value("1/0r").as_rational(), Err(NumberError::ZeroDenominator));
+        assert_eq!(value("1/99999999999999999999r").as_rational(), Err(NumberError::Overflow));
+        assert_eq!(value("1/3r").as_f64(), Err(NumberError::WrongType));
+    }
+
+    #[test]
+    fn test_complex() {
+        let cases: &[(&str, (f64, f64))] = &[
+            ("3+4i", (3.0, 4.0)),
+            ("5i", (0.0, 5.0)),
+            ("1.5-2.5i", (1.5, -2.5)),
+            ("-1+2i", (-1.0, 2.0)),
+            ("1e2+3e-1i", (100.0, 0.3)),
+            ("2.5e+1i", (0.0, 25.0)),
+            ("1.0+1_000i", (1.0, 1000.0)),
+        ];
+        for &(text, expected) in cases {
+            let v = value(text);
+            assert!(matches!(v, Value::Complex(_)), "{}", text);
+            assert_eq!(v.as_complex(), Ok(expected), "{}", text);
+        }
+    }
+}
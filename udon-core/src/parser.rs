@@ -1,12 +1,22 @@
 //! Generated parser - DO NOT EDIT
 //!
 //! Generated by descent from udon.desc
+//! Post-processed by generator/postprocess; hand-written parts live in
+//! the `control`, `event` and `streaming` submodules.
 //!
 //! Callback-based recursive descent parser.
 //! Call stack = element stack. True recursion handles nesting naturally.
 
 use std::ops::Range;
 use phf::phf_map;
+
+mod control;
+mod event;
+mod streaming;
+
+pub use control::Control;
+use control::{emit, ControlState};
+pub use streaming::{Events, StreamingParser};
 /// Events emitted by the parser.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Event<'a> {
@@ -206,6 +216,8 @@ pub enum ParseErrorCode {
     UnclosedText,
     NoTabs,
     UnclosedInterpolation,
+    /// A streaming parser's buffer limit was exceeded.
+    BufferOverflow,
 }
 
 /// Callback-based parser.
@@ -220,6 +232,8 @@ pub struct Parser<'a> {
     prepend_buf: Vec<u8>,
     line: u32,
     column: u32,
+    /// Skip and stop requests from the callback.
+    control: ControlState<'a>,
 }
 
 #[allow(unused_variables, dead_code)]
@@ -227,6 +241,7 @@ impl<'a> Parser<'a> {
     /// Create a new parser for the given input.
     pub fn new(input: &'a [u8]) -> Self {
         Self {
+            control: ControlState::default(),
             input,
             pos: 0,
             mark_pos: 0,
@@ -239,13 +254,17 @@ impl<'a> Parser<'a> {
 
     /// Parse the input, calling the callback for each event.
     ///
-    /// The callback receives events in document order.
-    /// For backpressure, have the callback send to a bounded channel.
-    pub fn parse<F>(mut self, mut on_event: F)
+    /// The callback receives events in document order. It may return
+    /// nothing, or a `Control` to skip the rest of the current element or
+    /// stop parsing.
+    /// For input read asynchronously, with backpressure, see
+    /// `stream::EventStream` (`async` feature).
+    pub fn parse<F, R>(mut self, mut on_event: F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> R,
+        R: Into<Control>,
     {
-        self.parse_document(&mut on_event);
+        self.parse_document(&mut |event| on_event(event).into());
     }
 
     // ========== Helpers ==========
@@ -519,16 +538,16 @@ impl<'a> Parser<'a> {
     /// Returns true if a keyword matched (event emitted), false otherwise.
     fn lookup_bare_kw<F>(&mut self, on_event: &mut F) -> bool
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         let content = self.term();
         if let Some(&id) = BARE_KW_KEYWORDS.get(content.as_ref()) {
             let span = self.span_from_mark();
             match id {
-                0 => on_event(Event::BoolTrue { content, span }),
-                1 => on_event(Event::BoolFalse { content, span }),
-                2 => on_event(Event::Nil { content, span }),
-                3 => on_event(Event::Nil { content, span }),
+                0 => emit!(self, on_event, Event::BoolTrue { content, span }),
+                1 => emit!(self, on_event, Event::BoolFalse { content, span }),
+                2 => emit!(self, on_event, Event::Nil { content, span }),
+                3 => emit!(self, on_event, Event::Nil { content, span }),
                 _ => unreachable!("keyword map contains only valid ids"),
             }
             true
@@ -540,7 +559,7 @@ impl<'a> Parser<'a> {
     /// Look up and emit keyword, or call fallback function.
     fn lookup_bare_kw_or_fallback<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         if !self.lookup_bare_kw(on_event) {
             self.parse_emit_bare_value(on_event);
@@ -551,7 +570,7 @@ impl<'a> Parser<'a> {
     /// Parse document
     fn parse_document<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         let mut col: i32 = 0;
         #[derive(Clone, Copy)]
@@ -574,7 +593,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\t') => {
-                    on_event(Event::Error { code: ParseErrorCode::NoTabs, span: self.span() });
+                    emit!(self, on_event, Event::Error { code: ParseErrorCode::NoTabs, span: self.span() });
                     self.scan_to1(b'\n');
                     state = State::Line;
                     continue;
@@ -761,7 +780,7 @@ impl<'a> Parser<'a> {
     /// Parse count_indent -> INT
     fn parse_count_indent<F>(&mut self, on_event: &mut F) -> i32
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         let mut result: i32 = 0;
         loop {
@@ -784,7 +803,7 @@ impl<'a> Parser<'a> {
     /// Parse parse_element_identity
     fn parse_parse_element_identity<F>(&mut self, close: u8, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         #[derive(Clone, Copy)]
         enum State { Identity, QuotedName, PostName, PostSuffix, Bracket, BracketClose, PostBracket, Class, PostClass,  }
@@ -905,7 +924,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::Attr { content: std::borrow::Cow::Borrowed(b"id"), span: self.span() });
+                    emit!(self, on_event, Event::Attr { content: std::borrow::Cow::Borrowed(b"id"), span: self.span() });
                     self.parse_value_bracket(on_event);
                     state = State::BracketClose;
                     continue;
@@ -959,13 +978,13 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if Self::is_xlbl_start(b) => {
-                    on_event(Event::Attr { content: std::borrow::Cow::Borrowed(b"class"), span: self.span() });
+                    emit!(self, on_event, Event::Attr { content: std::borrow::Cow::Borrowed(b"class"), span: self.span() });
                     self.parse_class_name(on_event);
                     state = State::PostClass;
                     continue;
                         }
                         Some(b'\'') => {
-                    on_event(Event::Attr { content: std::borrow::Cow::Borrowed(b"class"), span: self.span() });
+                    emit!(self, on_event, Event::Attr { content: std::borrow::Cow::Borrowed(b"class"), span: self.span() });
                     self.advance();
                     self.parse_quoted_class(on_event);
                     state = State::PostClass;
@@ -1005,10 +1024,11 @@ impl<'a> Parser<'a> {
     /// Parse element -> Element
     fn parse_element<F>(&mut self, elem_col: i32, parent_col: i32, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         let start_span = self.span();
-        on_event(Event::ElementStart { span: start_span.clone() });
+        self.control.block_col = elem_col;
+        emit!(self, on_event, Event::ElementStart { span: start_span.clone() });
         let mut content_base: i32 = -1;
         let mut col: i32 = 0;
         #[derive(Clone, Copy)]
@@ -1023,7 +1043,7 @@ impl<'a> Parser<'a> {
                 }
                 State::PostIdentity => {
                     if self.eof() {
-                        on_event(Event::ElementEnd { span: self.span() });
+                        emit!(self, on_event, Event::ElementEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -1046,7 +1066,7 @@ impl<'a> Parser<'a> {
                 }
                 State::PreContent => {
                     if self.eof() {
-                        on_event(Event::ElementEnd { span: self.span() });
+                        emit!(self, on_event, Event::ElementEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -1088,7 +1108,7 @@ impl<'a> Parser<'a> {
                 }
                 State::CheckSamelinePipe => {
                     if self.eof() {
-                        on_event(Event::ElementEnd { span: self.span() });
+                        emit!(self, on_event, Event::ElementEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -1111,12 +1131,12 @@ impl<'a> Parser<'a> {
                 }
                 State::CheckSamelineElemCol => {
                     if self.eof() {
-                        on_event(Event::ElementEnd { span: self.span() });
+                        emit!(self, on_event, Event::ElementEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
                         _ if self.col() - 1 <= elem_col => {
-                    on_event(Event::ElementEnd { span: self.span() });
+                    emit!(self, on_event, Event::ElementEnd { span: self.span() });
                     return;
                         }
                         _ => {
@@ -1128,7 +1148,7 @@ impl<'a> Parser<'a> {
                 }
                 State::CheckSamelineSemi => {
                     if self.eof() {
-                        on_event(Event::ElementEnd { span: self.span() });
+                        emit!(self, on_event, Event::ElementEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -1147,7 +1167,7 @@ impl<'a> Parser<'a> {
                 }
                 State::CheckSamelineBang => {
                     if self.eof() {
-                        on_event(Event::ElementEnd { span: self.span() });
+                        emit!(self, on_event, Event::ElementEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -1166,7 +1186,7 @@ impl<'a> Parser<'a> {
                 }
                 State::PostSamelineInline => {
                     if self.eof() {
-                    on_event(Event::ElementEnd { span: self.span() });
+                    emit!(self, on_event, Event::ElementEnd { span: self.span() });
                     return;
                     }
                     match self.peek() {
@@ -1184,7 +1204,7 @@ impl<'a> Parser<'a> {
                 }
                 State::PostChild => {
                     if self.eof() {
-                        on_event(Event::ElementEnd { span: self.span() });
+                        emit!(self, on_event, Event::ElementEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -1210,7 +1230,7 @@ impl<'a> Parser<'a> {
                 }
                 State::CheckPostPipeCol => {
                     if self.eof() {
-                        on_event(Event::ElementEnd { span: self.span() });
+                        emit!(self, on_event, Event::ElementEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -1227,13 +1247,13 @@ impl<'a> Parser<'a> {
                 }
                 State::Children => {
                     if self.eof() {
-                        on_event(Event::ElementEnd { span: self.span() });
+                        emit!(self, on_event, Event::ElementEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
                         Some(b'\n') => {
                     self.advance();
-                    on_event(Event::BlankLine { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
+                    emit!(self, on_event, Event::BlankLine { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
                     state = State::AfterNewline;
                     continue;
                         }
@@ -1244,7 +1264,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\t') => {
-                    on_event(Event::Error { code: ParseErrorCode::NoTabs, span: self.span() });
+                    emit!(self, on_event, Event::Error { code: ParseErrorCode::NoTabs, span: self.span() });
                     self.scan_to1(b'\n');
                     continue;
                         }
@@ -1257,13 +1277,13 @@ impl<'a> Parser<'a> {
                 }
                 State::AfterNewline => {
                     if self.eof() {
-                    on_event(Event::ElementEnd { span: self.span() });
+                    emit!(self, on_event, Event::ElementEnd { span: self.span() });
                     return;
                     }
                     match self.peek() {
                         Some(b'\n') => {
                     self.advance();
-                    on_event(Event::BlankLine { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
+                    emit!(self, on_event, Event::BlankLine { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
                     continue;
                         }
                         Some(b' ') => {
@@ -1273,7 +1293,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\t') => {
-                    on_event(Event::Error { code: ParseErrorCode::NoTabs, span: self.span() });
+                    emit!(self, on_event, Event::Error { code: ParseErrorCode::NoTabs, span: self.span() });
                     self.scan_to1(b'\n');
                     state = State::Children;
                     continue;
@@ -1287,7 +1307,7 @@ impl<'a> Parser<'a> {
                 }
                 State::ChildrenWs => {
                     if self.eof() {
-                        on_event(Event::ElementEnd { span: self.span() });
+                        emit!(self, on_event, Event::ElementEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -1302,7 +1322,7 @@ impl<'a> Parser<'a> {
                         }
                         Some(b'\n') => {
                     self.advance();
-                    on_event(Event::BlankLine { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
+                    emit!(self, on_event, Event::BlankLine { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
                     state = State::AfterNewline;
                     continue;
                         }
@@ -1314,17 +1334,17 @@ impl<'a> Parser<'a> {
                 }
                 State::AtContentBase => {
                     if self.eof() {
-                        on_event(Event::ElementEnd { span: self.span() });
+                        emit!(self, on_event, Event::ElementEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
                         _ if col <= elem_col => {
-                    on_event(Event::ElementEnd { span: self.span() });
+                    emit!(self, on_event, Event::ElementEnd { span: self.span() });
                     return;
                         }
                         Some(b'\n') => {
                     self.advance();
-                    on_event(Event::BlankLine { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
+                    emit!(self, on_event, Event::BlankLine { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
                     state = State::AfterNewline;
                     continue;
                         }
@@ -1375,12 +1395,12 @@ impl<'a> Parser<'a> {
                 }
                 State::CheckChild => {
                     if self.eof() {
-                        on_event(Event::ElementEnd { span: self.span() });
+                        emit!(self, on_event, Event::ElementEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
                         _ if col <= elem_col => {
-                    on_event(Event::ElementEnd { span: self.span() });
+                    emit!(self, on_event, Event::ElementEnd { span: self.span() });
                     return;
                         }
                         _ if content_base >= 0 && col >= content_base => {
@@ -1395,7 +1415,7 @@ impl<'a> Parser<'a> {
                 }
                 State::ChildDispatch => {
                     if self.eof() {
-                        on_event(Event::ElementEnd { span: self.span() });
+                        emit!(self, on_event, Event::ElementEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -1405,7 +1425,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ if col < content_base => {
-                    on_event(Event::Warning { content: std::borrow::Cow::Borrowed(b"Inconsistent indentation"), span: self.span() });
+                    emit!(self, on_event, Event::Warning { content: std::borrow::Cow::Borrowed(b"Inconsistent indentation"), span: self.span() });
                     content_base = col;
                     state = State::ChildDispatchDo;
                     continue;
@@ -1418,7 +1438,7 @@ impl<'a> Parser<'a> {
                 }
                 State::ChildDispatchDo => {
                     if self.eof() {
-                        on_event(Event::ElementEnd { span: self.span() });
+                        emit!(self, on_event, Event::ElementEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -1468,7 +1488,7 @@ impl<'a> Parser<'a> {
                 }
                 State::ChildCheckAt => {
                     if self.eof() {
-                        on_event(Event::ElementEnd { span: self.span() });
+                        emit!(self, on_event, Event::ElementEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -1492,7 +1512,7 @@ impl<'a> Parser<'a> {
                 }
                 State::ChildCheckFreeform => {
                     if self.eof() {
-                        on_event(Event::ElementEnd { span: self.span() });
+                        emit!(self, on_event, Event::ElementEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -1510,7 +1530,7 @@ impl<'a> Parser<'a> {
                 }
                 State::ChildCheckFreeform2 => {
                     if self.eof() {
-                        on_event(Event::ElementEnd { span: self.span() });
+                        emit!(self, on_event, Event::ElementEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -1529,7 +1549,7 @@ impl<'a> Parser<'a> {
                 }
                 State::ChildApos => {
                     if self.eof() {
-                        on_event(Event::ElementEnd { span: self.span() });
+                        emit!(self, on_event, Event::ElementEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -1572,7 +1592,7 @@ impl<'a> Parser<'a> {
                 }
                 State::ChildPipe => {
                     if self.eof() {
-                        on_event(Event::ElementEnd { span: self.span() });
+                        emit!(self, on_event, Event::ElementEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -1600,7 +1620,7 @@ impl<'a> Parser<'a> {
                 }
                 State::AfterContent => {
                     if self.eof() {
-                    on_event(Event::ElementEnd { span: self.span() });
+                    emit!(self, on_event, Event::ElementEnd { span: self.span() });
                     return;
                     }
                     match self.peek() {
@@ -1616,7 +1636,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\t') => {
-                    on_event(Event::Error { code: ParseErrorCode::NoTabs, span: self.span() });
+                    emit!(self, on_event, Event::Error { code: ParseErrorCode::NoTabs, span: self.span() });
                     self.scan_to1(b'\n');
                     continue;
                         }
@@ -1634,12 +1654,12 @@ impl<'a> Parser<'a> {
     /// Parse name -> Name
     fn parse_name<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         self.mark();
         loop {
             if self.eof() {
-                on_event(Event::Name { content: self.term(), span: self.span_from_mark() });
+                emit!(self, on_event, Event::Name { content: self.term(), span: self.span_from_mark() });
                 return;
             }
             match self.peek() {
@@ -1649,7 +1669,7 @@ impl<'a> Parser<'a> {
                 }
                 _ => {
                     self.set_term(0);
-                    on_event(Event::Name { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Name { content: self.term(), span: self.span_from_mark() });
                     return;
                 }
             }
@@ -1659,7 +1679,7 @@ impl<'a> Parser<'a> {
     /// Parse skip_single_quoted
     fn parse_skip_single_quoted<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         loop {
             match self.scan_to3(b'\n', b'\'', b'\\') {
@@ -1675,7 +1695,7 @@ impl<'a> Parser<'a> {
                     self.advance();
                 }
                 None => {
-                    on_event(Event::Error { code: ParseErrorCode::Unclosed, span: self.span() });
+                    emit!(self, on_event, Event::Error { code: ParseErrorCode::Unclosed, span: self.span() });
                     return;
                 }
                 _ => unreachable!("scan_to only returns target chars"),
@@ -1686,18 +1706,18 @@ impl<'a> Parser<'a> {
     /// Parse quoted_name -> Name
     fn parse_quoted_name<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         self.mark();
         loop {
             if self.eof() {
-                on_event(Event::Name { content: self.term(), span: self.span_from_mark() });
+                emit!(self, on_event, Event::Name { content: self.term(), span: self.span_from_mark() });
                 return;
             }
                     self.parse_skip_single_quoted(on_event);
                     self.set_term(0);
                     self.advance();
-                    on_event(Event::Name { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Name { content: self.term(), span: self.span_from_mark() });
                     return;
         }
     }
@@ -1705,12 +1725,12 @@ impl<'a> Parser<'a> {
     /// Parse class_name -> BareValue
     fn parse_class_name<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         self.mark();
         loop {
             if self.eof() {
-                on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                 return;
             }
             match self.peek() {
@@ -1720,7 +1740,7 @@ impl<'a> Parser<'a> {
                 }
                 _ => {
                     self.set_term(0);
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                 }
             }
@@ -1730,18 +1750,18 @@ impl<'a> Parser<'a> {
     /// Parse quoted_class -> BareValue
     fn parse_quoted_class<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         self.mark();
         loop {
             if self.eof() {
-                on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                 return;
             }
                     self.parse_skip_single_quoted(on_event);
                     self.set_term(0);
                     self.advance();
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
         }
     }
@@ -1749,7 +1769,7 @@ impl<'a> Parser<'a> {
     /// Parse suffix
     fn parse_suffix<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         loop {
             if self.eof() {
@@ -1757,26 +1777,26 @@ impl<'a> Parser<'a> {
             }
             match self.peek() {
                 Some(b'?') => {
-                    on_event(Event::Attr { content: std::borrow::Cow::Borrowed(b"?"), span: self.span() });
-                    on_event(Event::BoolTrue { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
+                    emit!(self, on_event, Event::Attr { content: std::borrow::Cow::Borrowed(b"?"), span: self.span() });
+                    emit!(self, on_event, Event::BoolTrue { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
                     self.advance();
                     return;
                 }
                 Some(b'!') => {
-                    on_event(Event::Attr { content: std::borrow::Cow::Borrowed(b"!"), span: self.span() });
-                    on_event(Event::BoolTrue { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
+                    emit!(self, on_event, Event::Attr { content: std::borrow::Cow::Borrowed(b"!"), span: self.span() });
+                    emit!(self, on_event, Event::BoolTrue { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
                     self.advance();
                     return;
                 }
                 Some(b'*') => {
-                    on_event(Event::Attr { content: std::borrow::Cow::Borrowed(b"*"), span: self.span() });
-                    on_event(Event::BoolTrue { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
+                    emit!(self, on_event, Event::Attr { content: std::borrow::Cow::Borrowed(b"*"), span: self.span() });
+                    emit!(self, on_event, Event::BoolTrue { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
                     self.advance();
                     return;
                 }
                 Some(b'+') => {
-                    on_event(Event::Attr { content: std::borrow::Cow::Borrowed(b"+"), span: self.span() });
-                    on_event(Event::BoolTrue { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
+                    emit!(self, on_event, Event::Attr { content: std::borrow::Cow::Borrowed(b"+"), span: self.span() });
+                    emit!(self, on_event, Event::BoolTrue { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
                     self.advance();
                     return;
                 }
@@ -1790,7 +1810,7 @@ impl<'a> Parser<'a> {
     /// Parse block_attr
     fn parse_block_attr<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         #[derive(Clone, Copy)]
         enum State { Key, ValueStart, PostValue,  }
@@ -1833,7 +1853,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n') => {
-                    on_event(Event::BoolTrue { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
+                    emit!(self, on_event, Event::BoolTrue { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
                     return;
                         }
                         _ => {
@@ -1865,7 +1885,7 @@ impl<'a> Parser<'a> {
     /// Parse sameline_attr
     fn parse_sameline_attr<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         #[derive(Clone, Copy)]
         enum State { Key, ValueStart,  }
@@ -1908,7 +1928,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n' | b':' | b'|') => {
-                    on_event(Event::BoolTrue { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
+                    emit!(self, on_event, Event::BoolTrue { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
                     return;
                         }
                         _ => {
@@ -1924,7 +1944,7 @@ impl<'a> Parser<'a> {
     /// Parse sameline_attr_embedded
     fn parse_sameline_attr_embedded<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         #[derive(Clone, Copy)]
         enum State { Key, ValueStart,  }
@@ -1967,7 +1987,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n' | b':' | b'|' | b'}') => {
-                    on_event(Event::BoolTrue { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
+                    emit!(self, on_event, Event::BoolTrue { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
                     return;
                         }
                         _ => {
@@ -1983,12 +2003,12 @@ impl<'a> Parser<'a> {
     /// Parse attr_key -> Attr
     fn parse_attr_key<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         self.mark();
         loop {
             if self.eof() {
-                on_event(Event::Attr { content: self.term(), span: self.span_from_mark() });
+                emit!(self, on_event, Event::Attr { content: self.term(), span: self.span_from_mark() });
                 return;
             }
             match self.peek() {
@@ -1998,7 +2018,7 @@ impl<'a> Parser<'a> {
                 }
                 _ => {
                     self.set_term(0);
-                    on_event(Event::Attr { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Attr { content: self.term(), span: self.span_from_mark() });
                     return;
                 }
             }
@@ -2008,18 +2028,18 @@ impl<'a> Parser<'a> {
     /// Parse attr_key_quoted -> Attr
     fn parse_attr_key_quoted<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         self.mark();
         loop {
             if self.eof() {
-                on_event(Event::Attr { content: self.term(), span: self.span_from_mark() });
+                emit!(self, on_event, Event::Attr { content: self.term(), span: self.span_from_mark() });
                 return;
             }
                     self.parse_skip_single_quoted(on_event);
                     self.set_term(0);
                     self.advance();
-                    on_event(Event::Attr { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Attr { content: self.term(), span: self.span_from_mark() });
                     return;
         }
     }
@@ -2027,31 +2047,31 @@ impl<'a> Parser<'a> {
     /// Parse block_reference -> Reference
     fn parse_block_reference<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         self.mark();
                     self.scan_to1(b']');
                     self.set_term(0);
                     self.advance();
-        on_event(Event::Reference { content: self.term(), span: self.span_from_mark() });
+        emit!(self, on_event, Event::Reference { content: self.term(), span: self.span_from_mark() });
     }
 
     /// Parse attr_reference -> Reference
     fn parse_attr_reference<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         self.mark();
                     self.scan_to1(b']');
                     self.set_term(0);
                     self.advance();
-        on_event(Event::Reference { content: self.term(), span: self.span_from_mark() });
+        emit!(self, on_event, Event::Reference { content: self.term(), span: self.span_from_mark() });
     }
 
     /// Parse value
     fn parse_value<F>(&mut self, space_term: i32, bracket: u8, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         loop {
             if self.eof() {
@@ -2084,26 +2104,26 @@ impl<'a> Parser<'a> {
     /// Parse value_bracket -> BareValue
     fn parse_value_bracket<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         self.mark();
         loop {
             match self.scan_to3(b'\n', b']', b' ') {
                 Some(b']') => {
                     self.set_term(0);
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                 }
                 Some(b' ') => {
                     self.set_term(0);
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                 }
                 Some(b'\n') => {
                     self.advance();
                 }
                 None => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                 }
                 _ => unreachable!("scan_to only returns target chars"),
@@ -2114,7 +2134,7 @@ impl<'a> Parser<'a> {
     /// Parse double_quoted -> StringValue
     fn parse_double_quoted<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         self.mark();
         loop {
@@ -2122,7 +2142,7 @@ impl<'a> Parser<'a> {
                 Some(b'"') => {
                     self.set_term(0);
                     self.advance();
-                    on_event(Event::StringValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::StringValue { content: self.term(), span: self.span_from_mark() });
                     return;
                 }
                 Some(b'\\') => {
@@ -2134,8 +2154,8 @@ impl<'a> Parser<'a> {
                     self.advance();
                 }
                 None => {
-                    on_event(Event::StringValue { content: self.term(), span: self.span_from_mark() });
-                    on_event(Event::Error { code: ParseErrorCode::UnclosedStringValue, span: self.span() });
+                    emit!(self, on_event, Event::StringValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Error { code: ParseErrorCode::UnclosedStringValue, span: self.span() });
                     return;
                 }
                 _ => unreachable!("scan_to only returns target chars"),
@@ -2146,7 +2166,7 @@ impl<'a> Parser<'a> {
     /// Parse single_quoted -> StringValue
     fn parse_single_quoted<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         self.mark();
         loop {
@@ -2154,7 +2174,7 @@ impl<'a> Parser<'a> {
                 Some(b'\'') => {
                     self.set_term(0);
                     self.advance();
-                    on_event(Event::StringValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::StringValue { content: self.term(), span: self.span_from_mark() });
                     return;
                 }
                 Some(b'\\') => {
@@ -2166,8 +2186,8 @@ impl<'a> Parser<'a> {
                     self.advance();
                 }
                 None => {
-                    on_event(Event::StringValue { content: self.term(), span: self.span_from_mark() });
-                    on_event(Event::Error { code: ParseErrorCode::UnclosedStringValue, span: self.span() });
+                    emit!(self, on_event, Event::StringValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Error { code: ParseErrorCode::UnclosedStringValue, span: self.span() });
                     return;
                 }
                 _ => unreachable!("scan_to only returns target chars"),
@@ -2178,10 +2198,10 @@ impl<'a> Parser<'a> {
     /// Parse array -> Array
     fn parse_array<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         let start_span = self.span();
-        on_event(Event::ArrayStart { span: start_span.clone() });
+        emit!(self, on_event, Event::ArrayStart { span: start_span.clone() });
         #[derive(Clone, Copy)]
         enum State { Entry, Items,  }
         let mut state = State::Entry;
@@ -2189,7 +2209,7 @@ impl<'a> Parser<'a> {
             match state {
                 State::Entry => {
                     if self.eof() {
-                        on_event(Event::Error { code: ParseErrorCode::UnclosedArray, span: self.span() });
+                        emit!(self, on_event, Event::Error { code: ParseErrorCode::UnclosedArray, span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -2205,13 +2225,13 @@ impl<'a> Parser<'a> {
                 }
                 State::Items => {
                     if self.eof() {
-                        on_event(Event::Error { code: ParseErrorCode::UnclosedArray, span: self.span() });
+                        emit!(self, on_event, Event::Error { code: ParseErrorCode::UnclosedArray, span: self.span() });
                         return;
                     }
                     match self.peek() {
                         Some(b']') => {
                     self.advance();
-                    on_event(Event::ArrayEnd { span: self.span() });
+                    emit!(self, on_event, Event::ArrayEnd { span: self.span() });
                     return;
                         }
                         Some(b' ' | b'\t' | b'\n') => {
@@ -2231,7 +2251,7 @@ impl<'a> Parser<'a> {
     /// Parse prose
     fn parse_prose<F>(&mut self, line_col: i32, parent_col: i32, prepend: &'static [u8], on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
                     if parent_col >= 0 && line_col <= parent_col {
                     return;
@@ -2248,7 +2268,7 @@ impl<'a> Parser<'a> {
     /// Parse prose_backticks
     fn parse_prose_backticks<F>(&mut self, line_col: i32, parent_col: i32, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
                     if parent_col >= 0 && line_col <= parent_col {
                     return;
@@ -2265,7 +2285,7 @@ impl<'a> Parser<'a> {
     /// Parse text_backticks
     fn parse_text_backticks<F>(&mut self, line_col: i32, parent_col: i32, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         #[derive(Clone, Copy)]
         enum State { Entry, Main, CheckPipe, CheckSemi, CheckBang, AfterInline,  }
@@ -2283,7 +2303,7 @@ impl<'a> Parser<'a> {
                     match self.scan_to4(b'\n', b'|', b';', b'!') {
                         Some(b'\n') => {
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'|') => {
@@ -2303,7 +2323,7 @@ impl<'a> Parser<'a> {
                         }
                         None => {
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => unreachable!("scan_to only returns target chars"),
@@ -2312,13 +2332,13 @@ impl<'a> Parser<'a> {
                 State::CheckPipe => {
                     if self.eof() {
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
                         Some(b'{') => {
                     self.set_term(-1);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     self.advance();
                     self.parse_embedded(on_event);
                     state = State::AfterInline;
@@ -2333,13 +2353,13 @@ impl<'a> Parser<'a> {
                 State::CheckSemi => {
                     if self.eof() {
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
                         Some(b'{') => {
                     self.set_term(-1);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     self.advance();
                     self.parse_brace_comment(on_event);
                     state = State::AfterInline;
@@ -2354,13 +2374,13 @@ impl<'a> Parser<'a> {
                 State::CheckBang => {
                     if self.eof() {
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
                         Some(b'{') => {
                     self.set_term(-1);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     self.advance();
                     self.parse_sameline_directive(on_event);
                     state = State::AfterInline;
@@ -2394,10 +2414,10 @@ impl<'a> Parser<'a> {
     /// Parse freeform -> Freeform
     fn parse_freeform<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         let start_span = self.span();
-        on_event(Event::FreeformStart { span: start_span.clone() });
+        emit!(self, on_event, Event::FreeformStart { span: start_span.clone() });
         #[derive(Clone, Copy)]
         enum State { Lang, LineStart, Content, Line, MaybeEnd1, MaybeEnd2,  }
         let mut state = State::Lang;
@@ -2405,7 +2425,7 @@ impl<'a> Parser<'a> {
             match state {
                 State::Lang => {
                     if self.eof() {
-                        on_event(Event::Error { code: ParseErrorCode::UnclosedFreeform, span: self.span() });
+                        emit!(self, on_event, Event::Error { code: ParseErrorCode::UnclosedFreeform, span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -2429,7 +2449,7 @@ impl<'a> Parser<'a> {
                 }
                 State::LineStart => {
                     if self.eof() {
-                        on_event(Event::Error { code: ParseErrorCode::UnclosedFreeform, span: self.span() });
+                        emit!(self, on_event, Event::Error { code: ParseErrorCode::UnclosedFreeform, span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -2446,7 +2466,7 @@ impl<'a> Parser<'a> {
                 }
                 State::Content => {
                     if self.eof() {
-                        on_event(Event::Error { code: ParseErrorCode::UnclosedFreeform, span: self.span() });
+                        emit!(self, on_event, Event::Error { code: ParseErrorCode::UnclosedFreeform, span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -2469,14 +2489,14 @@ impl<'a> Parser<'a> {
                 State::Line => {
                     self.scan_to1(b'\n');
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     self.advance();
                     state = State::LineStart;
                     continue;
                 }
                 State::MaybeEnd1 => {
                     if self.eof() {
-                        on_event(Event::Error { code: ParseErrorCode::UnclosedFreeform, span: self.span() });
+                        emit!(self, on_event, Event::Error { code: ParseErrorCode::UnclosedFreeform, span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -2493,13 +2513,13 @@ impl<'a> Parser<'a> {
                 }
                 State::MaybeEnd2 => {
                     if self.eof() {
-                        on_event(Event::Error { code: ParseErrorCode::UnclosedFreeform, span: self.span() });
+                        emit!(self, on_event, Event::Error { code: ParseErrorCode::UnclosedFreeform, span: self.span() });
                         return;
                     }
                     match self.peek() {
                         Some(b'`') => {
                     self.advance();
-                    on_event(Event::FreeformEnd { span: self.span() });
+                    emit!(self, on_event, Event::FreeformEnd { span: self.span() });
                     return;
                         }
                         _ => {
@@ -2515,7 +2535,7 @@ impl<'a> Parser<'a> {
     /// Parse text
     fn parse_text<F>(&mut self, line_col: i32, parent_col: i32, prepend: &'static [u8], on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         #[derive(Clone, Copy)]
         enum State { Entry, Main, CheckPipe, CheckSemi, CheckBang, AfterInline,  }
@@ -2532,7 +2552,7 @@ impl<'a> Parser<'a> {
                     match self.scan_to4(b'\n', b'|', b';', b'!') {
                         Some(b'\n') => {
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'|') => {
@@ -2552,7 +2572,7 @@ impl<'a> Parser<'a> {
                         }
                         None => {
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => unreachable!("scan_to only returns target chars"),
@@ -2561,13 +2581,13 @@ impl<'a> Parser<'a> {
                 State::CheckPipe => {
                     if self.eof() {
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
                         Some(b'{') => {
                     self.set_term(-1);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     self.advance();
                     self.parse_embedded(on_event);
                     state = State::AfterInline;
@@ -2582,13 +2602,13 @@ impl<'a> Parser<'a> {
                 State::CheckSemi => {
                     if self.eof() {
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
                         Some(b'{') => {
                     self.set_term(-1);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     self.advance();
                     self.parse_brace_comment(on_event);
                     state = State::AfterInline;
@@ -2603,13 +2623,13 @@ impl<'a> Parser<'a> {
                 State::CheckBang => {
                     if self.eof() {
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
                         Some(b'{') => {
                     self.set_term(-1);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     self.advance();
                     self.parse_sameline_directive(on_event);
                     state = State::AfterInline;
@@ -2643,7 +2663,7 @@ impl<'a> Parser<'a> {
     /// Parse sameline_text
     fn parse_sameline_text<F>(&mut self, elem_col: i32, prepend: &'static [u8], on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         #[derive(Clone, Copy)]
         enum State { Entry, Main, CheckPipe, CheckPipeElemCol, CheckSemi, CheckBang, AfterInline,  }
@@ -2660,7 +2680,7 @@ impl<'a> Parser<'a> {
                     match self.scan_to4(b'\n', b'|', b';', b'!') {
                         Some(b'\n') => {
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'|') => {
@@ -2680,7 +2700,7 @@ impl<'a> Parser<'a> {
                         }
                         None => {
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => unreachable!("scan_to only returns target chars"),
@@ -2689,13 +2709,13 @@ impl<'a> Parser<'a> {
                 State::CheckPipe => {
                     if self.eof() {
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
                         Some(b'{') => {
                     self.set_term(-1);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     self.advance();
                     self.parse_embedded(on_event);
                     state = State::AfterInline;
@@ -2718,12 +2738,12 @@ impl<'a> Parser<'a> {
                     match self.peek() {
                         _ if self.col() - 1 <= elem_col => {
                     self.set_term(-1);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
                     self.set_term(-1);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     self.parse_element(self.col() - 1, elem_col, on_event);
                     state = State::AfterInline;
                     continue;
@@ -2733,13 +2753,13 @@ impl<'a> Parser<'a> {
                 State::CheckSemi => {
                     if self.eof() {
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
                         Some(b'{') => {
                     self.set_term(-1);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     self.advance();
                     self.parse_brace_comment(on_event);
                     state = State::AfterInline;
@@ -2747,7 +2767,7 @@ impl<'a> Parser<'a> {
                         }
                         _ => {
                     self.set_term(-1);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     self.parse_line_comment_content(on_event);
                     return;
                         }
@@ -2756,13 +2776,13 @@ impl<'a> Parser<'a> {
                 State::CheckBang => {
                     if self.eof() {
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
                         Some(b'{') => {
                     self.set_term(-1);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     self.advance();
                     self.parse_sameline_directive(on_event);
                     state = State::AfterInline;
@@ -2796,10 +2816,10 @@ impl<'a> Parser<'a> {
     /// Parse line_comment -> Comment
     fn parse_line_comment<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         let start_span = self.span();
-        on_event(Event::CommentStart { span: start_span.clone() });
+        emit!(self, on_event, Event::CommentStart { span: start_span.clone() });
         let comment_col: i32 = self.col() - 1;
         let mut content_base: i32 = -1;
         let mut col: i32 = 0;
@@ -2810,14 +2830,14 @@ impl<'a> Parser<'a> {
             match state {
                 State::Check => {
                     if self.eof() {
-                        on_event(Event::CommentEnd { span: self.span() });
+                        emit!(self, on_event, Event::CommentEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
                         Some(b'{') => {
                     self.advance();
                     self.parse_comment_text_braced(on_event);
-                    on_event(Event::CommentEnd { span: self.span() });
+                    emit!(self, on_event, Event::CommentEnd { span: self.span() });
                     return;
                         }
                         _ => {
@@ -2831,15 +2851,15 @@ impl<'a> Parser<'a> {
                     match self.scan_to1(b'\n') {
                         Some(b'\n') => {
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     self.advance();
                     state = State::Children;
                     continue;
                         }
                         None => {
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
-                    on_event(Event::CommentEnd { span: self.span() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::CommentEnd { span: self.span() });
                     return;
                         }
                         _ => unreachable!("scan_to only returns target chars"),
@@ -2847,7 +2867,7 @@ impl<'a> Parser<'a> {
                 }
                 State::Children => {
                     if self.eof() {
-                    on_event(Event::CommentEnd { span: self.span() });
+                    emit!(self, on_event, Event::CommentEnd { span: self.span() });
                     return;
                     }
                     match self.peek() {
@@ -2862,18 +2882,18 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\t') => {
-                    on_event(Event::CommentEnd { span: self.span() });
+                    emit!(self, on_event, Event::CommentEnd { span: self.span() });
                     return;
                         }
                         _ => {
-                    on_event(Event::CommentEnd { span: self.span() });
+                    emit!(self, on_event, Event::CommentEnd { span: self.span() });
                     return;
                         }
                     }
                 }
                 State::ChildrenWs => {
                     if self.eof() {
-                        on_event(Event::CommentEnd { span: self.span() });
+                        emit!(self, on_event, Event::CommentEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -2894,16 +2914,16 @@ impl<'a> Parser<'a> {
                 }
                 State::CheckContinuation => {
                     if self.eof() {
-                        on_event(Event::CommentEnd { span: self.span() });
+                        emit!(self, on_event, Event::CommentEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
                         _ if col <= comment_col => {
-                    on_event(Event::CommentEnd { span: self.span() });
+                    emit!(self, on_event, Event::CommentEnd { span: self.span() });
                     return;
                         }
                         Some(b'|' | b':' | b'!' | b';') => {
-                    on_event(Event::CommentEnd { span: self.span() });
+                    emit!(self, on_event, Event::CommentEnd { span: self.span() });
                     return;
                         }
                         _ if content_base < 0 => {
@@ -2913,7 +2933,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::Warning { content: std::borrow::Cow::Borrowed(b"Inconsistent indentation"), span: self.span() });
+                    emit!(self, on_event, Event::Warning { content: std::borrow::Cow::Borrowed(b"Inconsistent indentation"), span: self.span() });
                     content_base = col;
                     self.mark();
                     state = State::ContLine;
@@ -2923,12 +2943,12 @@ impl<'a> Parser<'a> {
                 }
                 State::AtContentBase => {
                     if self.eof() {
-                        on_event(Event::CommentEnd { span: self.span() });
+                        emit!(self, on_event, Event::CommentEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
                         Some(b'|' | b':' | b'!' | b';') => {
-                    on_event(Event::CommentEnd { span: self.span() });
+                    emit!(self, on_event, Event::CommentEnd { span: self.span() });
                     return;
                         }
                         _ => {
@@ -2940,7 +2960,7 @@ impl<'a> Parser<'a> {
                 }
                 State::ContContent => {
                     if self.eof() {
-                        on_event(Event::CommentEnd { span: self.span() });
+                        emit!(self, on_event, Event::CommentEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -2958,15 +2978,15 @@ impl<'a> Parser<'a> {
                     match self.scan_to1(b'\n') {
                         Some(b'\n') => {
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     self.advance();
                     state = State::Children;
                     continue;
                         }
                         None => {
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
-                    on_event(Event::CommentEnd { span: self.span() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::CommentEnd { span: self.span() });
                     return;
                         }
                         _ => unreachable!("scan_to only returns target chars"),
@@ -2979,21 +2999,21 @@ impl<'a> Parser<'a> {
     /// Parse line_comment_content -> Comment
     fn parse_line_comment_content<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         let start_span = self.span();
-        on_event(Event::CommentStart { span: start_span.clone() });
+        emit!(self, on_event, Event::CommentStart { span: start_span.clone() });
                     self.mark();
                     self.scan_to1(b'\n');
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
-        on_event(Event::CommentEnd { span: self.span() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
+        emit!(self, on_event, Event::CommentEnd { span: self.span() });
     }
 
     /// Parse skip_brace_balanced
     fn parse_skip_brace_balanced<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         let mut depth: i32 = 1;
         #[derive(Clone, Copy)]
@@ -3044,17 +3064,17 @@ impl<'a> Parser<'a> {
     /// Parse brace_comment -> Comment
     fn parse_brace_comment<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         let start_span = self.span();
-        on_event(Event::CommentStart { span: start_span.clone() });
+        emit!(self, on_event, Event::CommentStart { span: start_span.clone() });
         loop {
             if self.eof() {
-                on_event(Event::CommentEnd { span: self.span() });
+                emit!(self, on_event, Event::CommentEnd { span: self.span() });
                 return;
             }
                     self.parse_comment_text_braced(on_event);
-                    on_event(Event::CommentEnd { span: self.span() });
+                    emit!(self, on_event, Event::CommentEnd { span: self.span() });
                     return;
         }
     }
@@ -3062,7 +3082,7 @@ impl<'a> Parser<'a> {
     /// Parse comment_text_braced
     fn parse_comment_text_braced<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
                     self.mark();
         loop {
@@ -3071,7 +3091,7 @@ impl<'a> Parser<'a> {
             }
                     self.parse_skip_brace_balanced(on_event);
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     self.advance();
                     return;
         }
@@ -3080,10 +3100,10 @@ impl<'a> Parser<'a> {
     /// Parse embedded -> Embedded
     fn parse_embedded<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         let start_span = self.span();
-        on_event(Event::EmbeddedStart { span: start_span.clone() });
+        emit!(self, on_event, Event::EmbeddedStart { span: start_span.clone() });
         #[derive(Clone, Copy)]
         enum State { Identity, PostIdentity, PreContent,  }
         let mut state = State::Identity;
@@ -3096,13 +3116,13 @@ impl<'a> Parser<'a> {
                 }
                 State::PostIdentity => {
                     if self.eof() {
-                        on_event(Event::EmbeddedEnd { span: self.span() });
+                        emit!(self, on_event, Event::EmbeddedEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
                         Some(b'}') => {
                     self.advance();
-                    on_event(Event::EmbeddedEnd { span: self.span() });
+                    emit!(self, on_event, Event::EmbeddedEnd { span: self.span() });
                     return;
                         }
                         Some(b' ' | b'\t') => {
@@ -3112,20 +3132,20 @@ impl<'a> Parser<'a> {
                         }
                         _ => {
                     self.parse_embed_content(on_event);
-                    on_event(Event::EmbeddedEnd { span: self.span() });
+                    emit!(self, on_event, Event::EmbeddedEnd { span: self.span() });
                     return;
                         }
                     }
                 }
                 State::PreContent => {
                     if self.eof() {
-                        on_event(Event::EmbeddedEnd { span: self.span() });
+                        emit!(self, on_event, Event::EmbeddedEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
                         Some(b'}') => {
                     self.advance();
-                    on_event(Event::EmbeddedEnd { span: self.span() });
+                    emit!(self, on_event, Event::EmbeddedEnd { span: self.span() });
                     return;
                         }
                         Some(b' ' | b'\t') => {
@@ -3139,7 +3159,7 @@ impl<'a> Parser<'a> {
                         }
                         _ => {
                     self.parse_embed_content(on_event);
-                    on_event(Event::EmbeddedEnd { span: self.span() });
+                    emit!(self, on_event, Event::EmbeddedEnd { span: self.span() });
                     return;
                         }
                     }
@@ -3151,7 +3171,7 @@ impl<'a> Parser<'a> {
     /// Parse embed_content -> Text
     fn parse_embed_content<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         self.mark();
         #[derive(Clone, Copy)]
@@ -3164,7 +3184,7 @@ impl<'a> Parser<'a> {
                         Some(b'}') => {
                     self.set_term(0);
                     self.advance();
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'|') => {
@@ -3187,7 +3207,7 @@ impl<'a> Parser<'a> {
                         }
                         Some(b'"') => {
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     self.advance();
                     self.parse_double_quoted(on_event);
                     self.mark();
@@ -3196,7 +3216,7 @@ impl<'a> Parser<'a> {
                         }
                         Some(b'\'') => {
                     self.set_term(0);
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     self.advance();
                     self.parse_single_quoted(on_event);
                     self.mark();
@@ -3204,8 +3224,8 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         None => {
-                            on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
-                            on_event(Event::Error { code: ParseErrorCode::UnclosedText, span: self.span() });
+                            emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
+                            emit!(self, on_event, Event::Error { code: ParseErrorCode::UnclosedText, span: self.span() });
                             return;
                         }
                         _ => unreachable!("scan_to only returns target chars"),
@@ -3213,13 +3233,13 @@ impl<'a> Parser<'a> {
                 }
                 State::CheckPipe => {
                     if self.eof() {
-                        on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
-                        on_event(Event::Error { code: ParseErrorCode::UnclosedText, span: self.span() });
+                        emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
+                        emit!(self, on_event, Event::Error { code: ParseErrorCode::UnclosedText, span: self.span() });
                         return;
                     }
                     match self.peek() {
                         Some(b'{') => {
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     self.advance();
                     self.parse_embedded(on_event);
                     self.mark();
@@ -3236,13 +3256,13 @@ impl<'a> Parser<'a> {
                 }
                 State::CheckSemi => {
                     if self.eof() {
-                        on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
-                        on_event(Event::Error { code: ParseErrorCode::UnclosedText, span: self.span() });
+                        emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
+                        emit!(self, on_event, Event::Error { code: ParseErrorCode::UnclosedText, span: self.span() });
                         return;
                     }
                     match self.peek() {
                         Some(b'{') => {
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     self.advance();
                     self.parse_brace_comment(on_event);
                     self.mark();
@@ -3259,13 +3279,13 @@ impl<'a> Parser<'a> {
                 }
                 State::CheckBang => {
                     if self.eof() {
-                        on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
-                        on_event(Event::Error { code: ParseErrorCode::UnclosedText, span: self.span() });
+                        emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
+                        emit!(self, on_event, Event::Error { code: ParseErrorCode::UnclosedText, span: self.span() });
                         return;
                     }
                     match self.peek() {
                         Some(b'{') => {
-                    on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
                     self.advance();
                     self.parse_sameline_directive(on_event);
                     self.mark();
@@ -3287,10 +3307,11 @@ impl<'a> Parser<'a> {
     /// Parse block_directive -> Directive
     fn parse_block_directive<F>(&mut self, line_col: i32, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         let start_span = self.span();
-        on_event(Event::DirectiveStart { span: start_span.clone() });
+        self.control.block_col = line_col;
+        emit!(self, on_event, Event::DirectiveStart { span: start_span.clone() });
         let mut col: i32 = 0;
         #[derive(Clone, Copy)]
         enum State { Dispatch, RawKind, RawColon, RawEol, RawContent, RawCheck, RawLine, AfterName, Condition, Children, CheckChild, ChildDispatch, ChildApos, ChildPipe,  }
@@ -3299,7 +3320,7 @@ impl<'a> Parser<'a> {
             match state {
                 State::Dispatch => {
                     if self.eof() {
-                        on_event(Event::DirectiveEnd { span: self.span() });
+                        emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -3311,7 +3332,7 @@ impl<'a> Parser<'a> {
                         Some(b'{') => {
                     self.advance();
                     self.parse_sameline_directive(on_event);
-                    on_event(Event::DirectiveEnd { span: self.span() });
+                    emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                     return;
                         }
                         Some(b) if Self::is_xlbl_start(b) => {
@@ -3320,32 +3341,32 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::DirectiveEnd { span: self.span() });
+                    emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                     return;
                         }
                     }
                 }
                 State::RawKind => {
                     if self.eof() {
-                        on_event(Event::DirectiveEnd { span: self.span() });
+                        emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
                         Some(b) if Self::is_xlbl_start(b) => {
                     self.parse_name(on_event);
-                    on_event(Event::Raw { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
+                    emit!(self, on_event, Event::Raw { content: std::borrow::Cow::Borrowed(b""), span: self.span() });
                     state = State::RawColon;
                     continue;
                         }
                         _ => {
-                    on_event(Event::DirectiveEnd { span: self.span() });
+                    emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                     return;
                         }
                     }
                 }
                 State::RawColon => {
                     if self.eof() {
-                        on_event(Event::DirectiveEnd { span: self.span() });
+                        emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -3355,7 +3376,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::DirectiveEnd { span: self.span() });
+                    emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                     return;
                         }
                     }
@@ -3372,7 +3393,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         None => {
-                            on_event(Event::DirectiveEnd { span: self.span() });
+                            emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                             return;
                         }
                         _ => unreachable!("scan_to only returns target chars"),
@@ -3380,7 +3401,7 @@ impl<'a> Parser<'a> {
                 }
                 State::RawContent => {
                     if self.eof() {
-                        on_event(Event::DirectiveEnd { span: self.span() });
+                        emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -3394,19 +3415,19 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::DirectiveEnd { span: self.span() });
+                    emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                     return;
                         }
                     }
                 }
                 State::RawCheck => {
                     if self.eof() {
-                        on_event(Event::DirectiveEnd { span: self.span() });
+                        emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
                         _ if col <= line_col => {
-                    on_event(Event::DirectiveEnd { span: self.span() });
+                    emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                     return;
                         }
                         _ => {
@@ -3420,15 +3441,15 @@ impl<'a> Parser<'a> {
                     match self.scan_to1(b'\n') {
                         Some(b'\n') => {
                     self.set_term(0);
-                    on_event(Event::RawContent { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RawContent { content: self.term(), span: self.span_from_mark() });
                     self.advance();
                     state = State::RawContent;
                     continue;
                         }
                         None => {
                     self.set_term(0);
-                    on_event(Event::RawContent { content: self.term(), span: self.span_from_mark() });
-                    on_event(Event::DirectiveEnd { span: self.span() });
+                    emit!(self, on_event, Event::RawContent { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                     return;
                         }
                         _ => unreachable!("scan_to only returns target chars"),
@@ -3436,7 +3457,7 @@ impl<'a> Parser<'a> {
                 }
                 State::AfterName => {
                     if self.eof() {
-                        on_event(Event::DirectiveEnd { span: self.span() });
+                        emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -3458,7 +3479,7 @@ impl<'a> Parser<'a> {
                 }
                 State::Condition => {
                     if self.eof() {
-                        on_event(Event::DirectiveEnd { span: self.span() });
+                        emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -3477,7 +3498,7 @@ impl<'a> Parser<'a> {
                 }
                 State::Children => {
                     if self.eof() {
-                        on_event(Event::DirectiveEnd { span: self.span() });
+                        emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -3491,7 +3512,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\t') => {
-                    on_event(Event::Error { code: ParseErrorCode::NoTabs, span: self.span() });
+                    emit!(self, on_event, Event::Error { code: ParseErrorCode::NoTabs, span: self.span() });
                     self.scan_to1(b'\n');
                     continue;
                         }
@@ -3504,12 +3525,12 @@ impl<'a> Parser<'a> {
                 }
                 State::CheckChild => {
                     if self.eof() {
-                        on_event(Event::DirectiveEnd { span: self.span() });
+                        emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
                         _ if col <= line_col => {
-                    on_event(Event::DirectiveEnd { span: self.span() });
+                    emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                     return;
                         }
                         _ => {
@@ -3520,7 +3541,7 @@ impl<'a> Parser<'a> {
                 }
                 State::ChildDispatch => {
                     if self.eof() {
-                        on_event(Event::DirectiveEnd { span: self.span() });
+                        emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -3561,7 +3582,7 @@ impl<'a> Parser<'a> {
                 }
                 State::ChildApos => {
                     if self.eof() {
-                        on_event(Event::DirectiveEnd { span: self.span() });
+                        emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -3604,7 +3625,7 @@ impl<'a> Parser<'a> {
                 }
                 State::ChildPipe => {
                     if self.eof() {
-                        on_event(Event::DirectiveEnd { span: self.span() });
+                        emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -3633,18 +3654,18 @@ impl<'a> Parser<'a> {
     /// Parse directive_args -> Text
     fn parse_directive_args<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         self.mark();
                     self.scan_to1(b'\n');
                     self.set_term(0);
-        on_event(Event::Text { content: self.term(), span: self.span_from_mark() });
+        emit!(self, on_event, Event::Text { content: self.term(), span: self.span_from_mark() });
     }
 
     /// Parse sameline_directive
     fn parse_sameline_directive<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         loop {
             if self.eof() {
@@ -3672,7 +3693,7 @@ impl<'a> Parser<'a> {
     /// Parse interpolation -> Interpolation
     fn parse_interpolation<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         self.mark();
         #[derive(Clone, Copy)]
@@ -3693,8 +3714,8 @@ impl<'a> Parser<'a> {
                         }
                         None => {
                     self.set_term(0);
-                    on_event(Event::Interpolation { content: self.term(), span: self.span_from_mark() });
-                    on_event(Event::Error { code: ParseErrorCode::UnclosedInterpolation, span: self.span() });
+                    emit!(self, on_event, Event::Interpolation { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Error { code: ParseErrorCode::UnclosedInterpolation, span: self.span() });
                     return;
                         }
                         _ => unreachable!("scan_to only returns target chars"),
@@ -3702,18 +3723,18 @@ impl<'a> Parser<'a> {
                 }
                 State::Closing => {
                     if self.eof() {
-                        on_event(Event::Interpolation { content: self.term(), span: self.span_from_mark() });
+                        emit!(self, on_event, Event::Interpolation { content: self.term(), span: self.span_from_mark() });
                         return;
                     }
                     match self.peek() {
                         Some(b'}') => {
                     self.advance();
-                    on_event(Event::Interpolation { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Interpolation { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
-                    on_event(Event::Interpolation { content: self.term(), span: self.span_from_mark() });
-                    on_event(Event::Error { code: ParseErrorCode::UnclosedInterpolation, span: self.span() });
+                    emit!(self, on_event, Event::Interpolation { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Error { code: ParseErrorCode::UnclosedInterpolation, span: self.span() });
                     return;
                         }
                     }
@@ -3725,10 +3746,10 @@ impl<'a> Parser<'a> {
     /// Parse sameline_raw -> Directive
     fn parse_sameline_raw<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         let start_span = self.span();
-        on_event(Event::DirectiveStart { span: start_span.clone() });
+        emit!(self, on_event, Event::DirectiveStart { span: start_span.clone() });
         let mut depth: i32 = 0;
         #[derive(Clone, Copy)]
         enum State { Kind, Content, Scan, CheckClose,  }
@@ -3737,7 +3758,7 @@ impl<'a> Parser<'a> {
             match state {
                 State::Kind => {
                     if self.eof() {
-                        on_event(Event::DirectiveEnd { span: self.span() });
+                        emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -3772,7 +3793,7 @@ impl<'a> Parser<'a> {
                             self.advance();
                         }
                         None => {
-                            on_event(Event::DirectiveEnd { span: self.span() });
+                            emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                             return;
                         }
                         _ => unreachable!("scan_to only returns target chars"),
@@ -3780,7 +3801,7 @@ impl<'a> Parser<'a> {
                 }
                 State::CheckClose => {
                     if self.eof() {
-                        on_event(Event::DirectiveEnd { span: self.span() });
+                        emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -3792,9 +3813,9 @@ impl<'a> Parser<'a> {
                         }
                         _ => {
                     self.set_term(0);
-                    on_event(Event::RawContent { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RawContent { content: self.term(), span: self.span_from_mark() });
                     self.advance();
-                    on_event(Event::DirectiveEnd { span: self.span() });
+                    emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                     return;
                         }
                     }
@@ -3806,10 +3827,10 @@ impl<'a> Parser<'a> {
     /// Parse sameline_dir_body -> Directive
     fn parse_sameline_dir_body<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         let start_span = self.span();
-        on_event(Event::DirectiveStart { span: start_span.clone() });
+        emit!(self, on_event, Event::DirectiveStart { span: start_span.clone() });
         #[derive(Clone, Copy)]
         enum State { Name, AfterName, Args,  }
         let mut state = State::Name;
@@ -3817,7 +3838,7 @@ impl<'a> Parser<'a> {
             match state {
                 State::Name => {
                     if self.eof() {
-                        on_event(Event::DirectiveEnd { span: self.span() });
+                        emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
@@ -3828,20 +3849,20 @@ impl<'a> Parser<'a> {
                         }
                         _ => {
                     self.parse_skip_brace_balanced(on_event);
-                    on_event(Event::DirectiveEnd { span: self.span() });
+                    emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                     return;
                         }
                     }
                 }
                 State::AfterName => {
                     if self.eof() {
-                        on_event(Event::DirectiveEnd { span: self.span() });
+                        emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
                         Some(b'}') => {
                     self.advance();
-                    on_event(Event::DirectiveEnd { span: self.span() });
+                    emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                     return;
                         }
                         Some(b' ' | b'\t') => {
@@ -3851,25 +3872,25 @@ impl<'a> Parser<'a> {
                         }
                         _ => {
                     self.parse_skip_brace_balanced(on_event);
-                    on_event(Event::DirectiveEnd { span: self.span() });
+                    emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                     return;
                         }
                     }
                 }
                 State::Args => {
                     if self.eof() {
-                        on_event(Event::DirectiveEnd { span: self.span() });
+                        emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                         return;
                     }
                     match self.peek() {
                         Some(b'}') => {
                     self.advance();
-                    on_event(Event::DirectiveEnd { span: self.span() });
+                    emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                     return;
                         }
                         _ => {
                     self.parse_skip_brace_balanced(on_event);
-                    on_event(Event::DirectiveEnd { span: self.span() });
+                    emit!(self, on_event, Event::DirectiveEnd { span: self.span() });
                     return;
                         }
                     }
@@ -3881,14 +3902,14 @@ impl<'a> Parser<'a> {
     /// Parse emit_bare_value
     fn parse_emit_bare_value<F>(&mut self, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
         loop {
             if self.eof() {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
             }
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
         }
     }
@@ -3896,7 +3917,7 @@ impl<'a> Parser<'a> {
     /// Parse typed_value
     fn parse_typed_value<F>(&mut self, space_term: i32, bracket: u8, on_event: &mut F)
     where
-        F: FnMut(Event<'a>),
+        F: FnMut(Event<'a>) -> Control,
     {
                     self.mark();
         #[derive(Clone, Copy)]
@@ -3906,12 +3927,12 @@ impl<'a> Parser<'a> {
             match state {
                 State::Main => {
                     if self.eof() {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
                         Some(b'\n') => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -3919,7 +3940,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'@') => {
@@ -3978,7 +3999,7 @@ impl<'a> Parser<'a> {
                     match self.scan_to2(b'\n', b']') {
                         Some(b']') => {
                     self.set_term(0);
-                    on_event(Event::Reference { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Reference { content: self.term(), span: self.span_from_mark() });
                     self.advance();
                     return;
                         }
@@ -4002,7 +4023,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -4014,7 +4035,7 @@ impl<'a> Parser<'a> {
                     match self.peek() {
                         Some(b';') => {
                     self.set_term(-1);
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -4115,7 +4136,7 @@ impl<'a> Parser<'a> {
                 }
                 State::RelNumDec => {
                     if self.eof() {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -4140,7 +4161,7 @@ impl<'a> Parser<'a> {
                         }
                         Some(b'i') => {
                     self.advance();
-                    on_event(Event::Complex { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Complex { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'+' | b'-') => {
@@ -4150,7 +4171,7 @@ impl<'a> Parser<'a> {
                         }
                         Some(b's' | b'S') => {
                     self.advance();
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'm' | b'M') => {
@@ -4160,26 +4181,26 @@ impl<'a> Parser<'a> {
                         }
                         Some(b'h' | b'H') => {
                     self.advance();
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'd' | b'D') => {
                     self.advance();
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'w' | b'W') => {
                     self.advance();
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'y' | b'Y') => {
                     self.advance();
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'\n') => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -4187,7 +4208,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -4207,7 +4228,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -4218,7 +4239,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -4229,17 +4250,17 @@ impl<'a> Parser<'a> {
                 }
                 State::RelDurM => {
                     if self.eof() {
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
                         Some(b'o' | b'O') => {
                     self.advance();
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'\n') => {
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -4247,7 +4268,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -4267,7 +4288,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -4278,7 +4299,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -4289,7 +4310,7 @@ impl<'a> Parser<'a> {
                 }
                 State::RelNumZero => {
                     if self.eof() {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -4324,7 +4345,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n') => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -4332,7 +4353,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -4352,7 +4373,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -4363,7 +4384,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -4374,7 +4395,7 @@ impl<'a> Parser<'a> {
                 }
                 State::RelNumFloatFrac => {
                     if self.eof() {
-                    on_event(Event::Float { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Float { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -4389,7 +4410,7 @@ impl<'a> Parser<'a> {
                         }
                         Some(b'i') => {
                     self.advance();
-                    on_event(Event::Complex { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Complex { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'+' | b'-') => {
@@ -4399,7 +4420,7 @@ impl<'a> Parser<'a> {
                         }
                         Some(b's' | b'S') => {
                     self.advance();
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'm' | b'M') => {
@@ -4409,26 +4430,26 @@ impl<'a> Parser<'a> {
                         }
                         Some(b'h' | b'H') => {
                     self.advance();
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'd' | b'D') => {
                     self.advance();
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'w' | b'W') => {
                     self.advance();
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'y' | b'Y') => {
                     self.advance();
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'\n') => {
-                    on_event(Event::Float { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Float { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -4436,7 +4457,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::Float { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Float { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -4456,7 +4477,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::Float { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Float { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -4467,7 +4488,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::Float { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Float { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -4573,7 +4594,7 @@ impl<'a> Parser<'a> {
                 }
                 State::RelIsoDurDateAfter => {
                     if self.eof() {
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -4587,7 +4608,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n') => {
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -4595,7 +4616,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -4615,7 +4636,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -4626,7 +4647,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -4717,7 +4738,7 @@ impl<'a> Parser<'a> {
                 }
                 State::RelIsoDurTimeAfter => {
                     if self.eof() {
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -4726,7 +4747,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n') => {
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -4734,7 +4755,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -4754,7 +4775,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -4765,7 +4786,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::RelativeTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -4776,7 +4797,7 @@ impl<'a> Parser<'a> {
                 }
                 State::NumZero => {
                     if self.eof() {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -4811,7 +4832,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n') => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -4819,7 +4840,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -4839,7 +4860,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -4850,7 +4871,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -4861,7 +4882,7 @@ impl<'a> Parser<'a> {
                 }
                 State::NumDec => {
                     if self.eof() {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -4886,7 +4907,7 @@ impl<'a> Parser<'a> {
                         }
                         Some(b'i') => {
                     self.advance();
-                    on_event(Event::Complex { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Complex { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'-') => {
@@ -4906,7 +4927,7 @@ impl<'a> Parser<'a> {
                         }
                         Some(b's' | b'S') => {
                     self.advance();
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'm' | b'M') => {
@@ -4916,26 +4937,26 @@ impl<'a> Parser<'a> {
                         }
                         Some(b'h' | b'H') => {
                     self.advance();
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'd' | b'D') => {
                     self.advance();
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'w' | b'W') => {
                     self.advance();
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'y' | b'Y') => {
                     self.advance();
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'\n') => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -4943,7 +4964,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -4963,7 +4984,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -4974,7 +4995,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -5017,7 +5038,7 @@ impl<'a> Parser<'a> {
                 }
                 State::DateAfterMonth => {
                     if self.eof() {
-                    on_event(Event::Date { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Date { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -5032,7 +5053,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n') => {
-                    on_event(Event::Date { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Date { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -5040,7 +5061,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::Date { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Date { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -5060,7 +5081,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::Date { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Date { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -5071,7 +5092,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::Date { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Date { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -5114,7 +5135,7 @@ impl<'a> Parser<'a> {
                 }
                 State::DateComplete => {
                     if self.eof() {
-                    on_event(Event::Date { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Date { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -5124,7 +5145,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n') => {
-                    on_event(Event::Date { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Date { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -5132,7 +5153,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::Date { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Date { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -5152,7 +5173,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::Date { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Date { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -5163,7 +5184,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::Date { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Date { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -5206,7 +5227,7 @@ impl<'a> Parser<'a> {
                 }
                 State::TimeAfterMin => {
                     if self.eof() {
-                    on_event(Event::Time { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Time { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -5216,7 +5237,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n') => {
-                    on_event(Event::Time { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Time { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -5224,7 +5245,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::Time { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Time { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -5244,7 +5265,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::Time { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Time { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -5255,7 +5276,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::Time { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Time { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -5298,7 +5319,7 @@ impl<'a> Parser<'a> {
                 }
                 State::TimeComplete => {
                     if self.eof() {
-                    on_event(Event::Time { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Time { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -5308,7 +5329,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n') => {
-                    on_event(Event::Time { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Time { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -5316,7 +5337,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::Time { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Time { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -5336,7 +5357,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::Time { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Time { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -5347,7 +5368,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::Time { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Time { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -5358,7 +5379,7 @@ impl<'a> Parser<'a> {
                 }
                 State::TimeFrac => {
                     if self.eof() {
-                    on_event(Event::Time { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Time { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -5367,7 +5388,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n') => {
-                    on_event(Event::Time { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Time { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -5375,7 +5396,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::Time { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Time { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -5395,7 +5416,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::Time { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Time { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -5406,7 +5427,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::Time { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Time { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -5497,7 +5518,7 @@ impl<'a> Parser<'a> {
                 }
                 State::DatetimeAfterMin => {
                     if self.eof() {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -5508,7 +5529,7 @@ impl<'a> Parser<'a> {
                         }
                         Some(b'Z') => {
                     self.advance();
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'+' | b'-') => {
@@ -5517,7 +5538,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n') => {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -5525,7 +5546,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -5545,7 +5566,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -5556,7 +5577,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -5599,7 +5620,7 @@ impl<'a> Parser<'a> {
                 }
                 State::DatetimeAfterSec => {
                     if self.eof() {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -5610,7 +5631,7 @@ impl<'a> Parser<'a> {
                         }
                         Some(b'Z') => {
                     self.advance();
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'+' | b'-') => {
@@ -5619,7 +5640,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n') => {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -5627,7 +5648,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -5647,7 +5668,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -5658,7 +5679,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -5669,7 +5690,7 @@ impl<'a> Parser<'a> {
                 }
                 State::DatetimeFrac => {
                     if self.eof() {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -5679,7 +5700,7 @@ impl<'a> Parser<'a> {
                         }
                         Some(b'Z') => {
                     self.advance();
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'+' | b'-') => {
@@ -5688,7 +5709,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n') => {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -5696,7 +5717,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -5716,7 +5737,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -5727,7 +5748,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -5770,7 +5791,7 @@ impl<'a> Parser<'a> {
                 }
                 State::DatetimeOffsetAfterHour => {
                     if self.eof() {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -5780,7 +5801,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n') => {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -5788,7 +5809,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -5808,7 +5829,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -5819,7 +5840,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -5862,12 +5883,12 @@ impl<'a> Parser<'a> {
                 }
                 State::DatetimeComplete => {
                     if self.eof() {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
                         Some(b'\n') => {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -5875,7 +5896,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -5895,7 +5916,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -5906,7 +5927,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::DateTime { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::DateTime { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -5917,17 +5938,17 @@ impl<'a> Parser<'a> {
                 }
                 State::DurM => {
                     if self.eof() {
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
                         Some(b'o' | b'O') => {
                     self.advance();
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'\n') => {
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -5935,7 +5956,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -5955,7 +5976,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -5966,7 +5987,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6072,7 +6093,7 @@ impl<'a> Parser<'a> {
                 }
                 State::IsoDurDateAfter => {
                     if self.eof() {
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -6086,7 +6107,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n') => {
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -6094,7 +6115,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6114,7 +6135,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -6125,7 +6146,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6216,7 +6237,7 @@ impl<'a> Parser<'a> {
                 }
                 State::IsoDurTimeAfter => {
                     if self.eof() {
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -6225,7 +6246,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n') => {
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -6233,7 +6254,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6253,7 +6274,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -6264,7 +6285,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6275,7 +6296,7 @@ impl<'a> Parser<'a> {
                 }
                 State::NumHex => {
                     if self.eof() {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -6284,7 +6305,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n') => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -6292,7 +6313,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6312,7 +6333,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -6323,7 +6344,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6334,7 +6355,7 @@ impl<'a> Parser<'a> {
                 }
                 State::NumOct => {
                     if self.eof() {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -6343,7 +6364,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n') => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -6351,7 +6372,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6371,7 +6392,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -6382,7 +6403,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6393,7 +6414,7 @@ impl<'a> Parser<'a> {
                 }
                 State::NumBin => {
                     if self.eof() {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -6402,7 +6423,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n') => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -6410,7 +6431,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6430,7 +6451,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -6441,7 +6462,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::Integer { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Integer { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6452,7 +6473,7 @@ impl<'a> Parser<'a> {
                 }
                 State::NumFloatFrac => {
                     if self.eof() {
-                    on_event(Event::Float { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Float { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -6467,7 +6488,7 @@ impl<'a> Parser<'a> {
                         }
                         Some(b'i') => {
                     self.advance();
-                    on_event(Event::Complex { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Complex { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'+' | b'-') => {
@@ -6477,7 +6498,7 @@ impl<'a> Parser<'a> {
                         }
                         Some(b's' | b'S') => {
                     self.advance();
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'm' | b'M') => {
@@ -6487,26 +6508,26 @@ impl<'a> Parser<'a> {
                         }
                         Some(b'h' | b'H') => {
                     self.advance();
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'd' | b'D') => {
                     self.advance();
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'w' | b'W') => {
                     self.advance();
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'y' | b'Y') => {
                     self.advance();
-                    on_event(Event::Duration { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Duration { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'\n') => {
-                    on_event(Event::Float { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Float { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -6514,7 +6535,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::Float { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Float { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6534,7 +6555,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::Float { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Float { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -6545,7 +6566,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::Float { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Float { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6556,7 +6577,7 @@ impl<'a> Parser<'a> {
                 }
                 State::NumFloatExp => {
                     if self.eof() {
-                    on_event(Event::Float { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Float { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -6570,7 +6591,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n') => {
-                    on_event(Event::Float { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Float { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -6578,7 +6599,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::Float { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Float { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6598,7 +6619,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::Float { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Float { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -6609,7 +6630,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::Float { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Float { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6620,7 +6641,7 @@ impl<'a> Parser<'a> {
                 }
                 State::NumFloatExpDigits => {
                     if self.eof() {
-                    on_event(Event::Float { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Float { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -6630,7 +6651,7 @@ impl<'a> Parser<'a> {
                         }
                         Some(b'i') => {
                     self.advance();
-                    on_event(Event::Complex { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Complex { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'+' | b'-') => {
@@ -6639,7 +6660,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n') => {
-                    on_event(Event::Float { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Float { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -6647,7 +6668,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::Float { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Float { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6667,7 +6688,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::Float { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Float { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -6678,7 +6699,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::Float { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Float { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6689,7 +6710,7 @@ impl<'a> Parser<'a> {
                 }
                 State::NumRationalDenom => {
                     if self.eof() {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -6699,11 +6720,11 @@ impl<'a> Parser<'a> {
                         }
                         Some(b'r') => {
                     self.advance();
-                    on_event(Event::Rational { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Rational { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'\n') => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -6711,7 +6732,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6731,7 +6752,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -6742,7 +6763,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6768,7 +6789,7 @@ impl<'a> Parser<'a> {
                 }
                 State::NumComplexImag => {
                     if self.eof() {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -6788,11 +6809,11 @@ impl<'a> Parser<'a> {
                         }
                         Some(b'i') => {
                     self.advance();
-                    on_event(Event::Complex { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Complex { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'\n') => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -6800,7 +6821,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6820,7 +6841,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -6831,7 +6852,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6842,7 +6863,7 @@ impl<'a> Parser<'a> {
                 }
                 State::NumComplexImagFrac => {
                     if self.eof() {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -6857,11 +6878,11 @@ impl<'a> Parser<'a> {
                         }
                         Some(b'i') => {
                     self.advance();
-                    on_event(Event::Complex { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Complex { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'\n') => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -6869,7 +6890,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6889,7 +6910,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -6900,7 +6921,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6911,7 +6932,7 @@ impl<'a> Parser<'a> {
                 }
                 State::NumComplexImagExp => {
                     if self.eof() {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -6925,7 +6946,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b'\n') => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -6933,7 +6954,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6953,7 +6974,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -6964,7 +6985,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -6975,7 +6996,7 @@ impl<'a> Parser<'a> {
                 }
                 State::NumComplexImagExpD => {
                     if self.eof() {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                     }
                     match self.peek() {
@@ -6985,11 +7006,11 @@ impl<'a> Parser<'a> {
                         }
                         Some(b'i') => {
                     self.advance();
-                    on_event(Event::Complex { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::Complex { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b'\n') => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -6997,7 +7018,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -7017,7 +7038,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -7028,7 +7049,7 @@ impl<'a> Parser<'a> {
                     }
                     match self.peek() {
                         Some(b';') => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -7040,7 +7061,7 @@ impl<'a> Parser<'a> {
                 State::String => {
                     match self.scan_to2(b'\n', b' ') {
                         Some(b'\n') => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         Some(b' ') => {
@@ -7048,11 +7069,11 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         Some(b) if b == bracket => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         None => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => unreachable!("scan_to only returns target chars"),
@@ -7069,7 +7090,7 @@ impl<'a> Parser<'a> {
                     continue;
                         }
                         _ => {
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                     }
@@ -7081,7 +7102,7 @@ impl<'a> Parser<'a> {
                     match self.peek() {
                         Some(b';') => {
                     self.set_term(-1);
-                    on_event(Event::BareValue { content: self.term(), span: self.span_from_mark() });
+                    emit!(self, on_event, Event::BareValue { content: self.term(), span: self.span_from_mark() });
                     return;
                         }
                         _ => {
@@ -7332,161 +7353,6 @@ impl StreamEvent {
     }
 }
 
-/// Streaming parser for chunk-by-chunk input.
-///
-/// Wraps the single-buffer `Parser` to handle input arriving in chunks.
-/// Buffers incomplete content across chunk boundaries.
-///
-/// # Example
-///
-/// ```ignore
-/// let mut parser = StreamingParser::new();
-/// loop {
-///     match parser.parse(chunk, |event| handle(event)) {
-///         ParseResult::Complete => break,
-///         ParseResult::NeedMoreData => {
-///             chunk = get_next_chunk();
-///             if chunk.is_empty() {
-///                 parser.finish(|event| handle(event));
-///                 break;
-///             }
-
-///         }
-
-///     }
-
-/// }
-
-/// ```
-
-pub struct StreamingParser {
-    /// Buffer for incomplete content at chunk boundaries.
-    buffer: Vec<u8>,
-    /// Maximum buffer size before error (default 4KB).
-    max_buffer: usize,
-    /// Global byte offset for span calculation.
-    global_offset: usize,
-    /// Line number (persists across chunks).
-    line: u32,
-    /// Column number (persists across chunks).
-    column: u32,
-}
-impl Default for StreamingParser {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-impl StreamingParser {
-    /// Create a new streaming parser with default settings.
-    pub fn new() -> Self {
-        Self::with_max_buffer(4096)
-    }
-
-    /// Create a streaming parser with custom buffer limit.
-    pub fn with_max_buffer(max_buffer: usize) -> Self {
-        Self {
-            buffer: Vec::new(),
-            max_buffer,
-            global_offset: 0,
-            line: 1,
-            column: 1,
-        }
-    }
-
-    /// Parse a chunk of input, emitting events for complete lines.
-    ///
-    /// Uses line-oriented streaming: only parses complete lines (ending in `\n`).
-    /// Incomplete lines are buffered until more data arrives.
-    ///
-    /// Returns `NeedMoreData` if the chunk ends mid-line.
-    /// Call `finish()` after the last chunk to handle any remaining content.
-    pub fn parse<F>(&mut self, chunk: &[u8], mut on_event: F) -> ParseResult
-    where
-        F: FnMut(StreamEvent),
-    {
-        // Append new chunk to buffer
-        self.buffer.extend_from_slice(chunk);
-        if self.buffer.is_empty() {
-            return ParseResult::NeedMoreData;
-        }
-
-        // Check buffer size limit
-        if self.buffer.len() > self.max_buffer {
-            on_event(StreamEvent::Error {
-                code: ParseErrorCode::UnexpectedEof, // Buffer overflow
-                span: self.global_offset..self.global_offset,
-            });
-            self.buffer.clear();
-            return ParseResult::Complete;
-        }
-
-        // Find last complete line (ending with \n)
-        let last_newline = self.buffer.iter().rposition(|&b| b == b'\n');
-        let parse_end = match last_newline {
-            Some(pos) => pos + 1, // Include the newline
-            None => return ParseResult::NeedMoreData, // No complete line yet
-        };
-        // Extract complete lines to parse
-        let to_parse: Vec<u8> = self.buffer.drain(..parse_end).collect();
-        let offset = self.global_offset;
-        // Parse complete lines
-        let mut inner = Parser::new(&to_parse);
-        inner.line = self.line;
-        inner.column = self.column;
-        inner.parse(|event| {
-            on_event(StreamEvent::from_event(event, offset));
-        });
-        // Update state
-        self.global_offset += to_parse.len();
-        for &b in &to_parse {
-            if b == b'\n' {
-                self.line += 1;
-                self.column = 1;
-            } else {
-                self.column += 1;
-            }
-        }
-        ParseResult::NeedMoreData
-    }
-
-    /// Signal end of input and handle any remaining buffered content.
-    ///
-    /// This triggers EOF handling for any incomplete constructs.
-    pub fn finish<F>(mut self, mut on_event: F)
-    where
-        F: FnMut(StreamEvent),
-    {
-        if self.buffer.is_empty() {
-            return;
-        }
-
-        // Parse remaining buffer - this will hit EOF
-        let input = std::mem::take(&mut self.buffer);
-        let offset = self.global_offset;
-        let mut inner = Parser::new(&input);
-        inner.line = self.line;
-        inner.column = self.column;
-        inner.parse(|event| {
-            on_event(StreamEvent::from_event(event, offset));
-        });
-    }
-
-    /// Returns the current global byte offset.
-    pub fn offset(&self) -> usize {
-        self.global_offset
-    }
-
-    /// Returns the current line number.
-    pub fn line(&self) -> u32 {
-        self.line
-    }
-
-    /// Returns the current column number.
-    pub fn column(&self) -> u32 {
-        self.column
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,426 @@
+//! Callback control: skipping subtrees and stopping early.
+//!
+//! Every event the generated parser produces goes through `Parser::emit`,
+//! which tracks open constructs and acts on the `Control` the callback
+//! returns. Skipping a block element or directive moves the parser straight
+//! to its end when indentation alone shows where that is (see
+//! `subtree_end`); otherwise the rest of the construct is parsed with its
+//! events dropped.
+
+use super::{Event, Parser};
+
+/// What the parser should do after an event, as returned by the callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Control {
+    /// Carry on with the next event.
+    #[default]
+    Continue,
+    /// Skip the rest of the innermost open element, embedded element or
+    /// directive (the one just started, for a start event). The next event
+    /// is its `ElementEnd`, `EmbeddedEnd` or `DirectiveEnd`.
+    ///
+    /// From the start event of a block element or directive, the parser
+    /// jumps to the first line indented no deeper than it, without parsing
+    /// the lines in between. If those lines hold something that may span
+    /// lines (quoted strings, arrays or `[id]`s left open at the end of a
+    /// line, braces, backticks, tabs, sameline children),
+    /// or for any other event, the skipped input is parsed as usual and its
+    /// events are dropped.
+    SkipChildren,
+    /// Stop parsing; no further events are emitted.
+    Stop,
+}
+
+impl From<()> for Control {
+    fn from(_: ()) -> Self {
+        Control::Continue
+    }
+}
+
+/// Emit an event through `Parser::emit`, building it before `self` is borrowed.
+macro_rules! emit {
+    ($parser:expr, $on_event:expr, $event:expr) => {{
+        let event = $event;
+        $parser.emit($on_event, event)
+    }};
+}
+pub(super) use emit;
+
+/// Skip and stop state of a `Parser`.
+#[derive(Debug, Default)]
+pub(super) struct ControlState<'a> {
+    /// Number of open elements, embedded elements and directives.
+    open: usize,
+    /// While non-zero, events are dropped until `open` falls below it.
+    skip_to: usize,
+    /// The full input, while a truncated one makes the parser unwind a
+    /// skipped subtree.
+    hidden: Option<&'a [u8]>,
+    /// Set once the callback returns `Control::Stop`.
+    stopped: bool,
+    /// Indentation column the latest block element or directive is parsed
+    /// with. After sameline children it can differ from the line's own.
+    pub(super) block_col: i32,
+}
+
+/// Block constructs whose end can be found from indentation.
+#[derive(Clone, Copy)]
+enum Block {
+    Element,
+    Directive,
+}
+
+impl<'a> Parser<'a> {
+    /// Deliver an event to the callback and act on the `Control` it returns.
+    #[inline(always)]
+    pub(super) fn emit<F>(&mut self, on_event: &mut F, event: Event<'a>)
+    where
+        F: FnMut(Event<'a>) -> Control,
+    {
+        let state = &mut self.control;
+        if state.stopped {
+            return;
+        }
+        let block = match event {
+            Event::ElementStart { .. } => Some(Block::Element),
+            Event::DirectiveStart { .. } => Some(Block::Directive),
+            _ => None,
+        };
+        let opens = block.is_some() || matches!(event, Event::EmbeddedStart { .. });
+        let closes =
+            matches!(event, Event::ElementEnd { .. } | Event::EmbeddedEnd { .. } | Event::DirectiveEnd { .. });
+        if opens {
+            state.open += 1;
+        } else if closes {
+            state.open -= 1;
+        }
+        if state.skip_to > 0 {
+            if state.open >= state.skip_to {
+                return;
+            }
+            // The end of the skipped construct
+            state.skip_to = 0;
+            if let Some(input) = state.hidden.take() {
+                self.input = input;
+            }
+        }
+
+        match on_event(event) {
+            Control::Continue => {}
+            Control::SkipChildren => {
+                if !closes {
+                    self.control.skip_to = self.control.open;
+                    if let Some(block) = block {
+                        self.jump_to_end(block);
+                    }
+                }
+            }
+            Control::Stop => {
+                // Everything still open closes at the new EOF, silently
+                self.control.stopped = true;
+                self.input = &self.input[..self.pos];
+            }
+        }
+    }
+
+    /// Move to the end of the block just started, if `subtree_end` finds it.
+    ///
+    /// The input is cut off there, so every open parse function sees EOF and
+    /// returns; `emit` restores it once the block's end event arrives, with
+    /// the parser exactly where parsing the subtree would have left it.
+    fn jump_to_end(&mut self, block: Block) {
+        let Some(end) = subtree_end(self.input, self.pos, block, self.control.block_col) else {
+            return;
+        };
+        let skipped = &self.input[self.pos..end];
+        match memchr::memrchr(b'\n', skipped) {
+            Some(last) => {
+                self.line += memchr::memchr_iter(b'\n', skipped).count() as u32;
+                self.column = (skipped.len() - last) as u32;
+            }
+            None => self.column += skipped.len() as u32,
+        }
+        self.control.hidden = Some(self.input);
+        self.input = &self.input[..end];
+        self.pos = end;
+    }
+}
+
+/// Where the parser closes the block element or directive whose `|` or `!`
+/// is the byte before `pos`: the first non-space byte of the first later
+/// line indented no deeper than it, or the end of the input.
+///
+/// Returns `None` unless that is certain from the lines alone: the block
+/// must start its line, indented by the `col` it is parsed with, and no
+/// line may leave open anything that continues onto the next one.
+fn subtree_end(input: &[u8], pos: usize, block: Block, col: i32) -> Option<usize> {
+    let line_start = memchr::memrchr(b'\n', &input[..pos - 1]).map_or(0, |i| i + 1);
+    let indent = pos - 1 - line_start;
+    if indent as i32 != col || input[line_start..pos - 1].iter().any(|&b| b != b' ') {
+        return None;
+    }
+    let first_end = memchr::memchr(b'\n', &input[pos..]).map_or(input.len(), |i| pos + i);
+    let head = &input[pos..first_end];
+    let raw = match block {
+        Block::Element => {
+            element_line(head)?;
+            false
+        }
+        Block::Directive => directive_head(head)?,
+    };
+
+    let mut start = first_end + 1;
+    let end = loop {
+        if start >= input.len() {
+            break input.len();
+        }
+        let line_end = memchr::memchr(b'\n', &input[start..]).map_or(input.len(), |i| start + i);
+        let line = &input[start..line_end];
+        let spaces = line.iter().take_while(|&&b| b == b' ').count();
+        if line.get(spaces) == Some(&b'\t') {
+            // Tabs count towards indentation in ways spaces alone cannot show
+            return None;
+        }
+        if line.is_empty() {
+            // Blank in every construct
+        } else if raw {
+            // Raw content is opaque; any line indented no deeper ends it
+            if spaces <= indent {
+                break start + spaces;
+            }
+        } else if spaces == line.len() {
+            // Whitespace-only lines are blank in elements, but end a
+            // directive that is not inside a deeper element
+            if matches!(block, Block::Directive) && spaces <= indent {
+                return None;
+            }
+        } else if spaces <= indent {
+            break start + spaces;
+        } else {
+            content_line(&line[spaces..])?;
+        }
+        start = line_end + 1;
+    };
+
+    // Freeform blocks, braces and tabs follow their own rules
+    if !raw && memchr::memchr3(b'`', b'{', b'\t', &input[line_start..end]).is_some() {
+        return None;
+    }
+    Some(end)
+}
+
+/// Whether a directive's first line, after the `!`, is a plain `!name args`
+/// (returning `false`) or a raw `!:lang:` (returning `true`).
+fn directive_head(head: &[u8]) -> Option<bool> {
+    let raw = head.first() == Some(&b':');
+    let name = &head[raw as usize..];
+    if !name.first().is_some_and(|&b| Parser::is_xlbl_start(b)) {
+        return None;
+    }
+    let len = name.iter().take_while(|&&b| Parser::is_xlbl_cont(b)).count();
+    match (raw, name.get(len)) {
+        (true, Some(b':')) | (false, None | Some(b' ')) => Some(raw),
+        _ => None,
+    }
+}
+
+/// Check a line inside the subtree, from its first non-space byte.
+fn content_line(line: &[u8]) -> Option<()> {
+    match line {
+        [b'|', next, ..] if starts_identity(*next) => element_line(&line[1..]),
+        [b':', ..] => block_attr(line),
+        [b'@', b'[', rest @ ..] => {
+            // A reference runs to its `]`, then the line is read afresh
+            let close = memchr::memchr(b']', rest)?;
+            rest[close + 1..].is_empty().then_some(())
+        }
+        // Prose, comments and directive arguments run to the end of the line
+        _ => Some(()),
+    }
+}
+
+/// Check an element line, from just after its `|`: identity, then sameline
+/// attributes, text and comment.
+fn element_line(line: &[u8]) -> Option<()> {
+    let mut i = identity_end(line)?;
+    // Attributes may follow the identity or another attribute
+    loop {
+        while line.get(i) == Some(&b' ') {
+            i += 1;
+        }
+        match line.get(i) {
+            None | Some(b';') => return Some(()),
+            Some(b':') => i = attr_end(line, i, true)?,
+            _ => break,
+        }
+    }
+    // Sameline text: only an inline element or a comment changes the mode
+    while i < line.len() {
+        match line[i] {
+            // Children on the element's line change where it ends
+            b'|' if line.get(i + 1).is_some_and(|&b| starts_identity(b)) => return None,
+            b';' if i + 1 < line.len() => return Some(()),
+            _ => i += 1,
+        }
+    }
+    Some(())
+}
+
+/// Bytes after `|` that start an element rather than prose.
+fn starts_identity(b: u8) -> bool {
+    Parser::is_xlbl_start(b) || matches!(b, b'\'' | b'[' | b'.' | b'?' | b'!' | b'*' | b'+')
+}
+
+/// End of an element identity (`name[id].class?`) starting at `line[0]`.
+fn identity_end(line: &[u8]) -> Option<usize> {
+    let mut i = 0;
+    while i < line.len() && line[i] != b' ' {
+        match line[i] {
+            b'\'' => i = closing_quote(line, i)? + 1,
+            b'[' => {
+                // An id runs to `]` or a space, whichever comes first
+                let close = line[i..].iter().position(|&b| b == b']' || b == b' ')?;
+                if line[i + close] != b']' {
+                    return None;
+                }
+                i += close + 1;
+            }
+            b'"' => return None,
+            _ => i += 1,
+        }
+    }
+    Some(i)
+}
+
+/// A block attribute line. Quoted and array values return to the line
+/// dispatcher, which misreads anything after them, so nothing may follow.
+fn block_attr(line: &[u8]) -> Option<()> {
+    let end = attr_end(line, 0, false)?;
+    (end == line.len() || line[end] == b';').then_some(())
+}
+
+/// End of the attribute whose `:` is at `line[i]`. Sameline bare values end
+/// at a space; block ones run to the end of the line.
+fn attr_end(line: &[u8], mut i: usize, sameline: bool) -> Option<usize> {
+    i += 1;
+    match line.get(i) {
+        Some(b'[') => return memchr::memchr(b']', &line[i..]).map(|close| i + close + 1),
+        Some(b'\'') => i = closing_quote(line, i)? + 1,
+        Some(&b) if Parser::is_xlbl_start(b) => {
+            i += line[i..].iter().take_while(|&&b| Parser::is_xlbl_cont(b)).count();
+        }
+        _ => return None,
+    }
+    while line.get(i) == Some(&b' ') {
+        i += 1;
+    }
+    match line.get(i) {
+        None | Some(b':' | b'|') => Some(i),
+        Some(b'"' | b'\'') => closing_quote(line, i).map(|close| close + 1),
+        Some(b'[') => array_end(line, i),
+        Some(_) if sameline => Some(i + line[i..].iter().take_while(|&&b| b != b' ').count()),
+        Some(_) => Some(line.len()),
+    }
+}
+
+/// End of the array whose `[` is at `line[i]`.
+fn array_end(line: &[u8], mut i: usize) -> Option<usize> {
+    let mut depth = 0;
+    while i < line.len() {
+        match line[i] {
+            b'[' => depth += 1,
+            b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            b'"' | b'\'' if matches!(line[i - 1], b'[' | b' ') => i = closing_quote(line, i)?,
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Index of the quote closing the one at `line[open]`, skipping escapes.
+fn closing_quote(line: &[u8], open: usize) -> Option<usize> {
+    let quote = line[open];
+    let mut i = open + 1;
+    while i < line.len() {
+        match line[i] {
+            b'\\' => i += 2,
+            b if b == quote => return Some(i),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `subtree_end` for the block whose marker is the first `|` or `!`,
+    /// parsed at the column of that marker.
+    fn end_of(input: &[u8]) -> Option<usize> {
+        let marker = input.iter().position(|&b| b == b'|' || b == b'!').unwrap();
+        let block = if input[marker] == b'|' { Block::Element } else { Block::Directive };
+        let col = marker - input[..marker].iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+        subtree_end(input, marker + 1, block, col as i32)
+    }
+
+    #[test]
+    fn test_control() {
+        let input = b"|a :x 1\n  |b\n    text\n  |c\n|config :debug true\n|d\n";
+        let mut names = Vec::new();
+        Parser::new(input).parse(|event| match event {
+            Event::Name { content, .. } if content.as_ref() == b"b" => Control::SkipChildren,
+            Event::Name { content, .. } => {
+                names.push(content);
+                if names.len() == 3 {
+                    Control::Stop
+                } else {
+                    Control::Continue
+                }
+            }
+            Event::Text { .. } => panic!("skipped text was emitted"),
+            _ => Control::Continue,
+        });
+        assert_eq!(names, [&b"a"[..], b"c", b"config"]);
+
+        // Skipping from a start event drops the whole element but its end
+        let mut events = Vec::new();
+        Parser::new(b"|a\n  |{b |c}\n").parse(|event| {
+            let skip = matches!(event, Event::EmbeddedStart { .. });
+            events.push(event);
+            if skip {
+                Control::SkipChildren
+            } else {
+                Control::Continue
+            }
+        });
+        assert!(matches!(events[2..4], [Event::EmbeddedStart { .. }, Event::EmbeddedEnd { .. }]));
+        assert!(matches!(events.last(), Some(Event::ElementEnd { .. })));
+    }
+
+    #[test]
+    fn test_subtree_end() {
+        assert_eq!(end_of(b"|a :x \"1\"\n  |b :y [1 2]\n\n    text\n|c\n"), Some(34));
+        assert_eq!(end_of(b"  |a\n    |b\n   \n  |c\n"), Some(18));
+        assert_eq!(end_of(b"|a\n  |b\n"), Some(8));
+        assert_eq!(end_of(b"!if x\n  |a\n|b\n"), Some(11));
+        assert_eq!(end_of(b"  !:js:\n    if (x) {\n   `\n\n  |b\n"), Some(29));
+
+        // Open at the end of a line, or outside the fast path's rules
+        assert_eq!(end_of(b"|a :x \"1\n|b\"\n|c\n"), None);
+        assert_eq!(end_of(b"|a\n  :x [1\n2]\n|c\n"), None);
+        assert_eq!(end_of(b"|a\n  |b[x\ny]\n|c\n"), None);
+        assert_eq!(end_of(b"|a\n  ```\n|b\n  ```\n"), None);
+        assert_eq!(end_of(b"|a\n  ;{ x\n}\n"), None);
+        assert_eq!(end_of(b"|a |b\n|c\n"), None);
+        assert_eq!(end_of(b"|a\n  :x \"1\" :y 2\n"), None);
+        assert_eq!(end_of(b"x |a\n"), None);
+        assert_eq!(end_of(b"  !if x\n    |a\n \n|b\n"), None);
+    }
+}
@@ -0,0 +1,131 @@
+//! Span and nesting accessors for parser events.
+
+use std::ops::Range;
+
+use super::{Event, StreamEvent};
+
+impl<'a> Event<'a> {
+    /// Byte range of this event in the source.
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            Event::ElementStart { span, .. } => span.clone(),
+            Event::ElementEnd { span, .. } => span.clone(),
+            Event::EmbeddedStart { span, .. } => span.clone(),
+            Event::EmbeddedEnd { span, .. } => span.clone(),
+            Event::DirectiveStart { span, .. } => span.clone(),
+            Event::DirectiveEnd { span, .. } => span.clone(),
+            Event::ArrayStart { span, .. } => span.clone(),
+            Event::ArrayEnd { span, .. } => span.clone(),
+            Event::FreeformStart { span, .. } => span.clone(),
+            Event::FreeformEnd { span, .. } => span.clone(),
+            Event::Name { span, .. } => span.clone(),
+            Event::Text { span, .. } => span.clone(),
+            Event::CommentStart { span, .. } => span.clone(),
+            Event::CommentEnd { span, .. } => span.clone(),
+            Event::Attr { span, .. } => span.clone(),
+            Event::StringValue { span, .. } => span.clone(),
+            Event::BareValue { span, .. } => span.clone(),
+            Event::BoolTrue { span, .. } => span.clone(),
+            Event::BoolFalse { span, .. } => span.clone(),
+            Event::Nil { span, .. } => span.clone(),
+            Event::Interpolation { span, .. } => span.clone(),
+            Event::Reference { span, .. } => span.clone(),
+            Event::RawContent { span, .. } => span.clone(),
+            Event::Raw { span, .. } => span.clone(),
+            Event::Integer { span, .. } => span.clone(),
+            Event::Float { span, .. } => span.clone(),
+            Event::Rational { span, .. } => span.clone(),
+            Event::Complex { span, .. } => span.clone(),
+            Event::Warning { span, .. } => span.clone(),
+            Event::BlankLine { span, .. } => span.clone(),
+            Event::Date { span, .. } => span.clone(),
+            Event::Time { span, .. } => span.clone(),
+            Event::DateTime { span, .. } => span.clone(),
+            Event::Duration { span, .. } => span.clone(),
+            Event::RelativeTime { span, .. } => span.clone(),
+            Event::Error { span, .. } => span.clone(),
+        }
+    }
+
+    /// Change in nesting depth caused by this event (+1 for Start, -1 for End).
+    pub(super) fn depth_delta(&self) -> i32 {
+        match self {
+            Event::ElementStart { .. }
+            | Event::EmbeddedStart { .. }
+            | Event::DirectiveStart { .. }
+            | Event::ArrayStart { .. }
+            | Event::FreeformStart { .. }
+            | Event::CommentStart { .. } => 1,
+            Event::ElementEnd { .. }
+            | Event::EmbeddedEnd { .. }
+            | Event::DirectiveEnd { .. }
+            | Event::ArrayEnd { .. }
+            | Event::FreeformEnd { .. }
+            | Event::CommentEnd { .. } => -1,
+            _ => 0,
+        }
+    }
+}
+
+impl StreamEvent {
+    /// Byte range of this event in the source.
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            StreamEvent::ElementStart { span, .. } => span.clone(),
+            StreamEvent::ElementEnd { span, .. } => span.clone(),
+            StreamEvent::EmbeddedStart { span, .. } => span.clone(),
+            StreamEvent::EmbeddedEnd { span, .. } => span.clone(),
+            StreamEvent::DirectiveStart { span, .. } => span.clone(),
+            StreamEvent::DirectiveEnd { span, .. } => span.clone(),
+            StreamEvent::ArrayStart { span, .. } => span.clone(),
+            StreamEvent::ArrayEnd { span, .. } => span.clone(),
+            StreamEvent::FreeformStart { span, .. } => span.clone(),
+            StreamEvent::FreeformEnd { span, .. } => span.clone(),
+            StreamEvent::Name { span, .. } => span.clone(),
+            StreamEvent::Text { span, .. } => span.clone(),
+            StreamEvent::CommentStart { span, .. } => span.clone(),
+            StreamEvent::CommentEnd { span, .. } => span.clone(),
+            StreamEvent::Attr { span, .. } => span.clone(),
+            StreamEvent::StringValue { span, .. } => span.clone(),
+            StreamEvent::BareValue { span, .. } => span.clone(),
+            StreamEvent::BoolTrue { span, .. } => span.clone(),
+            StreamEvent::BoolFalse { span, .. } => span.clone(),
+            StreamEvent::Nil { span, .. } => span.clone(),
+            StreamEvent::Interpolation { span, .. } => span.clone(),
+            StreamEvent::Reference { span, .. } => span.clone(),
+            StreamEvent::RawContent { span, .. } => span.clone(),
+            StreamEvent::Raw { span, .. } => span.clone(),
+            StreamEvent::Integer { span, .. } => span.clone(),
+            StreamEvent::Float { span, .. } => span.clone(),
+            StreamEvent::Rational { span, .. } => span.clone(),
+            StreamEvent::Complex { span, .. } => span.clone(),
+            StreamEvent::Warning { span, .. } => span.clone(),
+            StreamEvent::BlankLine { span, .. } => span.clone(),
+            StreamEvent::Date { span, .. } => span.clone(),
+            StreamEvent::Time { span, .. } => span.clone(),
+            StreamEvent::DateTime { span, .. } => span.clone(),
+            StreamEvent::Duration { span, .. } => span.clone(),
+            StreamEvent::RelativeTime { span, .. } => span.clone(),
+            StreamEvent::Error { span, .. } => span.clone(),
+        }
+    }
+
+    /// Change in nesting depth caused by this event (+1 for Start, -1 for End).
+    pub(super) fn depth_delta(&self) -> i32 {
+        match self {
+            StreamEvent::ElementStart { .. }
+            | StreamEvent::EmbeddedStart { .. }
+            | StreamEvent::DirectiveStart { .. }
+            | StreamEvent::ArrayStart { .. }
+            | StreamEvent::FreeformStart { .. }
+            | StreamEvent::CommentStart { .. } => 1,
+            StreamEvent::ElementEnd { .. }
+            | StreamEvent::EmbeddedEnd { .. }
+            | StreamEvent::DirectiveEnd { .. }
+            | StreamEvent::ArrayEnd { .. }
+            | StreamEvent::FreeformEnd { .. }
+            | StreamEvent::CommentEnd { .. } => -1,
+            _ => 0,
+        }
+    }
+}
@@ -0,0 +1,616 @@
+//! Chunked and pull-based parsing on top of the callback `Parser`.
+
+use std::ops::Range;
+
+use super::{Event, ParseErrorCode, ParseResult, Parser, StreamEvent};
+
+/// Streaming parser for chunk-by-chunk input.
+///
+/// Wraps the single-buffer `Parser` to handle input arriving in chunks.
+/// Produces exactly the same event sequence (including spans) as
+/// `Parser::new(whole_input)`, however the input is split.
+///
+/// The recursive descent parser keeps its nesting context on the call stack,
+/// so it cannot be suspended mid-element. Instead, the streaming parser
+/// retains input from the start of the last unfinished top-level construct
+/// and re-parses it as more lines arrive. Events are only released once a
+/// later column-0 line proves the construct has closed, so an element that
+/// spans many chunks keeps its children, attributes and prose state.
+///
+/// Memory use is therefore bounded by the largest top-level construct,
+/// which suits log-style documents made of many top-level records.
+///
+/// # Limits
+///
+/// A single top-level construct (with everything nested in it) must fit in
+/// the buffer limit, 1MB by default (see `with_max_buffer`). A document
+/// with one root element larger than that cannot be streamed: `parse`
+/// reports `ParseErrorCode::BufferOverflow` and drops that construct. Use
+/// `Parser` or `parse_file` on such documents, or raise the limit.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut parser = StreamingParser::new();
+/// loop {
+///     match parser.parse(chunk, |event| handle(event)) {
+///         ParseResult::Complete => break,
+///         ParseResult::NeedMoreData => {
+///             chunk = get_next_chunk();
+///             if chunk.is_empty() {
+///                 parser.finish(|event| handle(event));
+///                 break;
+///             }
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct StreamingParser {
+    /// Input retained from the start of the current top-level construct,
+    /// followed by any incomplete trailing line.
+    buffer: Vec<u8>,
+    /// Maximum buffer size before error (default 1MB).
+    max_buffer: usize,
+    /// Global byte offset of `buffer[0]`, used for span calculation.
+    base_offset: usize,
+    /// Line number of `buffer[0]`.
+    base_line: u32,
+    /// Length of the buffer prefix made of complete lines already received.
+    scanned: usize,
+    /// Set after an overflow, until the next top-level line arrives.
+    discarding: bool,
+    /// Line number (persists across chunks).
+    line: u32,
+    /// Column number (persists across chunks).
+    column: u32,
+}
+impl Default for StreamingParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl StreamingParser {
+    /// Create a new streaming parser with default settings.
+    pub fn new() -> Self {
+        Self::with_max_buffer(1024 * 1024)
+    }
+
+    /// Create a streaming parser with custom buffer limit.
+    ///
+    /// The limit bounds the size of a single top-level construct (plus any
+    /// incomplete trailing line), since that is what must be retained until
+    /// it closes.
+    pub fn with_max_buffer(max_buffer: usize) -> Self {
+        Self {
+            buffer: Vec::new(),
+            max_buffer,
+            base_offset: 0,
+            base_line: 1,
+            scanned: 0,
+            discarding: false,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    /// Parse a chunk of input, emitting events for completed top-level constructs.
+    ///
+    /// Only complete lines (ending in `\n`) are considered. Events are emitted
+    /// once a following top-level line shows that the preceding constructs
+    /// are closed; everything after that point stays buffered.
+    ///
+    /// Returns `NeedMoreData` until the buffer limit is exceeded. Then an
+    /// `Error` event with `ParseErrorCode::BufferOverflow` is emitted, the
+    /// buffered construct is dropped, and `Complete` is returned. Parsing
+    /// may go on with further chunks; it resumes at the next top-level line.
+    /// Call `finish()` after the last chunk to handle any remaining content.
+    pub fn parse<F>(&mut self, chunk: &[u8], mut on_event: F) -> ParseResult
+    where
+        F: FnMut(StreamEvent),
+    {
+        // Append new chunk to buffer
+        self.buffer.extend_from_slice(chunk);
+        if self.discarding && !self.discard() {
+            return ParseResult::NeedMoreData;
+        }
+        self.release(&mut on_event);
+
+        // Check buffer size limit
+        if self.buffer.len() > self.max_buffer {
+            on_event(StreamEvent::Error {
+                code: ParseErrorCode::BufferOverflow,
+                span: self.base_offset..self.base_offset + self.buffer.len(),
+            });
+            self.discarding = true;
+            self.discard();
+            return ParseResult::Complete;
+        }
+        ParseResult::NeedMoreData
+    }
+
+    /// Emit the events of every buffered construct that has closed, and
+    /// drop its input.
+    fn release<F>(&mut self, on_event: &mut F)
+    where
+        F: FnMut(StreamEvent),
+    {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        // Find last complete line (ending with \n)
+        let parse_end = match self.buffer.iter().rposition(|&b| b == b'\n') {
+            Some(pos) => pos + 1, // Include the newline
+            None => return, // No complete line yet
+        };
+        if parse_end <= self.scanned {
+            return;
+        }
+
+        // Only re-parse when the new lines could close a top-level construct
+        let new_lines = self.scanned..parse_end;
+        self.line += memchr::memchr_iter(b'\n', &self.buffer[new_lines.clone()]).count() as u32;
+        self.column = 1;
+        self.scanned = parse_end;
+        let has_boundary = memchr::memchr_iter(b'\n', &self.buffer[new_lines.start.saturating_sub(1)..parse_end])
+            .map(|i| new_lines.start.saturating_sub(1) + i + 1)
+            .any(|start| start < parse_end && Self::is_top_level_line(&self.buffer, start));
+        if !has_boundary {
+            return;
+        }
+
+        // Parse everything buffered so far; events past the resume point are
+        // provisional (the parser saw EOF there) and are discarded.
+        let input = &self.buffer[..parse_end];
+        let offset = self.base_offset;
+        let mut events = Vec::new();
+        let mut inner = Parser::new(input);
+        inner.line = self.base_line;
+        inner.column = 1;
+        inner.parse(|event| {
+            events.push(StreamEvent::from_event(event, offset));
+        });
+
+        if let Some((count, cut)) = Self::resume_point(input, &events, offset, &mut Vec::new()) {
+            self.base_line += memchr::memchr_iter(b'\n', &input[..cut]).count() as u32;
+            for event in events.drain(..count) {
+                on_event(event);
+            }
+            self.buffer.drain(..cut);
+            self.base_offset += cut;
+            self.scanned -= cut;
+        }
+    }
+
+    /// Signal end of input and handle any remaining buffered content.
+    ///
+    /// This triggers EOF handling for any incomplete constructs.
+    pub fn finish<F>(mut self, mut on_event: F)
+    where
+        F: FnMut(StreamEvent),
+    {
+        if self.buffer.is_empty() || self.discarding {
+            return;
+        }
+
+        // Parse remaining buffer - this will hit EOF
+        let input = std::mem::take(&mut self.buffer);
+        let offset = self.base_offset;
+        let mut inner = Parser::new(&input);
+        inner.line = self.base_line;
+        inner.column = 1;
+        inner.parse(|event| {
+            on_event(StreamEvent::from_event(event, offset));
+        });
+    }
+
+    /// Parse everything `reader` yields, then finish.
+    ///
+    /// Input is read in chunks, so memory use stays bounded as for `parse`.
+    /// Interrupted reads are retried; other read errors are returned. If a
+    /// top-level construct exceeds the buffer limit, reading stops after
+    /// the `BufferOverflow` error event and an error of kind `InvalidData`
+    /// is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use udon_core::{StreamEvent, StreamingParser};
+    ///
+    /// let mut names = Vec::new();
+    /// StreamingParser::new()
+    ///     .parse_reader(&b"|a\n|b\n"[..], |event| {
+    ///         if let StreamEvent::Name { content, .. } = event {
+    ///             names.push(content);
+    ///         }
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(names, [b"a", b"b"]);
+    /// ```
+    pub fn parse_reader<R, F>(mut self, mut reader: R, mut on_event: F) -> std::io::Result<()>
+    where
+        R: std::io::Read,
+        F: FnMut(StreamEvent),
+    {
+        let mut chunk = vec![0; 64 * 1024];
+        loop {
+            let len = match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(len) => len,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            if self.parse(&chunk[..len], &mut on_event) == ParseResult::Complete {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    ParseErrorCode::BufferOverflow.message(),
+                ));
+            }
+        }
+        self.finish(on_event);
+        Ok(())
+    }
+
+    /// Returns the global byte offset of the end of the last complete line received.
+    pub fn offset(&self) -> usize {
+        self.base_offset + self.scanned
+    }
+
+    /// Returns the current line number.
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// Returns the current column number.
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+
+    /// Returns the number of bytes currently retained for re-parsing.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Drop the buffered input up to the next top-level line, after an
+    /// overflow. Returns whether one was found; if not, the last line break
+    /// is kept so that a top-level line in the next chunk can be recognized.
+    fn discard(&mut self) -> bool {
+        let resume = memchr::memchr_iter(b'\n', &self.buffer)
+            .map(|i| i + 1)
+            .find(|&start| Self::is_top_level_line(&self.buffer, start));
+        let cut = resume.unwrap_or_else(|| memchr::memrchr(b'\n', &self.buffer).unwrap_or(self.buffer.len()));
+        self.base_line += memchr::memchr_iter(b'\n', &self.buffer[..cut]).count() as u32;
+        self.base_offset += cut;
+        self.buffer.drain(..cut);
+        self.discarding = resume.is_none();
+        // Complete lines are counted as received; a kept line break is one
+        self.scanned = (self.discarding && !self.buffer.is_empty()) as usize;
+        self.line = self.base_line + self.scanned as u32;
+        self.column = 1;
+        resume.is_some()
+    }
+
+    /// A line starting at column 0 with content closes every open construct
+    /// except multi-line tokens (strings, arrays, freeform blocks, ...).
+    #[inline]
+    fn is_top_level_line(input: &[u8], start: usize) -> bool {
+        start > 0 && !matches!(input.get(start), None | Some(b' ' | b'\t' | b'\n'))
+    }
+
+    /// Find the last point where parsing can restart with a fresh `Parser`.
+    ///
+    /// That is a top-level line start `L` where every event before it has
+    /// closed (bracket depth 0), no content event runs into `L`, and every
+    /// later event starts at or after `L`. Returns the number of events
+    /// before that point and `L` as a buffer offset.
+    fn resume_point<E: Resumable>(
+        input: &[u8],
+        events: &[E],
+        offset: usize,
+        min_start_after: &mut Vec<usize>,
+    ) -> Option<(usize, usize)> {
+        min_start_after.clear();
+        min_start_after.resize(events.len() + 1, usize::MAX);
+        for (i, event) in events.iter().enumerate().rev() {
+            min_start_after[i] = min_start_after[i + 1].min(event.span().start - offset);
+        }
+
+        let mut best = None;
+        let mut depth = 0i32;
+        let mut content_end = 0usize;
+        let mut marker_end = 0usize;
+        for (i, event) in events.iter().enumerate() {
+            if depth == 0 && i > 0 {
+                let start = min_start_after[i];
+                let line_start = memchr::memrchr(b'\n', &input[..start]).map_or(0, |p| p + 1);
+                if Self::is_top_level_line(input, line_start)
+                    && content_end < line_start
+                    && marker_end <= line_start
+                {
+                    best = Some((i, line_start));
+                }
+            }
+
+            depth += event.depth_delta();
+            let span = event.span();
+            let (start, end) = (span.start - offset, span.end - offset);
+            if start == end {
+                marker_end = marker_end.max(end);
+            } else {
+                content_end = content_end.max(end);
+            }
+        }
+        best
+    }
+}
+
+
+/// Span and nesting of an event, as needed to find a resume point.
+trait Resumable {
+    fn span(&self) -> Range<usize>;
+    fn depth_delta(&self) -> i32;
+}
+
+impl Resumable for StreamEvent {
+    fn span(&self) -> Range<usize> {
+        StreamEvent::span(self)
+    }
+
+    fn depth_delta(&self) -> i32 {
+        StreamEvent::depth_delta(self)
+    }
+}
+
+impl Resumable for Event<'_> {
+    fn span(&self) -> Range<usize> {
+        Event::span(self)
+    }
+
+    fn depth_delta(&self) -> i32 {
+        Event::depth_delta(self)
+    }
+}
+
+impl<'a> Parser<'a> {
+    /// Iterate over the events of `input` instead of receiving them in a callback.
+    ///
+    /// The iterator yields exactly the events (including spans) of
+    /// `Parser::new(input).parse(..)`. It is lazy per top-level construct:
+    /// using the same resume points as `StreamingParser`, it parses one
+    /// top-level element, directive or run of prose at a time and buffers
+    /// that construct's events before yielding the first of them.
+    ///
+    /// The parser keeps its nesting on the call stack, so it cannot pause
+    /// inside a construct. A document with a single root element is
+    /// therefore parsed in full on the first call to `next`, and buffers all
+    /// of its events; the laziness pays off for documents made of many
+    /// top-level constructs. To stop early within one construct, use
+    /// `Parser::parse` with `Control::Stop` instead.
+    ///
+    /// Content is borrowed from `input` as with the callback API. The only
+    /// allocation is the iterator's event buffer, which is reused, so it
+    /// stops growing once it fits the largest top-level construct.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use udon_core::{Event, Parser};
+    ///
+    /// let input = b"|a\n  |b\n|config :debug true\n|c\n";
+    /// let config = Parser::events(input)
+    ///     .skip_while(|e| !matches!(e, Event::Name { content, .. } if content.as_ref() == b"config"))
+    ///     .nth(2);
+    /// assert!(matches!(config, Some(Event::BoolTrue { .. })));
+    /// ```
+    pub fn events(input: &'a [u8]) -> Events<'a> {
+        Events { input, pos: 0, line: 1, pending: Vec::new(), scratch: Vec::new() }
+    }
+}
+
+/// Pull-based iterator over a document's events, buffering one top-level
+/// construct at a time; see `Parser::events`.
+#[derive(Debug)]
+pub struct Events<'a> {
+    input: &'a [u8],
+    /// Start of the input not yet parsed, always a resume point.
+    pos: usize,
+    /// Line number at `pos`.
+    line: u32,
+    /// Events of the segment being yielded, in reverse order.
+    pending: Vec<Event<'a>>,
+    /// Scratch space for `StreamingParser::resume_point`.
+    scratch: Vec<usize>,
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Event<'a>;
+
+    fn next(&mut self) -> Option<Event<'a>> {
+        if self.pending.is_empty() && self.pos < self.input.len() {
+            self.fill();
+        }
+        self.pending.pop()
+    }
+}
+
+impl<'a> Events<'a> {
+    /// Parse the next segment of the input into `pending`.
+    ///
+    /// A window ending after the next top-level line is parsed; events
+    /// before its last resume point are final. If there is none (the
+    /// construct runs on, e.g. a multi-line string), the window is doubled.
+    fn fill(&mut self) {
+        let input = self.input;
+        let rest = &input[self.pos..];
+        let mut end = Self::window_end(rest, 1);
+        let cut = loop {
+            let window = &rest[..end];
+            let mut inner = Parser::new(window);
+            inner.line = self.line;
+            inner.column = 1;
+            let pending = &mut self.pending;
+            inner.parse(|event| pending.push(event));
+
+            if end == rest.len() {
+                break end;
+            }
+            if let Some((count, cut)) = StreamingParser::resume_point(window, &self.pending, 0, &mut self.scratch) {
+                self.pending.truncate(count);
+                break cut;
+            }
+            self.pending.clear();
+            end = Self::window_end(rest, 2 * end);
+        };
+
+        for event in &mut self.pending {
+            let span = Self::span_mut(event);
+            *span = (span.start + self.pos)..(span.end + self.pos);
+        }
+        self.pending.reverse();
+        self.line += memchr::memchr_iter(b'\n', &rest[..cut]).count() as u32;
+        self.pos += cut;
+    }
+
+    /// End of the first top-level line starting at or after `from`, or of the input.
+    fn window_end(input: &[u8], from: usize) -> usize {
+        let from = from.min(input.len());
+        memchr::memchr_iter(b'\n', &input[from - 1..])
+            .map(|i| from + i)
+            .find(|&start| StreamingParser::is_top_level_line(input, start))
+            .map_or(input.len(), |start| {
+                memchr::memchr(b'\n', &input[start..]).map_or(input.len(), |i| start + i + 1)
+            })
+    }
+
+    fn span_mut<'e>(event: &'e mut Event<'a>) -> &'e mut Range<usize> {
+        match event {
+            Event::ElementStart { span, .. }
+            | Event::ElementEnd { span, .. }
+            | Event::EmbeddedStart { span, .. }
+            | Event::EmbeddedEnd { span, .. }
+            | Event::DirectiveStart { span, .. }
+            | Event::DirectiveEnd { span, .. }
+            | Event::ArrayStart { span, .. }
+            | Event::ArrayEnd { span, .. }
+            | Event::FreeformStart { span, .. }
+            | Event::FreeformEnd { span, .. }
+            | Event::Name { span, .. }
+            | Event::Text { span, .. }
+            | Event::CommentStart { span, .. }
+            | Event::CommentEnd { span, .. }
+            | Event::Attr { span, .. }
+            | Event::StringValue { span, .. }
+            | Event::BareValue { span, .. }
+            | Event::BoolTrue { span, .. }
+            | Event::BoolFalse { span, .. }
+            | Event::Nil { span, .. }
+            | Event::Interpolation { span, .. }
+            | Event::Reference { span, .. }
+            | Event::RawContent { span, .. }
+            | Event::Raw { span, .. }
+            | Event::Integer { span, .. }
+            | Event::Float { span, .. }
+            | Event::Rational { span, .. }
+            | Event::Complex { span, .. }
+            | Event::Warning { span, .. }
+            | Event::BlankLine { span, .. }
+            | Event::Date { span, .. }
+            | Event::Time { span, .. }
+            | Event::DateTime { span, .. }
+            | Event::Duration { span, .. }
+            | Event::RelativeTime { span, .. }
+            | Event::Error { span, .. } => span,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reader() {
+        /// Yields a few bytes per read, interrupted every other time.
+        struct Trickle<'a>(&'a [u8], bool);
+        impl std::io::Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                self.1 = !self.1;
+                if self.1 {
+                    return Err(std::io::ErrorKind::Interrupted.into());
+                }
+                let len = self.0.len().min(buf.len()).min(5);
+                buf[..len].copy_from_slice(&self.0[..len]);
+                self.0 = &self.0[len..];
+                Ok(len)
+            }
+        }
+
+        let input = b"|a :x 1\n  |b text\n|c :s \"multi\n|line\"\n|d";
+        let mut events = Vec::new();
+        StreamingParser::new().parse_reader(Trickle(input, false), |event| events.push(event)).unwrap();
+        let mut expected = Vec::new();
+        Parser::new(input).parse(|event| expected.push(StreamEvent::from_event(event, 0)));
+        assert_eq!(events, expected);
+
+        let mut events = Vec::new();
+        let err = StreamingParser::with_max_buffer(8)
+            .parse_reader(&b"|a\n|b 123456789\n  |c\n"[..], |event| events.push(event))
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(matches!(events.last(), Some(StreamEvent::Error { code: ParseErrorCode::BufferOverflow, .. })));
+    }
+
+    #[test]
+    fn test_buffer_overflow() {
+        let mut parser = StreamingParser::with_max_buffer(16);
+        let mut events = Vec::new();
+        assert_eq!(parser.parse(b"|a\n", |e| events.push(e)), ParseResult::NeedMoreData);
+        assert_eq!(parser.parse(b"|b :x 1\n  |c 12345", |e| events.push(e)), ParseResult::Complete);
+        assert_eq!(parser.parse(b"6789\n  |d\n", |e| events.push(e)), ParseResult::NeedMoreData);
+        assert_eq!(parser.parse(b"|e\n", |e| events.push(e)), ParseResult::NeedMoreData);
+        parser.finish(|e| events.push(e));
+
+        // `|a` closed before the overflow; `|b` is dropped whole
+        let input = b"|a\n|b :x 1\n  |c 123456789\n  |d\n|e\n";
+        let mut expected = Vec::new();
+        Parser::new(&input[..3]).parse(|e| expected.push(StreamEvent::from_event(e, 0)));
+        expected.push(StreamEvent::Error { code: ParseErrorCode::BufferOverflow, span: 3..21 });
+        Parser::new(&input[31..]).parse(|e| expected.push(StreamEvent::from_event(e, 31)));
+        assert_eq!(events, expected);
+
+        // No top-level line within the chunk that overflows
+        let mut parser = StreamingParser::with_max_buffer(8);
+        let mut events = Vec::new();
+        assert_eq!(parser.parse(b"|a 123456789", |e| events.push(e)), ParseResult::Complete);
+        assert_eq!(parser.parse(b"0\n", |e| events.push(e)), ParseResult::NeedMoreData);
+        assert_eq!((parser.offset(), parser.line()), (14, 2));
+        assert_eq!(parser.parse(b"|c\n", |e| events.push(e)), ParseResult::NeedMoreData);
+        parser.finish(|e| events.push(e));
+        assert!(matches!(events[0], StreamEvent::Error { code: ParseErrorCode::BufferOverflow, .. }));
+        assert!(matches!(events[1], StreamEvent::ElementStart { ref span } if *span == (15..15)));
+    }
+
+    #[test]
+    fn test_events_by_construct() {
+        // A string running over a top-level line is parsed as a whole
+        let input = b"|a :x 1\n  |b\n|c :s \"multi\n|line\"\n|d\n";
+        let mut expected = Vec::new();
+        Parser::new(input).parse(|event| expected.push(event));
+        for n in 0..=expected.len() {
+            let events: Vec<_> = Parser::events(input).take(n).collect();
+            assert_eq!(events, expected[..n]);
+        }
+        assert_eq!(Parser::events(input).count(), expected.len());
+
+        // Each window is cut at a resume point, whatever the line lengths
+        let mut input = Vec::new();
+        for i in 0..40 {
+            input.extend(format!("|r{i} :n {}\n  |c\n", "x".repeat(i * 7)).bytes());
+        }
+        input.extend(b"|last :s \"a\n|b\n|c\"\n");
+        let mut expected = Vec::new();
+        Parser::new(&input).parse(|event| expected.push(event));
+        assert_eq!(Parser::events(&input).collect::<Vec<_>>(), expected);
+    }
+}
@@ -0,0 +1,295 @@
+//! Raw blocks and per-language handlers.
+//!
+//! UDON has three ways to embed code verbatim:
+//!
+//! - `!raw:lang` followed by an indented body,
+//! - `!:lang: code` (or `!{:lang:code}` inline),
+//! - ` ```lang ` freeform fences.
+//!
+//! [`Node::raw_block`] gives all three the same shape: the language and
+//! the code with its common indentation removed.
+//!
+//! [`RawHandlers`] maps languages to handlers that turn a block into
+//! something structured. The built-in handlers parse `udon` blocks into a
+//! subtree and, with the `json` feature, `json` blocks into [`Value`]s;
+//! blocks in other languages are left verbatim.
+//!
+//! # Example
+//!
+//! ```
+//! use udon_core::tree::Document;
+//! use udon_core::raw::{RawContent, RawHandlers};
+//!
+//! let doc = Document::parse(b"|page\n  !raw:udon\n    |item[a]\n  ```sh\n  ls -l\n  ```\n").unwrap();
+//! let blocks = RawHandlers::builtin().process(&doc).unwrap();
+//!
+//! let RawContent::Tree(tree) = &blocks[0].1 else { panic!() };
+//! assert!(tree.get_by_id("a").is_some());
+//! let RawContent::Verbatim(code) = &blocks[1].1 else { panic!() };
+//! assert_eq!(code, "ls -l");
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::reference::descendants;
+use crate::span::Span;
+use crate::tree::{Document, Node, NodeId, NodeKind, Value};
+use crate::writer::{content_text, is_raw_directive};
+
+/// A raw block's language and code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawBlock<'doc> {
+    pub lang: Option<&'doc str>,
+    /// The code, with the indentation common to its lines removed.
+    pub code: String,
+    pub span: Span,
+}
+
+impl<'doc, 'a: 'doc> Node<'doc, 'a> {
+    /// For a raw directive or freeform block, its language and code.
+    ///
+    /// The raw node inside a `!:lang:` directive is part of the directive's
+    /// block and also gives the directive's language on its own.
+    pub fn raw_block(&self) -> Option<RawBlock<'doc>> {
+        let (lang, code) = match self.kind() {
+            NodeKind::Raw { lang, content } => (lang.as_deref(), dedent(&content_text(*self, content))),
+            NodeKind::Directive { name, attrs }
+                if name == "raw" && attrs.len() <= 1 && attrs.iter().all(|a| matches!(a.value, Value::BoolTrue)) =>
+            {
+                (attrs.first().map(|a| a.name.as_ref()), raw_body(*self))
+            }
+            NodeKind::Directive { name, attrs } if attrs.is_empty() && is_raw_directive(*self) => {
+                (Some(name.as_ref()), raw_body(*self))
+            }
+            _ => return None,
+        };
+        Some(RawBlock { lang, code, span: self.span() })
+    }
+}
+
+/// Body of a raw directive. With source, the lines are taken from it so
+/// relative indentation and blank lines survive; otherwise the children's
+/// content is joined.
+fn raw_body(node: Node<'_, '_>) -> String {
+    let piece = |child: Node<'_, '_>| match child.kind() {
+        NodeKind::Text(s) => Some(s.to_string()),
+        NodeKind::Raw { content, .. } => Some(content.to_string()),
+        _ => None,
+    };
+    let source = node.source();
+    let start = node.span().start as usize;
+    let Some(line_end) = source.get(start..).and_then(|s| s.iter().position(|&b| b == b'\n')).map(|i| start + i) else {
+        return dedent(&node.children().filter_map(piece).collect::<Vec<_>>().join("\n"));
+    };
+
+    let mut lines = Vec::new();
+    // Content on the directive's own line (`!:lang: code`)
+    let mut body_end = None;
+    for child in node.children() {
+        if (child.span().start as usize) < line_end {
+            lines.extend(piece(child).filter(|s| !s.is_empty()));
+        } else {
+            body_end = Some(child.span().end as usize);
+        }
+    }
+    // An empty body can sit at the end of the directive's line
+    if let Some(body) = body_end.and_then(|end| source.get(line_end + 1..end)) {
+        lines.push(dedent(&String::from_utf8_lossy(body)));
+    }
+    lines.join("\n")
+}
+
+/// Remove the indentation (spaces and tabs) common to all non-blank lines.
+fn dedent(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let leading = |line: &str| line.len() - line.trim_start_matches([' ', '\t']).len();
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| leading(line))
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| line[leading(line).min(indent)..].trim_end_matches('\r'))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// What a handler made of a raw block.
+#[derive(Debug)]
+pub enum RawContent {
+    /// The code as-is; the result for languages without a handler.
+    Verbatim(String),
+    /// Values, e.g. from a `json` block.
+    Values(Vec<Value<'static>>),
+    /// A parsed subtree, e.g. from a `udon` block.
+    Tree(Document<'static>),
+}
+
+/// Error returned when a handler rejects a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawError {
+    pub message: String,
+    /// Span of the raw block.
+    pub span: Span,
+}
+
+impl fmt::Display for RawError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}..{}", self.message, self.span.start, self.span.end)
+    }
+}
+
+impl std::error::Error for RawError {}
+
+/// A handler: takes a block's code.
+pub type RawHandler = dyn Fn(&str) -> Result<RawContent, String> + Send + Sync;
+
+/// Registry of handlers by language.
+#[derive(Default)]
+pub struct RawHandlers {
+    handlers: HashMap<String, Box<RawHandler>>,
+}
+
+impl fmt::Debug for RawHandlers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut langs: Vec<_> = self.handlers.keys().collect();
+        langs.sort();
+        f.debug_struct("RawHandlers").field("langs", &langs).finish()
+    }
+}
+
+impl RawHandlers {
+    /// A registry that leaves every block verbatim.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The built-in handlers: `udon` parses the code as a document and,
+    /// with the `json` feature, `json` parses a sequence of JSON scalars
+    /// and arrays into values.
+    pub fn builtin() -> Self {
+        let mut handlers = Self::new();
+        handlers.insert("udon", |code| {
+            Document::parse_owned(code.as_bytes().to_vec()).map(RawContent::Tree).map_err(|err| err.to_string())
+        });
+        #[cfg(feature = "json")]
+        handlers.insert("json", |code| {
+            serde_json::Deserializer::from_str(code)
+                .into_iter::<serde_json::Value>()
+                .map(|json| {
+                    let json = json.map_err(|err| err.to_string())?;
+                    crate::json::value_from_json(&json).map_err(|err| err.to_string())
+                })
+                .collect::<Result<_, _>>()
+                .map(RawContent::Values)
+        });
+        handlers
+    }
+
+    /// Register a handler for a language, replacing any existing one.
+    pub fn insert(
+        &mut self,
+        lang: impl Into<String>,
+        handler: impl Fn(&str) -> Result<RawContent, String> + Send + Sync + 'static,
+    ) {
+        self.handlers.insert(lang.into(), Box::new(handler));
+    }
+
+    pub fn get(&self, lang: &str) -> Option<&RawHandler> {
+        self.handlers.get(lang).map(|h| h.as_ref())
+    }
+
+    /// Run the handler for a block's language, or return it verbatim.
+    pub fn handle(&self, block: &RawBlock<'_>) -> Result<RawContent, RawError> {
+        match block.lang.and_then(|lang| self.get(lang)) {
+            Some(handler) => handler(&block.code).map_err(|message| RawError {
+                message: format!("{} block: {}", block.lang.unwrap_or_default(), message),
+                span: block.span,
+            }),
+            None => Ok(RawContent::Verbatim(block.code.clone())),
+        }
+    }
+
+    /// Handle every raw block in a document, in document order.
+    pub fn process(&self, doc: &Document<'_>) -> Result<Vec<(NodeId, RawContent)>, RawError> {
+        let mut results = Vec::new();
+        let mut nodes = descendants(doc.root());
+        while let Some(node) = nodes.next() {
+            let Some(block) = node.raw_block() else { continue };
+            results.push((node.id(), self.handle(&block)?));
+            // The raw nodes inside a raw directive are part of its block
+            if matches!(node.kind(), NodeKind::Directive { .. }) {
+                for _ in descendants(node) {
+                    nodes.next();
+                }
+            }
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_blocks() {
+        let source = b"|a\n  !raw:sql\n    SELECT 1\n      FROM t\n  ```sh\n    ls\n  ```\n!:js:\n  x()\n";
+        let doc = Document::parse(source).unwrap();
+        let blocks = RawHandlers::new().process(&doc).unwrap();
+        let summary: Vec<_> = blocks
+            .iter()
+            .map(|(id, content)| {
+                let RawContent::Verbatim(code) = content else { panic!() };
+                (doc.get(*id).unwrap().raw_block().unwrap().lang, code.as_str())
+            })
+            .collect();
+        assert_eq!(summary, [(Some("sql"), "SELECT 1\n  FROM t"), (Some("sh"), "ls"), (Some("js"), "x()")]);
+
+        // The tree carries the language of fences and `!:lang:` blocks
+        let freeform = doc.root().first_child().unwrap().children().nth(1).unwrap();
+        assert!(matches!(freeform.kind(), NodeKind::Raw { lang: Some(lang), .. } if lang == "sh"));
+        let js = doc.root().children().nth(1).unwrap().last_child().unwrap();
+        assert!(matches!(js.kind(), NodeKind::Raw { lang: Some(lang), content } if lang == "js" && content == "x()"));
+
+        // An inline raw directive with no body
+        let doc = Document::parse(b"!:js\n").unwrap();
+        assert_eq!(doc.root().first_child().unwrap().raw_block().unwrap().code, "");
+        assert!(crate::html::to_html(&doc).is_ok());
+        // Only ASCII indentation is stripped
+        assert_eq!(dedent("    a\n    \u{3000}b\n  \n"), "a\n\u{3000}b\n");
+    }
+
+    #[test]
+    fn test_handlers() {
+        let doc = Document::parse(b"!raw:udon\n  |x :n 1\n!raw:sql\n  SELECT 1\n!raw:bad\n  x\n").unwrap();
+        let mut handlers = RawHandlers::builtin();
+        handlers.insert("sql", |code| Ok(RawContent::Verbatim(code.to_lowercase())));
+        handlers.insert("bad", |_| Err("rejected".to_string()));
+
+        let err = handlers.process(&doc).unwrap_err();
+        assert_eq!((err.message.as_str(), err.span), ("bad block: rejected", Span::new(40, 52)));
+
+        let blocks: Vec<_> = doc.root().children().filter_map(|n| n.raw_block()).collect();
+        let RawContent::Tree(tree) = handlers.handle(&blocks[0]).unwrap() else { panic!() };
+        let x = tree.root().first_child().unwrap();
+        assert!(matches!(x.as_element().unwrap().attr("n"), Some(Value::Integer(n)) if n == "1"));
+        assert!(matches!(handlers.handle(&blocks[1]).unwrap(), RawContent::Verbatim(code) if code == "select 1"));
+        let verbatim = RawHandlers::new().handle(&blocks[1]).unwrap();
+        assert!(matches!(verbatim, RawContent::Verbatim(code) if code == "SELECT 1"));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json_handler() {
+        let doc = Document::parse(b"!raw:json\n  [1, \"a\"] true\n").unwrap();
+        let blocks = RawHandlers::builtin().process(&doc).unwrap();
+        let RawContent::Values(values) = &blocks[0].1 else { panic!() };
+        assert_eq!(values.len(), 2);
+        assert!(matches!(&values[0], Value::Array(items) if items.len() == 2));
+        assert!(matches!(values[1], Value::BoolTrue));
+    }
+}
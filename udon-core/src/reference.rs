@@ -0,0 +1,317 @@
+//! ID references: `@[id]` element insertion and `:[id]` attribute merge.
+//!
+//! Both forms parse to a `NodeKind::Reference` child. [`Node::resolve_reference`]
+//! finds the element a reference points at, [`Document::check_references`]
+//! reports dangling references and duplicate ids, and the opt-in
+//! [`Document::expand_references`] pass applies the references in place.
+//!
+//! # Example
+//!
+//! ```
+//! use udon_core::tree::{Document, NodeKind};
+//!
+//! let mut doc = Document::parse(b"|license[mit] MIT License\n|project\n  @[mit]\n").unwrap();
+//! assert!(doc.expand_references().is_empty());
+//!
+//! let project = doc.root().select_first("project").unwrap().unwrap();
+//! let license = project.first_child().unwrap();
+//! assert_eq!(license.as_element().unwrap().name(), "license");
+//! assert_eq!(license.all_text(), "MIT License");
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::diagnostic::{Code, Diagnostic};
+use crate::tree::{Attribute, Document, Node, NodeId, NodeKind};
+
+/// Default for [`Document::expand_references_with_max_nodes`].
+pub const DEFAULT_MAX_EXPANDED_NODES: usize = 100_000;
+
+/// Which form a reference was written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceKind {
+    /// `@[id]`: insert the whole element.
+    Element,
+    /// `:[id]`: merge the element's attributes into the parent.
+    Attributes,
+}
+
+impl<'doc, 'a: 'doc> Node<'doc, 'a> {
+    /// For a reference node, which form it was written in.
+    ///
+    /// References built in code (without source) are `Element` references.
+    pub fn reference_kind(&self) -> Option<ReferenceKind> {
+        let NodeKind::Reference(_) = self.kind() else {
+            return None;
+        };
+        match self.source().get(self.span().start as usize) {
+            Some(b':') => Some(ReferenceKind::Attributes),
+            _ => Some(ReferenceKind::Element),
+        }
+    }
+
+    /// For a reference node, the element it points at.
+    pub fn resolve_reference(&self) -> Option<Node<'doc, 'a>> {
+        match self.kind() {
+            NodeKind::Reference(id) => self.document().get_by_id(id),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Document<'a> {
+    /// Report references to ids that no element has (`DanglingReference`,
+    /// an error) and elements that reuse an earlier element's id
+    /// (`DuplicateId`, a warning; references resolve to the first).
+    pub fn check_references(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut seen: HashMap<&str, NodeId> = HashMap::new();
+
+        for node in descendants(self.root()) {
+            match node.kind() {
+                NodeKind::Element { id: Some(id), .. } => {
+                    if let Some(&first) = seen.get(id.as_ref()) {
+                        let first = self.get(first).unwrap().span();
+                        diagnostics.push(Diagnostic::warning(
+                            Code::DuplicateId,
+                            node.header_span(&format!("[{}]", id)).unwrap_or(node.span()),
+                            format!("id '{}' is already used at {}..{}", id, first.start, first.end),
+                        ));
+                    } else {
+                        seen.insert(id, node.id());
+                    }
+                }
+                NodeKind::Reference(id) if self.get_by_id(id).is_none() => {
+                    diagnostics.push(Diagnostic::error(
+                        Code::DanglingReference,
+                        node.span(),
+                        format!("no element has id '{}'", id),
+                    ));
+                }
+                _ => {}
+            }
+        }
+        diagnostics
+    }
+
+    /// Apply references in place, copying at most
+    /// [`DEFAULT_MAX_EXPANDED_NODES`] nodes.
+    ///
+    /// `@[id]` is replaced by a deep copy of the target element; copied
+    /// nodes keep the spans of the element they were copied from but not
+    /// its id (so ids stay unique), and references inside the copy are
+    /// expanded in turn. `:[id]` merges the target's attributes into the
+    /// enclosing element (its own attributes win) and is removed.
+    ///
+    /// Returns [`check_references`](Self::check_references) diagnostics plus
+    /// `CyclicReference` errors for references inside their own target,
+    /// which are left unexpanded.
+    pub fn expand_references(&mut self) -> Vec<Diagnostic> {
+        self.expand_references_with_max_nodes(DEFAULT_MAX_EXPANDED_NODES)
+    }
+
+    /// Like [`expand_references`](Self::expand_references), copying at
+    /// most `max_nodes` nodes in total. The reference that would exceed the
+    /// limit gets an `ExpansionLimit` error, and it and all later ones are
+    /// left unexpanded.
+    pub fn expand_references_with_max_nodes(&mut self, max_nodes: usize) -> Vec<Diagnostic> {
+        let mut diagnostics = self.check_references();
+        // Copies made during expansion, mapped to the node they copy
+        let mut origin: HashMap<NodeId, NodeId> = HashMap::new();
+        let mut copied = 0;
+
+        // References in the tree, in document order; those inside copies
+        // are added as the copies are made.
+        let mut pending: VecDeque<NodeId> = references(self.root()).collect();
+        while let Some(reference) = pending.pop_front() {
+            let node = self.get(reference).unwrap();
+            let Some(kind) = node.reference_kind() else { continue };
+            let Some(target) = node.resolve_reference() else { continue };
+            let target = target.id();
+
+            let inside_target = ancestors(node).any(|a| {
+                a.id() == target || origin.get(&a.id()) == Some(&target)
+            });
+            if inside_target {
+                let span = self.get(reference).unwrap().span();
+                diagnostics.push(Diagnostic::error(
+                    Code::CyclicReference,
+                    span,
+                    "reference is inside the element it refers to",
+                ));
+                continue;
+            }
+
+            match kind {
+                ReferenceKind::Element => {
+                    let size = 1 + descendants(self.get(target).unwrap()).count();
+                    if copied + size > max_nodes {
+                        diagnostics.push(Diagnostic::error(
+                            Code::ExpansionLimit,
+                            self.get(reference).unwrap().span(),
+                            format!("expanding references would copy more than {} nodes", max_nodes),
+                        ));
+                        break;
+                    }
+                    copied += size;
+                    self.copy_into(reference, target, &mut origin);
+                    pending.extend(references(self.get(reference).unwrap()));
+                }
+                ReferenceKind::Attributes => {
+                    let parent = self.get(reference).unwrap().parent().unwrap().id();
+                    self.merge_attributes(parent, target);
+                    self.detach(reference);
+                }
+            }
+        }
+        diagnostics
+    }
+
+    /// Turn `dest` into a copy of `source`, then append copies of its children.
+    fn copy_into(&mut self, dest: NodeId, source: NodeId, origin: &mut HashMap<NodeId, NodeId>) {
+        let node = self.get(source).unwrap();
+        let (mut kind, span) = (node.kind().clone(), node.span());
+        if let NodeKind::Element { id, .. } = &mut kind {
+            *id = None;
+        }
+        let children: Vec<NodeId> = node.children().map(|c| c.id()).collect();
+
+        *self.kind_mut(dest) = kind;
+        self.set_span(dest, span);
+        origin.insert(dest, source);
+        for child in children {
+            let kind = self.get(child).unwrap().kind().clone();
+            let copy = self.append_child(dest, kind);
+            self.copy_into(copy, child, origin);
+        }
+    }
+
+    fn merge_attributes(&mut self, into: NodeId, from: NodeId) {
+        let Some(source) = self.get(from).and_then(|n| n.as_element()) else { return };
+        let existing = self.get(into).and_then(|n| n.as_element()).map_or(&[][..], |el| el.attributes());
+        let merged: Vec<Attribute<'a>> = source
+            .attributes()
+            .iter()
+            .filter(|a| !existing.iter().any(|e| e.name == a.name))
+            .cloned()
+            .collect();
+        if let NodeKind::Element { attrs, .. } | NodeKind::Directive { attrs, .. } = self.kind_mut(into) {
+            attrs.extend(merged);
+        }
+    }
+}
+
+pub(crate) fn descendants<'doc, 'a>(node: Node<'doc, 'a>) -> impl Iterator<Item = Node<'doc, 'a>> {
+    let mut stack: Vec<Node<'doc, 'a>> = node.children().collect();
+    stack.reverse();
+    std::iter::from_fn(move || {
+        let next = stack.pop()?;
+        let start = stack.len();
+        stack.extend(next.children());
+        stack[start..].reverse();
+        Some(next)
+    })
+}
+
+/// Reference nodes below `node`, in document order.
+fn references<'doc>(node: Node<'doc, '_>) -> impl Iterator<Item = NodeId> + 'doc {
+    descendants(node).filter(|n| matches!(n.kind(), NodeKind::Reference(_))).map(|n| n.id())
+}
+
+fn ancestors<'doc, 'a>(node: Node<'doc, 'a>) -> impl Iterator<Item = Node<'doc, 'a>> {
+    std::iter::successors(node.parent(), |n| n.parent())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::Severity;
+
+    const DOC: &[u8] = b"\
+|license[mit]
+  MIT License
+|db[base] :pool 5 :host localhost
+|project
+  @[mit]
+|database[dev] :[base] :pool 2
+";
+
+    #[test]
+    fn test_get_by_id_and_resolve() {
+        let doc = Document::parse(DOC).unwrap();
+        assert_eq!(doc.get_by_id("mit").unwrap().as_element().unwrap().name(), "license");
+        assert!(doc.get_by_id("missing").is_none());
+
+        let project = doc.root().select_first("project").unwrap().unwrap();
+        let reference = project.first_child().unwrap();
+        assert_eq!(reference.reference_kind(), Some(ReferenceKind::Element));
+        assert_eq!(reference.resolve_reference().unwrap().id(), doc.get_by_id("mit").unwrap().id());
+
+        let database = doc.get_by_id("dev").unwrap();
+        let merge = database.first_child().unwrap();
+        assert_eq!(merge.reference_kind(), Some(ReferenceKind::Attributes));
+    }
+
+    #[test]
+    fn test_expand() {
+        let mut doc = Document::parse(DOC).unwrap();
+        assert!(doc.expand_references().is_empty());
+
+        let project = doc.root().select_first("project").unwrap().unwrap();
+        let copy = project.first_child().unwrap();
+        assert_eq!(copy.as_element().unwrap().name(), "license");
+        assert_eq!(copy.all_text(), "MIT License");
+        // The copy does not take the id
+        assert_eq!(copy.as_element().unwrap().id(), None);
+        assert!(doc.check_references().is_empty());
+
+        let database = doc.get_by_id("dev").unwrap().as_element().unwrap();
+        assert_eq!(database.children().count(), 0);
+        let names: Vec<_> = database.attributes().iter().map(|a| a.name.as_ref()).collect();
+        assert_eq!(names, ["pool", "host"]);
+        assert_eq!(doc.span_text(database.attribute("pool").unwrap().value_span), "2");
+    }
+
+    #[test]
+    fn test_nested_and_cyclic() {
+        let mut doc = Document::parse(b"|a[x]\n  @[y]\n|b[y]\n  |leaf\n|c\n  @[x]\n|d[z]\n  @[z]\n").unwrap();
+        let diagnostics = doc.expand_references();
+        assert_eq!(diagnostics.iter().map(|d| d.code).collect::<Vec<_>>(), [Code::CyclicReference]);
+        assert_eq!(doc.span_text(diagnostics[0].span), "@[z]");
+
+        let c = doc.root().select_first("c").unwrap().unwrap();
+        assert_eq!(c.select("a > b > leaf").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_expansion_limit() {
+        // Each element holds two copies of the one before it
+        let mut source = String::from("|a[r0] x\n");
+        for i in 1..40 {
+            source.push_str(&format!("|a[r{}]\n  @[r{}]\n  @[r{}]\n", i, i - 1, i - 1));
+        }
+        let mut doc = Document::parse(source.as_bytes()).unwrap();
+        let diagnostics = doc.expand_references_with_max_nodes(1000);
+        assert_eq!(diagnostics.iter().map(|d| d.code).collect::<Vec<_>>(), [Code::ExpansionLimit]);
+        assert!(descendants(doc.root()).count() < 1000 + 200);
+        assert!(doc.check_references().is_empty());
+
+        // References outside the tree are left alone
+        let mut doc = Document::parse(b"|a[x]\n|b\n  @[x]\n").unwrap();
+        let b = doc.root().children().nth(1).unwrap().id();
+        let reference = doc.get(b).unwrap().first_child().unwrap().id();
+        doc.detach(b);
+        assert!(doc.expand_references().is_empty());
+        assert!(matches!(doc.get(reference).unwrap().kind(), NodeKind::Reference(_)));
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let doc = Document::parse(b"|a[x]\n|b[x]\n|c\n  @[nope]\n").unwrap();
+        let diagnostics = doc.check_references();
+        let codes: Vec<_> = diagnostics.iter().map(|d| (d.code, d.severity)).collect();
+        assert_eq!(codes, [(Code::DuplicateId, Severity::Warning), (Code::DanglingReference, Severity::Error)]);
+        assert_eq!(doc.span_text(diagnostics[0].span), "[x]");
+        assert_eq!(doc.span_text(diagnostics[1].span), "@[nope]");
+    }
+}
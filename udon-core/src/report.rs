@@ -0,0 +1,166 @@
+//! Human-readable reports for diagnostics.
+//!
+//! A report shows the severity, code and message, the line and column,
+//! the offending source line with a caret underline, and a help hint when
+//! the code has one:
+//!
+//! ```text
+//! error[NoTabs]: tabs are not allowed for indentation
+//!  --> config.udon:2:1
+//!   |
+//! 2 |     :port 8080
+//!   | ^
+//!   = help: indent with spaces instead of tabs
+//! ```
+//!
+//! # Example
+//!
+//! ```
+//! use udon_core::tree::Document;
+//! use udon_core::ReportRenderer;
+//!
+//! let source = b"|server\n\t:port 8080\n";
+//! let (_, diagnostics) = Document::parse_with_diagnostics(source);
+//! let report = ReportRenderer::new(source).file_name("config.udon").render_all(&diagnostics);
+//! assert!(report.starts_with("error[NoTabs]: tabs are not allowed for indentation\n --> config.udon:2:1\n"));
+//! ```
+
+use crate::diagnostic::Diagnostic;
+use crate::span::LineIndex;
+
+/// Width a tab is expanded to in the source excerpt.
+const TAB_WIDTH: usize = 4;
+
+/// Renders diagnostics against the source they were reported for.
+#[derive(Debug, Clone)]
+pub struct ReportRenderer<'a> {
+    index: LineIndex<'a>,
+    file_name: Option<String>,
+}
+
+impl<'a> ReportRenderer<'a> {
+    pub fn new(source: &'a [u8]) -> Self {
+        ReportRenderer { index: LineIndex::new(source), file_name: None }
+    }
+
+    /// Name to show before the line and column.
+    pub fn file_name(mut self, name: impl Into<String>) -> Self {
+        self.file_name = Some(name.into());
+        self
+    }
+
+    /// Render one diagnostic. The result ends with a newline.
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let start = self.index.location(diagnostic.span.start as usize);
+        let end = self.index.location(diagnostic.span.end as usize);
+        let line = start.line as usize;
+        let text = self.index.line_text(line);
+
+        // Underline to the end of the span, or of the line if it continues
+        let caret_end = if end.line == start.line { end.column } else { text.chars().count() as u32 + 1 };
+        let before = display_width(text.chars().take(start.column as usize - 1));
+        let width = display_width(
+            text.chars()
+                .skip(start.column as usize - 1)
+                .take(caret_end.saturating_sub(start.column) as usize),
+        );
+
+        let gutter = " ".repeat(line.to_string().len());
+        let mut out = format!("{}[{}]: {}\n", diagnostic.severity, diagnostic.code, diagnostic.message);
+        out.push_str(&format!("{} --> ", &gutter[1..]));
+        if let Some(name) = &self.file_name {
+            out.push_str(name);
+            out.push(':');
+        }
+        out.push_str(&format!("{}:{}\n", start.line, start.column));
+        out.push_str(&format!("{} |\n", gutter));
+        out.push_str(format!("{} | {}", line, expand_tabs(&text)).trim_end());
+        out.push('\n');
+        out.push_str(&format!("{} | {}{}\n", gutter, " ".repeat(before), "^".repeat(width.max(1))));
+        if let Some(help) = diagnostic.help() {
+            out.push_str(&format!("{} = help: {}\n", gutter, help));
+        }
+        out
+    }
+
+    /// Render several diagnostics, separated by blank lines.
+    pub fn render_all(&self, diagnostics: &[Diagnostic]) -> String {
+        diagnostics.iter().map(|d| self.render(d)).collect::<Vec<_>>().join("\n")
+    }
+}
+
+fn display_width(chars: impl Iterator<Item = char>) -> usize {
+    chars.map(|c| if c == '\t' { TAB_WIDTH } else { 1 }).sum()
+}
+
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', &" ".repeat(TAB_WIDTH))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::Code;
+    use crate::span::Span;
+    use crate::tree::Document;
+
+    #[test]
+    fn test_reports() {
+        let source = b"|server\r\n\t:port 8080\r\n|p caf\xc3\xa9 @[x]\n";
+        let (_, diagnostics) = Document::parse_with_diagnostics(source);
+        let renderer = ReportRenderer::new(source).file_name("config.udon");
+        assert_eq!(
+            renderer.render(&diagnostics[0]),
+            "error[NoTabs]: tabs are not allowed for indentation\n --> config.udon:2:1\n  |\n2 |     :port 8080\n  | ^\n  = help: indent with spaces instead of tabs\n",
+        );
+
+        // Columns count characters; the underline covers the span
+        let dangling = Diagnostic::error(Code::DanglingReference, Span::new(31, 35), "no element with id `x`");
+        assert_eq!(
+            ReportRenderer::new(source).render(&dangling),
+            "error[DanglingReference]: no element with id `x`\n --> 3:9\n  |\n3 | |p café @[x]\n  |         ^^^^\n  = help: add an element with this id, or correct the reference\n",
+        );
+
+        let err = Document::parse(b"|a :list [1 2\n").unwrap_err();
+        let report = ReportRenderer::new(b"|a :list [1 2\n").render(&err.to_diagnostic());
+        assert!(report.starts_with("error[UnclosedArray]: unclosed array: missing `]`\n --> 2:1\n"), "{}", report);
+        assert!(report.ends_with("  = help: close the array with `]`\n"), "{}", report);
+    }
+
+    #[test]
+    fn test_report_edges() {
+        use crate::parser::ParseErrorCode;
+
+        let eof = |source: &[u8]| Diagnostic::error(Code::Parse(ParseErrorCode::UnexpectedEof), Span::new(source.len(), source.len()), "unexpected end of input");
+
+        // Empty input still has a line 1 to point at
+        assert_eq!(
+            ReportRenderer::new(b"").render(&eof(b"")),
+            "error[UnexpectedEof]: unexpected end of input\n --> 1:1\n  |\n1 |\n  | ^\n  = help: the input ends inside a construct; check for a missing closing delimiter\n",
+        );
+        // At the end of input without a final line ending, the caret sits after the last character
+        let source = b"|a\n  |p \"x";
+        assert!(ReportRenderer::new(source).render(&eof(source)).contains(" --> 2:8\n  |\n2 |   |p \"x\n  |        ^\n"));
+        // ...and with one, on the empty line after it
+        let source = b"|a\n  |p \"x\r\n";
+        assert!(ReportRenderer::new(source).render(&eof(source)).contains(" --> 3:1\n  |\n3 |\n  | ^\n"));
+
+        // A span running onto later lines is underlined to the end of its first line
+        let source = b"|a\r\n  :x 1\r\n|b\r\n";
+        let span = Diagnostic::warning(Code::DuplicateId, Span::new(6, 17), "spans lines");
+        assert_eq!(
+            ReportRenderer::new(source).render(&span),
+            "warning[DuplicateId]: spans lines\n --> 2:3\n  |\n2 |   :x 1\n  |   ^^^^\n  = help: give each element a unique id; references resolve to the first\n",
+        );
+        // Covering a CRLF's \r does not underline past the text
+        let span = Diagnostic::info(Code::MalformedLet, Span::new(6, 11), "crlf");
+        assert_eq!(ReportRenderer::new(source).render(&span), "info[MalformedLet]: crlf\n --> 2:3\n  |\n2 |   :x 1\n  |   ^^^^\n");
+
+        // Columns and carets count characters, whatever their UTF-8 length
+        let source = "|p 日本🎉 @[x]\n".as_bytes();
+        let reference = Diagnostic::error(Code::DanglingReference, Span::new(14, 18), "no element with id `x`");
+        assert!(ReportRenderer::new(source).render(&reference).contains(" --> 1:8\n  |\n1 | |p 日本🎉 @[x]\n  |        ^^^^\n"));
+        let wide = Diagnostic::error(Code::DanglingReference, Span::new(3, 13), "no element with id `x`");
+        assert!(ReportRenderer::new(source).render(&wide).contains(" --> 1:4\n  |\n1 | |p 日本🎉 @[x]\n  |    ^^^\n"));
+    }
+}
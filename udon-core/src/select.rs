@@ -0,0 +1,477 @@
+//! CSS-style selectors over `tree::Document`.
+//!
+//! Selectors use UDON's own element syntax where it has one:
+//!
+//! | Selector | Matches |
+//! |----------|---------|
+//! | `heading` | elements named `heading` |
+//! | `*` | any element |
+//! | `[intro]` | the element with id `intro` |
+//! | `.featured` | elements with class `featured` |
+//! | `[:author]` | elements with an `:author` attribute |
+//! | `[:lang=en]`, `[:title="A B"]` | elements whose attribute has that value |
+//! | `field?`, `field!`, `field*`, `field+` | elements with that suffix flag |
+//! | `a b` | `b` anywhere below an `a` |
+//! | `a > b` | `b` directly under an `a` |
+//! | `a, b` | elements matching either |
+//!
+//! # Example
+//!
+//! ```
+//! use udon_core::tree::Document;
+//!
+//! let doc = Document::parse(b"|article.featured\n  |heading Welcome\n  |section\n    |heading Nested\n").unwrap();
+//! let direct = doc.select("article.featured > heading").unwrap();
+//! assert_eq!(direct.len(), 1);
+//! assert_eq!(doc.select("article heading").unwrap().len(), 2);
+//! ```
+
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::tree::{unescape, Document, ElementView, Node, Value};
+
+/// Error returned for a malformed selector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorError {
+    pub message: String,
+    /// Byte offset into the selector string.
+    pub position: usize,
+}
+
+impl fmt::Display for SelectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for SelectorError {}
+
+/// A parsed selector, reusable across queries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector {
+    alternatives: Vec<Complex>,
+}
+
+/// Compounds joined by combinators, stored left to right.
+#[derive(Debug, Clone, PartialEq)]
+struct Complex {
+    first: Compound,
+    rest: Vec<(Combinator, Compound)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+/// Conditions on a single element.
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Compound {
+    name: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+    attrs: Vec<(String, Option<String>)>,
+    suffixes: Vec<char>,
+}
+
+impl Selector {
+    /// Parse a selector string.
+    pub fn parse(input: &str) -> Result<Self, SelectorError> {
+        SelectorParser { input, pos: 0 }.parse()
+    }
+
+    /// Check whether a node matches the selector.
+    pub fn matches(&self, node: Node<'_, '_>) -> bool {
+        node.as_element().is_some() && self.alternatives.iter().any(|complex| complex.matches(node))
+    }
+
+    /// Matching descendants of `scope`, lazily in document order.
+    fn select_in<'s, 'doc, 'a>(&'s self, scope: Node<'doc, 'a>) -> impl Iterator<Item = Node<'doc, 'a>> + 's
+    where
+        'doc: 's,
+        'a: 's,
+    {
+        let mut stack: Vec<Node<'doc, 'a>> = scope.children().collect();
+        stack.reverse();
+        std::iter::from_fn(move || {
+            while let Some(node) = stack.pop() {
+                let start = stack.len();
+                stack.extend(node.children());
+                stack[start..].reverse();
+                if self.matches(node) {
+                    return Some(node);
+                }
+            }
+            None
+        })
+    }
+}
+
+impl std::str::FromStr for Selector {
+    type Err = SelectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Selector::parse(s)
+    }
+}
+
+impl Complex {
+    fn matches(&self, node: Node<'_, '_>) -> bool {
+        let compounds: Vec<_> = std::iter::once((None, &self.first))
+            .chain(self.rest.iter().map(|(c, s)| (Some(*c), s)))
+            .collect();
+        matches_from(&compounds, compounds.len() - 1, node)
+    }
+}
+
+/// Match `compounds[..=index]` with `compounds[index]` on `node`, walking
+/// ancestors for the combinators to its left.
+fn matches_from(compounds: &[(Option<Combinator>, &Compound)], index: usize, node: Node<'_, '_>) -> bool {
+    let (combinator, compound) = compounds[index];
+    let Some(el) = node.as_element() else {
+        return false;
+    };
+    if !compound.matches(el) {
+        return false;
+    }
+    match combinator {
+        None => true,
+        Some(Combinator::Child) => node.parent().is_some_and(|p| matches_from(compounds, index - 1, p)),
+        Some(Combinator::Descendant) => {
+            let mut ancestor = node.parent();
+            while let Some(a) = ancestor {
+                if matches_from(compounds, index - 1, a) {
+                    return true;
+                }
+                ancestor = a.parent();
+            }
+            false
+        }
+    }
+}
+
+impl Compound {
+    fn matches(&self, el: ElementView<'_, '_>) -> bool {
+        self.name.as_ref().is_none_or(|name| el.name() == name)
+            && self.id.as_ref().is_none_or(|id| el.id() == Some(id.as_str()))
+            && self.classes.iter().all(|class| el.has_class(class))
+            && self.attrs.iter().all(|(name, expected)| match (el.attr(name), expected) {
+                (None, _) => false,
+                (Some(_), None) => true,
+                (Some(value), Some(expected)) => value_text(value).is_some_and(|text| text == expected.as_str()),
+            })
+            && self
+                .suffixes
+                .iter()
+                .all(|s| matches!(el.attr(s.encode_utf8(&mut [0; 4])), Some(Value::BoolTrue)))
+    }
+}
+
+/// Text of a scalar value, for `[:key=value]`: strings with their escapes
+/// resolved, other values as written.
+fn value_text<'v>(value: &'v Value<'_>) -> Option<Cow<'v, str>> {
+    let text = match value {
+        Value::String(s) => return Some(unescape(s)),
+        Value::Bare(s)
+        | Value::Integer(s)
+        | Value::Float(s)
+        | Value::Rational(s)
+        | Value::Complex(s)
+        | Value::Date(s)
+        | Value::Time(s)
+        | Value::DateTime(s)
+        | Value::Duration(s)
+        | Value::RelativeTime(s) => s.as_ref(),
+        Value::BoolTrue => "true",
+        Value::BoolFalse => "false",
+        Value::Nil => "null",
+        Value::Array(_) => return None,
+    };
+    Some(Cow::Borrowed(text))
+}
+
+// ============================================================================
+// Parsing
+// ============================================================================
+
+struct SelectorParser<'s> {
+    input: &'s str,
+    pos: usize,
+}
+
+impl SelectorParser<'_> {
+    fn parse(mut self) -> Result<Selector, SelectorError> {
+        let mut alternatives = vec![self.complex()?];
+        while self.eat(',') {
+            alternatives.push(self.complex()?);
+        }
+        self.skip_ws();
+        match self.peek() {
+            None => Ok(Selector { alternatives }),
+            Some(c) => Err(self.error(format!("unexpected '{}'", c))),
+        }
+    }
+
+    fn complex(&mut self) -> Result<Complex, SelectorError> {
+        self.skip_ws();
+        let first = self.compound()?;
+        let mut rest = Vec::new();
+        loop {
+            let had_ws = self.skip_ws();
+            let combinator = match self.peek() {
+                Some('>') => {
+                    self.pos += 1;
+                    self.skip_ws();
+                    Combinator::Child
+                }
+                Some(',') | None => break,
+                Some(_) if had_ws => Combinator::Descendant,
+                Some(c) => return Err(self.error(format!("unexpected '{}'", c))),
+            };
+            rest.push((combinator, self.compound()?));
+        }
+        Ok(Complex { first, rest })
+    }
+
+    fn compound(&mut self) -> Result<Compound, SelectorError> {
+        let start = self.pos;
+        let mut compound = Compound::default();
+
+        if self.eat('*') {
+            // Universal selector; a `*` after anything else is a suffix flag
+        } else if let Some(name) = self.label()? {
+            compound.name = Some(name);
+        }
+
+        loop {
+            match self.peek() {
+                Some('.') => {
+                    self.pos += 1;
+                    let class = self.label()?.ok_or_else(|| self.error("expected class name"))?;
+                    compound.classes.push(class);
+                }
+                Some('[') => {
+                    self.pos += 1;
+                    if self.eat(':') {
+                        let name = self.label()?.ok_or_else(|| self.error("expected attribute name"))?;
+                        let value = if self.eat('=') { Some(self.value()?) } else { None };
+                        compound.attrs.push((name, value));
+                    } else {
+                        let id = self.until(']');
+                        if id.is_empty() {
+                            return Err(self.error("expected id"));
+                        }
+                        compound.id = Some(id.to_string());
+                    }
+                    if !self.eat(']') {
+                        return Err(self.error("expected ']'"));
+                    }
+                }
+                Some(c @ ('?' | '!' | '*' | '+')) => {
+                    self.pos += 1;
+                    compound.suffixes.push(c);
+                }
+                _ => break,
+            }
+        }
+
+        if self.pos == start {
+            return Err(match self.peek() {
+                Some(c) => self.error(format!("unexpected '{}'", c)),
+                None => self.error("expected selector"),
+            });
+        }
+        Ok(compound)
+    }
+
+    /// An element name, class or attribute key, optionally single-quoted.
+    fn label(&mut self) -> Result<Option<String>, SelectorError> {
+        if self.eat('\'') {
+            return self.quoted('\'').map(Some);
+        }
+        let rest = &self.input[self.pos..];
+        let len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+            .unwrap_or(rest.len());
+        self.pos += len;
+        Ok((len > 0).then(|| rest[..len].to_string()))
+    }
+
+    /// An attribute value: quoted, or bare up to `]`.
+    fn value(&mut self) -> Result<String, SelectorError> {
+        match self.peek() {
+            Some(q @ ('"' | '\'')) => {
+                self.pos += 1;
+                self.quoted(q)
+            }
+            _ => Ok(self.until(']').trim().to_string()),
+        }
+    }
+
+    /// The rest of a quoted string, after its opening quote.
+    fn quoted(&mut self, quote: char) -> Result<String, SelectorError> {
+        let mut out = String::new();
+        let mut chars = self.input[self.pos..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    if let Some((_, next)) = chars.next() {
+                        out.push(next);
+                    }
+                }
+                c if c == quote => {
+                    self.pos += i + 1;
+                    return Ok(out);
+                }
+                c => out.push(c),
+            }
+        }
+        self.pos = self.input.len();
+        Err(self.error("unclosed quote"))
+    }
+
+    fn until(&mut self, end: char) -> &str {
+        let rest = &self.input[self.pos..];
+        let len = rest.find(end).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Skip whitespace, returning whether there was any.
+    fn skip_ws(&mut self) -> bool {
+        let rest = &self.input[self.pos..];
+        let len = rest.len() - rest.trim_start().len();
+        self.pos += len;
+        len > 0
+    }
+
+    fn error(&self, message: impl Into<String>) -> SelectorError {
+        SelectorError { message: message.into(), position: self.pos }
+    }
+}
+
+// ============================================================================
+// Document / Node entry points
+// ============================================================================
+
+impl<'a> Document<'a> {
+    /// All elements matching a selector, in document order.
+    pub fn select(&self, selector: &str) -> Result<Vec<Node<'_, 'a>>, SelectorError> {
+        self.root().select(selector)
+    }
+}
+
+impl<'doc, 'a: 'doc> Node<'doc, 'a> {
+    /// All descendant elements matching a selector, in document order.
+    pub fn select(&self, selector: &str) -> Result<Vec<Node<'doc, 'a>>, SelectorError> {
+        Ok(Selector::parse(selector)?.select_in(*self).collect())
+    }
+
+    /// The first descendant element matching a selector.
+    pub fn select_first(&self, selector: &str) -> Result<Option<Node<'doc, 'a>>, SelectorError> {
+        Ok(Selector::parse(selector)?.select_in(*self).next())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &[u8] = b"\
+|article[intro].featured :lang en
+  |heading Welcome
+  |section.body
+    |heading Nested
+    |field! :type integer
+    |field? :type \"long text\"
+|article.plain
+  |heading Other
+  |p see |{a :href x} here
+";
+
+    fn names(nodes: &[Node<'_, '_>]) -> Vec<String> {
+        nodes
+            .iter()
+            .map(|n| {
+                let el = n.as_element().unwrap();
+                n.all_text().trim().to_string() + "@" + el.name()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_name_class_id() {
+        let doc = Document::parse(DOC).unwrap();
+        assert_eq!(doc.select("heading").unwrap().len(), 3);
+        assert_eq!(doc.select("article").unwrap().len(), 2);
+        assert_eq!(doc.select(".featured").unwrap().len(), 1);
+        assert_eq!(doc.select("[intro]").unwrap().len(), 1);
+        assert_eq!(doc.select("article[intro].featured").unwrap().len(), 1);
+        assert_eq!(doc.select("article.missing").unwrap().len(), 0);
+        assert_eq!(doc.select("*").unwrap().len(), 10);
+    }
+
+    #[test]
+    fn test_combinators() {
+        let doc = Document::parse(DOC).unwrap();
+        let direct = doc.select("article.featured > heading").unwrap();
+        assert_eq!(names(&direct), ["Welcome@heading"]);
+
+        let all = doc.select("article.featured heading").unwrap();
+        assert_eq!(names(&all), ["Welcome@heading", "Nested@heading"]);
+
+        assert_eq!(doc.select("article > section > heading").unwrap().len(), 1);
+        assert_eq!(doc.select("p a").unwrap().len(), 1);
+        assert_eq!(doc.select("heading, a").unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_attributes_and_suffixes() {
+        let doc = Document::parse(DOC).unwrap();
+        assert_eq!(doc.select("[:lang]").unwrap().len(), 1);
+        assert_eq!(doc.select("[:lang=en]").unwrap().len(), 1);
+        assert_eq!(doc.select("[:lang=fr]").unwrap().len(), 0);
+        assert_eq!(doc.select("field[:type=\"long text\"]").unwrap().len(), 1);
+        let quoted = Document::parse(b"|a :q \"say \\\"hi\\\"\"\n|b :q 'say \\\"hi\\\"'\n").unwrap();
+        assert_eq!(names(&quoted.select("[:q='say \"hi\"']").unwrap()), ["@a", "@b"]);
+        assert_eq!(doc.select("field!").unwrap().len(), 1);
+        assert_eq!(doc.select("field?").unwrap().len(), 1);
+        assert_eq!(doc.select("field*").unwrap().len(), 0);
+        assert_eq!(doc.select("*!").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_select_first_scoped() {
+        let doc = Document::parse(DOC).unwrap();
+        let plain = doc.select("article.plain").unwrap()[0];
+        let heading = plain.select_first("heading").unwrap().unwrap();
+        assert_eq!(heading.all_text(), "Other");
+        assert!(plain.select_first("section").unwrap().is_none());
+        // The scope itself is not a candidate, but ancestors still satisfy combinators
+        assert_eq!(plain.select("article > heading").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_selectors() {
+        for bad in ["", "a >", "a..b", "[", "[:]", "a[:k=\"open]", "a ~ b", ","] {
+            assert!(Selector::parse(bad).is_err(), "{:?} should not parse", bad);
+        }
+        let err = Selector::parse("a ~ b").unwrap_err();
+        assert_eq!(err.position, 2);
+    }
+}
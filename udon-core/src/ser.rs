@@ -0,0 +1,635 @@
+//! serde serialization to UDON text (`serde` feature).
+//!
+//! The mapping mirrors [`crate::de`], so serialized values deserialize
+//! back to the same Rust value:
+//!
+//! - A struct or map becomes an element. Scalar fields become `:name value`
+//!   attributes, sequences of scalars become arrays, and nested structs or
+//!   maps become child elements named after the field. A sequence of
+//!   structs becomes repeated child elements.
+//! - `$id`, `$classes` and `$text` fields set the element's id, classes
+//!   and text content.
+//! - Integers, floats, booleans and `None` / `()` are written in the
+//!   grammar's Integer, Float, `true` / `false` and `null` forms, so parsing
+//!   the output gives the same typed [`Value`]s. Strings are written bare
+//!   when that parses back as the same string, and quoted otherwise.
+//! - Unit enum variants are written as their name. Newtype and struct
+//!   variants become a child element named after the variant.
+//!
+//! The top-level value must be a struct or map. [`to_string`] names the
+//! root element after the struct (use `#[serde(rename = "...")]` to change
+//! it); [`to_string_named`] takes the name explicitly.
+//!
+//! # Example
+//!
+//! ```
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize)]
+//! #[serde(rename = "server")]
+//! struct Server {
+//!     host: &'static str,
+//!     port: u16,
+//!     ratio: f64,
+//!     tags: Vec<&'static str>,
+//!     backup: Option<&'static str>,
+//! }
+//!
+//! let server = Server { host: "localhost", port: 8080, ratio: 1.0, tags: vec!["a", "b"], backup: None };
+//! assert_eq!(
+//!     udon_core::to_string(&server).unwrap(),
+//!     "|server\n  :host localhost\n  :port 8080\n  :ratio 1.0\n  :tags [a b]\n  :backup null\n",
+//! );
+//! ```
+
+use std::borrow::Cow;
+use std::fmt;
+
+use serde::ser::{self, Serialize};
+
+use crate::tree::{string_value, unescape, Attribute, Document, NodeId, NodeKind, Value};
+use crate::writer::to_udon_string;
+
+/// Error returned when a value cannot be represented in UDON.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error { message: msg.to_string() }
+    }
+}
+
+fn error(message: impl Into<String>) -> Error {
+    Error { message: message.into() }
+}
+
+/// Serialize a struct as a UDON element named after the struct.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    Ok(to_udon_string(&to_document(value)?))
+}
+
+/// Serialize a struct or map as a UDON element with the given name.
+pub fn to_string_named<T: Serialize + ?Sized>(value: &T, name: &str) -> Result<String, Error> {
+    let content = value.serialize(ContentSerializer)?;
+    Ok(to_udon_string(&build(content, Some(name))?))
+}
+
+/// Serialize a struct into a document holding one element named after the
+/// struct.
+pub fn to_document<T: Serialize + ?Sized>(value: &T) -> Result<Document<'static>, Error> {
+    build(value.serialize(ContentSerializer)?, None)
+}
+
+fn build(content: Content, name: Option<&str>) -> Result<Document<'static>, Error> {
+    let Content::Element(name_hint, element) = content else {
+        return Err(error("the top-level value must be a struct or map"));
+    };
+    let name = match (name, name_hint) {
+        (Some(name), _) => name.to_string(),
+        (None, Some(hint)) => hint.to_string(),
+        (None, None) => return Err(error("a map needs an element name; use to_string_named")),
+    };
+    let mut doc = Document::new();
+    let root = doc.root().id();
+    element.append_to(&mut doc, root, name);
+    Ok(doc)
+}
+
+// ============================================================================
+// Intermediate content
+// ============================================================================
+
+/// A serialized value, before it is placed in the tree.
+enum Content {
+    Value(Value<'static>),
+    /// A struct (with its name) or map.
+    Element(Option<&'static str>, Element),
+    Seq(Vec<Content>),
+    /// A newtype or struct enum variant.
+    Variant(&'static str, Box<Content>),
+}
+
+#[derive(Default)]
+struct Element {
+    id: Option<String>,
+    classes: Vec<String>,
+    attrs: Vec<(String, Value<'static>)>,
+    text: Option<String>,
+    children: Vec<(String, Element)>,
+}
+
+impl Element {
+    /// Place a field's content in the element.
+    fn insert(&mut self, key: String, content: Content) -> Result<(), Error> {
+        match key.as_str() {
+            "$id" => self.id = Some(scalar_text(content, "$id")?),
+            "$classes" => {
+                let Content::Seq(items) = content else {
+                    return Err(error("$classes must be a sequence of strings"));
+                };
+                for item in items {
+                    self.classes.push(scalar_text(item, "$classes")?);
+                }
+            }
+            "$text" => self.text = Some(scalar_text(content, "$text")?),
+            _ => match content {
+                Content::Value(value) => self.attrs.push((key, value)),
+                Content::Element(_, element) => self.children.push((key, element)),
+                Content::Seq(items) if items.iter().all(|i| matches!(i, Content::Element(..))) && !items.is_empty() => {
+                    for item in items {
+                        if let Content::Element(_, element) = item {
+                            self.children.push((key.clone(), element));
+                        }
+                    }
+                }
+                Content::Seq(items) => self.attrs.push((key, array(items)?)),
+                Content::Variant(variant, content) => {
+                    let mut wrapper = Element::default();
+                    wrapper.children.push((variant.to_string(), variant_element(*content)?));
+                    self.children.push((key, wrapper));
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn append_to(self, doc: &mut Document<'static>, parent: NodeId, name: String) {
+        let attrs = self
+            .attrs
+            .into_iter()
+            .map(|(name, value)| Attribute::new(name, value))
+            .collect();
+        let kind = NodeKind::Element {
+            name: Cow::Owned(name),
+            id: self.id.map(Cow::Owned),
+            classes: self.classes.into_iter().map(Cow::Owned).collect(),
+            attrs,
+            embedded: false,
+        };
+        let id = doc.append_child(parent, kind);
+        if let Some(text) = self.text {
+            doc.append_child(id, NodeKind::Text(Cow::Owned(text)));
+        }
+        for (name, child) in self.children {
+            child.append_to(doc, id, name);
+        }
+    }
+}
+
+/// The element for a newtype or struct variant's content.
+fn variant_element(content: Content) -> Result<Element, Error> {
+    match content {
+        Content::Element(_, element) => Ok(element),
+        content => Ok(Element { text: Some(scalar_text(content, "a variant")?), ..Element::default() }),
+    }
+}
+
+fn array(items: Vec<Content>) -> Result<Value<'static>, Error> {
+    let values = items
+        .into_iter()
+        .map(|item| match item {
+            Content::Value(value) => Ok(value),
+            Content::Seq(items) => array(items),
+            _ => Err(error("arrays can only hold scalars and arrays")),
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Value::Array(values))
+}
+
+/// Text of a scalar, for ids, classes and text content.
+fn scalar_text(content: Content, what: &str) -> Result<String, Error> {
+    match content {
+        Content::Value(Value::String(s)) => Ok(unescape(&s).into_owned()),
+        Content::Value(Value::Bare(s) | Value::Integer(s) | Value::Float(s)) => Ok(s.into_owned()),
+        Content::Value(Value::BoolTrue) => Ok("true".into()),
+        Content::Value(Value::BoolFalse) => Ok("false".into()),
+        _ => Err(error(format!("{} must be a string or number", what))),
+    }
+}
+
+fn integer(n: impl fmt::Display) -> Content {
+    Content::Value(Value::Integer(Cow::Owned(n.to_string())))
+}
+
+/// `Debug` always includes a `.` or exponent, so the text stays a Float.
+fn float(f: impl fmt::Debug, finite: bool) -> Result<Content, Error> {
+    if !finite {
+        return Err(error("NaN and infinite floats cannot be written"));
+    }
+    Ok(Content::Value(Value::Float(Cow::Owned(format!("{:?}", f)))))
+}
+
+// ============================================================================
+// Serializer
+// ============================================================================
+
+struct ContentSerializer;
+
+impl ser::Serializer for ContentSerializer {
+    type Ok = Content;
+    type Error = Error;
+    type SerializeSeq = SeqBuilder;
+    type SerializeTuple = SeqBuilder;
+    type SerializeTupleStruct = SeqBuilder;
+    type SerializeTupleVariant = ser::Impossible<Content, Error>;
+    type SerializeMap = MapBuilder;
+    type SerializeStruct = MapBuilder;
+    type SerializeStructVariant = MapBuilder;
+
+    fn serialize_bool(self, v: bool) -> Result<Content, Error> {
+        Ok(Content::Value(if v { Value::BoolTrue } else { Value::BoolFalse }))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Content, Error> {
+        Ok(integer(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Content, Error> {
+        Ok(integer(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Content, Error> {
+        Ok(integer(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Content, Error> {
+        Ok(integer(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Content, Error> {
+        Ok(integer(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Content, Error> {
+        Ok(integer(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Content, Error> {
+        Ok(integer(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Content, Error> {
+        Ok(integer(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Content, Error> {
+        Ok(integer(v))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Content, Error> {
+        Ok(integer(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Content, Error> {
+        float(v, v.is_finite())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Content, Error> {
+        float(v, v.is_finite())
+    }
+
+    fn serialize_char(self, v: char) -> Result<Content, Error> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Content, Error> {
+        Ok(Content::Value(string_value(v)))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Content, Error> {
+        Ok(Content::Seq(v.iter().map(integer).collect()))
+    }
+
+    fn serialize_none(self) -> Result<Content, Error> {
+        Ok(Content::Value(Value::Nil))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Content, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Content, Error> {
+        Ok(Content::Value(Value::Nil))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Content, Error> {
+        Ok(Content::Value(Value::Nil))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Content, Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Content, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Content, Error> {
+        Ok(Content::Variant(variant, Box::new(value.serialize(self)?)))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqBuilder, Error> {
+        Ok(SeqBuilder { items: Vec::with_capacity(len.unwrap_or(0)) })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqBuilder, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SeqBuilder, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(error(format!("tuple variant {}::{} cannot be written", name, variant)))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapBuilder, Error> {
+        Ok(MapBuilder { name: None, variant: None, element: Element::default(), key: None })
+    }
+
+    fn serialize_struct(self, name: &'static str, _len: usize) -> Result<MapBuilder, Error> {
+        Ok(MapBuilder { name: Some(name), variant: None, element: Element::default(), key: None })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<MapBuilder, Error> {
+        Ok(MapBuilder { name: None, variant: Some(variant), element: Element::default(), key: None })
+    }
+}
+
+struct SeqBuilder {
+    items: Vec<Content>,
+}
+
+impl SeqBuilder {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.items.push(value.serialize(ContentSerializer)?);
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for SeqBuilder {
+    type Ok = Content;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Content, Error> {
+        Ok(Content::Seq(self.items))
+    }
+}
+
+impl ser::SerializeTuple for SeqBuilder {
+    type Ok = Content;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Content, Error> {
+        Ok(Content::Seq(self.items))
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqBuilder {
+    type Ok = Content;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Content, Error> {
+        Ok(Content::Seq(self.items))
+    }
+}
+
+struct MapBuilder {
+    name: Option<&'static str>,
+    variant: Option<&'static str>,
+    element: Element,
+    key: Option<String>,
+}
+
+impl MapBuilder {
+    fn field<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> Result<(), Error> {
+        let content = value.serialize(ContentSerializer)?;
+        self.element.insert(key, content)
+    }
+
+    fn finish(self) -> Content {
+        match self.variant {
+            Some(variant) => Content::Variant(variant, Box::new(Content::Element(None, self.element))),
+            None => Content::Element(self.name, self.element),
+        }
+    }
+}
+
+impl ser::SerializeMap for MapBuilder {
+    type Ok = Content;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        let key = key.serialize(ContentSerializer)?;
+        self.key = Some(scalar_text(key, "a map key")?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.key.take().expect("serialize_value called before serialize_key");
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Content, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStruct for MapBuilder {
+    type Ok = Content;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.field(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<Content, Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStructVariant for MapBuilder {
+    type Ok = Content;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.field(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<Content, Error> {
+        Ok(self.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    #[serde(rename = "config")]
+    struct Config {
+        #[serde(rename = "$id")]
+        id: String,
+        name: String,
+        count: i64,
+        big: u64,
+        ratio: f64,
+        scale: f32,
+        enabled: bool,
+        off: bool,
+        missing: Option<i32>,
+        tags: Vec<String>,
+        matrix: Vec<Vec<i32>>,
+        quoted: String,
+        numeric_text: String,
+        mode: Mode,
+        limits: Limits,
+        server: Vec<Server>,
+        backend: Backend,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Mode {
+        Fast,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Limits {
+        cpu: f64,
+        #[serde(rename = "$text")]
+        note: String,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Server {
+        #[serde(rename = "$classes", default)]
+        classes: Vec<String>,
+        host: String,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    enum Backend {
+        Redis { url: String },
+    }
+
+    fn sample() -> Config {
+        Config {
+            id: "main".into(),
+            name: "demo".into(),
+            count: -42,
+            big: u64::MAX,
+            ratio: 2.0,
+            scale: 0.1,
+            enabled: true,
+            off: false,
+            missing: None,
+            tags: vec!["a".into(), "b c".into()],
+            matrix: vec![vec![1, 2], vec![]],
+            quoted: "say \"hi\"\\now\nnext".into(),
+            numeric_text: "42".into(),
+            mode: Mode::Fast,
+            limits: Limits { cpu: 1.5, note: "Soft limits\napply here".into() },
+            server: vec![
+                Server { classes: vec!["primary".into()], host: "db1".into() },
+                Server { classes: vec![], host: "db2".into() },
+            ],
+            backend: Backend::Redis { url: "redis://cache:6379".into() },
+        }
+    }
+
+    #[test]
+    fn test_typed_values() {
+        let text = to_string(&sample()).unwrap();
+        let doc = Document::parse(text.as_bytes()).unwrap();
+        let el = doc.get_by_id("main").unwrap().as_element().unwrap();
+        assert_eq!(el.name(), "config");
+        assert!(matches!(el.attr("count"), Some(Value::Integer(s)) if s == "-42"));
+        assert!(matches!(el.attr("big"), Some(Value::Integer(s)) if s == "18446744073709551615"));
+        assert!(matches!(el.attr("ratio"), Some(Value::Float(s)) if s == "2.0"));
+        assert!(matches!(el.attr("scale"), Some(Value::Float(s)) if s == "0.1"));
+        assert!(matches!(el.attr("enabled"), Some(Value::BoolTrue)));
+        assert!(matches!(el.attr("off"), Some(Value::BoolFalse)));
+        assert!(matches!(el.attr("missing"), Some(Value::Nil)));
+        assert!(matches!(el.attr("tags"), Some(Value::Array(items)) if items.len() == 2));
+        assert!(matches!(el.attr("numeric_text"), Some(Value::String(s)) if s == "42"));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let config = sample();
+        let text = to_string(&config).unwrap();
+        let back: Config = crate::de::from_str(&text).unwrap();
+        assert_eq!(back, config);
+    }
+
+    #[test]
+    fn test_maps_and_errors() {
+        let map = BTreeMap::from([("b", 2), ("a", 1)]);
+        assert_eq!(to_string_named(&map, "limits").unwrap(), "|limits\n  :a 1\n  :b 2\n");
+        assert!(to_string(&map).is_err());
+        assert!(to_string(&5).is_err());
+
+        #[derive(Serialize)]
+        struct Bad {
+            value: f64,
+        }
+        let err = to_string(&Bad { value: f64::NAN }).unwrap_err();
+        assert!(err.message.contains("NaN"));
+    }
+}
@@ -2,6 +2,8 @@
 //!
 //! These types are stable and hand-written (not generated).
 
+use std::borrow::Cow;
+
 /// Byte offset span into the source buffer.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct Span {
@@ -32,10 +34,18 @@ impl Span {
     pub fn is_empty(&self) -> bool {
         self.start == self.end
     }
+
+    /// The span as a `usize` range, for slicing the source.
+    #[inline]
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.start as usize..self.end as usize
+    }
 }
 
 /// Source location for error reporting.
-#[derive(Debug, Clone, Copy, Default)]
+///
+/// `line` and `column` are 1-based; `column` counts characters, not bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct Location {
     pub line: u32,
     pub column: u32,
@@ -52,3 +62,138 @@ impl Location {
         }
     }
 }
+
+/// Maps byte offsets in a source buffer to lines and columns.
+///
+/// Lines end at `\n`; a `\r` before it is part of the line ending, so
+/// CRLF files report the same columns as LF files. Columns count UTF-8
+/// characters, and an offset inside a multi-byte character maps to that
+/// character.
+///
+/// # Example
+///
+/// ```
+/// use udon_core::span::{LineIndex, Location};
+///
+/// let source = "|a\r\n  :name Zoë :x 1\r\n".as_bytes();
+/// let index = LineIndex::new(source);
+/// let one = source.len() - 3;
+/// assert_eq!(index.location(one), Location::new(2, 16, one));
+/// assert_eq!(index.line_text(2), "  :name Zoë :x 1");
+/// ```
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    source: &'a [u8],
+    /// Byte offset where each line starts.
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a [u8]) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(memchr::memchr_iter(b'\n', source).map(|i| i + 1));
+        LineIndex { source, line_starts }
+    }
+
+    /// Number of lines. Text after the last `\n` (even if empty) counts
+    /// as a line.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Location of a byte offset. Offsets past the end map to the end.
+    pub fn location(&self, offset: usize) -> Location {
+        let mut offset = offset.min(self.source.len());
+        // Back up to the start of a UTF-8 character
+        while offset > 0 && offset < self.source.len() && (self.source[offset] & 0xC0) == 0x80 {
+            offset -= 1;
+        }
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let start = self.line_starts[line - 1];
+        let column = self.source[start..offset].iter().filter(|&&b| (b & 0xC0) != 0x80).count();
+        Location::new(line as u32, column as u32 + 1, offset)
+    }
+
+    /// Byte span of a 1-based line, without its line ending.
+    pub fn line_span(&self, line: usize) -> Span {
+        let Some(&start) = line.checked_sub(1).and_then(|i| self.line_starts.get(i)) else {
+            return Span::new(self.source.len(), self.source.len());
+        };
+        let mut end = self.line_starts.get(line).map_or(self.source.len(), |&next| next - 1);
+        if end > start && self.source[end - 1] == b'\r' {
+            end -= 1;
+        }
+        Span::new(start, end)
+    }
+
+    /// Text of a 1-based line, without its line ending.
+    pub fn line_text(&self, line: usize) -> Cow<'a, str> {
+        String::from_utf8_lossy(&self.source[self.line_span(line).range()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_index() {
+        let source = "|a\n  :x 1\r\n\n  Zoë — ok\n".as_bytes();
+        let index = LineIndex::new(source);
+        assert_eq!(index.line_count(), 5);
+        assert_eq!(index.location(0), Location::new(1, 1, 0));
+        assert_eq!(index.location(2), Location::new(1, 3, 2));
+        assert_eq!(index.location(5), Location::new(2, 3, 5));
+        // The \r of a CRLF ending sits after the last column
+        assert_eq!(index.location(9), Location::new(2, 7, 9));
+        assert_eq!(index.location(11), Location::new(3, 1, 11));
+
+        let ok = source.len() - 3;
+        assert_eq!(index.location(ok), Location::new(4, 9, ok));
+        // Inside the two-byte ë
+        let e = 16;
+        assert_eq!(index.location(e + 1), Location::new(4, 5, e));
+        assert_eq!(index.location(1000), Location::new(5, 1, source.len()));
+
+        assert_eq!(index.line_text(2), "  :x 1");
+        assert_eq!(index.line_text(3), "");
+        assert_eq!(index.line_text(4), "  Zoë — ok");
+        assert_eq!(index.line_span(5), Span::new(source.len(), source.len()));
+        assert_eq!(index.line_text(9), "");
+    }
+
+    #[test]
+    fn test_line_index_edges() {
+        // Empty input is one empty line
+        let index = LineIndex::new(b"");
+        assert_eq!(index.line_count(), 1);
+        assert_eq!(index.location(0), Location::new(1, 1, 0));
+        assert_eq!(index.line_text(1), "");
+        assert_eq!(index.line_span(1), Span::new(0, 0));
+
+        // CRLF throughout, ending with a line ending
+        let source = b"|a\r\n|b\r\n";
+        let index = LineIndex::new(source);
+        assert_eq!(index.line_count(), 3);
+        assert_eq!(index.line_span(1), Span::new(0, 2));
+        assert_eq!(index.line_span(2), Span::new(4, 6));
+        assert_eq!(index.location(3), Location::new(1, 4, 3));
+        assert_eq!(index.location(4), Location::new(2, 1, 4));
+        assert_eq!(index.location(source.len()), Location::new(3, 1, source.len()));
+        // A lone \r is part of the line
+        assert_eq!(LineIndex::new(b"a\rb").line_text(1), "a\rb");
+
+        // Without a final line ending, the end of input is on the last line
+        let source = "|p 日本\n  é🎉x".as_bytes();
+        let index = LineIndex::new(source);
+        assert_eq!(index.line_count(), 2);
+        assert_eq!(index.location(source.len()), Location::new(2, 6, source.len()));
+        // Three- and four-byte characters are one column each
+        assert_eq!(index.location(6), Location::new(1, 5, 6));
+        assert_eq!(index.location(8), Location::new(1, 5, 6));
+        let x = source.len() - 1;
+        assert_eq!(index.location(x), Location::new(2, 5, x));
+        assert_eq!(index.location(x - 2), Location::new(2, 4, x - 4));
+        assert_eq!(index.line_text(2), "  é🎉x");
+    }
+}
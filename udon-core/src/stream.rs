@@ -0,0 +1,199 @@
+//! Async event stream (`async` feature).
+//!
+//! [`EventStream`] reads UDON from a `futures` [`AsyncRead`] and yields
+//! owned [`StreamEvent`]s as a [`Stream`], using [`StreamingParser`] to
+//! handle input that arrives in pieces. Nothing is read until the consumer
+//! polls for an event it does not have yet, so a slow consumer holds back
+//! the reader rather than filling a buffer.
+//!
+//! Tokio readers can be adapted with `tokio_util::compat`.
+//!
+//! # Blocking
+//!
+//! Parsing runs synchronously inside `poll_next`. Each poll reads and parses
+//! at most one chunk, yielding to the executor before the next, but parsing
+//! a chunk may re-parse the whole buffered top-level construct: up to the
+//! parser's buffer limit, 1MB by default. Where that is too long to hold an
+//! executor thread, lower the limit with [`EventStream::with_parser`] or
+//! parse on a blocking thread with [`StreamingParser::parse_reader`].
+//!
+//! # Example
+//!
+//! ```
+//! use futures::StreamExt;
+//! use udon_core::stream::EventStream;
+//! use udon_core::StreamEvent;
+//!
+//! futures::executor::block_on(async {
+//!     let mut events = EventStream::new(&b"|a\n|b\n"[..]);
+//!     let mut names = Vec::new();
+//!     while let Some(event) = events.next().await {
+//!         if let StreamEvent::Name { content, .. } = event.unwrap() {
+//!             names.push(content);
+//!         }
+//!     }
+//!     assert_eq!(names, [b"a", b"b"]);
+//! });
+//! ```
+
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::{ready, Stream};
+use futures_io::AsyncRead;
+
+use crate::parser::{ParseErrorCode, ParseResult, StreamEvent, StreamingParser};
+
+/// Stream of the events parsed from an async reader.
+///
+/// Yields `Err` once if reading fails, then ends. If a top-level construct
+/// exceeds the parser's buffer limit, the `BufferOverflow` error event is
+/// followed by an `Err` of kind `InvalidData`, and the stream ends.
+#[derive(Debug)]
+pub struct EventStream<R> {
+    reader: R,
+    /// `None` once the input has ended or reading failed.
+    parser: Option<StreamingParser>,
+    chunk: Vec<u8>,
+    /// Events parsed from the last chunk and not yet yielded.
+    pending: VecDeque<StreamEvent>,
+    /// Error to yield once `pending` is empty.
+    error: Option<io::Error>,
+}
+
+impl<R: AsyncRead + Unpin> EventStream<R> {
+    /// Stream the events of `reader`.
+    pub fn new(reader: R) -> Self {
+        Self::with_parser(reader, StreamingParser::new())
+    }
+
+    /// Stream the events of `reader` through `parser`, e.g. one made with
+    /// [`StreamingParser::with_max_buffer`].
+    pub fn with_parser(reader: R, parser: StreamingParser) -> Self {
+        EventStream {
+            reader,
+            parser: Some(parser),
+            chunk: vec![0; 64 * 1024],
+            pending: VecDeque::new(),
+            error: None,
+        }
+    }
+
+    /// The reader, e.g. to reuse a connection after the document.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for EventStream<R> {
+    type Item = io::Result<StreamEvent>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(event) = this.pending.pop_front() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+            if let Some(err) = this.error.take() {
+                return Poll::Ready(Some(Err(err)));
+            }
+            let Some(parser) = this.parser.as_mut() else {
+                return Poll::Ready(None);
+            };
+
+            let pending = &mut this.pending;
+            match ready!(Pin::new(&mut this.reader).poll_read(cx, &mut this.chunk)) {
+                Ok(0) => {
+                    if let Some(parser) = this.parser.take() {
+                        parser.finish(|event| pending.push_back(event));
+                    }
+                }
+                Ok(len) => {
+                    if parser.parse(&this.chunk[..len], |event| pending.push_back(event)) == ParseResult::Complete {
+                        this.parser = None;
+                        this.error = Some(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            ParseErrorCode::BufferOverflow.message(),
+                        ));
+                    } else if pending.is_empty() {
+                        // Bound the work done in one poll
+                        cx.waker().wake_by_ref();
+                        return Poll::Pending;
+                    }
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => {
+                    this.parser = None;
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::StreamExt;
+
+    /// Yields a few bytes per read, and is pending every other time.
+    struct Trickle<'a> {
+        input: &'a [u8],
+        ready: bool,
+        reads: usize,
+    }
+
+    impl AsyncRead for Trickle<'_> {
+        fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+            self.ready = !self.ready;
+            if !self.ready {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            if self.input.is_empty() && self.reads > 0 {
+                return Poll::Ready(Err(io::Error::other("reset")));
+            }
+            let len = self.input.len().min(buf.len()).min(4);
+            buf[..len].copy_from_slice(&self.input[..len]);
+            self.input = &self.input[len..];
+            self.reads += 1;
+            Poll::Ready(Ok(len))
+        }
+    }
+
+    #[test]
+    fn test_event_stream() {
+        let input = b"|a :x 1\n  |b text\n|c :s \"multi\n|line\"\n|d";
+        let mut expected = Vec::new();
+        StreamingParser::new().parse_reader(&input[..], |event| expected.push(event)).unwrap();
+
+        let events: Vec<_> = block_on(EventStream::new(&input[..]).collect());
+        assert_eq!(events.into_iter().collect::<io::Result<Vec<_>>>().unwrap(), expected);
+
+        // Events of closed records come out before the reader has finished
+        let mut stream = EventStream::new(Trickle { input, ready: false, reads: 0 });
+        let first = block_on(stream.next()).unwrap().unwrap();
+        assert_eq!(first, expected[0]);
+        assert!(!stream.reader.input.is_empty());
+
+        // A read error ends the stream after the events before it
+        let rest: Vec<_> = block_on(stream.collect());
+        let (last, events) = rest.split_last().unwrap();
+        assert_eq!(last.as_ref().unwrap_err().to_string(), "reset");
+        let events: Vec<_> = events.iter().map(|event| event.as_ref().unwrap().clone()).collect();
+        assert!(expected[1..].starts_with(&events));
+
+        // Overflow ends the stream with an error after its event
+        let parser = StreamingParser::with_max_buffer(8);
+        let events: Vec<_> = block_on(EventStream::with_parser(&b"|a\n|b 123456789\n  |c\n"[..], parser).collect());
+        let (last, events) = events.split_last().unwrap();
+        assert_eq!(last.as_ref().unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(
+            events.last(),
+            Some(Ok(StreamEvent::Error { code: ParseErrorCode::BufferOverflow, .. }))
+        ));
+    }
+}
@@ -0,0 +1,915 @@
+//! Template expansion for document-style UDON.
+//!
+//! [`Engine::render`] takes a document and a data context and returns a
+//! new document with every dynamic node evaluated away:
+//!
+//! - `!if` / `!unless` / `!elif` / `!else` keep the body of the first
+//!   branch whose condition holds.
+//! - `!for item in items` repeats its body for each list item; over a map,
+//!   `!for key, value in map` binds each entry. `!for i, item in list`
+//!   binds the index too.
+//! - `!let name = expr` binds `name` for its body, or, without a body, for
+//!   the rest of the enclosing block.
+//! - `!include name` expands a partial registered with
+//!   [`Engine::partial`]. To load partials from files instead, expand
+//!   includes first with [`Document::expand_includes`].
+//! - `!{{expr}}` interpolations become text.
+//!
+//! Expressions use the [`expr`](crate::expr) grammar. Paths that do not
+//! exist evaluate to null; a negative index counts from the end of a list.
+//! Integer and float literals become numbers, other typed literals (dates,
+//! rationals, ...) their source text. `and` and `or` give booleans.
+//!
+//! `null`, `false`, empty strings, empty lists and empty maps are false in
+//! a condition; everything else, including `0`, is true.
+//!
+//! Filters are looked up in a [`Filters`] registry; see
+//! [`Filters::builtin`] for the defaults. Loop iterations, include
+//! nesting, expression nesting and the size of values and output are
+//! limited so that untrusted templates cannot run away.
+//!
+//! Raw blocks and the rest of the document are copied through unchanged;
+//! other directives are an error.
+//!
+//! # Example
+//!
+//! ```
+//! use udon_core::tree::Document;
+//! use udon_core::template::{Data, Engine};
+//! use udon_core::to_udon_string;
+//!
+//! let doc = Document::parse(b"|ul\n  !for name in names\n    |li !{{name | capitalize}}\n").unwrap();
+//! let data = Data::from_iter([("names", Data::from(vec!["ann", "bo"]))]);
+//! let out = Engine::new().render(&doc, &data).unwrap();
+//! assert_eq!(to_udon_string(&out), "|ul\n  |li Ann\n  |li Bo\n");
+//! ```
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::directive::{Argument, Block, BranchKind};
+use crate::expr::{BinaryOp, Expr, ExprError, ExprKind};
+use crate::span::Span;
+use crate::tree::{unescape, Document, Node, NodeId, NodeKind, Value};
+
+/// A value in the data context.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Data {
+    #[default]
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    List(Vec<Data>),
+    Map(BTreeMap<String, Data>),
+}
+
+impl Data {
+    /// Whether the value counts as true in a condition.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Data::Null | Data::Bool(false) => false,
+            Data::String(s) => !s.is_empty(),
+            Data::List(items) => !items.is_empty(),
+            Data::Map(map) => !map.is_empty(),
+            _ => true,
+        }
+    }
+
+    /// Look up a key in a map, or an index in a list.
+    pub fn get(&self, key: &str) -> Option<&Data> {
+        match self {
+            Data::Map(map) => map.get(key),
+            Data::List(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        }
+    }
+
+    /// Text the value renders as: strings as-is, `null` as nothing, lists
+    /// as their items joined with `", "`.
+    pub fn to_text(&self) -> String {
+        match self {
+            Data::Null => String::new(),
+            Data::Bool(b) => b.to_string(),
+            Data::Integer(n) => n.to_string(),
+            Data::Float(f) => f.to_string(),
+            Data::String(s) => s.clone(),
+            Data::List(items) => items.iter().map(Data::to_text).collect::<Vec<_>>().join(", "),
+            Data::Map(map) => {
+                let entries: Vec<_> = map.iter().map(|(k, v)| format!("{}: {}", k, v.to_text())).collect();
+                entries.join(", ")
+            }
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Data::Null => "null",
+            Data::Bool(_) => "bool",
+            Data::Integer(_) => "integer",
+            Data::Float(_) => "float",
+            Data::String(_) => "string",
+            Data::List(_) => "list",
+            Data::Map(_) => "map",
+        }
+    }
+}
+
+impl From<bool> for Data {
+    fn from(b: bool) -> Self {
+        Data::Bool(b)
+    }
+}
+
+impl From<i64> for Data {
+    fn from(n: i64) -> Self {
+        Data::Integer(n)
+    }
+}
+
+impl From<i32> for Data {
+    fn from(n: i32) -> Self {
+        Data::Integer(n.into())
+    }
+}
+
+impl From<f64> for Data {
+    fn from(f: f64) -> Self {
+        Data::Float(f)
+    }
+}
+
+impl From<&str> for Data {
+    fn from(s: &str) -> Self {
+        Data::String(s.to_string())
+    }
+}
+
+impl From<String> for Data {
+    fn from(s: String) -> Self {
+        Data::String(s)
+    }
+}
+
+impl<T: Into<Data>> From<Vec<T>> for Data {
+    fn from(items: Vec<T>) -> Self {
+        Data::List(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<Data>> From<Option<T>> for Data {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Data::Null, Into::into)
+    }
+}
+
+impl<K: Into<String>, V: Into<Data>> FromIterator<(K, V)> for Data {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Data::Map(iter.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<&serde_json::Value> for Data {
+    fn from(value: &serde_json::Value) -> Self {
+        use serde_json::Value as Json;
+        match value {
+            Json::Null => Data::Null,
+            Json::Bool(b) => Data::Bool(*b),
+            Json::Number(n) => n.as_i64().map_or_else(|| Data::Float(n.as_f64().unwrap_or(f64::NAN)), Data::Integer),
+            Json::String(s) => Data::String(s.clone()),
+            Json::Array(items) => Data::List(items.iter().map(Data::from).collect()),
+            Json::Object(map) => Data::Map(map.iter().map(|(k, v)| (k.clone(), Data::from(v))).collect()),
+        }
+    }
+}
+
+/// Error returned when a template cannot be expanded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateError {
+    pub message: String,
+    /// Span of the offending node or argument, in the document (or
+    /// partial) it came from.
+    pub span: Span,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}..{}", self.message, self.span.start, self.span.end)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+impl From<ExprError> for TemplateError {
+    fn from(err: ExprError) -> Self {
+        TemplateError { message: err.message, span: err.span }
+    }
+}
+
+/// A filter: takes the piped value and the filter's arguments.
+pub type Filter = dyn Fn(&Data, &[Data]) -> Result<Data, String> + Send + Sync;
+
+/// Registry of named filters.
+#[derive(Default)]
+pub struct Filters {
+    filters: HashMap<String, Box<Filter>>,
+}
+
+impl fmt::Debug for Filters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<_> = self.filters.keys().collect();
+        names.sort();
+        f.debug_struct("Filters").field("names", &names).finish()
+    }
+}
+
+impl Filters {
+    /// An empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The built-in filters:
+    ///
+    /// - `capitalize`, `upcase`, `downcase`
+    /// - `escape`: HTML-escape
+    /// - `truncate n`: keep the first `n` characters, adding `...` if any
+    ///   were cut
+    /// - `default value`: `value` if the input is false in a condition
+    /// - `size`: length of a string, list or map
+    /// - `join [sep]`: join list items (default separator `" "`)
+    /// - `first`, `last`: first or last list item (or character)
+    /// - `where key [value]`: list items that are maps whose `key` equals
+    ///   `value`, or is true if no value is given
+    pub fn builtin() -> Self {
+        let mut filters = Self::new();
+        filters.insert("capitalize", |v, _| {
+            let text = v.to_text();
+            let mut chars = text.chars();
+            Ok(Data::String(chars.next().map_or_else(String::new, |c| c.to_uppercase().chain(chars).collect())))
+        });
+        filters.insert("upcase", |v, _| Ok(Data::String(v.to_text().to_uppercase())));
+        filters.insert("downcase", |v, _| Ok(Data::String(v.to_text().to_lowercase())));
+        filters.insert("escape", |v, _| Ok(Data::String(escape_html(&v.to_text()))));
+        filters.insert("truncate", |v, args| {
+            let n = match args.first() {
+                Some(Data::Integer(n)) if *n >= 0 => *n as usize,
+                _ => return Err("expected a non-negative length".to_string()),
+            };
+            let text = v.to_text();
+            if text.chars().count() <= n {
+                return Ok(Data::String(text));
+            }
+            Ok(Data::String(text.chars().take(n).chain("...".chars()).collect()))
+        });
+        filters.insert("default", |v, args| {
+            let fallback = args.first().ok_or("expected a default value")?;
+            Ok(if v.is_truthy() { v.clone() } else { fallback.clone() })
+        });
+        filters.insert("size", |v, _| {
+            let size = match v {
+                Data::String(s) => s.chars().count(),
+                Data::List(items) => items.len(),
+                Data::Map(map) => map.len(),
+                Data::Null => 0,
+                other => return Err(format!("cannot take the size of a {}", other.type_name())),
+            };
+            Ok(Data::Integer(size as i64))
+        });
+        filters.insert("join", |v, args| {
+            let sep = args.first().map_or_else(|| " ".to_string(), Data::to_text);
+            match v {
+                Data::List(items) => Ok(Data::String(items.iter().map(Data::to_text).collect::<Vec<_>>().join(&sep))),
+                other => Ok(Data::String(other.to_text())),
+            }
+        });
+        filters.insert("first", |v, _| {
+            Ok(match v {
+                Data::List(items) => items.first().cloned().unwrap_or_default(),
+                Data::String(s) => s.chars().next().map(String::from).into(),
+                _ => Data::Null,
+            })
+        });
+        filters.insert("last", |v, _| {
+            Ok(match v {
+                Data::List(items) => items.last().cloned().unwrap_or_default(),
+                Data::String(s) => s.chars().next_back().map(String::from).into(),
+                _ => Data::Null,
+            })
+        });
+        filters.insert("where", |v, args| {
+            let key = args.first().ok_or("expected a key")?.to_text();
+            let Data::List(items) = v else {
+                return Err(format!("expected a list, got a {}", v.type_name()));
+            };
+            let keep = |item: &Data| match (item.get(&key), args.get(1)) {
+                (Some(found), Some(wanted)) => equal(found, wanted),
+                (Some(found), None) => found.is_truthy(),
+                (None, _) => false,
+            };
+            Ok(Data::List(items.iter().filter(|item| keep(item)).cloned().collect()))
+        });
+        filters
+    }
+
+    /// Register a filter, replacing any with the same name.
+    pub fn insert(
+        &mut self,
+        name: impl Into<String>,
+        filter: impl Fn(&Data, &[Data]) -> Result<Data, String> + Send + Sync + 'static,
+    ) {
+        self.filters.insert(name.into(), Box::new(filter));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Filter> {
+        self.filters.get(name).map(|f| f.as_ref())
+    }
+}
+
+/// Default for [`Engine::max_iterations`].
+pub const DEFAULT_MAX_ITERATIONS: usize = 10_000;
+
+/// Default for [`Engine::max_depth`].
+pub const DEFAULT_MAX_DEPTH: usize = 32;
+
+/// Default for [`Engine::max_output`]: 16 MiB.
+pub const DEFAULT_MAX_OUTPUT: usize = 16 << 20;
+
+/// Configurable template engine.
+#[derive(Debug)]
+pub struct Engine {
+    filters: Filters,
+    partials: HashMap<String, Document<'static>>,
+    max_iterations: usize,
+    max_depth: usize,
+    max_output: usize,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine {
+            filters: Filters::builtin(),
+            partials: HashMap::new(),
+            max_iterations: DEFAULT_MAX_ITERATIONS,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_output: DEFAULT_MAX_OUTPUT,
+        }
+    }
+}
+
+impl Engine {
+    /// Create an engine with the built-in filters and default limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a filter, replacing any built-in with the same name.
+    pub fn filter(
+        mut self,
+        name: impl Into<String>,
+        filter: impl Fn(&Data, &[Data]) -> Result<Data, String> + Send + Sync + 'static,
+    ) -> Self {
+        self.filters.insert(name, filter);
+        self
+    }
+
+    /// Replace the whole filter registry.
+    pub fn filters(mut self, filters: Filters) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    /// Register a partial for `!include name`.
+    pub fn partial(mut self, name: impl Into<String>, doc: Document<'static>) -> Self {
+        self.partials.insert(name.into(), doc);
+        self
+    }
+
+    /// Maximum number of `!for` iterations in one render, across all loops.
+    pub fn max_iterations(mut self, max: usize) -> Self {
+        self.max_iterations = max;
+        self
+    }
+
+    /// Maximum nesting of `!include`s, of each expression (see
+    /// [`Expr::parse_with_max_depth`]; evaluation recurses no deeper than
+    /// the parsed expression), and of the lists and maps expressions build.
+    pub fn max_depth(mut self, max: usize) -> Self {
+        self.max_depth = max;
+        self
+    }
+
+    /// Maximum size in bytes of any list, map or filter result an
+    /// expression builds, and of all text in one render's output.
+    pub fn max_output(mut self, max: usize) -> Self {
+        self.max_output = max;
+        self
+    }
+
+    /// Expand a document against a data context.
+    ///
+    /// Top-level entries of a map context are visible as variables; any
+    /// other context has no variables.
+    pub fn render(&self, doc: &Document<'_>, data: &Data) -> Result<Document<'static>, TemplateError> {
+        let mut out = Document::new();
+        let root = out.root().id();
+        let globals = match data {
+            Data::Map(map) => map.clone(),
+            _ => BTreeMap::new(),
+        };
+        let mut state = State { scopes: vec![globals], iterations: 0, depth: 0, output: 0 };
+        self.render_children(doc.root(), &mut out, root, &mut state)?;
+        Ok(out)
+    }
+
+    fn render_children(
+        &self,
+        node: Node<'_, '_>,
+        out: &mut Document<'static>,
+        parent: NodeId,
+        state: &mut State,
+    ) -> Result<(), TemplateError> {
+        let (blocks, diagnostics) = node.directive_blocks();
+        if let Some(diagnostic) = diagnostics.into_iter().next() {
+            return Err(TemplateError { message: diagnostic.message, span: diagnostic.span });
+        }
+
+        // Text and interpolations on one source line become one text node
+        let mut line: Option<(String, Node<'_, '_>)> = None;
+        for block in blocks {
+            let inline = match &block {
+                Block::Node(child) => self.inline_text(*child, state)?.map(|text| (text, *child)),
+                _ => None,
+            };
+            if let Some((text, child)) = inline {
+                state.output += text.len();
+                if state.output > self.max_output {
+                    return Err(TemplateError {
+                        message: format!("output larger than {} bytes", self.max_output),
+                        span: child.span(),
+                    });
+                }
+                let gap = line.as_ref().and_then(|(_, last)| child.source_since(*last, last.span().end));
+                match (&mut line, gap) {
+                    (Some((pending, last)), Some(gap)) if !gap.contains(&b'\n') => {
+                        // Keep spacing the parser dropped between the pieces
+                        if gap.iter().all(|&b| b == b' ' || b == b'\t') {
+                            pending.push_str(&String::from_utf8_lossy(gap));
+                        }
+                        pending.push_str(&text);
+                        *last = child;
+                    }
+                    _ => {
+                        if let Some((pending, _)) = line.take() {
+                            out.append_child(parent, NodeKind::Text(Cow::Owned(pending)));
+                        }
+                        line = Some((text, child));
+                    }
+                }
+                continue;
+            }
+            if let Some((pending, _)) = line.take() {
+                out.append_child(parent, NodeKind::Text(Cow::Owned(pending)));
+            }
+            match block {
+                Block::Node(child) => self.render_node(child, out, parent, state)?,
+                Block::Conditional(chain) => {
+                    for branch in &chain.branches {
+                        let taken = match (branch.kind, branch.condition) {
+                            (BranchKind::Else, _) | (_, None) => true,
+                            (BranchKind::Unless, Some(cond)) => !self.eval_argument(cond, state)?.is_truthy(),
+                            (_, Some(cond)) => self.eval_argument(cond, state)?.is_truthy(),
+                        };
+                        if taken {
+                            self.render_scoped(branch.node, out, parent, state, BTreeMap::new())?;
+                            break;
+                        }
+                    }
+                }
+                Block::For(each) => {
+                    let names: Vec<&str> = each.binding.text.split(',').map(str::trim).collect();
+                    let bad_binding = || TemplateError {
+                        message: format!("invalid loop binding `{}`", each.binding.text),
+                        span: each.binding.span,
+                    };
+                    if names.len() > 2 || names.iter().any(|n| !is_name(n)) {
+                        return Err(bad_binding());
+                    }
+                    let entries: Vec<(Data, Data)> = match self.eval_argument(each.iterable, state)? {
+                        Data::List(items) => {
+                            items.into_iter().enumerate().map(|(i, item)| (Data::Integer(i as i64), item)).collect()
+                        }
+                        Data::Map(map) => map.into_iter().map(|(k, v)| (Data::String(k), v)).collect(),
+                        Data::Null => Vec::new(),
+                        other => {
+                            return Err(TemplateError {
+                                message: format!("cannot loop over a {}", other.type_name()),
+                                span: each.iterable.span,
+                            })
+                        }
+                    };
+                    for (key, value) in entries {
+                        state.iterations += 1;
+                        if state.iterations > self.max_iterations {
+                            return Err(TemplateError {
+                                message: format!("more than {} loop iterations", self.max_iterations),
+                                span: each.node.span(),
+                            });
+                        }
+                        let scope = match names[..] {
+                            [item] => BTreeMap::from([(item.to_string(), value)]),
+                            [k, v] => BTreeMap::from([(k.to_string(), key), (v.to_string(), value)]),
+                            _ => return Err(bad_binding()),
+                        };
+                        self.render_scoped(each.node, out, parent, state, scope)?;
+                    }
+                }
+                Block::Let(binding) => {
+                    if !is_name(binding.name.text) {
+                        return Err(TemplateError {
+                            message: format!("invalid variable name `{}`", binding.name.text),
+                            span: binding.name.span,
+                        });
+                    }
+                    let value = self.eval_argument(binding.value, state)?;
+                    if binding.node.directive_body().next().is_some() {
+                        let scope = BTreeMap::from([(binding.name.text.to_string(), value)]);
+                        self.render_scoped(binding.node, out, parent, state, scope)?;
+                    } else if let Some(scope) = state.scopes.last_mut() {
+                        scope.insert(binding.name.text.to_string(), value);
+                    }
+                }
+                Block::Include(include) => {
+                    let name = include.path.text.trim();
+                    let partial = self.partials.get(name).ok_or_else(|| TemplateError {
+                        message: format!("unknown partial `{}`", name),
+                        span: include.path.span,
+                    })?;
+                    if state.depth >= self.max_depth {
+                        return Err(TemplateError {
+                            message: format!("includes nested more than {} deep", self.max_depth),
+                            span: include.node.span(),
+                        });
+                    }
+                    state.depth += 1;
+                    let result = self.render_scoped(partial.root(), out, parent, state, BTreeMap::new());
+                    state.depth -= 1;
+                    result?;
+                }
+            }
+        }
+        if let Some((pending, _)) = line {
+            out.append_child(parent, NodeKind::Text(Cow::Owned(pending)));
+        }
+        Ok(())
+    }
+
+    /// Render a node's children (a directive's body) in a new scope.
+    fn render_scoped(
+        &self,
+        node: Node<'_, '_>,
+        out: &mut Document<'static>,
+        parent: NodeId,
+        state: &mut State,
+        scope: BTreeMap<String, Data>,
+    ) -> Result<(), TemplateError> {
+        state.scopes.push(scope);
+        let result = self.render_children(node, out, parent, state);
+        state.scopes.pop();
+        result
+    }
+
+    fn render_node(
+        &self,
+        node: Node<'_, '_>,
+        out: &mut Document<'static>,
+        parent: NodeId,
+        state: &mut State,
+    ) -> Result<(), TemplateError> {
+        match node.kind() {
+            // The parser wraps an interpolation at the start of a line
+            NodeKind::Directive { name, .. } if name.is_empty() => self.render_children(node, out, parent, state),
+            NodeKind::Directive { name, .. } if node.raw_block().is_none() => Err(TemplateError {
+                message: format!("unsupported directive `!{}`", name),
+                span: node.span(),
+            }),
+            NodeKind::Directive { .. } | NodeKind::Comment(_) | NodeKind::Raw { .. } => {
+                copy_subtree(node, out, parent);
+                Ok(())
+            }
+            kind => {
+                let id = out.append_child(parent, kind.clone().into_owned());
+                self.render_children(node, out, id, state)
+            }
+        }
+    }
+
+    /// The text of a text or interpolation node (or a wrapped
+    /// interpolation), or `None` for any other node.
+    fn inline_text(&self, node: Node<'_, '_>, state: &State) -> Result<Option<String>, TemplateError> {
+        match node.kind() {
+            NodeKind::Text(text) => Ok(Some(text.to_string())),
+            NodeKind::Interpolation(_) => match node.interpolation_source() {
+                Some((text, start)) => {
+                    let expr = Expr::parse_with_max_depth(text, start, self.max_depth)?;
+                    Ok(Some(self.evaluate(&expr, state)?.to_text()))
+                }
+                None => Ok(None),
+            },
+            NodeKind::Directive { name, .. } if name.is_empty() && node.children().count() == 1 => {
+                node.first_child().map_or(Ok(None), |child| self.inline_text(child, state))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn eval_argument(&self, argument: Argument<'_>, state: &State) -> Result<Data, TemplateError> {
+        let expr = Expr::parse_with_max_depth(argument.text, argument.span.start as usize, self.max_depth)?;
+        self.evaluate(&expr, state)
+    }
+
+    /// Evaluate an expression against the current scopes.
+    fn evaluate(&self, expr: &Expr<'_>, state: &State) -> Result<Data, TemplateError> {
+        let error = |message: String| TemplateError { message, span: expr.span };
+        match &expr.kind {
+            ExprKind::Variable(name) => Ok(state.lookup(name).cloned().unwrap_or_default()),
+            ExprKind::Literal(value) => literal(value).map_err(error),
+            ExprKind::List(items) => {
+                let items = items.iter().map(|item| self.evaluate(item, state)).collect::<Result<_, _>>()?;
+                self.bounded(Data::List(items)).map_err(error)
+            }
+            ExprKind::Field { base, name } => Ok(self.evaluate(base, state)?.get(name).cloned().unwrap_or_default()),
+            ExprKind::Index { base, index } => {
+                let base = self.evaluate(base, state)?;
+                let found = match (&base, self.evaluate(index, state)?) {
+                    (Data::List(items), Data::Integer(i)) => {
+                        let i = if i < 0 { items.len() as i64 + i } else { i };
+                        usize::try_from(i).ok().and_then(|i| items.get(i))
+                    }
+                    (Data::Map(map), Data::String(key)) => map.get(&key),
+                    (Data::Null, _) => None,
+                    (base, index) => {
+                        return Err(error(format!("cannot index a {} with a {}", base.type_name(), index.type_name())))
+                    }
+                };
+                Ok(found.cloned().unwrap_or_default())
+            }
+            ExprKind::Not(operand) => Ok(Data::Bool(!self.evaluate(operand, state)?.is_truthy())),
+            ExprKind::Binary { op: BinaryOp::And, left, right } => Ok(Data::Bool(
+                self.evaluate(left, state)?.is_truthy() && self.evaluate(right, state)?.is_truthy(),
+            )),
+            ExprKind::Binary { op: BinaryOp::Or, left, right } => Ok(Data::Bool(
+                self.evaluate(left, state)?.is_truthy() || self.evaluate(right, state)?.is_truthy(),
+            )),
+            ExprKind::Binary { op, left, right } => {
+                let (left, right) = (self.evaluate(left, state)?, self.evaluate(right, state)?);
+                let result = match op {
+                    BinaryOp::Eq => equal(&left, &right),
+                    BinaryOp::Ne => !equal(&left, &right),
+                    _ => {
+                        let ordering = compare(&left, &right).ok_or_else(|| {
+                            error(format!("cannot compare a {} with a {}", left.type_name(), right.type_name()))
+                        })?;
+                        match op {
+                            BinaryOp::Lt => ordering.is_lt(),
+                            BinaryOp::Le => ordering.is_le(),
+                            BinaryOp::Gt => ordering.is_gt(),
+                            _ => ordering.is_ge(),
+                        }
+                    }
+                };
+                Ok(Data::Bool(result))
+            }
+            ExprKind::Filter { input, name, name_span, args } => {
+                let filter = self.filters.get(name).ok_or_else(|| TemplateError {
+                    message: format!("unknown filter `{}`", name),
+                    span: *name_span,
+                })?;
+                let input = self.evaluate(input, state)?;
+                let args = args.iter().map(|arg| self.evaluate(arg, state)).collect::<Result<Vec<_>, _>>()?;
+                let result = filter(&input, &args).map_err(|message| error(format!("filter `{}`: {}", name, message)))?;
+                self.bounded(result).map_err(error)
+            }
+        }
+    }
+}
+
+impl Engine {
+    /// A value an expression built, if it is within the size and nesting
+    /// limits.
+    fn bounded(&self, value: Data) -> Result<Data, String> {
+        size(&value, self.max_depth).map_err(|()| format!("value nested more than {} deep", self.max_depth)).and_then(
+            |size| match size > self.max_output {
+                true => Err(format!("value larger than {} bytes", self.max_output)),
+                false => Ok(value),
+            },
+        )
+    }
+}
+
+/// Rough size of a value in bytes, or `Err` if it nests deeper than `depth`.
+fn size(value: &Data, depth: usize) -> Result<usize, ()> {
+    let nested = |items: &mut dyn Iterator<Item = &Data>| -> Result<usize, ()> {
+        let depth = depth.checked_sub(1).ok_or(())?;
+        let mut total = 0usize;
+        for item in items {
+            total = total.saturating_add(size(item, depth)?).saturating_add(1);
+        }
+        Ok(total)
+    };
+    match value {
+        Data::String(s) => Ok(s.len()),
+        Data::List(items) => nested(&mut items.iter()),
+        Data::Map(map) => Ok(nested(&mut map.values())?.saturating_add(map.keys().map(String::len).sum())),
+        _ => Ok(8),
+    }
+}
+
+/// Numbers become numbers; other typed values keep their source text.
+fn literal(value: &Value<'_>) -> Result<Data, String> {
+    Ok(match value {
+        Value::String(s) => Data::String(unescape(s).into_owned()),
+        Value::Integer(_) => Data::Integer(value.as_i64().map_err(|e| e.to_string())?),
+        Value::Float(_) => Data::Float(value.as_f64().map_err(|e| e.to_string())?),
+        Value::BoolTrue => Data::Bool(true),
+        Value::BoolFalse => Data::Bool(false),
+        Value::Nil => Data::Null,
+        Value::Array(items) => Data::List(items.iter().map(literal).collect::<Result<_, _>>()?),
+        Value::Bare(s)
+        | Value::Rational(s)
+        | Value::Complex(s)
+        | Value::Date(s)
+        | Value::Time(s)
+        | Value::DateTime(s)
+        | Value::Duration(s)
+        | Value::RelativeTime(s) => Data::String(s.to_string()),
+    })
+}
+
+fn copy_subtree(node: Node<'_, '_>, out: &mut Document<'static>, parent: NodeId) {
+    let id = out.append_child(parent, node.kind().clone().into_owned());
+    for child in node.children() {
+        copy_subtree(child, out, id);
+    }
+}
+
+struct State {
+    /// Innermost last.
+    scopes: Vec<BTreeMap<String, Data>>,
+    iterations: usize,
+    depth: usize,
+    /// Bytes of text produced so far.
+    output: usize,
+}
+
+impl State {
+    fn lookup(&self, name: &str) -> Option<&Data> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+}
+
+fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+fn equal(a: &Data, b: &Data) -> bool {
+    match (a, b) {
+        (Data::Integer(x), Data::Float(y)) | (Data::Float(y), Data::Integer(x)) => *x as f64 == *y,
+        _ => a == b,
+    }
+}
+
+fn compare(a: &Data, b: &Data) -> Option<std::cmp::Ordering> {
+    match (a, b) {
+        (Data::Integer(x), Data::Integer(y)) => Some(x.cmp(y)),
+        (Data::Integer(x), Data::Float(y)) => (*x as f64).partial_cmp(y),
+        (Data::Float(x), Data::Integer(y)) => x.partial_cmp(&(*y as f64)),
+        (Data::Float(x), Data::Float(y)) => x.partial_cmp(y),
+        (Data::String(x), Data::String(y)) => Some(x.cmp(y)),
+        _ => None,
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writer::to_udon_string;
+
+    fn render(engine: &Engine, source: &[u8], data: &Data) -> Result<String, TemplateError> {
+        let doc = Document::parse(source).unwrap();
+        engine.render(&doc, data).map(|out| to_udon_string(&out))
+    }
+
+    fn context() -> Data {
+        let post = |title: &str, featured: bool| {
+            Data::from_iter([("title", Data::from(title)), ("featured", featured.into())])
+        };
+        Data::from_iter([
+            ("user", Data::from_iter([("name", Data::from("ada")), ("admin", true.into())])),
+            ("posts", Data::List(vec![post("one", false), post("two", true)])),
+            ("tags", Data::from(vec!["a", "b"])),
+            ("count", Data::from(3)),
+        ])
+    }
+
+    #[test]
+    fn test_directives() {
+        let engine = Engine::new();
+        let data = context();
+
+        let out = render(&engine, b"!if user.admin\n  |p admin\n!else\n  |p guest\n", &data).unwrap();
+        assert_eq!(out, "|p admin\n");
+        let out = render(&engine, b"!if count > 5\n  |p many\n!elif count == 3\n  |p three\n!else\n  |p few\n", &data);
+        assert_eq!(out.unwrap(), "|p three\n");
+        let out = render(&engine, b"!unless missing\n  |p shown\n", &data).unwrap();
+        assert_eq!(out, "|p shown\n");
+
+        let out = render(&engine, b"|ul\n  !for i, post in posts\n    |li !{{i}} !{{post.title}}\n", &data).unwrap();
+        assert_eq!(out, "|ul\n  |li 0 one\n  |li 1 two\n");
+
+        let source = b"!let featured = posts | where \"featured\" true | first\n  |h1 !{{featured.title | upcase}}\n";
+        assert_eq!(render(&engine, source, &data).unwrap(), "|h1 TWO\n");
+        let out = render(&engine, b"!let n = tags | size\n|p !{{n}} tags: !{{tags | join \", \"}}\n", &data);
+        assert_eq!(out.unwrap(), "|p 2 tags: a, b\n");
+
+        let footer = Document::parse_owned(b"|footer !{{user.name}}\n".to_vec()).unwrap();
+        let engine = Engine::new().partial("footer", footer);
+        assert_eq!(render(&engine, b"!include footer\n", &data).unwrap(), "|footer ada\n");
+    }
+
+    #[test]
+    fn test_filters() {
+        let engine = Engine::new().filter("twice", |v, _| Ok(Data::String(v.to_text().repeat(2))));
+        let data = context();
+        let out = render(&engine, b"|p !{{user.name | capitalize | twice}}\n", &data).unwrap();
+        assert_eq!(out, "|p AdaAda\n");
+        let out = render(&engine, b"|p !{{\"<b>\" | escape}} !{{missing | default \"none\"}}\n", &data).unwrap();
+        assert_eq!(out, "|p\n  &lt;b&gt; none\n");
+        let out = render(&engine, b"|p !{{\"abcdef\" | truncate 3}}\n", &data).unwrap();
+        assert_eq!(out, "|p abc...\n");
+
+        let err = render(&engine, b"|p !{{user | nope}}\n", &data).unwrap_err();
+        assert_eq!(err.message, "unknown filter `nope`");
+        assert_eq!(err.span, Span::new(13, 17));
+
+        let out = render(&engine, b"|p\n  !{{posts[-1].title}} and !{{tags[0]}}\n", &data).unwrap();
+        assert_eq!(out, "|p two and a\n");
+        let out = render(&engine, b"!if user.admin and not (count < 3 or missing)\n  |p yes\n", &data).unwrap();
+        assert_eq!(out, "|p yes\n");
+        let err = render(&engine, b"!if count >\n  |p\n", &data).unwrap_err();
+        assert_eq!((err.message.as_str(), err.span), ("expected expression", Span::new(11, 11)));
+    }
+
+    #[test]
+    fn test_limits() {
+        let data = context();
+        let engine = Engine::new().max_iterations(3);
+        let err = render(&engine, b"!for a in tags\n  !for b in tags\n    |x\n", &data).unwrap_err();
+        assert_eq!(err.message, "more than 3 loop iterations");
+
+        let looping = Document::parse_owned(b"!include self\n".to_vec()).unwrap();
+        let engine = Engine::new().max_depth(4).partial("self", looping);
+        let err = render(&engine, b"!include self\n", &data).unwrap_err();
+        assert_eq!(err.message, "includes nested more than 4 deep");
+        let err = render(&engine, b"!if a and b and c and d and e\n  |p\n", &data).unwrap_err();
+        assert_eq!(err.message, "expression nested more than 4 deep");
+        let deep = format!("|p !{{{{{}x{}}}}}\n", "(".repeat(200_000), ")".repeat(200_000));
+        let err = render(&Engine::new(), deep.as_bytes(), &data).unwrap_err();
+        assert_eq!(err.message, format!("expression nested more than {} deep", DEFAULT_MAX_DEPTH));
+
+        let growing = format!("!let a = \"xxxxxxxx\"\n{}|p {{{{a}}}}\n", "!let a = [a a a a] | join a\n".repeat(20));
+        let err = render(&Engine::new().max_output(1000), growing.as_bytes(), &data).unwrap_err();
+        assert_eq!(err.message, "value larger than 1000 bytes");
+        let err = render(&Engine::new(), growing.as_bytes(), &data).unwrap_err();
+        assert_eq!(err.message, format!("value larger than {} bytes", DEFAULT_MAX_OUTPUT));
+        let repeated = "!for a in tags\n  |p {{title}}\n".repeat(10);
+        let err = render(&Engine::new().max_output(40), repeated.as_bytes(), &data).unwrap_err();
+        assert_eq!(err.message, "output larger than 40 bytes");
+
+        let err = render(&Engine::new(), b"!cache\n  |p\n", &data).unwrap_err();
+        assert_eq!(err.message, "unsupported directive `!cache`");
+        let raw = b"!raw:sql\n  SELECT 1\n";
+        let out = render(&Engine::new(), raw, &data).unwrap();
+        assert_eq!(out, to_udon_string(&Document::parse(raw).unwrap()));
+    }
+}
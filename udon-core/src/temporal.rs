@@ -0,0 +1,819 @@
+//! Decoded temporal values (dates, times, durations, relative times).
+//!
+//! The parser classifies temporal values following `generator/values.desc`
+//! but keeps only their source text. The types here decode that text:
+//!
+//! - [`Date`]: `YYYY-MM-DD` or `YYYY-MM`
+//! - [`Time`]: `HH:MM`, `HH:MM:SS` or `HH:MM:SS.fffffffff`
+//! - [`DateTime`]: a date, `T`, a time, then optionally `Z` or `±HH[:MM]`
+//! - [`Duration`]: ISO (`P1Y2M3DT4H5M6S`, `PT1.5H`) or shorthand (`30s`,
+//!   `5m`, `1.5h`, `1d`, `2w`, `3mo`, `1y`)
+//! - [`RelativeTime`]: `+` or `-` followed by a duration
+//!
+//! Months and years have no fixed length, so a [`Duration`] keeps them
+//! apart from its fixed-length part. [`RelativeTime::resolve`] applies them
+//! on the calendar (in UTC); [`Duration::to_std`] uses the average
+//! Gregorian month instead.
+//!
+//! With the `chrono` or `time` feature, the decoded types convert into that
+//! crate's types via `TryFrom`.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::{Duration, UNIX_EPOCH};
+//! use udon_core::tree::Document;
+//!
+//! let doc = Document::parse(b"|job :start 2024-01-31T09:30Z :every 1.5h :expires +1mo\n").unwrap();
+//! let job = doc.root().first_child().unwrap().as_element().unwrap();
+//!
+//! let start = job.attr("start").unwrap().as_datetime().unwrap();
+//! assert_eq!((start.date.month, start.time.hour, start.offset), (1, 9, Some(0)));
+//!
+//! let every = job.attr("every").unwrap().as_duration().unwrap();
+//! assert_eq!(every.to_std().unwrap(), Duration::from_secs(5400));
+//!
+//! // 2024-01-31 plus one month is clamped to the end of February
+//! let expires = job.attr("expires").unwrap().as_relative_time().unwrap();
+//! let jan_31 = UNIX_EPOCH + Duration::from_secs(1_706_659_200);
+//! let feb_29 = UNIX_EPOCH + Duration::from_secs(1_709_164_800);
+//! assert_eq!(expires.resolve(jan_31).unwrap(), feb_29);
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration as StdDuration, SystemTime, UNIX_EPOCH};
+
+use crate::tree::Value;
+
+/// Average Gregorian month (365.2425 days / 12), used where a month must
+/// become a fixed length.
+pub const AVERAGE_MONTH: StdDuration = StdDuration::from_secs(2_629_746);
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+const NANOS_PER_DAY: i128 = 86_400 * NANOS_PER_SEC as i128;
+
+/// Error returned when decoding or converting a temporal value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemporalError {
+    /// The value is not of the requested temporal type.
+    WrongType,
+    /// The text is malformed, or names a field that does not exist
+    /// (month 13, February 30th, hour 24).
+    Invalid,
+    /// The value does not fit the target representation.
+    OutOfRange,
+}
+
+impl fmt::Display for TemporalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TemporalError::WrongType => "value is not of the requested temporal type",
+            TemporalError::Invalid => "malformed or invalid temporal value",
+            TemporalError::OutOfRange => "temporal value out of range",
+        })
+    }
+}
+
+impl std::error::Error for TemporalError {}
+
+/// A calendar date. `day` is `None` for the `YYYY-MM` form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i32,
+    pub month: u8,
+    pub day: Option<u8>,
+}
+
+/// A time of day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Time {
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    /// Fractional seconds; digits past nanosecond precision are dropped.
+    pub nanosecond: u32,
+}
+
+/// A date and time of day, with an optional UTC offset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DateTime {
+    pub date: Date,
+    pub time: Time,
+    /// Offset from UTC in seconds east (`Z` is `Some(0)`). `None` is a
+    /// local time with no offset given.
+    pub offset: Option<i32>,
+}
+
+/// A length of time, split into calendar months and a fixed-length part.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Duration {
+    /// Months and years (a year counts as 12). May be fractional (`1.5mo`).
+    pub months: f64,
+    /// Weeks, days, hours, minutes and seconds.
+    pub fixed: StdDuration,
+}
+
+/// A duration before (`-`) or after (`+`) some instant.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RelativeTime {
+    pub negative: bool,
+    pub duration: Duration,
+}
+
+// ============================================================================
+// Value accessors
+// ============================================================================
+
+impl Value<'_> {
+    /// Decode a date value.
+    pub fn as_date(&self) -> Result<Date, TemporalError> {
+        match self {
+            Value::Date(s) => s.parse(),
+            _ => Err(TemporalError::WrongType),
+        }
+    }
+
+    /// Decode a time value.
+    pub fn as_time(&self) -> Result<Time, TemporalError> {
+        match self {
+            Value::Time(s) => s.parse(),
+            _ => Err(TemporalError::WrongType),
+        }
+    }
+
+    /// Decode a date-time value.
+    pub fn as_datetime(&self) -> Result<DateTime, TemporalError> {
+        match self {
+            Value::DateTime(s) => s.parse(),
+            _ => Err(TemporalError::WrongType),
+        }
+    }
+
+    /// Decode a duration value.
+    pub fn as_duration(&self) -> Result<Duration, TemporalError> {
+        match self {
+            Value::Duration(s) => s.parse(),
+            _ => Err(TemporalError::WrongType),
+        }
+    }
+
+    /// Decode a relative time value.
+    pub fn as_relative_time(&self) -> Result<RelativeTime, TemporalError> {
+        match self {
+            Value::RelativeTime(s) => s.parse(),
+            _ => Err(TemporalError::WrongType),
+        }
+    }
+}
+
+// ============================================================================
+// Parsing
+// ============================================================================
+
+impl FromStr for Date {
+    type Err = TemporalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (year, rest) = s.split_once('-').ok_or(TemporalError::Invalid)?;
+        let (month, day) = match rest.split_once('-') {
+            Some((month, day)) => (month, Some(two_digits(day)?)),
+            None => (rest, None),
+        };
+        let year = leading_number(year)?;
+        let year = i32::try_from(year).map_err(|_| TemporalError::OutOfRange)?;
+        let date = Date { year, month: two_digits(month)?, day };
+        if !(1..=12).contains(&date.month) {
+            return Err(TemporalError::Invalid);
+        }
+        if let Some(day) = date.day {
+            if day == 0 || day > days_in_month(year as i64, date.month as u32) as u8 {
+                return Err(TemporalError::Invalid);
+            }
+        }
+        Ok(date)
+    }
+}
+
+impl FromStr for Time {
+    type Err = TemporalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (hour, rest) = s.split_once(':').ok_or(TemporalError::Invalid)?;
+        let (minute, rest) = rest.split_at_checked(2).ok_or(TemporalError::Invalid)?;
+        let (second, nanosecond) = match rest.strip_prefix(':') {
+            Some(rest) => {
+                let (second, rest) = rest.split_at_checked(2).ok_or(TemporalError::Invalid)?;
+                let nanosecond = match rest.strip_prefix('.') {
+                    Some(frac) => fraction_nanos(frac)?,
+                    None if rest.is_empty() => 0,
+                    None => return Err(TemporalError::Invalid),
+                };
+                (two_digits(second)?, nanosecond)
+            }
+            None if rest.is_empty() => (0, 0),
+            None => return Err(TemporalError::Invalid),
+        };
+        let time = Time {
+            hour: u8::try_from(leading_number(hour)?).map_err(|_| TemporalError::Invalid)?,
+            minute: two_digits(minute)?,
+            second,
+            nanosecond,
+        };
+        if time.hour > 23 || time.minute > 59 || time.second > 59 {
+            return Err(TemporalError::Invalid);
+        }
+        Ok(time)
+    }
+}
+
+impl FromStr for DateTime {
+    type Err = TemporalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (date, rest) = s.split_once('T').ok_or(TemporalError::Invalid)?;
+        let (time, offset) = match rest.find(['Z', '+', '-']) {
+            Some(i) => (&rest[..i], Some(parse_offset(&rest[i..])?)),
+            None => (rest, None),
+        };
+        // Unlike a bare time, the hour here is always two digits
+        if time.find(':') != Some(2) {
+            return Err(TemporalError::Invalid);
+        }
+        Ok(DateTime { date: date.parse()?, time: time.parse()?, offset })
+    }
+}
+
+fn parse_offset(s: &str) -> Result<i32, TemporalError> {
+    if s == "Z" {
+        return Ok(0);
+    }
+    let (negative, rest) = (s.starts_with('-'), &s[1..]);
+    let (hours, minutes) = match rest.split_once(':') {
+        Some((hours, minutes)) => (two_digits(hours)?, two_digits(minutes)?),
+        None => (two_digits(rest)?, 0),
+    };
+    if hours > 23 || minutes > 59 {
+        return Err(TemporalError::Invalid);
+    }
+    let seconds = hours as i32 * 3600 + minutes as i32 * 60;
+    Ok(if negative { -seconds } else { seconds })
+}
+
+impl FromStr for Duration {
+    type Err = TemporalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut acc = Accumulator::default();
+        if let Some(iso) = s.strip_prefix(['P', 'p']) {
+            let (date, time) = match iso.find(['T', 't']) {
+                Some(i) => (&iso[..i], Some(&iso[i + 1..])),
+                None => (iso, None),
+            };
+            let mut rest = date;
+            while !rest.is_empty() {
+                let (int, frac, unit, tail) = component(rest)?;
+                match unit.to_ascii_uppercase() {
+                    b'Y' => acc.months(int, frac, 12)?,
+                    b'M' => acc.months(int, frac, 1)?,
+                    b'W' => acc.nanos(int, frac, 7 * NANOS_PER_DAY as u128)?,
+                    b'D' => acc.nanos(int, frac, NANOS_PER_DAY as u128)?,
+                    _ => return Err(TemporalError::Invalid),
+                }
+                rest = tail;
+            }
+            if let Some(mut rest) = time {
+                if rest.is_empty() {
+                    return Err(TemporalError::Invalid);
+                }
+                while !rest.is_empty() {
+                    let (int, frac, unit, tail) = component(rest)?;
+                    match unit.to_ascii_uppercase() {
+                        b'H' => acc.nanos(int, frac, 3600 * NANOS_PER_SEC)?,
+                        b'M' => acc.nanos(int, frac, 60 * NANOS_PER_SEC)?,
+                        b'S' => acc.nanos(int, frac, NANOS_PER_SEC)?,
+                        _ => return Err(TemporalError::Invalid),
+                    }
+                    rest = tail;
+                }
+            } else if date.is_empty() {
+                return Err(TemporalError::Invalid);
+            }
+        } else {
+            let (int, frac, unit) = split_number(s)?;
+            match unit.to_ascii_lowercase().as_str() {
+                "s" => acc.nanos(int, frac, NANOS_PER_SEC)?,
+                "m" => acc.nanos(int, frac, 60 * NANOS_PER_SEC)?,
+                "h" => acc.nanos(int, frac, 3600 * NANOS_PER_SEC)?,
+                "d" => acc.nanos(int, frac, NANOS_PER_DAY as u128)?,
+                "w" => acc.nanos(int, frac, 7 * NANOS_PER_DAY as u128)?,
+                "mo" => acc.months(int, frac, 1)?,
+                "y" => acc.months(int, frac, 12)?,
+                _ => return Err(TemporalError::Invalid),
+            }
+        }
+        acc.finish()
+    }
+}
+
+impl FromStr for RelativeTime {
+    type Err = TemporalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let negative = match s.as_bytes().first() {
+            Some(b'+') => false,
+            Some(b'-') => true,
+            _ => return Err(TemporalError::Invalid),
+        };
+        Ok(RelativeTime { negative, duration: s[1..].parse()? })
+    }
+}
+
+/// Sums duration components: months as a float, the rest exactly.
+#[derive(Default)]
+struct Accumulator {
+    months: f64,
+    nanos: u128,
+}
+
+impl Accumulator {
+    fn months(&mut self, int: &str, frac: &str, per_unit: u32) -> Result<(), TemporalError> {
+        let text: String = format!("{}.{}0", int, frac).chars().filter(|&c| c != '_').collect();
+        let value: f64 = text.parse().map_err(|_| TemporalError::Invalid)?;
+        self.months += value * per_unit as f64;
+        Ok(())
+    }
+
+    fn nanos(&mut self, int: &str, frac: &str, per_unit: u128) -> Result<(), TemporalError> {
+        let whole = digits_value(int)?
+            .checked_mul(per_unit)
+            .ok_or(TemporalError::OutOfRange)?;
+        // Digits past 18 are far below a nanosecond for every unit
+        let frac: String = frac.chars().filter(|&c| c != '_').take(18).collect();
+        let part = match frac.len() {
+            0 => 0,
+            len => digits_value(&frac)? * per_unit / 10u128.pow(len as u32),
+        };
+        self.nanos = self
+            .nanos
+            .checked_add(whole + part)
+            .ok_or(TemporalError::OutOfRange)?;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Duration, TemporalError> {
+        let secs = u64::try_from(self.nanos / NANOS_PER_SEC).map_err(|_| TemporalError::OutOfRange)?;
+        let fixed = StdDuration::new(secs, (self.nanos % NANOS_PER_SEC) as u32);
+        Ok(Duration { months: self.months, fixed })
+    }
+}
+
+/// Split a `digits[.digits]` number off the front of `s`, returning the
+/// integer digits, the fraction digits and the remainder.
+fn split_number(s: &str) -> Result<(&str, &str, &str), TemporalError> {
+    let not_digit = |c: char| !c.is_ascii_digit() && c != '_';
+    if !s.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(TemporalError::Invalid);
+    }
+    let (int, rest) = s.split_at(s.find(not_digit).unwrap_or(s.len()));
+    let (frac, rest) = match rest.strip_prefix('.') {
+        Some(rest) => rest.split_at(rest.find(not_digit).unwrap_or(rest.len())),
+        None => ("", rest),
+    };
+    Ok((int, frac, rest))
+}
+
+/// Split one `digits[.digits]unit` ISO duration component off the front of `s`.
+fn component(s: &str) -> Result<(&str, &str, u8, &str), TemporalError> {
+    let (int, frac, rest) = split_number(s)?;
+    let unit = *rest.as_bytes().first().filter(|b| b.is_ascii()).ok_or(TemporalError::Invalid)?;
+    Ok((int, frac, unit, &rest[1..]))
+}
+
+fn digits_value(digits: &str) -> Result<u128, TemporalError> {
+    let mut value: u128 = 0;
+    for c in digits.chars().filter(|&c| c != '_') {
+        let digit = c.to_digit(10).ok_or(TemporalError::Invalid)?;
+        value = value
+            .checked_mul(10)
+            .and_then(|v| v.checked_add(digit as u128))
+            .ok_or(TemporalError::OutOfRange)?;
+    }
+    Ok(value)
+}
+
+/// A number that starts with a digit; `_` separators are allowed after it.
+fn leading_number(s: &str) -> Result<u128, TemporalError> {
+    if !s.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(TemporalError::Invalid);
+    }
+    digits_value(s)
+}
+
+fn two_digits(s: &str) -> Result<u8, TemporalError> {
+    match s.as_bytes() {
+        [a @ b'0'..=b'9', b @ b'0'..=b'9'] => Ok((a - b'0') * 10 + (b - b'0')),
+        _ => Err(TemporalError::Invalid),
+    }
+}
+
+fn fraction_nanos(frac: &str) -> Result<u32, TemporalError> {
+    if !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(TemporalError::Invalid);
+    }
+    let digits = &frac[..frac.len().min(9)];
+    let value: u32 = if digits.is_empty() { 0 } else { digits.parse().unwrap() };
+    Ok(value * 10u32.pow(9 - digits.len() as u32))
+}
+
+// ============================================================================
+// Durations and calendar arithmetic
+// ============================================================================
+
+impl Duration {
+    /// Convert to a fixed length, counting each month as [`AVERAGE_MONTH`].
+    pub fn to_std(&self) -> Result<StdDuration, TemporalError> {
+        let months = StdDuration::try_from_secs_f64(self.months * AVERAGE_MONTH.as_secs_f64())
+            .map_err(|_| TemporalError::OutOfRange)?;
+        self.fixed.checked_add(months).ok_or(TemporalError::OutOfRange)
+    }
+}
+
+impl TryFrom<Duration> for StdDuration {
+    type Error = TemporalError;
+
+    fn try_from(duration: Duration) -> Result<Self, Self::Error> {
+        duration.to_std()
+    }
+}
+
+impl RelativeTime {
+    /// The instant this relative time names, measured from `now`.
+    ///
+    /// Whole months move along the UTC calendar, clamping to the end of
+    /// shorter months (January 31st plus one month is February 28th or
+    /// 29th). Fractional months count as [`AVERAGE_MONTH`].
+    pub fn resolve(&self, now: SystemTime) -> Result<SystemTime, TemporalError> {
+        let sign: i128 = if self.negative { -1 } else { 1 };
+        let mut nanos: i128 = match now.duration_since(UNIX_EPOCH) {
+            Ok(after) => after.as_nanos() as i128,
+            Err(before) => -(before.duration().as_nanos() as i128),
+        };
+
+        let whole = self.duration.months.trunc();
+        if whole != 0.0 {
+            // Well past any calendar SystemTime can represent
+            if whole.abs() > 1e12 {
+                return Err(TemporalError::OutOfRange);
+            }
+            let (days, time) = (nanos.div_euclid(NANOS_PER_DAY), nanos.rem_euclid(NANOS_PER_DAY));
+            let (year, month, day) = civil_from_days(days as i64);
+            let total = year * 12 + (month as i64 - 1) + sign as i64 * whole as i64;
+            let (year, month) = (total.div_euclid(12), total.rem_euclid(12) as u32 + 1);
+            let day = day.min(days_in_month(year, month));
+            nanos = days_from_civil(year, month, day) as i128 * NANOS_PER_DAY + time;
+        }
+
+        let fraction = (self.duration.months - whole) * AVERAGE_MONTH.as_secs_f64() * 1e9;
+        nanos += sign * (self.duration.fixed.as_nanos() as i128 + fraction as i128);
+
+        let magnitude = nanos.unsigned_abs();
+        let secs = u64::try_from(magnitude / NANOS_PER_SEC).map_err(|_| TemporalError::OutOfRange)?;
+        let offset = StdDuration::new(secs, (magnitude % NANOS_PER_SEC) as u32);
+        let resolved = if nanos >= 0 {
+            UNIX_EPOCH.checked_add(offset)
+        } else {
+            UNIX_EPOCH.checked_sub(offset)
+        };
+        resolved.ok_or(TemporalError::OutOfRange)
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month as i64 + 9) % 12) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Inverse of [`days_from_civil`].
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// ============================================================================
+// Display
+// ============================================================================
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}", self.year, self.month)?;
+        if let Some(day) = self.day {
+            write!(f, "-{:02}", day)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}:{:02}", self.hour, self.minute, self.second)?;
+        if self.nanosecond != 0 {
+            let frac = format!("{:09}", self.nanosecond);
+            write!(f, ".{}", frac.trim_end_matches('0'))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}T{}", self.date, self.time)?;
+        match self.offset {
+            None => Ok(()),
+            Some(0) => f.write_str("Z"),
+            Some(offset) => {
+                let sign = if offset < 0 { '-' } else { '+' };
+                let offset = offset.unsigned_abs();
+                write!(f, "{}{:02}:{:02}", sign, offset / 3600, offset % 3600 / 60)
+            }
+        }
+    }
+}
+
+// ============================================================================
+// chrono / time conversions
+// ============================================================================
+
+#[cfg(feature = "chrono")]
+mod chrono_impls {
+    use super::{Date, DateTime, Duration, TemporalError, Time};
+
+    /// Fails for the `YYYY-MM` form, which names no single day.
+    impl TryFrom<Date> for chrono::NaiveDate {
+        type Error = TemporalError;
+
+        fn try_from(date: Date) -> Result<Self, Self::Error> {
+            let day = date.day.ok_or(TemporalError::Invalid)?;
+            chrono::NaiveDate::from_ymd_opt(date.year, date.month as u32, day as u32)
+                .ok_or(TemporalError::Invalid)
+        }
+    }
+
+    impl TryFrom<Time> for chrono::NaiveTime {
+        type Error = TemporalError;
+
+        fn try_from(time: Time) -> Result<Self, Self::Error> {
+            chrono::NaiveTime::from_hms_nano_opt(
+                time.hour as u32,
+                time.minute as u32,
+                time.second as u32,
+                time.nanosecond,
+            )
+            .ok_or(TemporalError::Invalid)
+        }
+    }
+
+    /// Ignores the offset, if any.
+    impl TryFrom<DateTime> for chrono::NaiveDateTime {
+        type Error = TemporalError;
+
+        fn try_from(dt: DateTime) -> Result<Self, Self::Error> {
+            Ok(chrono::NaiveDateTime::new(dt.date.try_into()?, dt.time.try_into()?))
+        }
+    }
+
+    /// Fails for a local time with no offset.
+    impl TryFrom<DateTime> for chrono::DateTime<chrono::FixedOffset> {
+        type Error = TemporalError;
+
+        fn try_from(dt: DateTime) -> Result<Self, Self::Error> {
+            let offset = dt.offset.and_then(chrono::FixedOffset::east_opt).ok_or(TemporalError::Invalid)?;
+            let naive = chrono::NaiveDateTime::try_from(dt)?;
+            naive.and_local_timezone(offset).single().ok_or(TemporalError::Invalid)
+        }
+    }
+
+    /// Months count as [`super::AVERAGE_MONTH`].
+    impl TryFrom<Duration> for chrono::TimeDelta {
+        type Error = TemporalError;
+
+        fn try_from(duration: Duration) -> Result<Self, Self::Error> {
+            chrono::TimeDelta::from_std(duration.to_std()?).map_err(|_| TemporalError::OutOfRange)
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+mod time_impls {
+    use super::{Date, DateTime, Duration, TemporalError, Time};
+
+    /// Fails for the `YYYY-MM` form, which names no single day.
+    impl TryFrom<Date> for time::Date {
+        type Error = TemporalError;
+
+        fn try_from(date: Date) -> Result<Self, Self::Error> {
+            let day = date.day.ok_or(TemporalError::Invalid)?;
+            let month = time::Month::try_from(date.month).map_err(|_| TemporalError::Invalid)?;
+            time::Date::from_calendar_date(date.year, month, day).map_err(|_| TemporalError::Invalid)
+        }
+    }
+
+    impl TryFrom<Time> for time::Time {
+        type Error = TemporalError;
+
+        fn try_from(t: Time) -> Result<Self, Self::Error> {
+            time::Time::from_hms_nano(t.hour, t.minute, t.second, t.nanosecond)
+                .map_err(|_| TemporalError::Invalid)
+        }
+    }
+
+    /// Ignores the offset, if any.
+    impl TryFrom<DateTime> for time::PrimitiveDateTime {
+        type Error = TemporalError;
+
+        fn try_from(dt: DateTime) -> Result<Self, Self::Error> {
+            Ok(time::PrimitiveDateTime::new(dt.date.try_into()?, dt.time.try_into()?))
+        }
+    }
+
+    /// Fails for a local time with no offset.
+    impl TryFrom<DateTime> for time::OffsetDateTime {
+        type Error = TemporalError;
+
+        fn try_from(dt: DateTime) -> Result<Self, Self::Error> {
+            let offset = dt.offset.ok_or(TemporalError::Invalid)?;
+            let offset = time::UtcOffset::from_whole_seconds(offset).map_err(|_| TemporalError::Invalid)?;
+            Ok(time::PrimitiveDateTime::try_from(dt)?.assume_offset(offset))
+        }
+    }
+
+    /// Months count as [`super::AVERAGE_MONTH`].
+    impl TryFrom<Duration> for time::Duration {
+        type Error = TemporalError;
+
+        fn try_from(duration: Duration) -> Result<Self, Self::Error> {
+            time::Duration::try_from(duration.to_std()?).map_err(|_| TemporalError::OutOfRange)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::Document;
+
+    /// Parse `text` as an attribute value, as the grammar classifies it.
+    fn value(text: &str) -> Value<'static> {
+        let source = format!("|a :v {}\n", text);
+        let doc = Document::parse(source.as_bytes()).unwrap();
+        let el = doc.root().first_child().unwrap().as_element().unwrap();
+        match el.attr("v").unwrap() {
+            Value::Date(s) => Value::Date(s.to_string().into()),
+            Value::Time(s) => Value::Time(s.to_string().into()),
+            Value::DateTime(s) => Value::DateTime(s.to_string().into()),
+            Value::Duration(s) => Value::Duration(s.to_string().into()),
+            Value::RelativeTime(s) => Value::RelativeTime(s.to_string().into()),
+            other => panic!("{} parsed as {:?}", text, other),
+        }
+    }
+
+    fn secs(secs: u64) -> StdDuration {
+        StdDuration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_dates_and_times() {
+        assert_eq!(value("2024-02-29").as_date(), Ok(Date { year: 2024, month: 2, day: Some(29) }));
+        assert_eq!(value("2024-07").as_date(), Ok(Date { year: 2024, month: 7, day: None }));
+        assert_eq!(value("2023-02-29").as_date(), Err(TemporalError::Invalid));
+        assert_eq!(value("2024-13").as_date(), Err(TemporalError::Invalid));
+
+        assert_eq!(
+            value("14:30").as_time(),
+            Ok(Time { hour: 14, minute: 30, second: 0, nanosecond: 0 })
+        );
+        assert_eq!(value("09:05:07.25").as_time().unwrap().nanosecond, 250_000_000);
+        assert_eq!(value("23:59:59.1234567891").as_time().unwrap().nanosecond, 123_456_789);
+        assert_eq!(value("24:00").as_time(), Err(TemporalError::Invalid));
+        assert_eq!(value("12:60").as_time(), Err(TemporalError::Invalid));
+        assert_eq!(value("12:30").as_date(), Err(TemporalError::WrongType));
+    }
+
+    #[test]
+    fn test_datetimes() {
+        let dt = value("2024-01-15T10:30:00Z").as_datetime().unwrap();
+        assert_eq!(dt.date, Date { year: 2024, month: 1, day: Some(15) });
+        assert_eq!((dt.time.hour, dt.time.minute, dt.offset), (10, 30, Some(0)));
+
+        assert_eq!(value("2024-01-15T10:30:00.5-05:30").as_datetime().unwrap().offset, Some(-19_800));
+        assert_eq!(value("2024-01-15T10:30+09").as_datetime().unwrap().offset, Some(32_400));
+        assert_eq!(value("2024-01-15T10:30").as_datetime().unwrap().offset, None);
+        assert_eq!(value("2024-01-15T25:00").as_datetime(), Err(TemporalError::Invalid));
+
+        for text in ["2024-01-15T10:30:00.5-05:30", "2024-07T08:00:00Z", "2024-01-15T10:30:00"] {
+            assert_eq!(value(text).as_datetime().unwrap().to_string(), text);
+        }
+    }
+
+    #[test]
+    fn test_durations() {
+        let cases: &[(&str, f64, StdDuration)] = &[
+            ("30s", 0.0, secs(30)),
+            ("5m", 0.0, secs(300)),
+            ("1.5h", 0.0, secs(5400)),
+            ("2d", 0.0, secs(172_800)),
+            ("1w", 0.0, secs(604_800)),
+            ("3mo", 3.0, secs(0)),
+            ("1y", 12.0, secs(0)),
+            ("P1Y2M3DT4H5M6S", 14.0, secs(3 * 86_400 + 4 * 3600 + 5 * 60 + 6)),
+            ("PT0.5S", 0.0, StdDuration::from_millis(500)),
+            ("P1.5D", 0.0, secs(129_600)),
+            ("P2W", 0.0, secs(1_209_600)),
+        ];
+        for &(text, months, fixed) in cases {
+            assert_eq!(value(text).as_duration(), Ok(Duration { months, fixed }), "{}", text);
+        }
+        assert_eq!(value("1mo").as_duration().unwrap().to_std(), Ok(AVERAGE_MONTH));
+        assert_eq!(value("1y").as_duration().unwrap().to_std(), Ok(secs(31_556_952)));
+        // A non-ASCII unit is invalid, not a panic
+        for text in ["P1é", "PT1é", "P1Y2é"] {
+            assert_eq!(text.parse::<Duration>(), Err(TemporalError::Invalid), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_relative_times() {
+        let base = UNIX_EPOCH + secs(1_706_659_200); // 2024-01-31T00:00:00Z
+        let resolve = |text: &str| value(text).as_relative_time().unwrap().resolve(base).unwrap();
+
+        assert_eq!(resolve("+1h"), base + secs(3600));
+        assert_eq!(resolve("-30d"), base - secs(30 * 86_400));
+        assert_eq!(resolve("+P1DT2H"), base + secs(86_400 + 7200));
+        // Months follow the calendar and clamp to shorter months
+        assert_eq!(resolve("+1mo"), UNIX_EPOCH + secs(1_709_164_800)); // 2024-02-29
+        assert_eq!(resolve("-2mo"), UNIX_EPOCH + secs(1_701_302_400)); // 2023-11-30
+        assert_eq!(resolve("+1y"), UNIX_EPOCH + secs(1_738_281_600)); // 2025-01-31
+        // Before the epoch
+        let early = UNIX_EPOCH - secs(86_400); // 1969-12-31
+        let relative: RelativeTime = "+2mo".parse().unwrap();
+        assert_eq!(relative.resolve(early), Ok(UNIX_EPOCH + secs(58 * 86_400))); // 1970-02-28
+    }
+
+    #[test]
+    fn test_civil_round_trip() {
+        for days in [-719_468, -1, 0, 59, 11_016, 19_783, 2_932_896] {
+            let (y, m, d) = civil_from_days(days);
+            assert_eq!(days_from_civil(y, m, d), days);
+        }
+        assert_eq!(civil_from_days(19_753), (2024, 1, 31));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono() {
+        let dt = value("2024-01-15T10:30:00.5+01:00").as_datetime().unwrap();
+        let converted = chrono::DateTime::<chrono::FixedOffset>::try_from(dt).unwrap();
+        assert_eq!(converted.to_rfc3339(), "2024-01-15T10:30:00.500+01:00");
+        assert!(chrono::NaiveDate::try_from(value("2024-07").as_date().unwrap()).is_err());
+        let delta = chrono::TimeDelta::try_from(value("1.5h").as_duration().unwrap()).unwrap();
+        assert_eq!(delta.num_minutes(), 90);
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time() {
+        let dt = value("2024-01-15T10:30:00-02:00").as_datetime().unwrap();
+        let converted = time::OffsetDateTime::try_from(dt).unwrap();
+        assert_eq!(converted.unix_timestamp(), 1_705_321_800);
+        assert!(time::OffsetDateTime::try_from(value("2024-01-15T10:30").as_datetime().unwrap()).is_err());
+        let duration = time::Duration::try_from(value("2w").as_duration().unwrap()).unwrap();
+        assert_eq!(duration.whole_days(), 14);
+    }
+}
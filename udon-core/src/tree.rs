@@ -20,7 +20,11 @@
 //! ```
 
 use std::borrow::Cow;
-use crate::parser::{Event, Parser};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use crate::diagnostic::Diagnostic;
+use crate::parser::{Event, ParseErrorCode, Parser};
+use crate::span::Span;
 
 // ============================================================================
 // Core Types
@@ -31,11 +35,11 @@ use crate::parser::{Event, Parser};
 pub struct NodeId(u32);
 
 impl NodeId {
-    fn new(index: usize) -> Self {
+    pub(crate) fn new(index: usize) -> Self {
         NodeId(index as u32)
     }
 
-    fn index(self) -> usize {
+    pub(crate) fn index(self) -> usize {
         self.0 as usize
     }
 }
@@ -46,10 +50,14 @@ struct NodeData<'a> {
     parent: Option<NodeId>,
     children: Vec<NodeId>,
     kind: NodeKind<'a>,
+    span: Span,
+    /// Index into `Document::included` for a node spliced in from another
+    /// file, whose span refers to that file's text.
+    included: Option<usize>,
 }
 
 /// The kind of node in the tree.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum NodeKind<'a> {
     /// Root document container.
     Document,
@@ -90,10 +98,36 @@ pub enum NodeKind<'a> {
 }
 
 /// An attribute on an element or directive.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Attribute<'a> {
     pub name: Cow<'a, str>,
     pub value: Value<'a>,
+    /// Source span of the attribute name (without the leading `:`).
+    pub name_span: Span,
+    /// Source span of the value, including quotes or array brackets.
+    pub value_span: Span,
+}
+
+impl<'a> Attribute<'a> {
+    /// Create an attribute with no source location (for trees built in code).
+    pub fn new(name: impl Into<Cow<'a, str>>, value: Value<'a>) -> Self {
+        Attribute {
+            name: name.into(),
+            value,
+            name_span: Span::empty(),
+            value_span: Span::empty(),
+        }
+    }
+
+    /// Copy any borrowed text so the attribute no longer borrows the input.
+    pub fn into_owned(self) -> Attribute<'static> {
+        Attribute {
+            name: owned(self.name),
+            value: self.value.into_owned(),
+            name_span: self.name_span,
+            value_span: self.value_span,
+        }
+    }
 }
 
 /// A typed value preserving original representation.
@@ -131,24 +165,146 @@ pub enum Value<'a> {
     RelativeTime(Cow<'a, str>),
 }
 
+impl Value<'_> {
+    /// Copy any borrowed text so the value no longer borrows the input.
+    pub fn into_owned(self) -> Value<'static> {
+        match self {
+            Value::String(s) => Value::String(owned(s)),
+            Value::Bare(s) => Value::Bare(owned(s)),
+            Value::Integer(s) => Value::Integer(owned(s)),
+            Value::Float(s) => Value::Float(owned(s)),
+            Value::Rational(s) => Value::Rational(owned(s)),
+            Value::Complex(s) => Value::Complex(owned(s)),
+            Value::BoolTrue => Value::BoolTrue,
+            Value::BoolFalse => Value::BoolFalse,
+            Value::Nil => Value::Nil,
+            Value::Array(items) => Value::Array(items.into_iter().map(Value::into_owned).collect()),
+            Value::Date(s) => Value::Date(owned(s)),
+            Value::Time(s) => Value::Time(owned(s)),
+            Value::DateTime(s) => Value::DateTime(owned(s)),
+            Value::Duration(s) => Value::Duration(owned(s)),
+            Value::RelativeTime(s) => Value::RelativeTime(owned(s)),
+        }
+    }
+}
+
+impl NodeKind<'_> {
+    /// Copy any borrowed text so the node kind no longer borrows the input.
+    pub fn into_owned(self) -> NodeKind<'static> {
+        match self {
+            NodeKind::Document => NodeKind::Document,
+            NodeKind::Element { name, id, classes, attrs, embedded } => NodeKind::Element {
+                name: owned(name),
+                id: id.map(owned),
+                classes: classes.into_iter().map(owned).collect(),
+                attrs: attrs.into_iter().map(Attribute::into_owned).collect(),
+                embedded,
+            },
+            NodeKind::Text(s) => NodeKind::Text(owned(s)),
+            NodeKind::Comment(s) => NodeKind::Comment(owned(s)),
+            NodeKind::Directive { name, attrs } => NodeKind::Directive {
+                name: owned(name),
+                attrs: attrs.into_iter().map(Attribute::into_owned).collect(),
+            },
+            NodeKind::Interpolation(s) => NodeKind::Interpolation(owned(s)),
+            NodeKind::Reference(s) => NodeKind::Reference(owned(s)),
+            NodeKind::Raw { lang, content } => NodeKind::Raw {
+                lang: lang.map(owned),
+                content: owned(content),
+            },
+        }
+    }
+}
+
+fn owned(s: Cow<'_, str>) -> Cow<'static, str> {
+    Cow::Owned(s.into_owned())
+}
+
+/// Resolve backslash escapes in a quoted string's source text.
+pub(crate) fn unescape(s: &str) -> Cow<'_, str> {
+    if !s.contains('\\') {
+        return Cow::Borrowed(s);
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('0') => out.push('\0'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// A value holding `s`: bare where the writer can keep it unquoted,
+/// otherwise a quoted string with backslash escapes.
+#[cfg(any(feature = "serde", feature = "json"))]
+pub(crate) fn string_value(s: &str) -> Value<'static> {
+    if !s.contains(['\\', '\n', '\t', '\r', '\0']) {
+        // The writer quotes this if it would not parse back as the same text
+        return Value::Bare(Cow::Owned(s.to_string()));
+    }
+    let mut escaped = String::with_capacity(s.len() + 2);
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\0' => escaped.push_str("\\0"),
+            c => escaped.push(c),
+        }
+    }
+    Value::String(Cow::Owned(escaped))
+}
+
 // ============================================================================
 // Document
 // ============================================================================
 
 /// A parsed UDON document as a tree.
+///
+/// `Document` is `Send + Sync`. A `Document<'static>` from
+/// [`parse_owned`](Self::parse_owned) or [`into_owned`](Self::into_owned)
+/// does not borrow the input, so it can be cached or shared behind an
+/// `Arc` after the input buffer is gone.
 #[derive(Debug)]
 pub struct Document<'a> {
     nodes: Vec<NodeData<'a>>,
     root: NodeId,
+    source: Cow<'a, [u8]>,
+    /// Text of the files include expansion spliced nodes from.
+    included: Vec<Vec<u8>>,
+    /// First element carrying each id, in document order. Built on the
+    /// first lookup and dropped by every mutation. Keys are owned so that
+    /// `Document` stays covariant in `'a`.
+    ids: OnceLock<HashMap<String, NodeId>>,
 }
 
 /// Error returned when parsing fails.
 #[derive(Debug)]
 pub struct ParseError {
     pub message: String,
+    pub code: ParseErrorCode,
     pub span: std::ops::Range<usize>,
 }
 
+impl ParseError {
+    /// The error as a [`Diagnostic`], e.g. for rendering with
+    /// [`ReportRenderer`](crate::report::ReportRenderer).
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic::parse_error(self.code, self.span.clone())
+    }
+}
+
 impl std::fmt::Display for ParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{} at {}..{}", self.message, self.span.start, self.span.end)
@@ -157,10 +313,81 @@ impl std::fmt::Display for ParseError {
 
 impl std::error::Error for ParseError {}
 
+/// Error from [`Document::parse_reader`].
+#[derive(Debug)]
+pub enum ReadError {
+    /// Reading the input failed.
+    Io(std::io::Error),
+    /// The input does not parse.
+    Parse(ParseError),
+}
+
+impl std::fmt::Display for ReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadError::Io(err) => write!(f, "read failed: {}", err),
+            ReadError::Parse(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReadError::Io(err) => Some(err),
+            ReadError::Parse(err) => Some(err),
+        }
+    }
+}
+
+impl From<std::io::Error> for ReadError {
+    fn from(err: std::io::Error) -> Self {
+        ReadError::Io(err)
+    }
+}
+
+impl From<ParseError> for ReadError {
+    fn from(err: ParseError) -> Self {
+        ReadError::Parse(err)
+    }
+}
+
+impl Default for Document<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<'a> Document<'a> {
+    /// Create an empty document, for building a tree in code.
+    pub fn new() -> Self {
+        TreeBuilder::new(b"").finish()
+    }
+
+    /// Append a node as the last child of `parent`, returning its ID.
+    ///
+    /// Nodes added this way have an empty span.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parent` does not belong to this document.
+    pub fn append_child(&mut self, parent: NodeId, kind: NodeKind<'a>) -> NodeId {
+        let id = NodeId::new(self.nodes.len());
+        self.nodes[parent.index()].children.push(id);
+        self.ids.take();
+        self.nodes.push(NodeData {
+            parent: Some(parent),
+            children: Vec::new(),
+            kind,
+            span: Span::empty(),
+            included: None,
+        });
+        id
+    }
+
     /// Parse input bytes into a document tree.
     pub fn parse(input: &'a [u8]) -> Result<Self, ParseError> {
-        let mut builder = TreeBuilder::new();
+        let mut builder = TreeBuilder::new(input);
         let mut first_error: Option<ParseError> = None;
 
         Parser::new(input).parse(|event| {
@@ -168,6 +395,7 @@ impl<'a> Document<'a> {
                 if let Event::Error { code, span } = &event {
                     first_error = Some(ParseError {
                         message: format!("{:?}", code),
+                        code: *code,
                         span: span.clone(),
                     });
                 } else {
@@ -183,6 +411,71 @@ impl<'a> Document<'a> {
         Ok(builder.finish())
     }
 
+    /// Parse input bytes, recovering from errors.
+    ///
+    /// Unlike [`parse`](Self::parse), this always returns a tree: whatever
+    /// the parser recovered, together with every parse error and warning
+    /// as a [`Diagnostic`] (in source order). Errors carry
+    /// [`Code::Parse`](crate::diagnostic::Code::Parse); warnings use
+    /// `Code::InconsistentIndentation`.
+    pub fn parse_with_diagnostics(input: &'a [u8]) -> (Self, Vec<Diagnostic>) {
+        let mut builder = TreeBuilder::new(input);
+        let mut diagnostics = Vec::new();
+
+        Parser::new(input).parse(|event| match event {
+            Event::Error { code, span } => diagnostics.push(Diagnostic::parse_error(code, span)),
+            Event::Warning { content, span } => diagnostics.push(Diagnostic::parse_warning(&content, span)),
+            event => builder.handle_event(event),
+        });
+
+        (builder.finish(), diagnostics)
+    }
+
+    /// Parse an owned buffer into a document that keeps it.
+    ///
+    /// Text is copied out of the buffer as for [`into_owned`](Self::into_owned);
+    /// the buffer itself is kept as the source, without another copy.
+    pub fn parse_owned(input: Vec<u8>) -> Result<Document<'static>, ParseError> {
+        let doc = Document::parse(&input)?;
+        let root = doc.root;
+        let nodes = doc.into_owned_nodes();
+        Ok(Document { nodes, ids: OnceLock::new(), root, source: Cow::Owned(input), included: Vec::new() })
+    }
+
+    /// Read all of `reader` and parse it into a document that keeps the input.
+    ///
+    /// A tree needs the whole input, so this reads to the end first; for
+    /// events as the input arrives, use
+    /// [`StreamingParser::parse_reader`](crate::parser::StreamingParser::parse_reader).
+    pub fn parse_reader(mut reader: impl std::io::Read) -> Result<Document<'static>, ReadError> {
+        let mut input = Vec::new();
+        reader.read_to_end(&mut input)?;
+        Ok(Document::parse_owned(input)?)
+    }
+
+    /// Copy any borrowed text so the document no longer borrows the input.
+    ///
+    /// Node IDs and spans are unchanged.
+    pub fn into_owned(self) -> Document<'static> {
+        let source = Cow::Owned(self.source.to_vec());
+        let (root, included) = (self.root, self.included.clone());
+        let nodes = self.into_owned_nodes();
+        Document { nodes, ids: OnceLock::new(), root, source, included }
+    }
+
+    fn into_owned_nodes(self) -> Vec<NodeData<'static>> {
+        self.nodes
+            .into_iter()
+            .map(|node| NodeData {
+                parent: node.parent,
+                children: node.children,
+                kind: node.kind.into_owned(),
+                span: node.span,
+                included: node.included,
+            })
+            .collect()
+    }
+
     /// Get the root node.
     pub fn root(&self) -> Node<'_, 'a> {
         Node { doc: self, id: self.root }
@@ -197,9 +490,101 @@ impl<'a> Document<'a> {
         }
     }
 
+    /// Get the element with the given id (`|name[id]`).
+    ///
+    /// If several elements share an id, the first in document order is returned.
+    pub fn get_by_id(&self, id: &str) -> Option<Node<'_, 'a>> {
+        let ids = self.ids.get_or_init(|| self.index_ids());
+        ids.get(id).map(|&node| Node { doc: self, id: node })
+    }
+
+    /// Map each id to its first element, walking the tree in document order.
+    fn index_ids(&self) -> HashMap<String, NodeId> {
+        let mut ids = HashMap::new();
+        let mut stack = vec![self.root];
+        while let Some(node) = stack.pop() {
+            let data = self.node_data(node);
+            if let NodeKind::Element { id: Some(id), .. } = &data.kind {
+                ids.entry(id.to_string()).or_insert(node);
+            }
+            stack.extend(data.children.iter().rev());
+        }
+        ids
+    }
+
+    /// Get the input the document was parsed from.
+    pub fn source(&self) -> &[u8] {
+        &self.source
+    }
+
+    /// Get the exact source text a node was parsed from.
+    pub fn source_text<'doc>(&self, node: Node<'doc, 'a>) -> Cow<'doc, str> {
+        let source = node.source();
+        let span = node.span();
+        let end = (span.end as usize).min(source.len());
+        let start = (span.start as usize).min(end);
+        String::from_utf8_lossy(&source[start..end])
+    }
+
+    /// Get the source text covered by a span (e.g. an attribute's
+    /// `value_span`) in the document's own source.
+    pub fn span_text(&self, span: Span) -> Cow<'_, str> {
+        let source = self.source();
+        let end = (span.end as usize).min(source.len());
+        let start = (span.start as usize).min(end);
+        String::from_utf8_lossy(&source[start..end])
+    }
+
     fn node_data(&self, id: NodeId) -> &NodeData<'a> {
         &self.nodes[id.index()]
     }
+
+    /// Mutable access to a node's kind, for resolution passes.
+    pub(crate) fn kind_mut(&mut self, id: NodeId) -> &mut NodeKind<'a> {
+        self.ids.take();
+        &mut self.nodes[id.index()].kind
+    }
+
+    pub(crate) fn set_span(&mut self, id: NodeId, span: Span) {
+        self.nodes[id.index()].span = span;
+    }
+
+    /// Keep the text of an included file, for [`set_included`](Self::set_included).
+    pub(crate) fn add_included(&mut self, text: Vec<u8>) -> usize {
+        self.included.push(text);
+        self.included.len() - 1
+    }
+
+    /// Mark a node as spliced from an included file, with its span in
+    /// that file.
+    pub(crate) fn set_included(&mut self, id: NodeId, included: usize, span: Span) {
+        let node = &mut self.nodes[id.index()];
+        node.included = Some(included);
+        node.span = span;
+    }
+
+    /// Unlink a node from its parent. It stays in the arena, unreachable.
+    pub(crate) fn detach(&mut self, id: NodeId) {
+        self.ids.take();
+        if let Some(parent) = self.nodes[id.index()].parent.take() {
+            self.nodes[parent.index()].children.retain(|&c| c != id);
+        }
+    }
+
+    /// Put a node's children in its place in its parent. The node stays in
+    /// the arena, unreachable.
+    pub(crate) fn unwrap_node(&mut self, id: NodeId) {
+        self.ids.take();
+        let Some(parent) = self.nodes[id.index()].parent.take() else { return };
+        let children = std::mem::take(&mut self.nodes[id.index()].children);
+        for &child in &children {
+            self.nodes[child.index()].parent = Some(parent);
+        }
+        let siblings = &mut self.nodes[parent.index()].children;
+        if let Some(position) = siblings.iter().position(|&c| c == id) {
+            siblings.splice(position..=position, children);
+        }
+    }
 }
 
 // ============================================================================
@@ -222,11 +607,40 @@ impl<'doc, 'a: 'doc> Node<'doc, 'a> {
         self.id
     }
 
+    /// Get the document this node belongs to.
+    pub fn document(&self) -> &'doc Document<'a> {
+        self.doc
+    }
+
     /// Get the node's kind.
-    pub fn kind(&self) -> &NodeKind<'a> {
+    pub fn kind(&self) -> &'doc NodeKind<'a> {
         &self.doc.node_data(self.id).kind
     }
 
+    /// Get the source span of the node, including sigils and delimiters.
+    pub fn span(&self) -> Span {
+        self.doc.node_data(self.id).span
+    }
+
+    /// The text the node's span refers to: the document's source, or for a
+    /// node spliced in by [include expansion](crate::include), its file's.
+    pub fn source(&self) -> &'doc [u8] {
+        match self.doc.node_data(self.id).included {
+            Some(index) => &self.doc.included[index],
+            None => self.doc.source(),
+        }
+    }
+
+    /// Source text from `offset` in `from`'s source to the start of this
+    /// node; `None` when the two nodes' spans refer to different texts or
+    /// the offset is past the start.
+    pub(crate) fn source_since(&self, from: Node<'_, 'a>, offset: u32) -> Option<&'doc [u8]> {
+        if from.doc.node_data(from.id).included != self.doc.node_data(self.id).included {
+            return None;
+        }
+        self.source().get(offset as usize..self.span().start as usize)
+    }
+
     /// Get the parent node, if any.
     pub fn parent(&self) -> Option<Node<'doc, 'a>> {
         self.doc.node_data(self.id).parent.map(|id| Node { doc: self.doc, id })
@@ -303,6 +717,29 @@ impl<'doc, 'a: 'doc> Node<'doc, 'a> {
         result
     }
 
+    /// Locate `needle` (e.g. `.class` or `[id]`) on the node's first source
+    /// line, as a whole token.
+    pub(crate) fn header_span(&self, needle: &str) -> Option<Span> {
+        let span = self.span();
+        let header = self.source().get(span.range())?;
+        let header = &header[..header.iter().position(|&b| b == b'\n').unwrap_or(header.len())];
+        let needle = needle.as_bytes();
+
+        let mut from = 0;
+        while let Some(pos) = header[from..].windows(needle.len()).position(|w| w == needle) {
+            let start = from + pos;
+            let end = start + needle.len();
+            let boundary = header
+                .get(end)
+                .is_none_or(|&b| !(b.is_ascii_alphanumeric() || b == b'_' || b == b'-' || b >= 0x80));
+            if boundary {
+                return Some(Span::new(span.start as usize + start, span.start as usize + end));
+            }
+            from = start + 1;
+        }
+        None
+    }
+
     fn collect_text(&self, buf: &mut String) {
         match self.kind() {
             NodeKind::Text(s) => buf.push_str(s),
@@ -341,7 +778,7 @@ impl<'doc, 'a: 'doc> ElementView<'doc, 'a> {
     }
 
     /// Get the element name.
-    pub fn name(&self) -> &str {
+    pub fn name(&self) -> &'doc str {
         if let NodeKind::Element { name, .. } = self.node.kind() {
             name.as_ref()
         } else {
@@ -350,7 +787,7 @@ impl<'doc, 'a: 'doc> ElementView<'doc, 'a> {
     }
 
     /// Get the element ID, if any.
-    pub fn id(&self) -> Option<&str> {
+    pub fn id(&self) -> Option<&'doc str> {
         if let NodeKind::Element { id, .. } = self.node.kind() {
             id.as_ref().map(|s| s.as_ref())
         } else {
@@ -359,7 +796,7 @@ impl<'doc, 'a: 'doc> ElementView<'doc, 'a> {
     }
 
     /// Get the element classes.
-    pub fn classes(&self) -> &[Cow<'a, str>] {
+    pub fn classes(&self) -> &'doc [Cow<'a, str>] {
         if let NodeKind::Element { classes, .. } = self.node.kind() {
             classes
         } else {
@@ -382,7 +819,7 @@ impl<'doc, 'a: 'doc> ElementView<'doc, 'a> {
     }
 
     /// Get an attribute value by name.
-    pub fn attr(&self, name: &str) -> Option<&Value<'a>> {
+    pub fn attr(&self, name: &str) -> Option<&'doc Value<'a>> {
         if let NodeKind::Element { attrs, .. } = self.node.kind() {
             attrs.iter().find(|a| a.name.as_ref() == name).map(|a| &a.value)
         } else {
@@ -390,6 +827,20 @@ impl<'doc, 'a: 'doc> ElementView<'doc, 'a> {
         }
     }
 
+    /// Get the full attribute (with spans) by name.
+    pub fn attribute(&self, name: &str) -> Option<&'doc Attribute<'a>> {
+        self.attributes().iter().find(|a| a.name.as_ref() == name)
+    }
+
+    /// Get all attributes (with spans) in source order.
+    pub fn attributes(&self) -> &'doc [Attribute<'a>] {
+        if let NodeKind::Element { attrs, .. } = self.node.kind() {
+            attrs
+        } else {
+            &[]
+        }
+    }
+
     /// Iterate over all attributes.
     pub fn attrs(&self) -> impl Iterator<Item = (&str, &Value<'a>)> {
         let attrs = if let NodeKind::Element { attrs, .. } = self.node.kind() {
@@ -422,24 +873,28 @@ impl<'doc, 'a> std::fmt::Debug for ElementView<'doc, 'a> {
 
 /// Builds a document tree from parser events.
 struct TreeBuilder<'a> {
+    input: &'a [u8],
     nodes: Vec<NodeData<'a>>,
     /// Stack of open node IDs.
     stack: Vec<NodeId>,
-    /// Current attribute being built.
-    current_attr: Option<Cow<'a, str>>,
-    /// Array nesting for values.
-    array_stack: Vec<Vec<Value<'a>>>,
+    /// Current attribute being built, with the span of its name.
+    current_attr: Option<(Cow<'a, str>, Span)>,
+    /// Array nesting for values, with each array's start offset.
+    array_stack: Vec<(Vec<Value<'a>>, u32)>,
 }
 
 impl<'a> TreeBuilder<'a> {
-    fn new() -> Self {
+    fn new(input: &'a [u8]) -> Self {
         // Create root document node
         let root = NodeData {
             parent: None,
             children: Vec::new(),
             kind: NodeKind::Document,
+            span: Span::new(0, input.len()),
+            included: None,
         };
         TreeBuilder {
+            input,
             nodes: vec![root],
             stack: vec![NodeId::new(0)],
             current_attr: None,
@@ -451,203 +906,292 @@ impl<'a> TreeBuilder<'a> {
         *self.stack.last().unwrap()
     }
 
-    fn push_node(&mut self, kind: NodeKind<'a>) -> NodeId {
+    fn push_node(&mut self, kind: NodeKind<'a>, span: Span) -> NodeId {
         let parent = self.current();
         let id = NodeId::new(self.nodes.len());
         self.nodes.push(NodeData {
             parent: Some(parent),
             children: Vec::new(),
             kind,
+            span,
+            included: None,
         });
         self.nodes[parent.index()].children.push(id);
+        if parent.index() != 0 {
+            self.extend(parent, span.end);
+        }
         id
     }
 
+    /// Open a container node whose start event sits just after `sigils`.
+    fn open_node(&mut self, kind: NodeKind<'a>, start: usize, sigils: &[&[u8]]) {
+        let start = self.back_over(start, sigils);
+        let id = self.push_node(kind, Span::new(start, start));
+        self.stack.push(id);
+    }
+
+    /// Close the current container. Delimited constructs (`}`, ``` ``` ```)
+    /// end at their end event; indentation-closed ones end at their last content.
+    fn close_node(&mut self, end: usize) {
+        let id = self.current();
+        if end > 0 && matches!(self.input.get(end - 1), Some(b'}' | b'`')) {
+            self.extend(id, end as u32);
+        }
+        self.stack.pop();
+        let end = self.nodes[id.index()].span.end;
+        if self.stack.len() > 1 {
+            self.extend(self.current(), end);
+        }
+    }
+
+    /// Grow a node's span to cover `end`.
+    fn extend(&mut self, id: NodeId, end: u32) {
+        let span = &mut self.nodes[id.index()].span;
+        span.end = span.end.max(end);
+    }
+
+    /// Move `pos` back over the first sigil that immediately precedes it.
+    fn back_over(&self, pos: usize, sigils: &[&[u8]]) -> usize {
+        let before = &self.input[..pos.min(self.input.len())];
+        sigils
+            .iter()
+            .find(|s| before.ends_with(s))
+            .map_or(pos, |s| pos - s.len())
+    }
+
+    /// Widen a content span to include its surrounding delimiters.
+    fn widen(&self, span: &std::ops::Range<usize>, open: &[&[u8]], close: &[u8]) -> Span {
+        let start = self.back_over(span.start, open);
+        let end = if start < span.start && self.input[span.end.min(self.input.len())..].starts_with(close) {
+            span.end + close.len()
+        } else {
+            span.end
+        };
+        Span::new(start, end)
+    }
+
     fn handle_event(&mut self, event: Event<'a>) {
         use Event::*;
 
         match event {
             // ---- Elements ----
-            ElementStart { .. } => {
-                let id = self.push_node(NodeKind::Element {
+            ElementStart { span } => {
+                let kind = NodeKind::Element {
                     name: Cow::Borrowed(""),
                     id: None,
                     classes: Vec::new(),
                     attrs: Vec::new(),
                     embedded: false,
-                });
-                self.stack.push(id);
+                };
+                self.open_node(kind, span.start, &[b"|"]);
             }
-            ElementEnd { .. } => {
-                self.stack.pop();
+            ElementEnd { span } => {
+                self.close_node(span.end);
             }
 
-            EmbeddedStart { .. } => {
-                let id = self.push_node(NodeKind::Element {
+            EmbeddedStart { span } => {
+                let kind = NodeKind::Element {
                     name: Cow::Borrowed(""),
                     id: None,
                     classes: Vec::new(),
                     attrs: Vec::new(),
                     embedded: true,
-                });
-                self.stack.push(id);
+                };
+                self.open_node(kind, span.start, &[b"|{"]);
             }
-            EmbeddedEnd { .. } => {
-                self.stack.pop();
+            EmbeddedEnd { span } => {
+                self.close_node(span.end);
             }
 
-            Name { content, .. } => {
+            Name { content, span } => {
                 let current = self.current();
-                if let NodeKind::Element { name, .. } | NodeKind::Directive { name, .. } =
-                    &mut self.nodes[current.index()].kind
-                {
-                    *name = bytes_to_cow(&content);
+                match &mut self.nodes[current.index()].kind {
+                    NodeKind::Element { name, .. } | NodeKind::Directive { name, .. } => {
+                        *name = bytes_to_cow(&content);
+                    }
+                    // The language after a freeform fence
+                    NodeKind::Raw { lang, .. } if !content.is_empty() => {
+                        *lang = Some(bytes_to_cow(&content));
+                    }
+                    _ => {}
                 }
+                self.extend(current, span.end as u32);
             }
 
             // ---- Attributes ----
-            Attr { content, .. } => {
-                let name = bytes_to_cow(&content);
-                // Check for special id/class attributes
-                if name == "id" || name == "class" {
-                    self.current_attr = Some(name);
-                } else {
-                    self.current_attr = Some(name);
-                }
+            Attr { content, span } => {
+                // id/class are intercepted when their BareValue arrives
+                self.current_attr = Some((bytes_to_cow(&content), Span::new(span.start, span.end)));
             }
 
             // ---- Values ----
-            StringValue { content, .. } => {
-                self.add_value(Value::String(bytes_to_cow(&content)));
+            StringValue { content, span } => {
+                // Closing quote matches whichever opening quote precedes the content
+                let quote = &self.input[span.start.saturating_sub(1)..span.start];
+                let value_span = self.widen(&span, &[b"\"", b"'"], quote);
+                // A quoted `:id` sets the id, for ids that brackets cannot hold
+                if self.current_attr.as_ref().is_some_and(|(name, _)| name == "id") {
+                    let current = self.current();
+                    if let NodeKind::Element { id, .. } = &mut self.nodes[current.index()].kind {
+                        *id = Some(bytes_to_cow(&content));
+                        self.current_attr = None;
+                        self.extend(current, value_span.end);
+                        return;
+                    }
+                }
+                self.add_value(Value::String(bytes_to_cow(&content)), value_span);
             }
-            BareValue { content, .. } => {
+            BareValue { content, span } => {
                 let s = bytes_to_cow(&content);
                 // Check if this is for id or class
-                if let Some(attr_name) = &self.current_attr {
-                    if attr_name == "id" {
-                        let current = self.current();
-                        if let NodeKind::Element { id, .. } = &mut self.nodes[current.index()].kind {
-                            *id = Some(s.clone());
-                        }
-                        self.current_attr = None;
-                        return;
-                    } else if attr_name == "class" {
+                if let Some((attr_name, _)) = &self.current_attr {
+                    if attr_name == "id" || attr_name == "class" {
+                        let is_id = attr_name == "id";
                         let current = self.current();
-                        if let NodeKind::Element { classes, .. } = &mut self.nodes[current.index()].kind {
-                            classes.push(s.clone());
+                        if let NodeKind::Element { id, classes, .. } = &mut self.nodes[current.index()].kind {
+                            if is_id {
+                                *id = Some(s);
+                            } else {
+                                classes.push(s);
+                            }
+                            self.current_attr = None;
+                            // Cover the closing `]` of an id
+                            let end = if is_id && self.input.get(span.end) == Some(&b']') {
+                                span.end + 1
+                            } else {
+                                span.end
+                            };
+                            self.extend(current, end as u32);
+                            return;
                         }
-                        self.current_attr = None;
-                        return;
                     }
                 }
-                self.add_value(Value::Bare(s));
+                self.add_value(Value::Bare(s), Span::new(span.start, span.end));
             }
-            Integer { content, .. } => {
-                self.add_value(Value::Integer(bytes_to_cow(&content)));
+            Integer { content, span } => {
+                self.add_value(Value::Integer(bytes_to_cow(&content)), Span::new(span.start, span.end));
             }
-            Float { content, .. } => {
-                self.add_value(Value::Float(bytes_to_cow(&content)));
+            Float { content, span } => {
+                self.add_value(Value::Float(bytes_to_cow(&content)), Span::new(span.start, span.end));
             }
-            Rational { content, .. } => {
-                self.add_value(Value::Rational(bytes_to_cow(&content)));
+            Rational { content, span } => {
+                self.add_value(Value::Rational(bytes_to_cow(&content)), Span::new(span.start, span.end));
             }
-            Complex { content, .. } => {
-                self.add_value(Value::Complex(bytes_to_cow(&content)));
+            Complex { content, span } => {
+                self.add_value(Value::Complex(bytes_to_cow(&content)), Span::new(span.start, span.end));
             }
-            Date { content, .. } => {
-                self.add_value(Value::Date(bytes_to_cow(&content)));
+            Date { content, span } => {
+                self.add_value(Value::Date(bytes_to_cow(&content)), Span::new(span.start, span.end));
             }
-            Time { content, .. } => {
-                self.add_value(Value::Time(bytes_to_cow(&content)));
+            Time { content, span } => {
+                self.add_value(Value::Time(bytes_to_cow(&content)), Span::new(span.start, span.end));
             }
-            DateTime { content, .. } => {
-                self.add_value(Value::DateTime(bytes_to_cow(&content)));
+            DateTime { content, span } => {
+                self.add_value(Value::DateTime(bytes_to_cow(&content)), Span::new(span.start, span.end));
             }
-            Duration { content, .. } => {
-                self.add_value(Value::Duration(bytes_to_cow(&content)));
+            Duration { content, span } => {
+                self.add_value(Value::Duration(bytes_to_cow(&content)), Span::new(span.start, span.end));
             }
-            RelativeTime { content, .. } => {
-                self.add_value(Value::RelativeTime(bytes_to_cow(&content)));
+            RelativeTime { content, span } => {
+                self.add_value(Value::RelativeTime(bytes_to_cow(&content)), Span::new(span.start, span.end));
             }
-            BoolTrue { .. } => {
-                self.add_value(Value::BoolTrue);
+            BoolTrue { span, .. } => {
+                self.add_value(Value::BoolTrue, Span::new(span.start, span.end));
             }
-            BoolFalse { .. } => {
-                self.add_value(Value::BoolFalse);
+            BoolFalse { span, .. } => {
+                self.add_value(Value::BoolFalse, Span::new(span.start, span.end));
             }
-            Nil { .. } => {
-                self.add_value(Value::Nil);
+            Nil { span, .. } => {
+                self.add_value(Value::Nil, Span::new(span.start, span.end));
             }
 
             // ---- Arrays ----
-            ArrayStart { .. } => {
-                self.array_stack.push(Vec::new());
+            ArrayStart { span } => {
+                self.array_stack.push((Vec::new(), span.start as u32));
             }
-            ArrayEnd { .. } => {
-                if let Some(items) = self.array_stack.pop() {
-                    self.add_value(Value::Array(items));
+            ArrayEnd { span } => {
+                if let Some((items, start)) = self.array_stack.pop() {
+                    self.add_value(Value::Array(items), Span::new(start as usize, span.end));
                 }
             }
 
             // ---- Text ----
-            Text { content, .. } => {
-                self.push_node(NodeKind::Text(bytes_to_cow(&content)));
+            // The parser emits empty text around some inline values; the
+            // writer has nothing to write for it, so keep no node
+            Text { content, span } if content.is_empty() => {
+                let current = self.current();
+                if current.index() != 0 {
+                    self.extend(current, span.end as u32);
+                }
+            }
+            Text { content, span } => {
+                self.push_node(NodeKind::Text(bytes_to_cow(&content)), Span::new(span.start, span.end));
             }
 
             // ---- Comments ----
             // Comments emit Text events between Start/End, which become the content
-            CommentStart { .. } => {
-                let id = self.push_node(NodeKind::Comment(Cow::Borrowed("")));
-                self.stack.push(id);
+            CommentStart { span } => {
+                self.open_node(NodeKind::Comment(Cow::Borrowed("")), span.start, &[b";{", b";"]);
             }
-            CommentEnd { .. } => {
-                self.stack.pop();
+            CommentEnd { span } => {
+                self.close_node(span.end);
             }
 
             // ---- Directives ----
-            DirectiveStart { .. } => {
-                let id = self.push_node(NodeKind::Directive {
+            DirectiveStart { span } => {
+                let kind = NodeKind::Directive {
                     name: Cow::Borrowed(""),
                     attrs: Vec::new(),
-                });
-                self.stack.push(id);
+                };
+                self.open_node(kind, span.start, &[b"!{:", b"!{", b"!"]);
             }
-            DirectiveEnd { .. } => {
-                self.stack.pop();
+            DirectiveEnd { span } => {
+                self.close_node(span.end);
             }
 
             // ---- Interpolation ----
-            Interpolation { content, .. } => {
-                self.push_node(NodeKind::Interpolation(bytes_to_cow(&content)));
+            Interpolation { content, span } => {
+                let node_span = self.widen(&span, &[b"!{{"], b"}}");
+                self.push_node(NodeKind::Interpolation(bytes_to_cow(&content)), node_span);
             }
 
             // ---- References ----
-            Reference { content, .. } => {
-                self.push_node(NodeKind::Reference(bytes_to_cow(&content)));
+            Reference { content, span } => {
+                let node_span = self.widen(&span, &[b"@[", b":["], b"]");
+                self.push_node(NodeKind::Reference(bytes_to_cow(&content)), node_span);
             }
 
             // ---- Raw/Freeform ----
-            FreeformStart { .. } => {
-                let id = self.push_node(NodeKind::Raw {
+            FreeformStart { span } => {
+                let kind = NodeKind::Raw {
                     lang: None,
                     content: Cow::Borrowed(""),
-                });
-                self.stack.push(id);
+                };
+                self.open_node(kind, span.start, &[b"```"]);
             }
-            FreeformEnd { .. } => {
-                self.stack.pop();
+            FreeformEnd { span } => {
+                self.close_node(span.end);
             }
-            RawContent { content, .. } | Raw { content, .. } => {
+            RawContent { content, span } | Raw { content, span } => {
                 // Raw content might be a child of freeform, or standalone
                 let current = self.current();
                 if let NodeKind::Raw { content: c, .. } = &mut self.nodes[current.index()].kind {
                     *c = bytes_to_cow(&content);
+                    self.extend(current, span.end as u32);
                 } else {
-                    // Standalone raw block
-                    self.push_node(NodeKind::Raw {
-                        lang: None,
+                    // Standalone raw block; in a `!:lang:` directive, the
+                    // directive's name is its language
+                    let lang = match &self.nodes[current.index()].kind {
+                        NodeKind::Directive { name, attrs } if !name.is_empty() && attrs.is_empty() => {
+                            Some(name.clone())
+                        }
+                        _ => None,
+                    };
+                    let kind = NodeKind::Raw {
+                        lang,
                         content: bytes_to_cow(&content),
-                    });
+                    };
+                    self.push_node(kind, Span::new(span.start, span.end));
                 }
             }
 
@@ -656,22 +1200,28 @@ impl<'a> TreeBuilder<'a> {
         }
     }
 
-    fn add_value(&mut self, value: Value<'a>) {
+    fn add_value(&mut self, value: Value<'a>, span: Span) {
         // If we're in an array context, add to the array
-        if let Some(arr) = self.array_stack.last_mut() {
+        if let Some((arr, _)) = self.array_stack.last_mut() {
             arr.push(value);
             return;
         }
 
         // Otherwise add as attribute
-        if let Some(attr_name) = self.current_attr.take() {
+        if let Some((attr_name, name_span)) = self.current_attr.take() {
             let current = self.current();
             match &mut self.nodes[current.index()].kind {
                 NodeKind::Element { attrs, .. } | NodeKind::Directive { attrs, .. } => {
-                    attrs.push(Attribute { name: attr_name, value });
+                    attrs.push(Attribute {
+                        name: attr_name,
+                        value,
+                        name_span,
+                        value_span: span,
+                    });
                 }
                 _ => {}
             }
+            self.extend(current, span.end.max(name_span.end));
         }
     }
 
@@ -679,6 +1229,9 @@ impl<'a> TreeBuilder<'a> {
         Document {
             nodes: self.nodes,
             root: NodeId::new(0),
+            source: Cow::Borrowed(self.input),
+            included: Vec::new(),
+            ids: OnceLock::new(),
         }
     }
 }
@@ -709,6 +1262,23 @@ fn bytes_to_cow<'a>(bytes: &std::borrow::Cow<'a, [u8]>) -> Cow<'a, str> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_reader() {
+        let doc = Document::parse_reader(&b"|div[a] Hello\n"[..]).unwrap();
+        let text = doc.get_by_id("a").unwrap().first_child().unwrap();
+        assert!(matches!(text.kind(), NodeKind::Text(s) if s == "Hello"));
+        assert!(matches!(Document::parse_reader(&b"|a :s \"open\n"[..]), Err(ReadError::Parse(_))));
+
+        struct Failing;
+        impl std::io::Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("disconnected"))
+            }
+        }
+        let err = Document::parse_reader(Failing).unwrap_err();
+        assert_eq!(err.to_string(), "read failed: disconnected");
+    }
+
     #[test]
     fn test_simple_element() {
         let doc = Document::parse(b"|div\n").unwrap();
@@ -733,6 +1303,39 @@ mod tests {
         assert!(el.has_class("class2"));
     }
 
+    #[test]
+    fn test_ids_follow_mutations() {
+        let mut doc = Document::parse(b"|a[x]\n  |b[y]\n|c[z]\n|d[z]\n").unwrap();
+        let name = |doc: &Document, id| doc.get_by_id(id).map(|n| n.as_element().unwrap().name().to_string());
+        let top: Vec<_> = doc.root().children().map(|n| n.id()).collect();
+        let (a, c) = (top[0], top[1]);
+        assert_eq!(name(&doc, "z").as_deref(), Some("c"));
+
+        // Removed elements no longer resolve; the next one with the id does
+        doc.detach(c);
+        assert_eq!(name(&doc, "z").as_deref(), Some("d"));
+
+        // An appended element counts from its place in the document
+        let kind = |name: &'static str, id: &'static str| NodeKind::Element {
+            name: name.into(),
+            id: Some(id.into()),
+            classes: Vec::new(),
+            attrs: Vec::new(),
+            embedded: false,
+        };
+        doc.append_child(a, kind("e", "z"));
+        assert_eq!(name(&doc, "z").as_deref(), Some("e"));
+
+        // Unwrapped elements are gone; their children stay
+        doc.unwrap_node(a);
+        assert_eq!(name(&doc, "x"), None);
+        assert_eq!(name(&doc, "y").as_deref(), Some("b"));
+
+        *doc.kind_mut(top[2]) = kind("f", "w");
+        assert_eq!(name(&doc, "w").as_deref(), Some("f"));
+        assert_eq!(name(&doc, "z").as_deref(), Some("e"));
+    }
+
     #[test]
     fn test_nested_elements() {
         let doc = Document::parse(b"|parent\n  |child\n").unwrap();
@@ -801,4 +1404,95 @@ mod tests {
         assert!(text.contains("Hello"));
         assert!(text.contains("world"));
     }
+
+    #[test]
+    fn test_node_spans() {
+        let input = b"|a[x].c :k v\n  |b Hello |{em world}\n|c\n";
+        let doc = Document::parse(input).unwrap();
+        let a = doc.root().first_child().unwrap();
+        let b = a.first_child().unwrap();
+        let em = b.children().find(|n| n.is_element()).unwrap();
+        let c = a.next_sibling().unwrap();
+
+        assert_eq!(doc.source_text(a), "|a[x].c :k v\n  |b Hello |{em world}");
+        assert_eq!(doc.source_text(b), "|b Hello |{em world}");
+        assert_eq!(doc.source_text(em), "|{em world}");
+        assert_eq!(doc.source_text(c), "|c");
+        assert_eq!(doc.source_text(b.first_child().unwrap()), "Hello ");
+        assert_eq!(doc.root().span(), Span::new(0, input.len()));
+    }
+
+    #[test]
+    fn test_attribute_spans() {
+        let doc = Document::parse(b"|el :name \"quoted\" :n 42 :tags [a b]\n").unwrap();
+        let el = doc.root().first_child().unwrap().as_element().unwrap();
+
+        let name = el.attribute("name").unwrap();
+        assert_eq!(doc.span_text(name.name_span), "name");
+        assert_eq!(doc.span_text(name.value_span), "\"quoted\"");
+        assert_eq!(doc.span_text(el.attribute("n").unwrap().value_span), "42");
+        assert_eq!(doc.span_text(el.attribute("tags").unwrap().value_span), "[a b]");
+    }
+
+    #[test]
+    fn test_owned_documents() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Document<'static>>();
+
+        let input = b"|a[x] :n 42 :tags [p q]\n  Hello\n".to_vec();
+        let borrowed = Document::parse(&input).unwrap().into_owned();
+        let parsed = Document::parse_owned(input).unwrap();
+
+        let shared = std::sync::Arc::new(parsed);
+        let handle = {
+            let shared = shared.clone();
+            std::thread::spawn(move || shared.get_by_id("x").unwrap().all_text())
+        };
+        assert_eq!(handle.join().unwrap(), "Hello");
+
+        for doc in [&borrowed, &*shared] {
+            let el = doc.get_by_id("x").unwrap().as_element().unwrap();
+            assert!(matches!(el.attr("n"), Some(Value::Integer(n)) if n == "42"));
+            assert!(matches!(el.attr("tags"), Some(Value::Array(items)) if items.len() == 2));
+            assert_eq!(doc.span_text(el.attribute("tags").unwrap().value_span), "[p q]");
+        }
+    }
+
+    #[test]
+    fn test_parse_with_diagnostics() {
+        use crate::diagnostic::{Code, Severity};
+
+        let input = b"|a\n\t:x 1\n|p\n    first line\n  second\n|b :list [1 2\n";
+        assert!(Document::parse(input).is_err());
+
+        let (doc, diagnostics) = Document::parse_with_diagnostics(input);
+        let codes: Vec<_> = diagnostics.iter().map(|d| (d.severity, d.code)).collect();
+        assert_eq!(
+            codes,
+            [
+                (Severity::Error, Code::Parse(ParseErrorCode::NoTabs)),
+                (Severity::Warning, Code::InconsistentIndentation),
+                (Severity::Error, Code::Parse(ParseErrorCode::UnclosedArray)),
+            ]
+        );
+        assert_eq!(diagnostics[0].span, Span::new(3, 3));
+        assert_eq!(diagnostics[0].message, "tabs are not allowed for indentation");
+        // The tree keeps what was recovered around the errors
+        let names: Vec<_> = doc.root().children().filter_map(|n| n.as_element().map(|e| e.name())).collect();
+        assert_eq!(names, ["a", "p", "b"]);
+
+        let (_, diagnostics) = Document::parse_with_diagnostics(b"|a :x 1\n");
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_dynamic_node_spans() {
+        let doc = Document::parse(b"|p Hi !{{user.name}}\n  @[footer]\n  ; note\n").unwrap();
+        let p = doc.root().first_child().unwrap();
+        let texts: Vec<_> = p.children().map(|n| doc.source_text(n).into_owned()).collect();
+
+        assert!(texts.contains(&"!{{user.name}}".to_string()), "{:?}", texts);
+        assert!(texts.contains(&"@[footer]".to_string()), "{:?}", texts);
+        assert!(texts.contains(&"; note".to_string()), "{:?}", texts);
+    }
 }
@@ -0,0 +1,747 @@
+//! UDON writer: serialize a `tree::Document` back to UDON text.
+//!
+//! The output re-parses to the same tree (up to source spans). Layout is
+//! normalized: attributes go on their own indented lines, children are
+//! indented by a fixed width, and inline runs (text, embedded elements,
+//! interpolations, brace comments) stay on one line.
+//!
+//! # Example
+//!
+//! ```
+//! use udon_core::tree::Document;
+//! use udon_core::to_udon_string;
+//!
+//! let doc = Document::parse(b"|server :host localhost :port 8080\n").unwrap();
+//! assert_eq!(to_udon_string(&doc), "|server\n  :host localhost\n  :port 8080\n");
+//! ```
+
+use crate::parser::{Event, Parser};
+use crate::reference::ReferenceKind;
+use crate::tree::{Document, Node, NodeKind, Value};
+
+/// Serialize a document to UDON text with default settings.
+pub fn to_udon_string(doc: &Document<'_>) -> String {
+    Writer::new().write(doc)
+}
+
+/// Configurable UDON writer.
+#[derive(Debug, Clone)]
+pub struct Writer {
+    indent: usize,
+}
+
+impl Default for Writer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Where a value is written, which decides what a bare value may contain.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Context {
+    /// Block attribute line: `:key value` to end of line.
+    Block,
+    /// Sameline attribute inside `|{...}`.
+    Embedded,
+    /// Array item inside `[...]`.
+    Array,
+}
+
+/// A line of inline content or a block-level node, in child order.
+enum Item<'doc, 'a> {
+    Line(String),
+    Block(Node<'doc, 'a>),
+}
+
+impl Writer {
+    /// Create a writer with two-space indentation.
+    pub fn new() -> Self {
+        Writer { indent: 2 }
+    }
+
+    /// Set the number of spaces per nesting level.
+    pub fn indent(mut self, width: usize) -> Self {
+        self.indent = width.max(1);
+        self
+    }
+
+    /// Serialize a whole document.
+    pub fn write(&self, doc: &Document<'_>) -> String {
+        let mut out = String::new();
+        self.write_items(doc.root(), 0, &mut out);
+        out
+    }
+
+    /// Serialize a single node (and its subtree) at the top level.
+    pub fn write_node(&self, node: Node<'_, '_>, out: &mut String) {
+        match node.kind() {
+            NodeKind::Document => self.write_items(node, 0, out),
+            NodeKind::Text(_)
+            | NodeKind::Interpolation(_)
+            | NodeKind::Element { embedded: true, .. } => {
+                let mut line = String::new();
+                self.write_inline(node, &mut line);
+                self.push_line(out, 0, &escape_line_start(&line));
+            }
+            _ => self.write_block(node, 0, out),
+        }
+    }
+
+    fn push_line(&self, out: &mut String, depth: usize, line: &str) {
+        out.extend(std::iter::repeat_n(' ', depth * self.indent));
+        out.push_str(line);
+        out.push('\n');
+    }
+
+    // ========== Block layout ==========
+
+    /// Group children into inline lines and block nodes.
+    ///
+    /// Consecutive text nodes came from separate lines, so each starts a new
+    /// line; inline nodes and comments following inline content continue the
+    /// current line.
+    fn layout<'doc, 'a>(&self, node: Node<'doc, 'a>) -> Vec<Item<'doc, 'a>> {
+        let mut items = Vec::new();
+        let mut line: Option<String> = None;
+        let mut prev_text = false;
+
+        for child in node.children() {
+            match child.kind() {
+                NodeKind::Text(s) => {
+                    let mut parts = s.split('\n');
+                    let first = parts.next().unwrap_or("");
+                    match line.as_mut() {
+                        Some(l) if !prev_text => l.push_str(first),
+                        _ => {
+                            items.extend(line.take().map(Item::Line));
+                            line = Some(escape_line_start(first));
+                        }
+                    }
+                    for part in parts {
+                        items.extend(line.take().map(Item::Line));
+                        line = Some(escape_line_start(part));
+                    }
+                }
+                _ if is_inline(child) => {
+                    self.write_inline(child, line.get_or_insert_with(String::new));
+                }
+                NodeKind::Comment(_) if line.is_some() => {
+                    self.write_inline(child, line.as_mut().unwrap());
+                }
+                _ => {
+                    items.extend(line.take().map(Item::Line));
+                    items.push(Item::Block(child));
+                }
+            }
+            prev_text = child.is_text();
+        }
+        items.extend(line.map(Item::Line));
+        items
+    }
+
+    fn write_items(&self, node: Node<'_, '_>, depth: usize, out: &mut String) {
+        for item in self.layout(node) {
+            match item {
+                Item::Line(line) => self.push_line(out, depth, &line),
+                Item::Block(child) => self.write_block(child, depth, out),
+            }
+        }
+    }
+
+    fn write_block(&self, node: Node<'_, '_>, depth: usize, out: &mut String) {
+        match node.kind() {
+            NodeKind::Element { .. } => self.write_element(node, depth, out),
+            // A line starting with `!{` is wrapped in an anonymous directive
+            NodeKind::Directive { name, .. } if name.is_empty() => {
+                let mut line = String::new();
+                for child in node.children() {
+                    self.write_inline(child, &mut line);
+                }
+                self.push_line(out, depth, &line);
+            }
+            NodeKind::Directive { name, attrs } if attrs.is_empty() && is_raw_directive(node) => {
+                // `!:lang:` keeps the rest of its line and each body line as raw content
+                let mut contents = node.children().filter_map(|child| match child.kind() {
+                    NodeKind::Raw { content, .. } => Some(content.as_ref()),
+                    _ => None,
+                });
+                let first = contents.next().unwrap_or("");
+                self.push_line(out, depth, &format!("!:{}:{}", name, first));
+                for content in contents {
+                    self.push_line(out, depth + 1, content);
+                }
+            }
+            NodeKind::Directive { name, attrs } => {
+                let mut header = String::from("!");
+                header.push_str(name);
+                for attr in attrs {
+                    header.push(':');
+                    write_label(&attr.name, &mut header);
+                    if !matches!(attr.value, Value::BoolTrue) {
+                        header.push(' ');
+                        write_value(&attr.value, Context::Embedded, &mut header);
+                    }
+                }
+                // The argument line arrives as the directive's first text child
+                let mut items = self.layout(node).into_iter().peekable();
+                if let Some(Item::Line(args)) = items.peek() {
+                    header.push(' ');
+                    header.push_str(args.strip_prefix('\'').unwrap_or(args));
+                    items.next();
+                }
+                self.push_line(out, depth, &header);
+                for item in items {
+                    match item {
+                        Item::Line(line) => self.push_line(out, depth + 1, &line),
+                        Item::Block(child) => self.write_block(child, depth + 1, out),
+                    }
+                }
+            }
+            NodeKind::Comment(content) => {
+                let text = content_text(node, content);
+                let mut lines = text.split('\n');
+                self.push_line(out, depth, &format!(";{}", lines.next().unwrap_or("")));
+                for line in lines {
+                    self.push_line(out, depth + 1, line);
+                }
+            }
+            NodeKind::Reference(id) => {
+                let sigil = match node.reference_kind() {
+                    Some(ReferenceKind::Attributes) => ':',
+                    _ => '@',
+                };
+                self.push_line(out, depth, &format!("{}[{}]", sigil, id));
+            }
+            NodeKind::Raw { lang, content } => {
+                let fence = format!("```{}", lang.as_deref().unwrap_or(""));
+                self.push_line(out, depth, &fence);
+                // Freeform content is verbatim, including its own indentation
+                if content.is_empty() {
+                    for child in node.children() {
+                        if let Some(text) = child.text_content() {
+                            out.push_str(text);
+                            out.push('\n');
+                        }
+                    }
+                } else {
+                    out.push_str(content);
+                    if !content.ends_with('\n') {
+                        out.push('\n');
+                    }
+                }
+                self.push_line(out, depth, "```");
+            }
+            NodeKind::Document => self.write_items(node, depth, out),
+            NodeKind::Text(_) | NodeKind::Interpolation(_) => {
+                let mut line = String::new();
+                self.write_inline(node, &mut line);
+                self.push_line(out, depth, &escape_line_start(&line));
+            }
+        }
+    }
+
+    fn write_element(&self, node: Node<'_, '_>, depth: usize, out: &mut String) {
+        let el = node.as_element().unwrap();
+        if el.is_embedded() {
+            let mut line = String::new();
+            self.write_inline(node, &mut line);
+            self.push_line(out, depth, &line);
+            return;
+        }
+
+        let mut header = String::from("|");
+        write_identity(node, &mut header);
+
+        let quoted_id = el.id().filter(|id| !is_bracket_id(id)).map(|id| Value::String(id.into()));
+        let attrs: Vec<_> = quoted_id
+            .iter()
+            .map(|id| ("id", id))
+            .chain(el.attrs().filter(|(name, _)| !is_suffix(name)))
+            .collect();
+        let items = self.layout(node);
+
+        // Leading text can stay on the element line when it cannot be
+        // mistaken for an attribute, comment or nested element there. The
+        // next line sets the content base then, so extra indentation on it
+        // would be lost.
+        let indented = matches!(items.get(1), Some(Item::Line(line)) if line.starts_with([' ', '\t']));
+        let mut items = items.into_iter().peekable();
+        if attrs.is_empty() && !indented {
+            if let Some(Item::Line(line)) = items.peek() {
+                if is_sameline_safe(line) {
+                    header.push(' ');
+                    header.push_str(line);
+                    items.next();
+                }
+            }
+        }
+        self.push_line(out, depth, &header);
+
+        for (name, value) in attrs {
+            let mut line = String::from(":");
+            write_label(name, &mut line);
+            line.push(' ');
+            write_value(value, Context::Block, &mut line);
+            self.push_line(out, depth + 1, &line);
+        }
+        for item in items {
+            match item {
+                Item::Line(line) => self.push_line(out, depth + 1, &line),
+                Item::Block(child) => self.write_block(child, depth + 1, out),
+            }
+        }
+    }
+
+    // ========== Inline content ==========
+
+    fn write_inline(&self, node: Node<'_, '_>, out: &mut String) {
+        match node.kind() {
+            NodeKind::Text(s) => out.push_str(s),
+            NodeKind::Interpolation(expr) => {
+                out.push_str("!{{");
+                out.push_str(expr);
+                out.push_str("}}");
+            }
+            NodeKind::Element { .. } => {
+                out.push_str("|{");
+                write_identity(node, out);
+                let el = node.as_element().unwrap();
+                if let Some(id) = el.id().filter(|id| !is_bracket_id(id)) {
+                    out.push_str(" :id ");
+                    write_value(&Value::String(id.into()), Context::Embedded, out);
+                }
+                for (name, value) in el.attrs().filter(|(name, _)| !is_suffix(name)) {
+                    out.push_str(" :");
+                    write_label(name, out);
+                    out.push(' ');
+                    write_value(value, Context::Embedded, out);
+                }
+                let mut content = String::new();
+                for child in node.children() {
+                    self.write_inline(child, &mut content);
+                }
+                if !content.is_empty() {
+                    out.push(' ');
+                    out.push_str(&content);
+                }
+                out.push('}');
+            }
+            NodeKind::Comment(content) => {
+                out.push_str(";{");
+                out.push_str(&content_text(node, content));
+                out.push('}');
+            }
+            NodeKind::Directive { name, .. } if name.is_empty() => {
+                for child in node.children() {
+                    self.write_inline(child, out);
+                }
+            }
+            NodeKind::Directive { name, .. } => {
+                let raw = is_raw_directive(node);
+                out.push_str(if raw { "!{:" } else { "!{" });
+                out.push_str(name);
+                if raw {
+                    out.push(':');
+                }
+                for child in node.children() {
+                    match child.kind() {
+                        NodeKind::Raw { content, .. } => out.push_str(content),
+                        _ => self.write_inline(child, out),
+                    }
+                }
+                out.push('}');
+            }
+            NodeKind::Reference(id) => {
+                out.push_str("@[");
+                out.push_str(id);
+                out.push(']');
+            }
+            NodeKind::Raw { lang, content } => {
+                out.push_str("!{:");
+                out.push_str(lang.as_deref().unwrap_or(""));
+                out.push(':');
+                out.push_str(content);
+                out.push('}');
+            }
+            NodeKind::Document => {
+                for child in node.children() {
+                    self.write_inline(child, out);
+                }
+            }
+        }
+    }
+}
+
+// ========== Helpers ==========
+
+/// Element suffix flags are stored as attributes named `?`, `!`, `*`, `+`.
+fn is_suffix(name: &str) -> bool {
+    matches!(name, "?" | "!" | "*" | "+")
+}
+
+/// Write `name[id].class...` followed by any suffix flags.
+///
+/// An id that cannot be written between brackets is left out; callers
+/// write it as a quoted `:id` attribute instead.
+fn write_identity(node: Node<'_, '_>, out: &mut String) {
+    let el = node.as_element().unwrap();
+    let name = el.name();
+    let mut anonymous = true;
+    if !name.is_empty() {
+        write_label(name, out);
+        anonymous = false;
+    }
+    if let Some(id) = el.id().filter(|id| is_bracket_id(id)) {
+        out.push('[');
+        out.push_str(id);
+        out.push(']');
+        anonymous = false;
+    }
+    for class in el.classes() {
+        out.push('.');
+        write_label(class, out);
+        anonymous = false;
+    }
+    for (name, value) in el.attrs() {
+        if is_suffix(name) && matches!(value, Value::BoolTrue) {
+            out.push_str(name);
+            anonymous = false;
+        }
+    }
+    if anonymous {
+        out.push_str("''");
+    }
+}
+
+/// Write an element name, class or attribute key, quoting it if it is not a plain label.
+pub(crate) fn write_label(name: &str, out: &mut String) {
+    if is_label(name) {
+        out.push_str(name);
+    } else {
+        write_single_quoted(name, out);
+    }
+}
+
+/// Ids in brackets run to `]` or a space, with no escapes.
+fn is_bracket_id(id: &str) -> bool {
+    !id.is_empty() && !id.contains(|c: char| c == ']' || c.is_whitespace())
+}
+
+/// Labels are XID_Start followed by XID_Continue or `-`.
+fn is_label(s: &str) -> bool {
+    use unicode_xid::UnicodeXID;
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_xid_start() => chars.all(|c| c == '-' || c.is_xid_continue()),
+        _ => false,
+    }
+}
+
+fn write_single_quoted(s: &str, out: &mut String) {
+    out.push('\'');
+    for c in s.chars() {
+        if c == '\'' || c == '\\' {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('\'');
+}
+
+/// Nodes written within a line rather than on their own.
+fn is_inline(node: Node<'_, '_>) -> bool {
+    match node.kind() {
+        NodeKind::Element { embedded, .. } => *embedded,
+        NodeKind::Interpolation(_) => true,
+        // Brace comments are only distinguishable by their source
+        NodeKind::Comment(_) => {
+            node.source().get(node.span().start as usize..).is_some_and(|s| s.starts_with(b";{"))
+        }
+        NodeKind::Directive { name, attrs } => {
+            !name.is_empty()
+                && attrs.is_empty()
+                && node.children().count() == 1
+                && is_raw_directive(node)
+                && !matches!(node.first_child().map(|c| c.kind()), Some(NodeKind::Raw { content, .. }) if content.contains('\n'))
+        }
+        _ => false,
+    }
+}
+
+/// Raw directives (`!:lang:` / `!{:lang:...}`) hold only raw nodes in the
+/// directive's language (or none, when built in code).
+pub(crate) fn is_raw_directive(node: Node<'_, '_>) -> bool {
+    let NodeKind::Directive { name, .. } = node.kind() else {
+        return false;
+    };
+    node.first_child().is_some()
+        && node.children().all(|child| match child.kind() {
+            NodeKind::Raw { lang, .. } => lang.as_ref().is_none_or(|lang| lang == name),
+            _ => false,
+        })
+}
+
+/// Escape prose that would otherwise be read as markup at the start of a line.
+fn escape_line_start(text: &str) -> String {
+    match text.as_bytes().first() {
+        Some(b'|' | b';' | b':' | b'!' | b'\'') => format!("'{}", text),
+        _ => text.to_string(),
+    }
+}
+
+/// Sameline text after an element name treats `;` as a comment and `|x` as
+/// a nested element, so only plain text and brace forms stay on that line.
+fn is_sameline_safe(line: &str) -> bool {
+    let bytes = line.as_bytes();
+    match bytes.first() {
+        None | Some(b' ' | b'\t' | b':' | b'\'') => return false,
+        Some(b'!') if bytes.get(1) != Some(&b'{') => return false,
+        _ => {}
+    }
+    let mut i = 0;
+    while i < bytes.len() {
+        // Interpolation expressions may contain filter pipes
+        if bytes[i..].starts_with(b"!{{") {
+            match line[i..].find("}}") {
+                Some(end) => i += end + 2,
+                None => return false,
+            }
+            continue;
+        }
+        if matches!(bytes[i], b'|' | b';') && bytes.get(i + 1) != Some(&b'{') {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Comment or freeform text comes from its content or, when parsed, its
+/// text children.
+pub(crate) fn content_text(node: Node<'_, '_>, content: &str) -> String {
+    if !content.is_empty() {
+        return content.to_string();
+    }
+    node.children()
+        .filter_map(|child| child.text_content().map(str::to_string))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub(crate) fn write_value(value: &Value<'_>, ctx: Context, out: &mut String) {
+    match value {
+        Value::String(s) => write_double_quoted(s, out),
+        Value::Bare(s) => {
+            if bare_survives(s, ctx) {
+                out.push_str(s);
+            } else {
+                write_double_quoted(s, out);
+            }
+        }
+        Value::Integer(s)
+        | Value::Float(s)
+        | Value::Rational(s)
+        | Value::Complex(s)
+        | Value::Date(s)
+        | Value::Time(s)
+        | Value::DateTime(s)
+        | Value::Duration(s)
+        | Value::RelativeTime(s) => out.push_str(s),
+        Value::BoolTrue => out.push_str("true"),
+        Value::BoolFalse => out.push_str("false"),
+        Value::Nil => out.push_str("null"),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(' ');
+                }
+                write_value(item, Context::Array, out);
+            }
+            out.push(']');
+        }
+    }
+}
+
+/// Write a double-quoted string. Existing backslash escapes are kept as-is
+/// (parsed strings hold their raw source text); bare `"` are escaped.
+fn write_double_quoted(s: &str, out: &mut String) {
+    out.push('"');
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                out.push('\\');
+                if let Some(next) = chars.next() {
+                    out.push(next);
+                }
+            }
+            '"' => out.push_str("\\\""),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Check that `text` written unquoted parses back as the same bare value.
+///
+/// Runs the real parser on a probe document so the decision always agrees
+/// with the grammar's typed-value and terminator rules.
+fn bare_survives(text: &str, ctx: Context) -> bool {
+    if text.is_empty() || text.contains('\n') {
+        return false;
+    }
+    let probe = match ctx {
+        Context::Block => format!("|x\n  :v {}\n", text),
+        Context::Embedded => format!("|{{x :v {}}}\n", text),
+        Context::Array => format!("|x :v [{}]\n", text),
+    };
+    let mut values = Vec::new();
+    Parser::new(probe.as_bytes()).parse(|event| match event {
+        Event::ElementStart { .. }
+        | Event::ElementEnd { .. }
+        | Event::EmbeddedStart { .. }
+        | Event::EmbeddedEnd { .. }
+        | Event::ArrayStart { .. }
+        | Event::ArrayEnd { .. }
+        | Event::Name { .. }
+        | Event::Attr { .. } => {}
+        Event::BareValue { content, .. } => values.push(Some(content.into_owned())),
+        _ => values.push(None),
+    });
+    matches!(values.as_slice(), [Some(content)] if content == text.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::Attribute;
+
+    /// Structural dump of a tree, ignoring spans.
+    fn shape(node: Node<'_, '_>, out: &mut String) {
+        match node.kind() {
+            NodeKind::Element { name, id, classes, attrs, embedded } => {
+                let attrs: Vec<_> = attrs.iter().map(|a| (a.name.as_ref(), &a.value)).collect();
+                out.push_str(&format!("E({:?} {:?} {:?} {:?} {})", name, id, classes, attrs, embedded));
+            }
+            kind => out.push_str(&format!("{:?}", kind)),
+        }
+        out.push('{');
+        for child in node.children() {
+            shape(child, out);
+        }
+        out.push('}');
+    }
+
+    fn assert_round_trip(input: &str) {
+        let doc = Document::parse(input.as_bytes()).unwrap();
+        let written = to_udon_string(&doc);
+        let reparsed = Document::parse(written.as_bytes()).unwrap();
+
+        let (mut before, mut after) = (String::new(), String::new());
+        shape(doc.root(), &mut before);
+        shape(reparsed.root(), &mut after);
+        assert_eq!(before, after, "input:\n{}\nwritten:\n{}", input, written);
+    }
+
+    #[test]
+    fn test_round_trip_elements() {
+        assert_round_trip("|a[main].x.y\n  |b\n    |c\n|d\n");
+        assert_round_trip("|field[id]! :type integer\n|field?\n");
+        assert_round_trip("|'odd name'.'odd class'\n");
+    }
+
+    #[test]
+    fn test_round_trip_values() {
+        assert_round_trip(
+            "|el\n  :s \"quoted \\\"x\\\"\"\n  :b two words\n  :i 0x1F\n  :f 3.5\n  :r 1/3r\n  :c 3+4i\n  :t true\n  :n null\n  :a [1 two \"three\" [4]]\n  :d 2025-12-22\n",
+        );
+    }
+
+    #[test]
+    fn test_round_trip_prose() {
+        assert_round_trip("|p |{code \"func() { return 1; }\"}");
+        assert_round_trip("|p Hello |{em big |{b world}} and !{{user.name}}.\n  Second line\n  'escaped |pipe\n");
+        assert_round_trip("|p\n  x ;{inline} y\n  ; block comment\n");
+        assert_round_trip("|el\n  ```\n  verbatim |not markup\n  ```\n");
+        assert_round_trip("|el\n  first\n    indented\n");
+    }
+
+    #[test]
+    fn test_round_trip_dynamics() {
+        assert_round_trip("|p !{{value | upcase}} and !{:json:{\"k\": 1}}.\n");
+        assert_round_trip("|parent\n  !{{first}}\n  !{{second}}\n");
+        assert_round_trip("!for item in items\n  |li !{{item}}\n");
+        assert_round_trip("!:sql:\n  SELECT * FROM users\n");
+        assert_round_trip("|a[x] :k 1\n|b :[x] :j 2\n  @[x]\n");
+    }
+
+    #[test]
+    fn test_bare_value_quoting() {
+        let mut doc = Document::new();
+        let root = doc.root().id();
+        let attrs = vec![
+            Attribute::new("plain", Value::Bare("hello".into())),
+            Attribute::new("numeric", Value::Bare("42".into())),
+            Attribute::new("keyword", Value::Bare("true".into())),
+            Attribute::new("spaced", Value::Bare("a b".into())),
+        ];
+        doc.append_child(root, NodeKind::Element {
+            name: "el".into(),
+            id: None,
+            classes: Vec::new(),
+            attrs,
+            embedded: false,
+        });
+
+        assert_eq!(
+            to_udon_string(&doc),
+            "|el\n  :plain hello\n  :numeric \"42\"\n  :keyword \"true\"\n  :spaced a b\n"
+        );
+    }
+
+    #[test]
+    fn test_escape_prose_prefix() {
+        let mut doc = Document::new();
+        let root = doc.root().id();
+        for text in ["|not an element", ";not a comment", ":not an attr", "!not a directive", "'quote"] {
+            doc.append_child(root, NodeKind::Text(text.into()));
+        }
+
+        let written = to_udon_string(&doc);
+        let reparsed = Document::parse(written.as_bytes()).unwrap();
+        let texts: Vec<_> = reparsed.root().children().filter_map(|n| n.text_content().map(String::from)).collect();
+        assert_eq!(texts, ["|not an element", ";not a comment", ":not an attr", "!not a directive", "'quote"]);
+    }
+
+    #[test]
+    fn test_quoted_ids() {
+        let mut doc = Document::new();
+        let root = doc.root().id();
+        for (id, embedded) in [("a b", false), ("x]y", false), ("", false), ("p q", true), ("plain", true)] {
+            doc.append_child(root, NodeKind::Element {
+                name: "el".into(),
+                id: Some(id.into()),
+                classes: vec!["c".into()],
+                attrs: vec![Attribute::new("k", Value::Bare("v".into()))],
+                embedded,
+            });
+        }
+
+        let written = to_udon_string(&doc);
+        assert!(written.starts_with("|el.c\n  :id \"a b\"\n  :k v\n"), "{}", written);
+        assert!(written.contains("|{el.c :id \"p q\" :k v}"), "{}", written);
+        assert!(written.contains("|{el[plain].c :k v}"), "{}", written);
+        let reparsed = Document::parse(written.as_bytes()).unwrap();
+        let elements: Vec<_> = reparsed.root().children().filter_map(|n| n.as_element()).collect();
+        let ids: Vec<_> = elements.iter().map(|el| el.id().unwrap()).collect();
+        assert_eq!(ids, ["a b", "x]y", "", "p q", "plain"]);
+        assert!(elements.iter().all(|el| el.attrs().count() == 1));
+    }
+
+    #[test]
+    fn test_embedded_inline() {
+        let doc = Document::parse(b"|p see |{a :href \"x y\" the link} here\n").unwrap();
+        assert_eq!(to_udon_string(&doc), "|p see |{a :href \"x y\" the link} here\n");
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //! Tests that the parser handles:
 //! 1. EOF at various positions in input
-//! 2. Input split across multiple chunks (when streaming is implemented)
+//! 2. Input split across multiple chunks
 //!
 //! These tests catch issues like:
 //! - Premature value emission (e.g., "true" emitting BoolTrue before seeing "-is-best")
@@ -12,7 +12,7 @@
 mod common;
 
 use common::{load_fixtures_by_name, Gen};
-use udon_core::{Parser, Event, StreamingParser, StreamEvent};
+use udon_core::{Control, Parser, Event, StreamingParser, StreamEvent};
 
 /// Collect events from parsing, returning formatted strings
 fn collect_events(input: &[u8]) -> Vec<String> {
@@ -338,22 +338,51 @@ fn stochastic_eof_on_fixtures() {
 }
 
 // =============================================================================
-// Chunk Boundary Tests (placeholder for when streaming is implemented)
+// Chunk Boundary Tests
 // =============================================================================
 
-/// Test that splitting input at chunk boundaries produces the same result
-/// for single-line inputs.
-///
-/// NOTE: StreamingParser uses line-oriented streaming, which means it parses
-/// complete lines independently. Multi-line elements (with child lines) will
-/// NOT produce identical results when split across line boundaries, because
-/// the parser doesn't track indentation state across parse calls.
-///
-/// For true cross-line streaming, the parser would need to track element stack
-/// state between chunks. This is a known limitation of line-oriented streaming.
+/// Event with its span, for comparisons that must also match source positions
+fn collect_events_with_spans(input: &[u8]) -> Vec<(String, std::ops::Range<usize>)> {
+    let mut events = Vec::new();
+    Parser::new(input).parse(|e| {
+        events.push((format_event(&e), e.span()));
+    });
+    events
+}
+
+/// Parse chunks with StreamingParser, keeping spans
+fn parse_multi_chunk_with_spans(chunks: &[&[u8]]) -> Vec<(String, std::ops::Range<usize>)> {
+    let mut events = Vec::new();
+    let mut parser = StreamingParser::new();
+
+    for chunk in chunks {
+        parser.parse(chunk, |event| {
+            events.push((format_stream_event(&event), event.span()));
+        });
+    }
+    parser.finish(|event| {
+        events.push((format_stream_event(&event), event.span()));
+    });
+
+    events
+}
+
+/// Split input into chunks at the given (sorted) positions
+fn split_at_positions<'a>(input: &'a [u8], positions: &[usize]) -> Vec<&'a [u8]> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    for &pos in positions {
+        chunks.push(&input[start..pos]);
+        start = pos;
+    }
+    chunks.push(&input[start..]);
+    chunks
+}
+
+/// Test that splitting input at chunk boundaries produces the same result,
+/// including for multi-line input where elements stay open across chunks.
 #[test]
 fn chunk_boundary_consistency() {
-    // Only test SINGLE-LINE inputs - multi-line requires stateful streaming
     let inputs = [
         b"|el :attr true-is-the-best\n".as_slice(),
         b"|el :count 42\n".as_slice(),
@@ -362,16 +391,23 @@ fn chunk_boundary_consistency() {
         b"|div[main].container\n".as_slice(),
         b"; comment line\n".as_slice(),
         b"|element\n".as_slice(),
+        b"|a\n  |b\n    |c\n".as_slice(),
+        b"|article[main].featured\n  :author Joseph\n  :count 42\n  Hello world\n".as_slice(),
+        b"|p\n  First line\n    indented more\n  back\n|q next\n".as_slice(),
+        b"|a\n\n  |b\n|c\n  text\n".as_slice(),
+        b"|el :tags [one\ntwo]\n|next\n".as_slice(),
+        b"|el\n  ```\n|not an element\n  ```\n|after\n".as_slice(),
+        b"; comment\n  continued\n|el\n".as_slice(),
     ];
 
     for full in inputs {
-        let full_events = collect_events(full);
+        let full_events = collect_events_with_spans(full);
 
         // Test splitting at every position
         for split_at in 1..full.len() {
             let chunk1 = &full[..split_at];
             let chunk2 = &full[split_at..];
-            let chunked_events = parse_multi_chunk(&[chunk1, chunk2]);
+            let chunked_events = parse_multi_chunk_with_spans(&[chunk1, chunk2]);
 
             if full_events != chunked_events {
                 panic!(
@@ -388,10 +424,224 @@ fn chunk_boundary_consistency() {
     }
 }
 
-/// Test multi-chunk parsing with more than 2 chunks (single-line input)
+/// Children in a later chunk stay nested under the element opened earlier
+#[test]
+fn nesting_survives_chunk_boundary() {
+    let events = parse_multi_chunk(&[b"|parent\n", b"  |child\n", b"  text\n", b"|sibling\n"]);
+
+    assert_eq!(
+        events,
+        vec![
+            "ElementStart",
+            "Name \"parent\"",
+            "ElementStart",
+            "Name \"child\"",
+            "ElementEnd",
+            "Text \"text\"",
+            "ElementEnd",
+            "ElementStart",
+            "Name \"sibling\"",
+            "ElementEnd",
+        ]
+    );
+}
+
+/// Completed top-level records are released instead of accumulating
+#[test]
+fn streaming_releases_closed_records() {
+    let mut parser = StreamingParser::new();
+    let mut elements = 0;
+
+    for i in 0..1000 {
+        let record = format!("|entry[e{}] :level info\n  message {}\n", i, i);
+        parser.parse(record.as_bytes(), |event| {
+            if matches!(event, StreamEvent::ElementStart { .. }) {
+                elements += 1;
+            }
+        });
+        assert!(parser.buffered() <= 2 * record.len(), "buffer grew to {}", parser.buffered());
+    }
+    parser.finish(|event| {
+        if matches!(event, StreamEvent::ElementStart { .. }) {
+            elements += 1;
+        }
+    });
+
+    assert_eq!(elements, 1000);
+}
+
+/// The example documents, every fixture, and all of them concatenated
+fn fixture_inputs() -> Vec<Vec<u8>> {
+    let manifest = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut inputs: Vec<Vec<u8>> = Vec::new();
+    for name in ["comprehensive.udon", "minimal.udon"] {
+        inputs.push(std::fs::read(manifest.join("../examples").join(name)).unwrap());
+    }
+    let mut fixture_files: Vec<_> = std::fs::read_dir(manifest.join("tests/fixtures"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    fixture_files.sort();
+    for path in fixture_files {
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+        inputs.extend(load_fixtures_by_name(&name).into_iter().map(|c| c.udon.into_bytes()));
+    }
+    // Concatenated fixtures give many top-level constructs per document
+    let all: Vec<u8> = inputs.iter().flat_map(|i| i.iter().copied().chain([b'\n'])).collect();
+    inputs.push(all);
+    inputs
+}
+
+/// Random chunkings of every fixture and the example documents must give
+/// exactly the single-buffer event sequence.
+#[test]
+fn stochastic_chunking_matches_single_buffer() {
+    let mut gen = Gen::from_env_or_random();
+
+    for input in &fixture_inputs() {
+        if input.is_empty() {
+            continue;
+        }
+        let full_events = collect_events_with_spans(input);
+
+        for _ in 0..4 {
+            let num_splits = gen.poisson(input.len() as f64 / 40.0 + 1.0);
+            let mut positions: Vec<usize> =
+                (0..num_splits).map(|_| gen.rng.gen_range(0..=input.len())).collect();
+            positions.sort_unstable();
+            let chunks = split_at_positions(input, &positions);
+            let chunked_events = parse_multi_chunk_with_spans(&chunks);
+
+            if full_events != chunked_events {
+                let first_diff = full_events
+                    .iter()
+                    .zip(&chunked_events)
+                    .position(|(a, b)| a != b)
+                    .unwrap_or(full_events.len().min(chunked_events.len()));
+                panic!(
+                    "Seed: {} - chunked parse differs at event {}\nSplits: {:?}\nInput: {:?}\n\
+                     Full:    {:?}\nChunked: {:?}",
+                    gen.seed,
+                    first_diff,
+                    positions,
+                    String::from_utf8_lossy(input),
+                    full_events.get(first_diff),
+                    chunked_events.get(first_diff)
+                );
+            }
+        }
+    }
+}
+
+/// The pull iterator yields exactly the callback events
+#[test]
+fn event_iterator_matches_callback() {
+    for input in &fixture_inputs() {
+        let mut expected = Vec::new();
+        Parser::new(input).parse(|e| expected.push(e));
+        let events: Vec<Event> = Parser::events(input).collect();
+        assert_eq!(events, expected, "Input: {:?}", String::from_utf8_lossy(input));
+    }
+}
+
+/// +1 for an element, embedded element or directive start, -1 for its end
+fn depth_delta(event: &Event) -> i32 {
+    match event {
+        Event::ElementStart { .. } | Event::EmbeddedStart { .. } | Event::DirectiveStart { .. } => 1,
+        Event::ElementEnd { .. } | Event::EmbeddedEnd { .. } | Event::DirectiveEnd { .. } => -1,
+        _ => 0,
+    }
+}
+
+/// Stopping after any event leaves exactly the events up to it, and
+/// skipping from any element-like start drops just that subtree.
+#[test]
+fn control_at_every_event() {
+    for input in &fixture_inputs() {
+        let mut full = Vec::new();
+        Parser::new(input).parse(|e| full.push(e));
+
+        for stop in 0..full.len() {
+            let mut events = Vec::new();
+            Parser::new(input).parse(|e| {
+                events.push(e);
+                if events.len() > stop { Control::Stop } else { Control::Continue }
+            });
+            assert_eq!(events, full[..=stop]);
+        }
+
+        assert_skips_match_filtering(input, &full);
+    }
+}
+
+/// Skipping at each start event must give the full event sequence with that
+/// construct's contents removed, whether or not the parser jumps ahead.
+fn assert_skips_match_filtering(input: &[u8], full: &[Event]) {
+    for (skip, event) in full.iter().enumerate() {
+        if depth_delta(event) != 1 {
+            continue;
+        }
+        let mut depth = 0;
+        let end = skip + full[skip..].iter().position(|e| {
+            depth += depth_delta(e);
+            depth == 0
+        }).unwrap();
+        let expected: Vec<_> = full[..=skip].iter().chain(&full[end..]).cloned().collect();
+
+        let mut events = Vec::new();
+        let mut seen = 0;
+        Parser::new(input).parse(|e| {
+            events.push(e);
+            seen += 1;
+            if seen == skip + 1 { Control::SkipChildren } else { Control::Continue }
+        });
+        assert_eq!(events, expected, "Input: {:?}, skipping event {}", String::from_utf8_lossy(input), skip);
+    }
+}
+
+/// Random nestings of elements, directives and multi-line constructs, to
+/// exercise `Control::SkipChildren` jumping over subtrees.
+#[test]
+fn stochastic_skip_children() {
+    let mut gen = Gen::from_env_or_random();
+    let pieces: &[&[u8]] = &[
+        b"!if x\n",
+        b"!:text:\n",
+        b"!raw:sql\n",
+        b"text\n",
+        b":key value\n",
+        b":key \"two\n lines\"\n",
+        b":list [a\n b]\n",
+        b"|[id\n x]\n",
+        b"|a |b :k 1\n",
+        b"; comment\n",
+        b"!{{ x }}\n",
+        b"```\n",
+        b"\t|tabbed\n",
+        b"   \n",
+        b"\n",
+    ];
+
+    for _ in 0..200 {
+        let mut input = Vec::new();
+        for _ in 0..gen.poisson(6.0) + 1 {
+            let indent = gen.indent_level() + if gen.chance(0.2) { 1 } else { 0 };
+            if gen.chance(0.5) {
+                input.extend(gen.udon_fragment(indent));
+            } else {
+                input.extend(b" ".repeat(indent));
+                input.extend(pieces[gen.rng.gen_range(0..pieces.len())]);
+            }
+        }
+        let mut full = Vec::new();
+        Parser::new(&input).parse(|e| full.push(e));
+        assert_skips_match_filtering(&input, &full);
+    }
+}
+
+/// Test multi-chunk parsing with more than 2 chunks
 #[test]
 fn multi_chunk_parsing() {
-    // Single line - multi-line requires stateful streaming (see chunk_boundary_consistency)
     let full = b"|el :author Joseph :count 42\n";
 
     // Parse in 4 chunks (roughly equal-sized)
@@ -415,7 +665,7 @@ fn byte_at_a_time_streaming() {
     let full = b"|el :attr value\n";
 
     // Feed one byte at a time
-    let chunks: Vec<&[u8]> = full.iter().map(|b| std::slice::from_ref(b)).collect();
+    let chunks: Vec<&[u8]> = full.iter().map(std::slice::from_ref).collect();
     let chunked_events = parse_multi_chunk(&chunks);
 
     let full_events = collect_events(full);
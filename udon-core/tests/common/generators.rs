@@ -25,7 +25,7 @@ impl Gen {
         let seed = std::env::var("UDON_TEST_SEED")
             .ok()
             .and_then(|s| s.parse().ok())
-            .unwrap_or_else(|| rand::random());
+            .unwrap_or_else(rand::random);
         Self::new(seed)
     }
 
@@ -2,6 +2,9 @@
 //!
 //! Provides fixture loading, stochastic test generation, and assertion helpers.
 
+// Each test binary uses a different subset of these helpers.
+#![allow(dead_code, unused_imports)]
+
 mod loader;
 mod harness;
 mod generators;